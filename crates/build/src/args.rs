@@ -16,6 +16,7 @@
 
 use anyhow::Result;
 use clap::Args;
+use contract_metadata::SourceTarget;
 use std::{
     convert::TryFrom,
     fmt,
@@ -45,7 +46,7 @@ impl TryFrom<&VerbosityFlags> for Verbosity {
 }
 
 /// Denotes if output should be printed to stdout.
-#[derive(Clone, Copy, Default, serde::Serialize, Eq, PartialEq)]
+#[derive(Clone, Copy, Default, serde::Serialize, serde::Deserialize, Eq, PartialEq)]
 pub enum Verbosity {
     /// Use default output
     #[default]
@@ -89,7 +90,15 @@ impl Network {
 
 /// Describes which artifacts to generate
 #[derive(
-    Copy, Clone, Default, Eq, PartialEq, Debug, clap::ValueEnum, serde::Serialize,
+    Copy,
+    Clone,
+    Default,
+    Eq,
+    PartialEq,
+    Debug,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
 )]
 #[clap(name = "build-artifacts")]
 pub enum BuildArtifacts {
@@ -215,6 +224,24 @@ impl Target {
     }
 }
 
+impl From<Target> for SourceTarget {
+    fn from(target: Target) -> Self {
+        match target {
+            Target::Wasm => SourceTarget::Wasm,
+            Target::RiscV => SourceTarget::RiscV,
+        }
+    }
+}
+
+impl From<SourceTarget> for Target {
+    fn from(target: SourceTarget) -> Self {
+        match target {
+            SourceTarget::Wasm => Target::Wasm,
+            SourceTarget::RiscV => Target::RiscV,
+        }
+    }
+}
+
 /// The mode to build the contract in.
 #[derive(
     Eq, PartialEq, Copy, Clone, Debug, Default, serde::Serialize, serde::Deserialize,
@@ -246,6 +273,18 @@ pub enum OutputType {
     Json,
 }
 
+/// The format of the messages printed while building.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum MessageFormat {
+    /// Coloured, human readable progress output.
+    #[default]
+    #[clap(name = "human")]
+    Human,
+    /// Newline delimited JSON build events.
+    #[clap(name = "json")]
+    Json,
+}
+
 #[derive(Default, Clone, Debug, Args)]
 pub struct UnstableOptions {
     /// Use the original manifest (Cargo.toml), do not modify for build optimizations
@@ -304,3 +343,113 @@ impl Features {
         }
     }
 }
+
+/// Define the packages of a cargo workspace to build.
+#[derive(Default, Clone, Debug, Args)]
+pub struct Packages {
+    /// Build all ink! contracts of the workspace
+    #[clap(long)]
+    workspace: bool,
+    /// Contract package(s) of the workspace to build
+    #[clap(long, short = 'p', value_name = "SPEC")]
+    package: Vec<String>,
+    /// Exclude contract package(s) from a `--workspace` build
+    #[clap(long, value_name = "SPEC", requires = "workspace")]
+    exclude: Vec<String>,
+}
+
+impl Packages {
+    /// Returns `true` if more than the contract of the manifest should be built, i.e.
+    /// `--workspace` or `--package` is supplied.
+    pub fn is_workspace_build(&self) -> bool {
+        self.workspace || !self.package.is_empty()
+    }
+
+    /// Selects the packages to build out of the names of all `contracts` of the
+    /// workspace.
+    ///
+    /// Returns an error if a `--package` or `--exclude` does not name a contract.
+    pub(crate) fn select<'a>(&self, contracts: &[&'a str]) -> Result<Vec<&'a str>> {
+        let unknown = self
+            .package
+            .iter()
+            .chain(self.exclude.iter())
+            .filter(|name| !contracts.contains(&name.as_str()))
+            .collect::<Vec<_>>();
+        if !unknown.is_empty() {
+            anyhow::bail!(
+                "No ink! contract named {:?} was found in the workspace, available \
+                contracts are {:?}",
+                unknown,
+                contracts
+            )
+        }
+        let selected = contracts
+            .iter()
+            .filter(|name| {
+                (self.workspace || self.package.iter().any(|p| p == *name))
+                    && !self.exclude.iter().any(|e| e == *name)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        if selected.is_empty() {
+            anyhow::bail!("No ink! contracts were selected to be built")
+        }
+        Ok(selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    const CONTRACTS: [&str; 3] = ["erc20", "flipper", "incrementer"];
+
+    #[test]
+    fn workspace_selects_all_contracts_except_excluded() {
+        let packages = Packages {
+            workspace: true,
+            exclude: vec!["flipper".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            packages.select(&CONTRACTS).unwrap(),
+            vec!["erc20", "incrementer"]
+        );
+    }
+
+    #[test]
+    fn package_selects_only_named_contracts() {
+        let packages = Packages {
+            package: vec!["incrementer".to_string(), "erc20".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            packages.select(&CONTRACTS).unwrap(),
+            vec!["erc20", "incrementer"]
+        );
+    }
+
+    #[test]
+    fn unknown_package_is_rejected() {
+        let packages = Packages {
+            package: vec!["dns".to_string()],
+            ..Default::default()
+        };
+        let err = packages.select(&CONTRACTS).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(r#"No ink! contract named ["dns"]"#));
+    }
+
+    #[test]
+    fn excluding_all_contracts_is_rejected() {
+        let packages = Packages {
+            workspace: true,
+            exclude: CONTRACTS.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        assert!(packages.select(&CONTRACTS).is_err());
+    }
+}
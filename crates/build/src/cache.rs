@@ -0,0 +1,401 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! A content-addressed cache of the post processed contract code and the ink! metadata.
+//!
+//! Other than the fingerprint of the code in the target directory, the
+//! entries of the cache are addressed by the hash of the code emitted by cargo and the
+//! hash of the sources of the contract and its local path dependencies, together with all
+//! settings influencing the post processing. The sources are part of the key since the
+//! ink! metadata can change without the code changing, e.g. for an edited doc comment.
+//! One cache directory can therefore be shared by CI runners or multiple worktrees of a
+//! repository, a hit skips `wasm-opt` as well as the compilation of the metadata
+//! generator.
+
+use crate::{
+    blake2_hash,
+    OptimizationPasses,
+    Target,
+    VERSION,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use serde_json::{
+    Map,
+    Value,
+};
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write as _,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+/// The file name of the cached ink! metadata in a cache entry.
+const INK_METADATA_FILE: &str = "ink-metadata.json";
+
+/// The key of a cache entry.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct CacheKey([u8; 32]);
+
+impl CacheKey {
+    /// Creates the key for the `original_code` emitted by cargo from the sources hashed
+    /// by [`sources_hash`], post processed with the given settings by the running version
+    /// of `cargo-contract`.
+    pub(crate) fn new(
+        original_code: &[u8],
+        sources_hash: [u8; 32],
+        target: Target,
+        optimization_passes: OptimizationPasses,
+        keep_debug_symbols: bool,
+        max_memory_pages: u32,
+        skip_validation: bool,
+    ) -> Self {
+        // the validation does not change the code, but a hit must not bypass it for
+        // code which has been cached by a build skipping it
+        let settings = format!(
+            "cargo-contract {VERSION}\ntarget {}\noptimization-passes {optimization_passes}\n\
+            keep-debug-symbols {keep_debug_symbols}\nmax-memory-pages {max_memory_pages}\n\
+            skip-validation {skip_validation}\ncode {}\nsources {}\n",
+            target.llvm_target(),
+            hex::encode(blake2_hash(original_code)),
+            hex::encode(sources_hash),
+        );
+        Self(blake2_hash(settings.as_bytes()))
+    }
+}
+
+/// Returns the names and directories of the local packages the `root` package depends
+/// on, directly or indirectly, starting with the `root` package itself.
+///
+/// The local packages are the ones without a source in the registry or a git repository,
+/// i.e. the members of the workspace and the path dependencies.
+pub(crate) fn local_packages(
+    metadata: &cargo_metadata::Metadata,
+    root: &cargo_metadata::Package,
+) -> Result<Vec<(String, PathBuf)>> {
+    let resolve = metadata
+        .resolve
+        .as_ref()
+        .context("The dependencies of the contract are not resolved")?;
+    let mut visited = HashSet::new();
+    let mut pending = vec![&root.id];
+    let mut packages = Vec::new();
+    while let Some(id) = pending.pop() {
+        if !visited.insert(id) {
+            continue
+        }
+        let package = &metadata[id];
+        if package.source.is_some() {
+            continue
+        }
+        let dir = package
+            .manifest_path
+            .parent()
+            .context("The manifest of a package has no parent directory")?;
+        packages.push((package.name.clone(), dir.as_std_path().to_path_buf()));
+        if let Some(node) = resolve.nodes.iter().find(|node| &node.id == id) {
+            pending.extend(node.dependencies.iter());
+        }
+    }
+    packages[1..].sort();
+    Ok(packages)
+}
+
+/// Hashes the sources of the `packages`, given by their names and directories, together
+/// with the lock file in the `workspace_root`.
+///
+/// All files of the packages are hashed, including their manifests, apart from hidden
+/// files and the contents of `target` directories.
+pub(crate) fn sources_hash(
+    packages: &[(String, PathBuf)],
+    workspace_root: &Path,
+) -> Result<[u8; 32]> {
+    let is_excluded = |entry: &walkdir::DirEntry| {
+        let name = entry.file_name().to_string_lossy();
+        entry.depth() > 0
+            && (name.starts_with('.') || (entry.file_type().is_dir() && name == "target"))
+    };
+    let mut files = Vec::new();
+    for (name, package_dir) in packages {
+        for entry in walkdir::WalkDir::new(package_dir)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| !is_excluded(entry))
+        {
+            let entry = entry.with_context(|| {
+                format!("Failed to read the sources in {}", package_dir.display())
+            })?;
+            if entry.file_type().is_file() {
+                let relative =
+                    Path::new(name).join(entry.path().strip_prefix(package_dir)?);
+                files.push((relative, entry.into_path()));
+            }
+        }
+    }
+    let lock_file = workspace_root.join("Cargo.lock");
+    if lock_file.exists() {
+        files.push((PathBuf::from("Cargo.lock (workspace)"), lock_file));
+    }
+
+    let mut listing = String::new();
+    for (relative, path) in files {
+        let contents = fs::read(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        listing.push_str(&format!(
+            "{} {}\n",
+            hex::encode(blake2_hash(&contents)),
+            relative.display()
+        ));
+    }
+    Ok(blake2_hash(listing.as_bytes()))
+}
+
+/// An entry of the build cache, which might not exist yet.
+#[derive(Clone, Debug)]
+pub(crate) struct CacheEntry {
+    dir: PathBuf,
+    code_file: String,
+}
+
+impl CacheEntry {
+    /// Returns the entry for the `key` in the `cache_dir`.
+    pub(crate) fn new(cache_dir: &Path, key: CacheKey, target: Target) -> Self {
+        Self {
+            dir: cache_dir.join(hex::encode(key.0)),
+            code_file: format!("code.{}", target.dest_extension()),
+        }
+    }
+
+    /// Copies the cached code to `dest`, returns `false` if there is none.
+    pub(crate) fn restore_code(&self, dest: &Path) -> Result<bool> {
+        let cached = self.dir.join(&self.code_file);
+        if !cached.exists() {
+            return Ok(false)
+        }
+        fs::copy(&cached, dest).with_context(|| {
+            format!("Failed to restore the code from cache {}", cached.display())
+        })?;
+        Ok(true)
+    }
+
+    /// Stores the post processed code at `code` in the cache.
+    pub(crate) fn store_code(&self, code: &Path) -> Result<()> {
+        self.store(&self.code_file, &fs::read(code)?)
+    }
+
+    /// Returns the cached ink! metadata, if any.
+    pub(crate) fn ink_metadata(&self) -> Result<Option<Map<String, Value>>> {
+        let cached = self.dir.join(INK_METADATA_FILE);
+        if !cached.exists() {
+            return Ok(None)
+        }
+        let contents = fs::read(&cached)?;
+        let ink_meta = serde_json::from_slice(&contents).with_context(|| {
+            format!("Invalid ink! metadata in cache {}", cached.display())
+        })?;
+        Ok(Some(ink_meta))
+    }
+
+    /// Stores the ink! metadata generated for the contract in the cache.
+    pub(crate) fn store_ink_metadata(&self, ink_meta: &Map<String, Value>) -> Result<()> {
+        self.store(INK_METADATA_FILE, &serde_json::to_vec(ink_meta)?)
+    }
+
+    /// Writes the file atomically, so that concurrent builds sharing the cache never
+    /// read a partially written file.
+    fn store(&self, file_name: &str, contents: &[u8]) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.dir.join(file_name);
+        let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+        file.write_all(contents)?;
+        file.persist(&path).with_context(|| {
+            format!("Failed to write to the build cache at {}", path.display())
+        })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn key(original_code: &[u8], optimization_passes: OptimizationPasses) -> CacheKey {
+        CacheKey::new(
+            original_code,
+            [0u8; 32],
+            Target::Wasm,
+            optimization_passes,
+            false,
+            16,
+            false,
+        )
+    }
+
+    #[test]
+    fn key_depends_on_code_and_settings() {
+        let code = b"\0asm\x01\0\0\0";
+        assert_eq!(
+            key(code, OptimizationPasses::Z),
+            key(code, OptimizationPasses::Z)
+        );
+        assert_ne!(
+            key(code, OptimizationPasses::Z),
+            key(code, OptimizationPasses::Three)
+        );
+        assert_ne!(
+            key(code, OptimizationPasses::Z),
+            key(b"\0asm\x01\0\0\x01", OptimizationPasses::Z)
+        );
+    }
+
+    #[test]
+    fn sources_hash_depends_on_the_package_files() {
+        // given
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("cargo-contract.test.")
+            .tempdir()
+            .unwrap();
+        let package_dir = tmp_dir.path().join("flipper");
+        let dependency_dir = tmp_dir.path().join("flipper-traits");
+        fs::create_dir_all(&package_dir).unwrap();
+        fs::create_dir_all(&dependency_dir).unwrap();
+        fs::write(package_dir.join("Cargo.toml"), "[package]").unwrap();
+        fs::write(package_dir.join("lib.rs"), "/// Flips the value.").unwrap();
+        fs::write(dependency_dir.join("lib.rs"), "/// The value.").unwrap();
+        let packages = [
+            ("flipper".to_string(), package_dir.clone()),
+            ("flipper-traits".to_string(), dependency_dir.clone()),
+        ];
+        let hash = sources_hash(&packages, tmp_dir.path()).unwrap();
+
+        // when
+        fs::create_dir_all(package_dir.join("target/ink")).unwrap();
+        fs::write(package_dir.join("target/ink/flipper.wasm"), b"\0asm").unwrap();
+        fs::create_dir(package_dir.join(".git")).unwrap();
+        fs::write(package_dir.join(".git/HEAD"), "ref: refs/heads/master").unwrap();
+
+        // then
+        assert_eq!(sources_hash(&packages, tmp_dir.path()).unwrap(), hash);
+
+        // when
+        fs::write(dependency_dir.join("lib.rs"), "/// The stored value.").unwrap();
+        let dependency_hash = sources_hash(&packages, tmp_dir.path()).unwrap();
+        fs::write(package_dir.join("lib.rs"), "/// Flips the stored value.").unwrap();
+
+        // then
+        assert_ne!(dependency_hash, hash);
+        assert_ne!(
+            sources_hash(&packages, tmp_dir.path()).unwrap(),
+            dependency_hash
+        );
+    }
+
+    #[test]
+    fn local_packages_are_the_transitive_path_dependencies() {
+        // given
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("cargo-contract.test.")
+            .tempdir()
+            .unwrap();
+        let package = |name: &str, dependencies: &str| {
+            let dir = tmp_dir.path().join(name);
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(dir.join("src/lib.rs"), "").unwrap();
+            fs::write(
+                dir.join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n\
+                    edition = \"2021\"\n\n[workspace]\n\n[dependencies]\n{dependencies}"
+                ),
+            )
+            .unwrap();
+            dir
+        };
+        let types = package("flipper-types", "");
+        let traits = package(
+            "flipper-traits",
+            "flipper-types = { path = \"../flipper-types\" }",
+        );
+        package("unrelated", "");
+        let flipper = package(
+            "flipper",
+            "flipper-traits = { path = \"../flipper-traits\" }",
+        );
+
+        // when
+        let metadata = cargo_metadata::MetadataCommand::new()
+            .manifest_path(flipper.join("Cargo.toml"))
+            .other_options(vec!["--offline".to_string()])
+            .exec()
+            .unwrap();
+        let root = metadata.root_package().unwrap();
+        let packages = local_packages(&metadata, root).unwrap();
+
+        // then
+        assert_eq!(
+            packages,
+            [
+                ("flipper".to_string(), flipper),
+                ("flipper-traits".to_string(), traits),
+                ("flipper-types".to_string(), types),
+            ]
+        );
+    }
+
+    #[test]
+    fn stored_artifacts_are_restored() {
+        // given
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("cargo-contract.test.")
+            .tempdir()
+            .unwrap();
+        let code_path = tmp_dir.path().join("flipper.wasm");
+        fs::write(&code_path, b"optimized").unwrap();
+        let mut ink_meta = Map::new();
+        ink_meta.insert("version".into(), "4".into());
+
+        let cache_dir = tmp_dir.path().join("cache");
+        let entry = CacheEntry::new(
+            &cache_dir,
+            key(b"original", OptimizationPasses::Z),
+            Target::Wasm,
+        );
+        let dest = tmp_dir.path().join("restored.wasm");
+        assert!(!entry.restore_code(&dest).unwrap());
+        assert_eq!(entry.ink_metadata().unwrap(), None);
+
+        // when
+        entry.store_code(&code_path).unwrap();
+        entry.store_ink_metadata(&ink_meta).unwrap();
+
+        // then
+        let entry = CacheEntry::new(
+            &cache_dir,
+            key(b"original", OptimizationPasses::Z),
+            Target::Wasm,
+        );
+        assert!(entry.restore_code(&dest).unwrap());
+        assert_eq!(fs::read(&dest).unwrap(), b"optimized");
+        assert_eq!(entry.ink_metadata().unwrap(), Some(ink_meta));
+    }
+}
@@ -15,7 +15,10 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
+    BuildHooks,
     ManifestPath,
+    Packages,
+    SizeBudget,
     Target,
 };
 use anyhow::{
@@ -23,6 +26,7 @@ use anyhow::{
     Result,
 };
 use cargo_metadata::{
+    DependencyKind,
     Metadata as CargoMetadata,
     MetadataCommand,
     Package,
@@ -52,7 +56,16 @@ pub struct CrateMetadata {
     pub documentation: Option<Url>,
     pub homepage: Option<Url>,
     pub user: Option<Map<String, Value>>,
+    /// The size budget of the contract code, if declared.
+    pub size_budget: Option<SizeBudget>,
+    /// The commands run at defined points of the build.
+    pub hooks: BuildHooks,
     pub target_directory: PathBuf,
+    /// The target directory cargo is invoked with.
+    ///
+    /// For a contract in a workspace this is shared by all contracts of the workspace,
+    /// while the artifacts are written to a sub-folder of it.
+    pub cargo_target_directory: PathBuf,
     pub target_file_path: PathBuf,
 }
 
@@ -69,7 +82,8 @@ impl CrateMetadata {
     /// Parses the contract manifest and returns relevant metadata.
     pub fn collect(manifest_path: &ManifestPath, target: Target) -> Result<Self> {
         let (metadata, root_package) = get_cargo_metadata(manifest_path)?;
-        let mut target_directory = metadata.target_directory.as_path().join("ink");
+        let cargo_target_directory = metadata.target_directory.as_path().join("ink");
+        let mut target_directory = cargo_target_directory.clone();
 
         // Normalize the final contract artifact name.
         let contract_artifact_name = root_package.name.replace('-', "_");
@@ -101,8 +115,8 @@ impl CrateMetadata {
             target_directory = target_directory.join(contract_artifact_name.clone());
         }
 
-        // {target_dir}/{target}/release/{contract_artifact_name}.{extension}
-        let mut original_code = target_directory.clone();
+        // {cargo_target_dir}/{target}/release/{contract_artifact_name}.{extension}
+        let mut original_code = cargo_target_directory.clone();
         original_code.push(target.llvm_target());
         original_code.push("release");
         original_code.push(root_package.name.clone());
@@ -132,6 +146,8 @@ impl CrateMetadata {
             documentation,
             homepage,
             user,
+            size_budget,
+            hooks,
         } = get_cargo_toml_metadata(manifest_path)?;
 
         let crate_metadata = CrateMetadata {
@@ -145,8 +161,11 @@ impl CrateMetadata {
             documentation,
             homepage,
             user,
+            size_budget,
+            hooks,
             target_file_path: target_directory.join(".target").into(),
             target_directory: target_directory.into(),
+            cargo_target_directory: cargo_target_directory.into(),
         };
         Ok(crate_metadata)
     }
@@ -163,6 +182,56 @@ impl CrateMetadata {
         let fname_bundle = format!("{}.contract", self.contract_artifact_name);
         target_directory.join(fname_bundle)
     }
+
+    /// Get the path of the size report of the contract's code.
+    pub fn size_report_path(&self) -> PathBuf {
+        let fname_report = format!("{}.size.json", self.contract_artifact_name);
+        self.target_directory.join(fname_report)
+    }
+
+    /// Get the path the ink! metadata embedded in the contract code is extracted to.
+    pub fn extracted_ink_metadata_path(&self) -> PathBuf {
+        self.target_directory.join(".ink_metadata.json")
+    }
+}
+
+/// Returns the manifest paths of the ink! contracts of the workspace of the manifest,
+/// selected by the `packages`.
+///
+/// All packages of the workspace with a dependency on `ink` are considered contracts.
+pub(crate) fn workspace_contracts(
+    manifest_path: &ManifestPath,
+    packages: &Packages,
+) -> Result<Vec<ManifestPath>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path.as_ref())
+        .no_deps()
+        .exec()
+        .with_context(|| {
+            format!(
+                "Error invoking `cargo metadata` for {}",
+                manifest_path.as_ref().display()
+            )
+        })?;
+    let contracts = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter(|package| {
+            package.dependencies.iter().any(|dependency| {
+                dependency.name == "ink" && dependency.kind == DependencyKind::Normal
+            })
+        })
+        .collect::<Vec<_>>();
+    let names = contracts
+        .iter()
+        .map(|package| package.name.as_str())
+        .collect::<Vec<_>>();
+    let selected = packages.select(&names)?;
+    contracts
+        .into_iter()
+        .filter(|package| selected.contains(&package.name.as_str()))
+        .map(|package| ManifestPath::new(&package.manifest_path))
+        .collect()
 }
 
 /// Get the result of `cargo metadata`, together with the root package id.
@@ -203,6 +272,8 @@ struct ExtraMetadata {
     documentation: Option<Url>,
     homepage: Option<Url>,
     user: Option<Map<String, Value>>,
+    size_budget: Option<SizeBudget>,
+    hooks: BuildHooks,
 }
 
 /// Read extra metadata not available via `cargo metadata` directly from `Cargo.toml`
@@ -218,7 +289,6 @@ fn get_cargo_toml_metadata(manifest_path: &ManifestPath) -> Result<ExtraMetadata
             .map(Url::parse)
             .transpose()
             .context(format!("{field_name} should be a valid URL"))
-            .map_err(Into::into)
     };
 
     let documentation = get_url("documentation")?;
@@ -236,9 +306,30 @@ fn get_cargo_toml_metadata(manifest_path: &ManifestPath) -> Result<ExtraMetadata
         })
         .transpose()?;
 
+    let size_budget = toml
+        .get("package")
+        .and_then(|v| v.get("metadata"))
+        .and_then(|v| v.get("contract"))
+        .and_then(|v| v.get("size-budget"))
+        .map(|v| v.clone().try_into())
+        .transpose()
+        .context("Invalid `[package.metadata.contract.size-budget]` section")?;
+
+    let hooks = toml
+        .get("package")
+        .and_then(|v| v.get("metadata"))
+        .and_then(|v| v.get("contract"))
+        .and_then(|v| v.get("hooks"))
+        .map(|v| v.clone().try_into())
+        .transpose()
+        .context("Invalid `[package.metadata.contract.hooks]` section")?
+        .unwrap_or_default();
+
     Ok(ExtraMetadata {
         documentation,
         homepage,
         user,
+        size_budget,
+        hooks,
     })
 }
@@ -0,0 +1,408 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Verifiable builds inside of a pinned container image.
+//!
+//! The build is executed by invoking `cargo contract build` inside of a locally
+//! available OCI image using the `docker` CLI. The contract's workspace is mounted at a
+//! fixed path and the environment is normalized, so that the same sources always
+//! result in a byte-for-byte identical contract binary.
+//!
+//! The repository digest of the image is recorded in the [`crate::BuildInfo`] of the
+//! resulting metadata, allowing third parties to fetch the very same image and
+//! reproduce the build.
+
+use crate::{
+    check_code_host_functions,
+    maybe_println,
+    BuildArtifacts,
+    BuildEvent,
+    BuildMode,
+    BuildResult,
+    CrateMetadata,
+    ExecuteArgs,
+    Network,
+    SizeReport,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use colored::Colorize;
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Command,
+};
+
+/// The default image used for verifiable builds, the tag is the version of the currently
+/// executing `cargo-contract`.
+pub const DEFAULT_IMAGE: &str = "paritytech/contracts-verifiable";
+
+/// The path at which the contract's workspace is mounted inside of the container.
+const CONTAINER_WORKSPACE_DIR: &str = "/contract";
+
+/// The path at which the cargo target directory is mounted inside of the container.
+const CONTAINER_TARGET_DIR: &str = "/target";
+
+/// The environment variable holding the repository digest of the image inside of the
+/// container, only a build it is set for records a build image in its metadata.
+const BUILD_IMAGE_ENV: &str = "CARGO_CONTRACT_BUILD_IMAGE";
+
+/// The timestamp used for `SOURCE_DATE_EPOCH`, any timestamps embedded by the
+/// toolchain will be normalized to this value.
+const SOURCE_DATE_EPOCH: &str = "0";
+
+/// The image to use for a verifiable build.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum ImageVariant {
+    /// The default image, tagged with the version of the running `cargo-contract`.
+    #[default]
+    Default,
+    /// A user supplied image, e.g. `my-registry/contracts-verifiable:1.0`.
+    Custom(String),
+}
+
+impl ImageVariant {
+    /// The full name of the image, including the tag.
+    pub fn name(&self) -> String {
+        match self {
+            Self::Default => format!("{DEFAULT_IMAGE}:{}", crate::VERSION),
+            Self::Custom(image) => image.clone(),
+        }
+    }
+}
+
+impl From<Option<String>> for ImageVariant {
+    fn from(image: Option<String>) -> Self {
+        image.map(Self::Custom).unwrap_or_default()
+    }
+}
+
+/// Executes a verifiable build of the contract inside of the image specified in
+/// `args.image`.
+///
+/// The image has to be available locally, it will not be pulled from a registry.
+pub(crate) fn docker_build(args: ExecuteArgs) -> Result<BuildResult> {
+    let ExecuteArgs {
+        manifest_path,
+        verbosity,
+        features,
+        build_mode,
+        network,
+        build_artifact,
+        unstable_flags,
+        optimization_passes,
+        keep_debug_symbols,
+        output_type,
+        message_format,
+        skip_wasm_validation,
+        target,
+        max_memory_pages,
+        image,
+        size_report,
+        size_report_baseline,
+        cache_dir,
+        host_functions,
+        ..
+    } = args;
+
+    if build_mode != BuildMode::Release {
+        anyhow::bail!("Verifiable builds are only supported in release mode.")
+    }
+    if matches!(build_artifact, BuildArtifacts::CheckOnly) {
+        anyhow::bail!("Verifiable builds require build artifacts to be generated.")
+    }
+    if cache_dir.is_some() {
+        anyhow::bail!(
+            "Verifiable builds can't use a build cache, the contract is always built \
+            from scratch inside of the image."
+        )
+    }
+    which::which("docker").context(
+        "The `docker` CLI was not found!\n\
+        Make sure it is installed and the binary is in your PATH environment.",
+    )?;
+
+    let image = image.name();
+    let digest = image_digest(&image)?;
+
+    let crate_metadata = CrateMetadata::collect(&manifest_path, target)?;
+    let host_workspace_dir = crate_metadata.cargo_meta.workspace_root.canonicalize()?;
+    let host_target_dir = crate_metadata.cargo_meta.target_directory.as_std_path();
+    std::fs::create_dir_all(host_target_dir)?;
+    let host_target_dir = host_target_dir.canonicalize()?;
+
+    let manifest_dir = manifest_path.absolute_directory()?;
+    let relative_manifest_dir = manifest_dir
+        .strip_prefix(&host_workspace_dir)
+        .with_context(|| {
+            format!(
+                "The contract at {} is not part of the workspace at {}",
+                manifest_dir.display(),
+                host_workspace_dir.display()
+            )
+        })?;
+    let container_manifest_dir =
+        Path::new(CONTAINER_WORKSPACE_DIR).join(relative_manifest_dir);
+
+    let mut build_args = vec![
+        "contract".to_owned(),
+        "build".to_owned(),
+        "--release".to_owned(),
+        "--output-json".to_owned(),
+        format!("--generate={}", value_arg(&build_artifact)),
+        format!("--target={}", value_arg(&target)),
+        format!("--max-memory-pages={max_memory_pages}"),
+        format!("--build-image={digest}"),
+    ];
+    if let Some(optimization_passes) = optimization_passes {
+        build_args.push(format!("--optimization-passes={optimization_passes}"));
+    }
+    if keep_debug_symbols {
+        build_args.push("--keep-debug-symbols".to_owned());
+    }
+    if skip_wasm_validation {
+        build_args.push("--skip-wasm-validation".to_owned());
+    }
+    if size_report {
+        build_args.push("--size-report".to_owned());
+    }
+    if network == Network::Offline {
+        build_args.push("--offline".to_owned());
+    }
+    if unstable_flags.original_manifest {
+        build_args.push("--unstable-options=original-manifest".to_owned());
+    }
+    features.append_to_args(&mut build_args);
+    // the host functions and the size report baseline are read from files which are not
+    // mounted into the container, they are applied to the build result below instead
+
+    let mut docker_args = vec![
+        "run".to_owned(),
+        "--rm".to_owned(),
+        format!(
+            "--volume={}:{CONTAINER_WORKSPACE_DIR}",
+            host_workspace_dir.display()
+        ),
+        format!(
+            "--volume={}:{CONTAINER_TARGET_DIR}",
+            host_target_dir.display()
+        ),
+        format!("--workdir={}", container_manifest_dir.display()),
+        format!("--env=CARGO_TARGET_DIR={CONTAINER_TARGET_DIR}"),
+        format!("--env=SOURCE_DATE_EPOCH={SOURCE_DATE_EPOCH}"),
+        format!("--env={BUILD_IMAGE_ENV}={digest}"),
+    ];
+    if let Some(user) = host_user() {
+        docker_args.push(format!("--user={user}"));
+    }
+    docker_args.push(digest.clone());
+    docker_args.push("cargo".to_owned());
+    docker_args.extend(build_args);
+
+    maybe_println!(
+        verbosity,
+        " {} {} {}",
+        "Building verifiable contract in".bright_green().bold(),
+        image.bold(),
+        format!("({digest})").bright_black()
+    );
+    tracing::debug!("Invoking docker with {:?}", docker_args);
+
+    let output = duct::cmd("docker", &docker_args)
+        .stdout_capture()
+        .unchecked()
+        .run()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "The verifiable build inside of the image {image} failed with {}",
+            output.status
+        )
+    }
+
+    let mut build_result: BuildResult = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse the build result of the verifiable build")?;
+    build_result.map_paths(Path::new(CONTAINER_TARGET_DIR), &host_target_dir);
+    build_result.verbosity = verbosity;
+    build_result.output_type = output_type;
+    if let Some(dest_code) = build_result.dest_wasm.as_ref() {
+        check_code_host_functions(dest_code, target, host_functions.as_ref())?;
+    }
+    if let (Some(size_report), Some(baseline)) =
+        (build_result.size_report.as_ref(), size_report_baseline)
+    {
+        build_result.size_report_diff =
+            Some(size_report.diff(&SizeReport::load(baseline)?));
+    }
+    BuildEvent::BuildFinished(&build_result).emit(message_format)?;
+    Ok(build_result)
+}
+
+/// Checks that the `build_image` recorded in the build info of the contract is the image
+/// of the verifiable build the build is executed in.
+///
+/// The image is passed to the build inside of the container, other builds can't claim to
+/// be reproducible with an image.
+pub(crate) fn check_build_image(build_image: Option<&str>) -> Result<()> {
+    let Some(build_image) = build_image else {
+        return Ok(())
+    };
+    match std::env::var(BUILD_IMAGE_ENV) {
+        Ok(image) if image == build_image => Ok(()),
+        _ => {
+            anyhow::bail!(
+                "The build image {build_image} can only be passed to the build inside of \
+                the container of a verifiable build, use `--verifiable` instead."
+            )
+        }
+    }
+}
+
+/// Returns the CLI representation of a `clap::ValueEnum` argument.
+fn value_arg<T: clap::ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .expect("arguments are never skipped; qed")
+        .get_name()
+        .to_owned()
+}
+
+/// Returns the repository digest of a locally available image, e.g.
+/// `paritytech/contracts-verifiable@sha256:…`.
+///
+/// Contrary to the local image id, the repository digest can be used by third parties
+/// to fetch the very same image from its registry.
+fn image_digest(image: &str) -> Result<String> {
+    let output = Command::new("docker")
+        .args(["image", "inspect", "--format={{json .RepoDigests}}", image])
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "The image {image} is not available locally.\n\
+            Verifiable builds only use local images, fetch it with `docker pull {image}`."
+        )
+    }
+    let repo_digests: Option<Vec<String>> = serde_json::from_slice(&output.stdout)
+        .with_context(|| format!("Unable to parse the digests of the image {image}"))?;
+    repo_digests
+        .unwrap_or_default()
+        .into_iter()
+        .next()
+        .with_context(|| {
+            format!(
+                "The image {image} has no repository digest.\n\
+                Verifiable builds require an image which has been pulled from or pushed \
+                to a registry, so that third parties are able to fetch it."
+            )
+        })
+}
+
+/// Returns the `uid:gid` of the current user, so that build artifacts written to the
+/// mounted directories are not owned by `root`.
+#[cfg(unix)]
+fn host_user() -> Option<String> {
+    let id = |flag| {
+        Command::new("id")
+            .arg(flag)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|id| id.trim().to_owned())
+    };
+    Some(format!("{}:{}", id("-u")?, id("-g")?))
+}
+
+#[cfg(not(unix))]
+fn host_user() -> Option<String> {
+    None
+}
+
+/// Returns the `--remap-path-prefix` flag which normalizes the paths of dependencies
+/// embedded into the contract binary.
+///
+/// The workspace is always mounted at the same path inside of the container, only the
+/// location of the cargo home depends on the image.
+pub(crate) fn remap_path_prefix_flag() -> Option<String> {
+    let cargo_home = cargo_home()?;
+    Some(format!(
+        "--remap-path-prefix={}=/cargo",
+        cargo_home.display()
+    ))
+}
+
+/// Returns the cargo home directory, determined the same way as cargo does.
+///
+/// That is `CARGO_HOME` if set, resolved against the current directory, otherwise
+/// `.cargo` in the home directory of the user.
+fn cargo_home() -> Option<PathBuf> {
+    match std::env::var_os("CARGO_HOME").filter(|home| !home.is_empty()) {
+        Some(cargo_home) => {
+            let cargo_home = PathBuf::from(cargo_home);
+            if cargo_home.is_absolute() {
+                Some(cargo_home)
+            } else {
+                std::env::current_dir().ok().map(|dir| dir.join(cargo_home))
+            }
+        }
+        None => home_dir().map(|home| home.join(".cargo")),
+    }
+}
+
+#[cfg(unix)]
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(not(unix))]
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("USERPROFILE")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_image_is_only_accepted_inside_of_the_container() {
+        // given
+        let digest = "paritytech/contracts-verifiable@sha256:01";
+        std::env::remove_var(BUILD_IMAGE_ENV);
+
+        // when
+        let host = check_build_image(Some(digest));
+        std::env::set_var(BUILD_IMAGE_ENV, digest);
+        let container = check_build_image(Some(digest));
+        let other = check_build_image(Some("my-registry/contracts-verifiable@sha256:02"));
+        std::env::remove_var(BUILD_IMAGE_ENV);
+
+        // then
+        assert!(host
+            .unwrap_err()
+            .to_string()
+            .contains("can only be passed to the build inside of the container"));
+        assert!(container.is_ok());
+        assert!(other.is_err());
+        assert!(check_build_image(None).is_ok());
+    }
+}
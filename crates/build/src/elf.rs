@@ -0,0 +1,591 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation and stripping of contracts built for RISC-V.
+//!
+//! The linker emits a statically linked, 32-bit little endian ELF executable. Host
+//! functions are imported as undefined symbols named `<module>::<function>`, which are
+//! resolved by the chain, the contract is entered through the `_start` function which is
+//! exported by the ink! codegen.
+
+use crate::validate_wasm;
+use anyhow::{
+    Context,
+    Result,
+};
+use object::{
+    elf,
+    pod,
+    read::elf::{
+        FileHeader as _,
+        ProgramHeader as _,
+        SectionHeader as _,
+        Sym as _,
+    },
+    LittleEndian,
+    SectionIndex,
+    U16,
+    U32,
+};
+use std::mem;
+
+pub(crate) type FileHeader = elf::FileHeader32<LittleEndian>;
+type SectionHeader = elf::SectionHeader32<LittleEndian>;
+
+pub(crate) const LE: LittleEndian = LittleEndian;
+
+/// Parses the header of the ELF `data`, fails if it is not an executable for 32-bit
+/// little endian RISC-V.
+pub(crate) fn parse(data: &[u8]) -> Result<&FileHeader> {
+    let header = FileHeader::parse(data)
+        .context("The contract code is not a valid 32-bit ELF file")?;
+    if !header.is_little_endian() {
+        anyhow::bail!("The contract code must be a little endian ELF file")
+    }
+    if header.e_machine(LE) != elf::EM_RISCV {
+        anyhow::bail!(
+            "The contract code is not built for RISC-V, the ELF is built for machine {}",
+            header.e_machine(LE)
+        )
+    }
+    if header.e_type(LE) != elf::ET_EXEC {
+        anyhow::bail!("The contract code must be an ELF executable")
+    }
+    Ok(header)
+}
+
+/// Validates the contract ELF.
+///
+/// - The contract must be statically linked.
+/// - The entry point must lie in an executable segment.
+/// - No undefined symbols but host function imports must be referenced, see
+///   [`validate_wasm::validate_undefined_symbols`].
+pub(crate) fn validate(data: &[u8]) -> Result<()> {
+    let header = parse(data)?;
+    let program_headers = header.program_headers(LE, data)?;
+    if program_headers
+        .iter()
+        .any(|ph| matches!(ph.p_type(LE), elf::PT_INTERP | elf::PT_DYNAMIC))
+    {
+        anyhow::bail!(
+            "The contract ELF is dynamically linked, contracts must be linked statically."
+        )
+    }
+
+    let entry = header.e_entry(LE);
+    let entry_is_executable = program_headers.iter().any(|ph| {
+        ph.p_type(LE) == elf::PT_LOAD
+            && ph.p_flags(LE) & elf::PF_X != 0
+            && (ph.p_vaddr(LE)..ph.p_vaddr(LE).saturating_add(ph.p_memsz(LE)))
+                .contains(&entry)
+    });
+    if !entry_is_executable {
+        anyhow::bail!(
+            "The entry point {entry:#x} of the contract ELF does not lie in an \
+            executable segment. ink! contracts for RISC-V are entered through `_start`."
+        )
+    }
+
+    let undefined = undefined_symbols(header, data)?;
+    validate_wasm::validate_undefined_symbols(undefined.iter().map(String::as_str))
+}
+
+/// Returns the names of the symbols the ELF `data` references without defining them.
+fn undefined_symbols(header: &FileHeader, data: &[u8]) -> Result<Vec<String>> {
+    let sections = header.sections(LE, data)?;
+    let symbols = sections.symbols(LE, data, elf::SHT_SYMTAB)?;
+    let mut undefined = Vec::new();
+    for symbol in symbols.iter().skip(1) {
+        // weak symbols which are not defined are resolved to `0` by the linker
+        if !symbol.is_undefined(LE) || symbol.st_bind() == elf::STB_WEAK {
+            continue
+        }
+        let name = symbols.symbol_name(LE, symbol)?;
+        if !name.is_empty() {
+            undefined.push(String::from_utf8_lossy(name).into_owned());
+        }
+    }
+    Ok(undefined)
+}
+
+/// Returns the host functions imported by the contract ELF `data` as
+/// `(module, function)`.
+pub(crate) fn host_function_imports(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let header = parse(data)?;
+    Ok(undefined_symbols(header, data)?
+        .iter()
+        .filter_map(|symbol| symbol.split_once("::"))
+        .map(|(module, function)| (module.to_string(), function.to_string()))
+        .collect())
+}
+
+/// Removes all sections which are not loaded into memory from the ELF `data`, e.g. the
+/// debug information and the symbol table.
+///
+/// Everything up to the end of the last loaded segment is copied as is, only the
+/// section header table is rewritten to reference the retained sections and a new
+/// section name string table. The host function imports are retained in a new symbol
+/// table only listing them.
+pub(crate) fn strip(data: &[u8]) -> Result<Vec<u8>> {
+    let header = parse(data)?;
+    let sections = header.sections(LE, data)?;
+    if sections.is_empty() {
+        return Ok(data.to_vec())
+    }
+    let imports: Vec<_> = undefined_symbols(header, data)?
+        .into_iter()
+        .filter(|symbol| symbol.contains("::"))
+        .collect();
+
+    let truncated = || anyhow::anyhow!("The contract ELF is truncated");
+    let program_headers_end = header.e_phoff(LE) as usize
+        + header.e_phnum(LE) as usize * header.e_phentsize(LE) as usize;
+    let mut end = program_headers_end.max(mem::size_of::<FileHeader>());
+    for ph in header.program_headers(LE, data)? {
+        let segment_end = (ph.p_offset(LE) as usize)
+            .checked_add(ph.p_filesz(LE) as usize)
+            .ok_or_else(truncated)?;
+        end = end.max(segment_end);
+    }
+    let is_alloc = |section: &SectionHeader| section.sh_flags(LE) & elf::SHF_ALLOC != 0;
+    for section in sections.iter().filter(|section| is_alloc(section)) {
+        if let Some((offset, size)) = section.file_range(LE) {
+            let section_end = offset.checked_add(size).ok_or_else(truncated)?;
+            end = end.max(usize::try_from(section_end).map_err(|_| truncated())?);
+        }
+    }
+    let mut out = data.get(..end).ok_or_else(truncated)?.to_vec();
+
+    let mut names = vec![0];
+    let mut retained = vec![*sections.section(SectionIndex(0))?];
+    let mut new_indices = vec![0; sections.len()];
+    for (index, section) in sections.iter().enumerate().skip(1) {
+        if !is_alloc(section) {
+            continue
+        }
+        let name = sections.section_name(LE, section)?;
+        let mut section = *section;
+        section.sh_name = U32::new(LE, names.len() as u32);
+        names.extend_from_slice(name);
+        names.push(0);
+        new_indices[index] = retained.len() as u32;
+        retained.push(section);
+    }
+    let new_index = |index: u32| new_indices.get(index as usize).copied().unwrap_or(0);
+    for section in retained.iter_mut().skip(1) {
+        section.sh_link = U32::new(LE, new_index(section.sh_link(LE)));
+        if section.sh_flags(LE) & elf::SHF_INFO_LINK != 0 {
+            section.sh_info = U32::new(LE, new_index(section.sh_info(LE)));
+        }
+    }
+
+    if !imports.is_empty() {
+        let mut strtab = vec![0];
+        let mut symtab = vec![elf::Sym32::<LittleEndian>::default()];
+        for import in &imports {
+            let symbol = elf::Sym32::<LittleEndian> {
+                st_name: U32::new(LE, strtab.len() as u32),
+                st_info: (elf::STB_GLOBAL << 4) | elf::STT_FUNC,
+                ..Default::default()
+            };
+            strtab.extend_from_slice(import.as_bytes());
+            strtab.push(0);
+            symtab.push(symbol);
+        }
+        let mut section = |name: &[u8], sh_type, data: &[u8], align: usize| {
+            out.resize((out.len() + align - 1) & !(align - 1), 0);
+            let mut section = retained[0];
+            section.sh_name = U32::new(LE, names.len() as u32);
+            names.extend_from_slice(name);
+            section.sh_type = U32::new(LE, sh_type);
+            section.sh_offset = U32::new(LE, out.len() as u32);
+            section.sh_size = U32::new(LE, data.len() as u32);
+            section.sh_addralign = U32::new(LE, align as u32);
+            out.extend_from_slice(data);
+            section
+        };
+        let mut symtab = section(
+            b".symtab\0",
+            elf::SHT_SYMTAB,
+            pod::bytes_of_slice(&symtab),
+            4,
+        );
+        let strtab = section(b".strtab\0", elf::SHT_STRTAB, &strtab, 1);
+        symtab.sh_link = U32::new(LE, retained.len() as u32 + 1);
+        // the index of the first global symbol, following the null symbol
+        symtab.sh_info = U32::new(LE, 1);
+        symtab.sh_entsize =
+            U32::new(LE, mem::size_of::<elf::Sym32<LittleEndian>>() as u32);
+        retained.push(symtab);
+        retained.push(strtab);
+    }
+
+    let shstrndx = retained.len();
+    let mut shstrtab = retained[0];
+    shstrtab.sh_name = U32::new(LE, names.len() as u32);
+    names.extend_from_slice(b".shstrtab\0");
+    shstrtab.sh_type = U32::new(LE, elf::SHT_STRTAB);
+    shstrtab.sh_offset = U32::new(LE, out.len() as u32);
+    shstrtab.sh_size = U32::new(LE, names.len() as u32);
+    shstrtab.sh_addralign = U32::new(LE, 1);
+    out.extend_from_slice(&names);
+    retained.push(shstrtab);
+
+    out.resize((out.len() + 3) & !3, 0);
+    let mut header = *header;
+    header.e_shoff = U32::new(LE, out.len() as u32);
+    header.e_shentsize = U16::new(LE, mem::size_of::<SectionHeader>() as u16);
+    header.e_shnum = U16::new(LE, retained.len() as u16);
+    header.e_shstrndx = U16::new(LE, shstrndx as u16);
+    out.extend_from_slice(pod::bytes_of_slice(&retained));
+    out[..mem::size_of::<FileHeader>()].copy_from_slice(pod::bytes_of(&header));
+    Ok(out)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use object::pod::Pod;
+
+    fn zeroed<T: Pod>() -> T {
+        *pod::from_bytes(&vec![0; mem::size_of::<T>()]).unwrap().0
+    }
+
+    /// A section of an ELF created by [`create_elf`].
+    pub struct Section {
+        pub name: &'static str,
+        pub sh_type: u32,
+        pub sh_flags: u32,
+        pub addr: u32,
+        pub data: Vec<u8>,
+    }
+
+    /// Creates a RISC-V executable with a loaded segment spanning all allocated
+    /// sections, followed by the remaining sections and a symbol table of `symbols`
+    /// given as `(name, value, size, info, section index)`.
+    pub fn create_elf(
+        sections: &[Section],
+        symbols: &[(&str, u32, u32, u8, u16)],
+    ) -> Vec<u8> {
+        let header_size = mem::size_of::<FileHeader>();
+        let ph_size = mem::size_of::<elf::ProgramHeader32<LittleEndian>>();
+        let mut out = vec![0; header_size + ph_size];
+
+        let mut strtab = vec![0];
+        let mut symtab: Vec<elf::Sym32<LittleEndian>> = Vec::new();
+        let mut null_symbol: elf::Sym32<LittleEndian> = zeroed();
+        symtab.push(null_symbol);
+        for (name, value, size, info, shndx) in symbols {
+            null_symbol.st_name = U32::new(LE, strtab.len() as u32);
+            strtab.extend_from_slice(name.as_bytes());
+            strtab.push(0);
+            null_symbol.st_value = U32::new(LE, *value);
+            null_symbol.st_size = U32::new(LE, *size);
+            null_symbol.st_info = *info;
+            null_symbol.st_shndx = U16::new(LE, *shndx);
+            symtab.push(null_symbol);
+        }
+
+        let mut all = Vec::new();
+        for section in sections {
+            all.push((
+                section.name,
+                section.sh_type,
+                section.sh_flags,
+                section.addr,
+                section.data.clone(),
+                0,
+                0,
+            ));
+        }
+        let strtab_index = all.len() as u32 + 2;
+        all.push((
+            ".symtab",
+            elf::SHT_SYMTAB,
+            0,
+            0,
+            pod::bytes_of_slice(&symtab).to_vec(),
+            strtab_index,
+            16,
+        ));
+        all.push((".strtab", elf::SHT_STRTAB, 0, 0, strtab, 0, 0));
+        let mut shstrtab = vec![0];
+        let mut name_offsets = Vec::new();
+        for (name, ..) in all.iter().chain([&(".shstrtab", 0, 0, 0, vec![], 0, 0)]) {
+            name_offsets.push(shstrtab.len() as u32);
+            shstrtab.extend_from_slice(name.as_bytes());
+            shstrtab.push(0);
+        }
+        all.push((".shstrtab", elf::SHT_STRTAB, 0, 0, shstrtab, 0, 0));
+
+        let null_section: SectionHeader = zeroed();
+        let mut headers = vec![null_section];
+        let mut segment_end = out.len();
+        for (i, (_, sh_type, sh_flags, addr, data, link, entsize)) in
+            all.iter().enumerate()
+        {
+            let mut section = null_section;
+            section.sh_name = U32::new(LE, name_offsets[i]);
+            section.sh_type = U32::new(LE, *sh_type);
+            section.sh_flags = U32::new(LE, *sh_flags);
+            section.sh_addr = U32::new(LE, *addr);
+            section.sh_offset = U32::new(LE, out.len() as u32);
+            section.sh_size = U32::new(LE, data.len() as u32);
+            section.sh_link = U32::new(LE, *link);
+            section.sh_entsize = U32::new(LE, *entsize);
+            section.sh_addralign = U32::new(LE, 1);
+            if sh_flags & elf::SHF_ALLOC != 0 {
+                segment_end = out.len() + data.len();
+            }
+            out.extend_from_slice(data);
+            headers.push(section);
+        }
+        out.resize((out.len() + 3) & !3, 0);
+        let shoff = out.len();
+        out.extend_from_slice(pod::bytes_of_slice(&headers));
+
+        let mut ph: elf::ProgramHeader32<LittleEndian> = zeroed();
+        ph.p_type = U32::new(LE, elf::PT_LOAD);
+        ph.p_flags = U32::new(LE, elf::PF_R | elf::PF_X);
+        ph.p_filesz = U32::new(LE, segment_end as u32);
+        ph.p_memsz = U32::new(LE, segment_end as u32);
+        out[header_size..header_size + ph_size].copy_from_slice(pod::bytes_of(&ph));
+
+        let mut header: FileHeader = zeroed();
+        header.e_ident.magic = elf::ELFMAG;
+        header.e_ident.class = elf::ELFCLASS32;
+        header.e_ident.data = elf::ELFDATA2LSB;
+        header.e_ident.version = elf::EV_CURRENT;
+        header.e_type = U16::new(LE, elf::ET_EXEC);
+        header.e_machine = U16::new(LE, elf::EM_RISCV);
+        header.e_version = U32::new(LE, elf::EV_CURRENT as u32);
+        header.e_entry = U32::new(LE, header_size as u32 + ph_size as u32);
+        header.e_phoff = U32::new(LE, header_size as u32);
+        header.e_shoff = U32::new(LE, shoff as u32);
+        header.e_ehsize = U16::new(LE, header_size as u16);
+        header.e_phentsize = U16::new(LE, ph_size as u16);
+        header.e_phnum = U16::new(LE, 1);
+        header.e_shentsize = U16::new(LE, mem::size_of::<SectionHeader>() as u16);
+        header.e_shnum = U16::new(LE, headers.len() as u16);
+        header.e_shstrndx = U16::new(LE, headers.len() as u16 - 1);
+        out[..header_size].copy_from_slice(pod::bytes_of(&header));
+        out
+    }
+
+    /// Creates a contract with a single function and a debug section.
+    pub fn create_contract(symbols: &[(&str, u32, u32, u8, u16)]) -> Vec<u8> {
+        create_elf(
+            &[
+                Section {
+                    name: ".text",
+                    sh_type: elf::SHT_PROGBITS,
+                    sh_flags: elf::SHF_ALLOC | elf::SHF_EXECINSTR,
+                    addr: 84,
+                    data: vec![0x73, 0, 0, 0, 0x67, 0x80, 0, 0],
+                },
+                Section {
+                    name: ".rodata",
+                    sh_type: elf::SHT_PROGBITS,
+                    sh_flags: elf::SHF_ALLOC,
+                    addr: 92,
+                    data: b"flipper".to_vec(),
+                },
+                Section {
+                    name: ".debug_info",
+                    sh_type: elf::SHT_PROGBITS,
+                    sh_flags: 0,
+                    addr: 0,
+                    data: vec![0xff; 1024],
+                },
+            ],
+            symbols,
+        )
+    }
+
+    /// Symbol info of a global function.
+    pub const GLOBAL_FUNC: u8 = (elf::STB_GLOBAL << 4) | elf::STT_FUNC;
+
+    fn section_names(data: &[u8]) -> Vec<String> {
+        let header = parse(data).unwrap();
+        let sections = header.sections(LE, data).unwrap();
+        sections
+            .iter()
+            .map(|section| {
+                String::from_utf8(sections.section_name(LE, section).unwrap().to_vec())
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_contract_passes_validation() {
+        // given
+        let elf = create_contract(&[("_start", 84, 8, GLOBAL_FUNC, 1)]);
+
+        // when
+        let res = validate(&elf);
+
+        // then
+        assert!(res.is_ok(), "{res:?}");
+    }
+
+    #[test]
+    fn must_reject_other_machines() {
+        // given
+        let mut elf = create_contract(&[]);
+        elf[18..20].copy_from_slice(&elf::EM_386.to_le_bytes());
+
+        // when
+        let res = parse(&elf);
+
+        // then
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("The contract code is not built for RISC-V"));
+    }
+
+    #[test]
+    fn must_catch_undefined_symbols() {
+        // given
+        let elf = create_contract(&[
+            ("_start", 84, 8, GLOBAL_FUNC, 1),
+            ("some_fn", 0, 0, GLOBAL_FUNC, elf::SHN_UNDEF),
+        ]);
+
+        // when
+        let res = validate(&elf);
+
+        // then
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("An undefined symbol was found in the contract ELF: some_fn."));
+    }
+
+    #[test]
+    fn host_function_imports_pass_validation() {
+        // given
+        let elf = create_contract(&[
+            ("_start", 84, 8, GLOBAL_FUNC, 1),
+            ("seal0::seal_input", 0, 0, GLOBAL_FUNC, elf::SHN_UNDEF),
+            ("env::seal_input", 0, 0, GLOBAL_FUNC, elf::SHN_UNDEF),
+        ]);
+
+        // when
+        let res = validate(&elf);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains(
+            "An unexpected host function import was found in the contract ELF: \
+            env::seal_input."
+        ));
+        assert!(!err.contains("seal0::seal_input"));
+        assert_eq!(
+            host_function_imports(&elf).unwrap(),
+            [
+                ("seal0".to_string(), "seal_input".to_string()),
+                ("env".to_string(), "seal_input".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn strip_rejects_segments_beyond_the_address_space() {
+        // given
+        let mut elf = create_contract(&[("_start", 84, 8, GLOBAL_FUNC, 1)]);
+        // the offset of the loaded segment
+        elf[56..60].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        // when
+        let res = strip(&elf);
+
+        // then
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("The contract ELF is truncated"));
+    }
+
+    #[test]
+    fn must_catch_ink_enforce_error_marker() {
+        // given
+        let elf = create_contract(&[(
+            "__ink_enforce_error_0x0210466c69700c6e657740d75d74",
+            0,
+            0,
+            GLOBAL_FUNC,
+            elf::SHN_UNDEF,
+        )]);
+
+        // when
+        let res = validate(&elf);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains(
+            "The ink! constructor `Flip::new` with the selector `0x40d75d74` contains an invalid trait call."
+        ));
+        assert!(!err.contains("An undefined symbol was found"));
+    }
+
+    #[test]
+    fn strip_removes_sections_which_are_not_loaded() {
+        // given
+        let elf = create_contract(&[("_start", 84, 8, GLOBAL_FUNC, 1)]);
+
+        // when
+        let stripped = strip(&elf).unwrap();
+
+        // then
+        assert!(stripped.len() < elf.len() - 1024);
+        assert_eq!(
+            section_names(&stripped),
+            ["", ".text", ".rodata", ".shstrtab"]
+        );
+        let header = parse(&stripped).unwrap();
+        let sections = header.sections(LE, &stripped[..]).unwrap();
+        let rodata = sections.section_by_name(LE, b".rodata").unwrap().1;
+        assert_eq!(rodata.data(LE, &stripped[..]).unwrap(), b"flipper");
+        // the program header and the loaded segment are copied as is
+        assert_eq!(&stripped[52..99], &elf[52..99]);
+        assert!(validate(&stripped).is_ok());
+    }
+
+    #[test]
+    fn strip_retains_host_function_imports() {
+        // given
+        let elf = create_contract(&[
+            ("_start", 84, 8, GLOBAL_FUNC, 1),
+            ("seal0::seal_input", 0, 0, GLOBAL_FUNC, elf::SHN_UNDEF),
+        ]);
+
+        // when
+        let stripped = strip(&elf).unwrap();
+
+        // then
+        assert_eq!(
+            section_names(&stripped),
+            ["", ".text", ".rodata", ".symtab", ".strtab", ".shstrtab"]
+        );
+        assert_eq!(
+            host_function_imports(&stripped).unwrap(),
+            [("seal0".to_string(), "seal_input".to_string())]
+        );
+        assert!(validate(&stripped).is_ok());
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Machine readable build events.
+//!
+//! With [`MessageFormat::Json`] the progress of a build is streamed to stdout as
+//! newline delimited JSON, one [`BuildEvent`] per line. Similar to cargo's own JSON
+//! messages, each event is tagged with a `reason` field.
+
+use crate::{
+    maybe_println,
+    BuildResult,
+    BuildSteps,
+    MessageFormat,
+    MetadataArtifacts,
+    OptimizationResult,
+    Verbosity,
+};
+use anyhow::Result;
+use colored::Colorize;
+
+/// An event emitted during a build.
+#[derive(serde::Serialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+pub enum BuildEvent<'a> {
+    /// A build step has been started.
+    StepStarted {
+        step: usize,
+        total_steps: Option<usize>,
+        description: &'a str,
+    },
+    /// A build step has been finished successfully.
+    StepFinished {
+        step: usize,
+        total_steps: Option<usize>,
+        description: &'a str,
+    },
+    /// A diagnostic emitted by the compiler, as forwarded from cargo.
+    CompilerMessage {
+        /// The id of the package the diagnostic originates from.
+        package_id: String,
+        /// The diagnostic in the JSON format of `rustc`.
+        message: serde_json::Value,
+    },
+    /// The result of optimizing the contract with `wasm-opt`.
+    WasmOptResult(&'a OptimizationResult),
+    /// The paths of the generated metadata artifacts.
+    MetadataArtifacts(&'a MetadataArtifacts),
+    /// The final result of the build.
+    BuildFinished(&'a BuildResult),
+}
+
+impl BuildEvent<'_> {
+    /// Prints the event as a single line of JSON, if the `message_format` is JSON.
+    pub fn emit(&self, message_format: MessageFormat) -> Result<()> {
+        if message_format == MessageFormat::Json {
+            println!("{}", serde_json::to_string(self)?);
+        }
+        Ok(())
+    }
+}
+
+/// A message printed by cargo invoked with `--message-format=json`.
+#[derive(serde::Deserialize)]
+struct CargoMessage {
+    reason: String,
+    #[serde(default)]
+    package_id: String,
+    #[serde(default)]
+    message: serde_json::Value,
+}
+
+/// Parses a line of cargo's JSON output, returning the contained compiler message.
+///
+/// All other messages, e.g. about compiled artifacts, are ignored.
+pub(crate) fn compiler_message(line: &str) -> Option<BuildEvent<'static>> {
+    let message: CargoMessage = serde_json::from_str(line).ok()?;
+    (message.reason == "compiler-message").then_some(BuildEvent::CompilerMessage {
+        package_id: message.package_id,
+        message: message.message,
+    })
+}
+
+/// A build step which has been started, used to report its completion.
+#[must_use]
+pub(crate) struct Step {
+    steps: BuildSteps,
+    description: &'static str,
+    message_format: MessageFormat,
+}
+
+impl Step {
+    /// Reports the start of the current build step.
+    pub(crate) fn start(
+        steps: BuildSteps,
+        description: &'static str,
+        verbosity: Verbosity,
+        message_format: MessageFormat,
+    ) -> Result<Self> {
+        match message_format {
+            MessageFormat::Human => {
+                maybe_println!(
+                    verbosity,
+                    " {} {}",
+                    format!("{steps}").bold(),
+                    description.bright_green().bold()
+                );
+            }
+            MessageFormat::Json => {
+                BuildEvent::StepStarted {
+                    step: steps.current_step,
+                    total_steps: steps.total_steps,
+                    description,
+                }
+                .emit(message_format)?
+            }
+        }
+        Ok(Self {
+            steps,
+            description,
+            message_format,
+        })
+    }
+
+    /// Reports the successful completion of the build step.
+    pub(crate) fn finish(self) -> Result<()> {
+        BuildEvent::StepFinished {
+            step: self.steps.current_step,
+            total_steps: self.steps.total_steps,
+            description: self.description,
+        }
+        .emit(self.message_format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn events_are_tagged_with_reason() {
+        // given
+        let event = BuildEvent::StepStarted {
+            step: 1,
+            total_steps: Some(4),
+            description: "Building cargo project",
+        };
+
+        // when
+        let json = serde_json::to_string(&event).unwrap();
+
+        // then
+        assert_eq!(
+            json,
+            r#"{"reason":"step-started","step":1,"total_steps":4,"description":"Building cargo project"}"#
+        );
+    }
+
+    #[test]
+    fn compiler_messages_are_forwarded() {
+        // given
+        let line = r#"{"reason":"compiler-message","package_id":"flipper 0.1.0","target":{},"message":{"code":null,"level":"warning","message":"unused variable: `x`"}}"#;
+
+        // when
+        let event = compiler_message(line).expect("compiler message must be parsed");
+
+        // then
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"reason":"compiler-message","package_id":"flipper 0.1.0","message":{"code":null,"level":"warning","message":"unused variable: `x`"}}"#
+        );
+    }
+
+    #[test]
+    fn other_cargo_messages_are_ignored() {
+        let line = r#"{"reason":"build-finished","success":true}"#;
+        assert!(compiler_message(line).is_none());
+        assert!(compiler_message("not json").is_none());
+    }
+}
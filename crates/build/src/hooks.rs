@@ -0,0 +1,339 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Commands run at defined points of a contract build.
+//!
+//! The hooks are declared in the `[package.metadata.contract.hooks]` section of the
+//! contract's `Cargo.toml`, each hook is either a single command or a list of commands:
+//!
+//! ```toml
+//! [package.metadata.contract.hooks]
+//! # before `cargo build` is invoked
+//! pre-build = "./scripts/check-env.sh"
+//! # after the contract code has been post processed and optimized
+//! post-wasm = ["./scripts/add-section.sh", "./scripts/sign.sh"]
+//! # after the metadata and the bundle have been written
+//! post-metadata = "./scripts/upload.sh"
+//! ```
+//!
+//! The commands are run by the system shell in the directory of the manifest, the
+//! paths of the build artifacts are passed via `CONTRACT_*` environment variables. A
+//! failing command aborts the build.
+
+use crate::{
+    events::Step,
+    BuildArtifacts,
+    BuildMode,
+    BuildSteps,
+    CrateMetadata,
+    MessageFormat,
+    Target,
+    Verbosity,
+};
+use anyhow::Result;
+use contract_metadata::SourceTarget;
+use std::{
+    ffi::OsString,
+    path::Path,
+};
+
+/// The point of a build at which the commands of a hook are run.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Hook {
+    /// Before the contract is compiled by `cargo`.
+    PreBuild,
+    /// After the contract code has been post processed and optimized.
+    PostWasm,
+    /// After the metadata and the contract bundle have been written.
+    PostMetadata,
+}
+
+impl Hook {
+    /// The name of the hook, as used in the `Cargo.toml`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PreBuild => "pre-build",
+            Self::PostWasm => "post-wasm",
+            Self::PostMetadata => "post-metadata",
+        }
+    }
+
+    /// The description of the build step running the hook.
+    fn step_description(&self) -> &'static str {
+        match self {
+            Self::PreBuild => "Running pre-build hooks",
+            Self::PostWasm => "Running post-wasm hooks",
+            Self::PostMetadata => "Running post-metadata hooks",
+        }
+    }
+}
+
+/// The commands of a hook, declared either as a single string or as a list.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(from = "OneOrMany")]
+pub struct HookCommands(pub Vec<String>);
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for HookCommands {
+    fn from(commands: OneOrMany) -> Self {
+        match commands {
+            OneOrMany::One(command) => Self(vec![command]),
+            OneOrMany::Many(commands) => Self(commands),
+        }
+    }
+}
+
+/// The build hooks of a contract, as declared in its `Cargo.toml`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BuildHooks {
+    /// Run before the contract is compiled by `cargo`.
+    #[serde(default)]
+    pub pre_build: HookCommands,
+    /// Run after the contract code has been post processed and optimized.
+    #[serde(default)]
+    pub post_wasm: HookCommands,
+    /// Run after the metadata and the contract bundle have been written.
+    #[serde(default)]
+    pub post_metadata: HookCommands,
+}
+
+impl BuildHooks {
+    /// Returns the commands declared for the `hook`.
+    pub fn commands(&self, hook: Hook) -> &[String] {
+        match hook {
+            Hook::PreBuild => &self.pre_build.0,
+            Hook::PostWasm => &self.post_wasm.0,
+            Hook::PostMetadata => &self.post_metadata.0,
+        }
+    }
+
+    /// Returns the number of build steps added by the hooks which are run when
+    /// building the `build_artifact`.
+    pub fn steps(&self, build_artifact: BuildArtifacts) -> usize {
+        let hooks: &[Hook] = match build_artifact {
+            BuildArtifacts::All => &[Hook::PreBuild, Hook::PostWasm, Hook::PostMetadata],
+            BuildArtifacts::CodeOnly => &[Hook::PreBuild, Hook::PostWasm],
+            BuildArtifacts::CheckOnly => &[Hook::PreBuild],
+        };
+        hooks
+            .iter()
+            .filter(|hook| !self.commands(**hook).is_empty())
+            .count()
+    }
+}
+
+/// Runs the commands of the `hook` as a separate build step, if any are declared.
+pub(crate) fn run(
+    hook: Hook,
+    crate_metadata: &CrateMetadata,
+    target: Target,
+    build_mode: BuildMode,
+    build_steps: &mut BuildSteps,
+    verbosity: Verbosity,
+    message_format: MessageFormat,
+) -> Result<()> {
+    let commands = crate_metadata.hooks.commands(hook);
+    if commands.is_empty() {
+        return Ok(())
+    }
+    let step = Step::start(
+        *build_steps,
+        hook.step_description(),
+        verbosity,
+        message_format,
+    )?;
+    build_steps.increment_current();
+
+    // the commands are run in the directory of the manifest, hence all paths are
+    // passed as absolute paths
+    let working_dir = crate_metadata.manifest_path.absolute_directory()?;
+    let manifest_path = std::fs::canonicalize(&crate_metadata.manifest_path)?;
+    let mut env: Vec<(&str, OsString)> = vec![
+        (
+            "CONTRACT_NAME",
+            crate_metadata.contract_artifact_name.clone().into(),
+        ),
+        ("CONTRACT_MANIFEST_PATH", manifest_path.into()),
+        (
+            "CONTRACT_TARGET_DIR",
+            crate_metadata.target_directory.clone().into(),
+        ),
+        (
+            "CONTRACT_TARGET",
+            SourceTarget::from(target).to_string().into(),
+        ),
+        ("CONTRACT_BUILD_MODE", build_mode.to_string().into()),
+    ];
+    if hook != Hook::PreBuild {
+        env.push((
+            "CONTRACT_CODE_PATH",
+            crate_metadata.dest_code.clone().into(),
+        ));
+    }
+    if hook == Hook::PostMetadata {
+        env.push((
+            "CONTRACT_METADATA_PATH",
+            crate_metadata.metadata_path().into(),
+        ));
+        env.push((
+            "CONTRACT_BUNDLE_PATH",
+            crate_metadata.contract_bundle_path().into(),
+        ));
+    }
+
+    run_commands(hook, commands, &working_dir, &env, verbosity)?;
+    step.finish()
+}
+
+/// Runs the `commands` one after another by the system shell, stopping at the first
+/// failing one.
+fn run_commands(
+    hook: Hook,
+    commands: &[String],
+    working_dir: &Path,
+    env: &[(&str, OsString)],
+    verbosity: Verbosity,
+) -> Result<()> {
+    for command in commands {
+        tracing::debug!("Running `{}` hook `{}`", hook.name(), command);
+        let mut cmd = shell(command).dir(working_dir).unchecked();
+        for (key, value) in env {
+            cmd = cmd.env(key, value);
+        }
+        // the stdout is reserved for the build result, e.g. when printed as JSON
+        cmd = if verbosity.is_verbose() {
+            cmd.stdout_to_stderr()
+        } else {
+            cmd.stdout_null()
+        };
+        let output = cmd.run()?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "The `{}` hook `{}` failed with {}",
+                hook.name(),
+                command,
+                output.status
+            )
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn shell(command: &str) -> duct::Expression {
+    duct::cmd("sh", ["-c", command])
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> duct::Expression {
+    duct::cmd("cmd", ["/C", command])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn hooks_are_parsed_from_toml() {
+        // given
+        let toml = r#"
+            pre-build = "./check-env.sh"
+            post-wasm = ["./add-section.sh", "./sign.sh"]
+        "#;
+
+        // when
+        let hooks: BuildHooks = toml::from_str(toml).unwrap();
+
+        // then
+        assert_eq!(hooks.commands(Hook::PreBuild), ["./check-env.sh"]);
+        assert_eq!(
+            hooks.commands(Hook::PostWasm),
+            ["./add-section.sh", "./sign.sh"]
+        );
+        assert!(hooks.commands(Hook::PostMetadata).is_empty());
+        assert_eq!(hooks.steps(BuildArtifacts::All), 2);
+        assert_eq!(hooks.steps(BuildArtifacts::CheckOnly), 1);
+    }
+
+    #[test]
+    fn unknown_hooks_are_rejected() {
+        let res = toml::from_str::<BuildHooks>(r#"post-build = "./sign.sh""#);
+        assert!(res.is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn artifact_paths_are_passed_to_the_commands() {
+        // given
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("cargo-contract.test.")
+            .tempdir()
+            .unwrap();
+        let env = [("CONTRACT_CODE_PATH", OsString::from("flipper.wasm"))];
+        let commands = [r#"echo "$CONTRACT_CODE_PATH" > code-path.txt"#.to_string()];
+
+        // when
+        run_commands(
+            Hook::PostWasm,
+            &commands,
+            tmp_dir.path(),
+            &env,
+            Verbosity::Quiet,
+        )
+        .unwrap();
+
+        // then
+        let code_path =
+            std::fs::read_to_string(tmp_dir.path().join("code-path.txt")).unwrap();
+        assert_eq!(code_path, "flipper.wasm\n");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_command_aborts_the_hook() {
+        // given
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("cargo-contract.test.")
+            .tempdir()
+            .unwrap();
+        let commands = ["exit 3".to_string(), "touch not-reached.txt".to_string()];
+
+        // when
+        let res = run_commands(
+            Hook::PostMetadata,
+            &commands,
+            tmp_dir.path(),
+            &[],
+            Verbosity::Quiet,
+        );
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(
+            err.starts_with("The `post-metadata` hook `exit 3` failed with"),
+            "{err}"
+        );
+        assert!(!tmp_dir.path().join("not-reached.txt").exists());
+    }
+}
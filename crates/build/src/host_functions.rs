@@ -0,0 +1,259 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Compatibility of the host functions imported by a contract with a chain.
+//!
+//! The host functions exposed by a version of `pallet-contracts` are listed in a JSON
+//! file, mapping each module to the names of its functions:
+//!
+//! ```json
+//! {
+//!   "seal0": ["seal_input", "seal_return", "seal_set_storage"],
+//!   "seal1": ["seal_call", "seal_instantiate"],
+//!   "seal2": ["seal_call"]
+//! }
+//! ```
+//!
+//! The runtime metadata of a chain does not describe its host functions, hence they
+//! can't be taken from it.
+
+use crate::elf;
+use anyhow::{
+    Context,
+    Result,
+};
+use parity_wasm::elements::{
+    External,
+    Module,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fs,
+    path::Path,
+};
+
+/// The host functions provided by a chain, by module.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct HostFunctions(BTreeMap<String, BTreeSet<String>>);
+
+impl HostFunctions {
+    /// Loads the host functions from the JSON file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| {
+            format!(
+                "Failed to deserialize the host functions of {}",
+                path.display()
+            )
+        })
+    }
+
+    /// Returns `true` if the function `name` of the `module` is provided.
+    pub fn contains(&self, module: &str, name: &str) -> bool {
+        self.0
+            .get(module)
+            .is_some_and(|functions| functions.contains(name))
+    }
+}
+
+impl<M, F> FromIterator<(M, F)> for HostFunctions
+where
+    M: Into<String>,
+    F: Into<String>,
+{
+    fn from_iter<I: IntoIterator<Item = (M, F)>>(iter: I) -> Self {
+        let mut host_functions = BTreeMap::<String, BTreeSet<String>>::new();
+        for (module, name) in iter {
+            host_functions
+                .entry(module.into())
+                .or_default()
+                .insert(name.into());
+        }
+        Self(host_functions)
+    }
+}
+
+/// Checks that all functions imported by the Wasm `module` are provided by the chain,
+/// fails with the list of the missing ones otherwise.
+pub fn check_host_functions(
+    module: &Module,
+    host_functions: &HostFunctions,
+) -> Result<()> {
+    let imports = module
+        .import_section()
+        .map(|section| section.entries())
+        .unwrap_or_default()
+        .iter()
+        .filter(|import| matches!(import.external(), External::Function(_)))
+        .map(|import| (import.module(), import.field()));
+    check_imports(imports, host_functions)
+}
+
+/// Checks that all host functions imported by the contract ELF `code` built for RISC-V
+/// are provided by the chain, fails with the list of the missing ones otherwise.
+pub fn check_elf_host_functions(
+    code: &[u8],
+    host_functions: &HostFunctions,
+) -> Result<()> {
+    let imports = elf::host_function_imports(code)?;
+    check_imports(
+        imports
+            .iter()
+            .map(|(module, function)| (module.as_str(), function.as_str())),
+        host_functions,
+    )
+}
+
+fn check_imports<'a, I>(imports: I, host_functions: &HostFunctions) -> Result<()>
+where
+    I: Iterator<Item = (&'a str, &'a str)>,
+{
+    let missing = imports
+        .filter(|(module, function)| !host_functions.contains(module, function))
+        .map(|(module, function)| format!("\n  - {module}::{function}"))
+        .collect::<String>();
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "The contract imports host functions which are not provided by the chain:\
+            {missing}\n\n\
+            The chain likely runs an older version of `pallet-contracts`, consider \
+            using an ink! version compatible with it."
+        )
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use pretty_assertions::assert_eq;
+
+    fn create_module(imports: &[(&str, &str)]) -> Module {
+        let mut module = builder::module()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .import()
+            .module("env")
+            .field("memory")
+            .external()
+            .memory(2, Some(16))
+            .build();
+        for (module_name, field) in imports {
+            module = module
+                .import()
+                .module(module_name)
+                .field(field)
+                .external()
+                .func(0)
+                .build();
+        }
+        module.build()
+    }
+
+    #[test]
+    fn missing_host_functions_of_elf_contracts_are_reported() {
+        // given
+        let host_functions: HostFunctions =
+            [("seal0", "seal_input")].into_iter().collect();
+        let elf = elf::tests::create_contract(&[
+            ("_start", 84, 8, elf::tests::GLOBAL_FUNC, 1),
+            ("seal0::seal_input", 0, 0, elf::tests::GLOBAL_FUNC, 0),
+            ("seal1::seal_call", 0, 0, elf::tests::GLOBAL_FUNC, 0),
+        ]);
+
+        // when
+        let res = check_elf_host_functions(&elf, &host_functions);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("- seal1::seal_call"));
+        assert!(!err.contains("seal0::seal_input"));
+    }
+
+    #[test]
+    fn host_functions_are_loaded_from_json() {
+        // given
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("cargo-contract.test.")
+            .tempdir()
+            .unwrap();
+        let path = tmp_dir.path().join("host-functions.json");
+        fs::write(
+            &path,
+            r#"{"seal0": ["seal_input"], "seal1": ["seal_call"]}"#,
+        )
+        .unwrap();
+
+        // when
+        let host_functions = HostFunctions::load(&path).unwrap();
+
+        // then
+        assert_eq!(
+            host_functions,
+            [("seal0", "seal_input"), ("seal1", "seal_call")]
+                .into_iter()
+                .collect()
+        );
+    }
+
+    #[test]
+    fn provided_host_functions_are_accepted() {
+        let host_functions: HostFunctions =
+            [("seal0", "seal_input"), ("seal1", "seal_call")]
+                .into_iter()
+                .collect();
+        let module = create_module(&[("seal0", "seal_input"), ("seal1", "seal_call")]);
+        assert!(check_host_functions(&module, &host_functions).is_ok());
+    }
+
+    #[test]
+    fn missing_host_functions_are_reported() {
+        // given
+        let host_functions: HostFunctions =
+            [("seal0", "seal_input"), ("seal1", "seal_call")]
+                .into_iter()
+                .collect();
+        let module = create_module(&[
+            ("seal0", "seal_input"),
+            ("seal2", "seal_call"),
+            ("seal0", "seal_caller_is_root"),
+        ]);
+
+        // when
+        let res = check_host_functions(&module, &host_functions);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(
+            err.starts_with(
+                "The contract imports host functions which are not provided by the \
+                chain:\n  - seal2::seal_call\n  - seal0::seal_caller_is_root\n"
+            ),
+            "{err}"
+        );
+    }
+}
@@ -17,12 +17,18 @@
 #![doc = include_str!("../README.md")]
 #![deny(unused_crate_dependencies)]
 
-use which as _;
-
 mod args;
+mod cache;
 mod crate_metadata;
+mod docker;
+mod elf;
+mod events;
+mod hooks;
+mod host_functions;
 pub mod metadata;
 mod new;
+mod size_budget;
+mod size_report;
 #[cfg(test)]
 mod tests;
 pub mod util;
@@ -39,8 +45,10 @@ pub use self::{
         BuildMode,
         BuildSteps,
         Features,
+        MessageFormat,
         Network,
         OutputType,
+        Packages,
         Target,
         UnstableFlags,
         UnstableOptions,
@@ -48,12 +56,41 @@ pub use self::{
         VerbosityFlags,
     },
     crate_metadata::CrateMetadata,
+    docker::{
+        ImageVariant,
+        DEFAULT_IMAGE,
+    },
+    events::BuildEvent,
+    hooks::{
+        BuildHooks,
+        Hook,
+        HookCommands,
+    },
+    host_functions::{
+        check_elf_host_functions,
+        check_host_functions,
+        HostFunctions,
+    },
     metadata::{
         BuildInfo,
         MetadataArtifacts,
         WasmOptSettings,
     },
     new::new_contract_project,
+    size_budget::{
+        BudgetEnforcement,
+        BudgetUsage,
+        SizeBudget,
+        SizeBudgetResult,
+    },
+    size_report::{
+        CrateSize,
+        DataSegmentSize,
+        FunctionSize,
+        SizeDiff,
+        SizeReport,
+        SizeReportDiff,
+    },
     util::DEFAULT_KEY_COL_WIDTH,
     wasm_opt::{
         OptimizationPasses,
@@ -67,7 +104,16 @@ pub use self::{
     },
 };
 
-use crate::wasm_opt::WasmOptHandler;
+use crate::{
+    cache::{
+        local_packages,
+        sources_hash,
+        CacheEntry,
+        CacheKey,
+    },
+    events::Step,
+    wasm_opt::WasmOptHandler,
+};
 
 use anyhow::{
     Context,
@@ -99,7 +145,7 @@ use strum::IntoEnumIterator;
 pub const DEFAULT_MAX_MEMORY_PAGES: u32 = 16;
 
 /// Version of the currently executing `cargo-contract` binary.
-const VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Arguments to use when executing `build` or `check` commands.
 #[derive(Clone)]
@@ -116,9 +162,32 @@ pub struct ExecuteArgs {
     pub keep_debug_symbols: bool,
     pub lint: bool,
     pub output_type: OutputType,
+    /// The format of the messages printed while building.
+    pub message_format: MessageFormat,
     pub skip_wasm_validation: bool,
     pub target: Target,
     pub max_memory_pages: u32,
+    /// Build the contract inside of a pinned container image, so that it can be
+    /// reproduced byte-for-byte by third parties.
+    pub verifiable: bool,
+    /// The image to use for a verifiable build.
+    pub image: ImageVariant,
+    /// The repository digest of the image the build is executed in.
+    ///
+    /// Only set for the build inside of the container of a verifiable build, it is
+    /// recorded in the [`BuildInfo`] of the contract.
+    pub build_image: Option<String>,
+    /// Generate a report attributing the size of the contract code to its functions,
+    /// data segments and crates.
+    pub size_report: bool,
+    /// A previously generated size report to compare the size report against.
+    pub size_report_baseline: Option<PathBuf>,
+    /// The directory of a cache of the post processed code and the ink! metadata,
+    /// which can be shared between builds, e.g. by CI runners or worktrees.
+    pub cache_dir: Option<PathBuf>,
+    /// The host functions provided by the chain the contract is built for, the imports
+    /// of a Wasm contract are checked against them.
+    pub host_functions: Option<HostFunctions>,
 }
 
 impl Default for ExecuteArgs {
@@ -135,15 +204,23 @@ impl Default for ExecuteArgs {
             keep_debug_symbols: Default::default(),
             lint: Default::default(),
             output_type: Default::default(),
+            message_format: Default::default(),
             skip_wasm_validation: Default::default(),
             target: Default::default(),
             max_memory_pages: DEFAULT_MAX_MEMORY_PAGES,
+            verifiable: Default::default(),
+            image: Default::default(),
+            build_image: Default::default(),
+            size_report: Default::default(),
+            size_report_baseline: Default::default(),
+            cache_dir: Default::default(),
+            host_functions: Default::default(),
         }
     }
 }
 
 /// Result of the build process.
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct BuildResult {
     /// Path to the resulting Wasm file.
     pub dest_wasm: Option<PathBuf>,
@@ -160,8 +237,17 @@ pub struct BuildResult {
     /// The verbosity flags.
     pub verbosity: Verbosity,
     /// The type of formatting to use for the build output.
-    #[serde(skip_serializing)]
+    #[serde(skip)]
     pub output_type: OutputType,
+    /// If requested, the size report of the contract code.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_report: Option<SizeReport>,
+    /// If a baseline was supplied, the difference of the size report to it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_report_diff: Option<SizeReportDiff>,
+    /// If declared in the manifest, the usage of the contract's size budget.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_budget: Option<SizeBudgetResult>,
 }
 
 impl BuildResult {
@@ -186,8 +272,8 @@ impl BuildResult {
             format!("{}", self.build_mode).to_uppercase().bold(),
         );
 
-        if self.build_artifact == BuildArtifacts::CodeOnly {
-            let out = format!(
+        let mut out = if self.build_artifact == BuildArtifacts::CodeOnly {
+            format!(
                 "{}{}Your contract's code is ready. You can find it here:\n{}",
                 opt_size_diff,
                 build_mode,
@@ -197,10 +283,23 @@ impl BuildResult {
                     .display()
                     .to_string()
                     .bold()
-            );
-            return out
+            )
+        } else {
+            self.display_artifacts(opt_size_diff, build_mode)
         };
+        if let Some(size_report) = self.size_report.as_ref() {
+            out.push_str(&format!("\n\n{}", size_report.display()));
+        }
+        if let Some(size_report_diff) = self.size_report_diff.as_ref() {
+            out.push_str(&format!("\n{}", size_report_diff.display()));
+        }
+        if let Some(size_budget) = self.size_budget.as_ref() {
+            out.push_str(&format!("\n\n{}", size_budget.display()));
+        }
+        out
+    }
 
+    fn display_artifacts(&self, opt_size_diff: String, build_mode: String) -> String {
         let mut out = format!(
             "{}{}Your contract artifacts are ready. You can find them in:\n{}\n\n",
             opt_size_diff,
@@ -235,6 +334,28 @@ impl BuildResult {
     pub fn serialize_json(&self) -> Result<String> {
         Ok(serde_json::to_string_pretty(self)?)
     }
+
+    /// Replaces the `from` prefix of all artifact paths with `to`.
+    ///
+    /// Used to translate the paths of a build executed in a container to the host.
+    fn map_paths(&mut self, from: &Path, to: &Path) {
+        let map = |path: &mut PathBuf| {
+            if let Ok(relative) = path.strip_prefix(from) {
+                *path = to.join(relative)
+            }
+        };
+        if let Some(dest_wasm) = self.dest_wasm.as_mut() {
+            map(dest_wasm)
+        }
+        if let Some(metadata_result) = self.metadata_result.as_mut() {
+            map(&mut metadata_result.dest_metadata);
+            map(&mut metadata_result.dest_bundle);
+        }
+        if let Some(optimization_result) = self.optimization_result.as_mut() {
+            map(&mut optimization_result.dest_wasm)
+        }
+        map(&mut self.target_directory);
+    }
 }
 
 /// Executes the supplied cargo command on the project in the specified directory,
@@ -268,11 +389,13 @@ fn exec_cargo_for_onchain_target(
     verbosity: Verbosity,
     unstable_flags: &UnstableFlags,
     target: Target,
+    message_format: MessageFormat,
+    remap_paths: bool,
 ) -> Result<()> {
     let cargo_build = |manifest_path: &ManifestPath| {
         let target_dir = format!(
             "--target-dir={}",
-            crate_metadata.target_directory.to_string_lossy()
+            crate_metadata.cargo_target_directory.to_string_lossy()
         );
 
         let mut args = vec![
@@ -283,6 +406,9 @@ fn exec_cargo_for_onchain_target(
             target_dir,
         ];
         network.append_to_args(&mut args);
+        if message_format == MessageFormat::Json {
+            args.push("--message-format=json".to_owned());
+        }
 
         let mut features = features.clone();
         if build_mode == BuildMode::Debug {
@@ -297,7 +423,14 @@ fn exec_cargo_for_onchain_target(
             env.push(("RUSTC_BOOTSTRAP", Some("1".to_string())))
         }
         // the linker needs our linker script as file
-        let rustflags = target.rustflags();
+        let mut rustflags = target.rustflags().to_string();
+        if remap_paths {
+            // normalize the paths embedded into the binary for verifiable builds
+            if let Some(flag) = docker::remap_path_prefix_flag() {
+                rustflags.push('\x1f');
+                rustflags.push_str(&flag);
+            }
+        }
         if matches!(target, Target::RiscV) {
             fs::create_dir_all(&crate_metadata.target_directory)?;
             let path = crate_metadata
@@ -311,14 +444,17 @@ fn exec_cargo_for_onchain_target(
             ));
             Some(path)
         } else {
-            env.push(("CARGO_ENCODED_RUSTFLAGS", Some(rustflags.to_string())));
+            env.push(("CARGO_ENCODED_RUSTFLAGS", Some(rustflags)));
             None
         };
 
         let cargo =
             util::cargo_cmd(command, &args, manifest_path.directory(), verbosity, env);
 
-        invoke_cargo_and_scan_for_error(cargo)
+        match message_format {
+            MessageFormat::Human => invoke_cargo_and_scan_for_error(cargo),
+            MessageFormat::Json => invoke_cargo_and_forward_messages(cargo),
+        }
     };
 
     if unstable_flags.original_manifest {
@@ -392,6 +528,29 @@ fn invoke_cargo_and_scan_for_error(cargo: duct::Expression) -> Result<()> {
     Ok(())
 }
 
+/// Executes the supplied cargo command with `--message-format=json`, forwarding the
+/// compiler messages as [`BuildEvent::CompilerMessage`].
+///
+/// Cargo's stderr is passed through, so that stdout only contains build events.
+fn invoke_cargo_and_forward_messages(cargo: duct::Expression) -> Result<()> {
+    let reader = io::BufReader::new(cargo.reader()?);
+    for line in io::BufRead::lines(reader) {
+        let Some(event) = events::compiler_message(&line?) else {
+            continue
+        };
+        event.emit(MessageFormat::Json)?;
+        if let BuildEvent::CompilerMessage { message, .. } = &event {
+            if message["code"]["code"] == "E0601" {
+                anyhow::bail!(
+                    "missing `no_main` attribute, your contract must be annotated with \
+                    `#![cfg_attr(not(feature = \"std\"), no_std, no_main)]`"
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
 /// Executes `cargo dylint` with the ink! linting driver that is built during
 /// the `build.rs`.
 ///
@@ -406,7 +565,7 @@ fn exec_cargo_dylint(crate_metadata: &CrateMetadata, verbosity: Verbosity) -> Re
         Verbosity::Default | Verbosity::Quiet => Verbosity::Quiet,
     };
 
-    let target_dir = &crate_metadata.target_directory.to_string_lossy();
+    let target_dir = &crate_metadata.cargo_target_directory.to_string_lossy();
     let args = vec!["--lib=ink_linting"];
     let env = vec![
         // We need to set the `CARGO_TARGET_DIR` environment variable in
@@ -568,7 +727,7 @@ fn strip_exports(module: &mut Module) {
 }
 
 /// Load and parse a Wasm file from disk.
-fn load_module<P: AsRef<Path>>(path: P) -> Result<Module> {
+pub fn load_module<P: AsRef<Path>>(path: P) -> Result<Module> {
     let path = path.as_ref();
     parity_wasm::deserialize_file(path).context(format!(
         "Loading of wasm module at '{}' failed",
@@ -576,6 +735,72 @@ fn load_module<P: AsRef<Path>>(path: P) -> Result<Module> {
     ))
 }
 
+/// Creates the size report of the optimized contract code.
+///
+/// The functions of a RISC-V contract are resolved using the symbol table of the ELF
+/// emitted by the linker.
+fn create_size_report(
+    crate_metadata: &CrateMetadata,
+    target: Target,
+) -> Result<SizeReport> {
+    if target == Target::RiscV {
+        return SizeReport::from_elf_with_symbols(
+            &crate_metadata.dest_code,
+            &crate_metadata.original_code,
+        )
+    }
+    SizeReport::from_path(&crate_metadata.dest_code)
+}
+
+/// Checks the host function imports of the final contract code at `dest_code` against
+/// the `host_functions` of the chain, if given.
+pub(crate) fn check_code_host_functions(
+    dest_code: &Path,
+    target: Target,
+    host_functions: Option<&HostFunctions>,
+) -> Result<()> {
+    match (target, host_functions) {
+        (Target::Wasm, Some(host_functions)) => {
+            let module = load_module(dest_code)?;
+            check_host_functions(&module, host_functions)
+        }
+        (Target::RiscV, Some(host_functions)) => {
+            let code = fs::read(dest_code)?;
+            check_elf_host_functions(&code, host_functions)
+        }
+        (_, None) => Ok(()),
+    }
+}
+
+/// Checks the final contract code against the size budget declared in the manifest.
+///
+/// Fails if the budget is exceeded and enforced as an error, a warning is printed if it
+/// is only enforced as a warning.
+fn check_size_budget(
+    crate_metadata: &CrateMetadata,
+    target: Target,
+    verbosity: Verbosity,
+) -> Result<Option<SizeBudgetResult>> {
+    let Some(size_budget) = crate_metadata.size_budget.as_ref() else {
+        return Ok(None)
+    };
+    let code_size = fs::metadata(&crate_metadata.dest_code)?.len();
+    let module = match target {
+        Target::Wasm => Some(load_module(&crate_metadata.dest_code)?),
+        Target::RiscV => None,
+    };
+    let result = size_budget.check(code_size, module.as_ref());
+    if let Some(warning) = result.enforce()? {
+        maybe_println!(
+            verbosity,
+            "{} {}",
+            "warning:".yellow().bold(),
+            warning.bold()
+        );
+    }
+    Ok(Some(result))
+}
+
 /// Performs required post-processing steps on the Wasm artifact.
 fn post_process_wasm(
     crate_metadata: &CrateMetadata,
@@ -583,8 +808,15 @@ fn post_process_wasm(
     verbosity: &Verbosity,
     max_memory_pages: u32,
 ) -> Result<()> {
+    let code = fs::read(&crate_metadata.original_code)?;
+    // validate the module before deserializing it, so that instructions which can't be
+    // deserialized, e.g. of the SIMD proposal, are reported with the offending function
+    if !skip_wasm_validation {
+        validate_wasm::validate_module(&code, max_memory_pages)?;
+    }
+
     // Deserialize Wasm module from a file.
-    let mut module = load_module(&crate_metadata.original_code)
+    let mut module = parity_wasm::deserialize_buffer::<Module>(&code)
         .context("Loading of original wasm failed")?;
 
     strip_exports(&mut module);
@@ -612,6 +844,45 @@ fn post_process_wasm(
     Ok(())
 }
 
+/// Performs required post-processing steps on the RISC-V artifact.
+///
+/// All sections which are not loaded into memory are stripped, unless debug symbols
+/// should be kept.
+fn post_process_riscv(
+    crate_metadata: &CrateMetadata,
+    skip_validation: bool,
+    keep_debug_symbols: bool,
+    verbosity: &Verbosity,
+) -> Result<()> {
+    let code = fs::read(&crate_metadata.original_code)?;
+    elf::parse(&code).with_context(|| {
+        format!(
+            "Loading of the original ELF at '{}' failed",
+            crate_metadata.original_code.display()
+        )
+    })?;
+
+    if !skip_validation {
+        elf::validate(&code)?;
+    } else {
+        maybe_println!(
+            verbosity,
+            " {}",
+            "Skipping ELF validation! Contract code may be invalid."
+                .bright_yellow()
+                .bold()
+        );
+    }
+
+    let code = if keep_debug_symbols {
+        code
+    } else {
+        elf::strip(&code)?
+    };
+    fs::write(&crate_metadata.dest_code, code)?;
+    Ok(())
+}
+
 /// Asserts that the contract's dependencies are compatible to the ones used in ink!.
 ///
 /// This function utilizes `cargo tree`, which takes semver into consideration.
@@ -663,6 +934,10 @@ pub fn assert_debug_mode_supported(ink_version: &Version) -> anyhow::Result<()>
 ///
 /// It does so by invoking `cargo build` and then post processing the final binary.
 pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
+    if args.verifiable {
+        return docker::docker_build(args)
+    }
+
     let ExecuteArgs {
         manifest_path,
         verbosity,
@@ -675,10 +950,18 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         keep_debug_symbols,
         lint,
         output_type,
+        message_format,
         skip_wasm_validation,
         target,
         max_memory_pages,
+        build_image,
+        size_report,
+        size_report_baseline,
+        cache_dir,
+        host_functions,
+        ..
     } = args;
+    docker::check_build_image(build_image.as_deref())?;
 
     // The CLI flag `optimization-passes` overwrites optimization passes which are
     // potentially defined in the `Cargo.toml` profile.
@@ -686,12 +969,9 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         Some(opt_passes) => opt_passes,
         None => {
             let mut manifest = Manifest::new(manifest_path.clone())?;
-            match manifest.profile_optimization_passes() {
-                // if no setting is found, neither on the cli nor in the profile,
-                // then we use the default
-                None => OptimizationPasses::default(),
-                Some(opt_passes) => opt_passes,
-            }
+            // if no setting is found, neither on the cli nor in the profile,
+            // then we use the default
+            manifest.profile_optimization_passes().unwrap_or_default()
         }
     };
 
@@ -703,118 +983,197 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
     }
 
     let maybe_lint = |steps: &mut BuildSteps| -> Result<()> {
-        let total_steps = build_artifact.steps();
+        let total_steps =
+            build_artifact.steps() + crate_metadata.hooks.steps(build_artifact);
         if lint {
             steps.set_total_steps(total_steps + 1);
-            maybe_println!(
+            let step = Step::start(
+                *steps,
+                "Checking ink! linting rules",
                 verbosity,
-                " {} {}",
-                format!("{steps}").bold(),
-                "Checking ink! linting rules".bright_green().bold()
-            );
+                message_format,
+            )?;
             steps.increment_current();
             exec_cargo_dylint(&crate_metadata, verbosity)?;
-            Ok(())
+            step.finish()
         } else {
             steps.set_total_steps(total_steps);
             Ok(())
         }
     };
 
-    let build =
-        || -> Result<(Option<OptimizationResult>, BuildInfo, PathBuf, BuildSteps)> {
-            let mut build_steps = BuildSteps::new();
-            let pre_fingerprint = Fingerprint::new(&crate_metadata)?;
+    type BuildOutput = (
+        Option<OptimizationResult>,
+        BuildInfo,
+        PathBuf,
+        BuildSteps,
+        Option<SizeReport>,
+        Option<SizeBudgetResult>,
+        Option<CacheEntry>,
+    );
+    let build = || -> Result<BuildOutput> {
+        let mut build_steps = BuildSteps::new();
+        let pre_fingerprint = Fingerprint::new(&crate_metadata)?;
+
+        hooks::run(
+            Hook::PreBuild,
+            &crate_metadata,
+            target,
+            build_mode,
+            &mut build_steps,
+            verbosity,
+            message_format,
+        )?;
 
-            maybe_println!(
-                verbosity,
-                " {} {}",
-                format!("{build_steps}").bold(),
-                "Building cargo project".bright_green().bold()
-            );
-            build_steps.increment_current();
-            exec_cargo_for_onchain_target(
-                &crate_metadata,
-                "build",
-                &features,
-                build_mode,
-                network,
-                verbosity,
-                &unstable_flags,
-                target,
-            )?;
+        let step = Step::start(
+            build_steps,
+            "Building cargo project",
+            verbosity,
+            message_format,
+        )?;
+        build_steps.increment_current();
+        exec_cargo_for_onchain_target(
+            &crate_metadata,
+            "build",
+            &features,
+            build_mode,
+            network,
+            verbosity,
+            &unstable_flags,
+            target,
+            message_format,
+            build_image.is_some(),
+        )?;
+        step.finish()?;
 
-            // we persist the latest target we used so we trigger a rebuild when we switch
-            fs::write(&crate_metadata.target_file_path, target.llvm_target())?;
+        // we persist the latest target we used so we trigger a rebuild when we switch
+        fs::write(&crate_metadata.target_file_path, target.llvm_target())?;
 
-            let cargo_contract_version = if let Ok(version) = Version::parse(VERSION) {
-                version
-            } else {
-                anyhow::bail!(
-                    "Unable to parse version number for the currently running \
+        let cargo_contract_version = if let Ok(version) = Version::parse(VERSION) {
+            version
+        } else {
+            anyhow::bail!(
+                "Unable to parse version number for the currently running \
                     `cargo-contract` binary."
-                );
-            };
+            );
+        };
 
-            let build_info = BuildInfo {
-                rust_toolchain: util::rust_toolchain()?,
-                cargo_contract_version,
-                build_mode,
-                wasm_opt_settings: WasmOptSettings {
-                    optimization_passes,
-                    keep_debug_symbols,
-                },
-            };
-
-            let post_fingerprint =
-                Fingerprint::new(&crate_metadata)?.ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Expected '{}' to be generated by build",
-                        crate_metadata.original_code.display()
-                    )
-                })?;
+        let build_info = BuildInfo {
+            rust_toolchain: util::rust_toolchain()?,
+            cargo_contract_version,
+            build_mode,
+            wasm_opt_settings: WasmOptSettings {
+                optimization_passes,
+                keep_debug_symbols,
+            },
+            image: build_image.clone(),
+            target,
+        };
 
-            tracing::debug!(
-                "Fingerprint before build: {:?}, after build: {:?}",
-                pre_fingerprint,
-                post_fingerprint
-            );
+        let post_fingerprint = Fingerprint::new(&crate_metadata)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Expected '{}' to be generated by build",
+                crate_metadata.original_code.display()
+            )
+        })?;
+
+        tracing::debug!(
+            "Fingerprint before build: {:?}, after build: {:?}",
+            pre_fingerprint,
+            post_fingerprint
+        );
 
-            let dest_code_path = crate_metadata.dest_code.clone();
+        let dest_code_path = crate_metadata.dest_code.clone();
 
-            if pre_fingerprint == Some(post_fingerprint)
-                && crate_metadata.dest_code.exists()
-            {
-                tracing::info!(
-                    "No changes in the original wasm at {}, fingerprint {:?}. \
-                Skipping Wasm optimization and metadata generation.",
-                    crate_metadata.original_code.display(),
-                    pre_fingerprint
+        let cache_entry = match cache_dir.as_ref() {
+            Some(cache_dir) => {
+                let packages = local_packages(
+                    &crate_metadata.cargo_meta,
+                    &crate_metadata.root_package,
+                )?;
+                let key = CacheKey::new(
+                    &fs::read(&crate_metadata.original_code)?,
+                    sources_hash(
+                        &packages,
+                        crate_metadata.cargo_meta.workspace_root.as_std_path(),
+                    )?,
+                    target,
+                    optimization_passes,
+                    keep_debug_symbols,
+                    max_memory_pages,
+                    skip_wasm_validation,
                 );
-                return Ok((None, build_info, dest_code_path, build_steps))
+                Some(CacheEntry::new(cache_dir, key, target))
             }
+            None => None,
+        };
 
-            maybe_lint(&mut build_steps)?;
-
-            maybe_println!(
-                verbosity,
-                " {} {}",
-                format!("{build_steps}").bold(),
-                "Post processing code".bright_green().bold()
+        if pre_fingerprint == Some(post_fingerprint)
+            && crate_metadata.dest_code.exists()
+            && !size_report
+        {
+            tracing::info!(
+                "No changes in the original wasm at {}, fingerprint {:?}. \
+                Skipping Wasm optimization and metadata generation.",
+                crate_metadata.original_code.display(),
+                pre_fingerprint
             );
-            build_steps.increment_current();
+            check_code_host_functions(
+                &crate_metadata.dest_code,
+                target,
+                host_functions.as_ref(),
+            )?;
+            let size_budget = check_size_budget(&crate_metadata, target, verbosity)?;
+            return Ok((
+                None,
+                build_info,
+                dest_code_path,
+                build_steps,
+                None,
+                size_budget,
+                cache_entry,
+            ))
+        }
 
-            // remove build artifacts so we don't have anything stale lingering around
-            for t in Target::iter() {
-                fs::remove_file(
-                    crate_metadata.dest_code.with_extension(t.dest_extension()),
-                )
+        maybe_lint(&mut build_steps)?;
+
+        let step = Step::start(
+            build_steps,
+            "Post processing code",
+            verbosity,
+            message_format,
+        )?;
+        build_steps.increment_current();
+
+        // remove build artifacts so we don't have anything stale lingering around
+        for t in Target::iter() {
+            fs::remove_file(crate_metadata.dest_code.with_extension(t.dest_extension()))
                 .ok();
-            }
+        }
+        fs::remove_file(crate_metadata.extracted_ink_metadata_path()).ok();
+        if target == Target::Wasm && metadata::extract_ink_metadata(&crate_metadata)? {
+            tracing::info!("Found the ink! metadata embedded in the contract code");
+        }
+
+        let original_size =
+            fs::metadata(&crate_metadata.original_code)?.len() as f64 / 1000.0;
 
-            let original_size =
-                fs::metadata(&crate_metadata.original_code)?.len() as f64 / 1000.0;
+        // the size report requires the names of the functions, which are not cached
+        let restored = match cache_entry.as_ref() {
+            Some(entry) if !size_report => {
+                entry.restore_code(&crate_metadata.dest_code)?
+            }
+            _ => false,
+        };
 
+        let mut size_report_with_names = None;
+        if restored {
+            maybe_println!(
+                verbosity,
+                " {}",
+                "Restored the post processed code from the build cache".bright_green()
+            );
+        } else {
             match target {
                 Target::Wasm => {
                     post_process_wasm(
@@ -823,106 +1182,210 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
                         &verbosity,
                         max_memory_pages,
                     )?;
-                    let handler =
-                        WasmOptHandler::new(optimization_passes, keep_debug_symbols)?;
+                    // the name section is retained for the size report, which takes
+                    // the function names from the code it measures before they are
+                    // stripped
+                    let keep_names = size_report && !keep_debug_symbols;
+                    let handler = WasmOptHandler::new(
+                        optimization_passes,
+                        keep_debug_symbols || keep_names,
+                    )?;
                     handler.optimize(
                         &crate_metadata.dest_code,
                         &crate_metadata.contract_artifact_name,
                     )?;
+                    if keep_names {
+                        size_report_with_names =
+                            Some(SizeReport::from_path_stripping_names(
+                                &crate_metadata.dest_code,
+                            )?);
+                    }
                 }
                 Target::RiscV => {
-                    fs::copy(&crate_metadata.original_code, &crate_metadata.dest_code)?;
+                    post_process_riscv(
+                        &crate_metadata,
+                        skip_wasm_validation,
+                        keep_debug_symbols,
+                        &verbosity,
+                    )?;
                 }
             }
+            if let Some(entry) = cache_entry.as_ref() {
+                entry.store_code(&crate_metadata.dest_code)?;
+            }
+        }
 
-            let optimized_size = fs::metadata(&dest_code_path)?.len() as f64 / 1000.0;
-
-            let optimization_result = OptimizationResult {
-                dest_wasm: crate_metadata.dest_code.clone(),
-                original_size,
-                optimized_size,
-            };
+        let optimized_size = fs::metadata(&dest_code_path)?.len() as f64 / 1000.0;
+        let size_report = match size_report_with_names {
+            Some(size_report) => Some(size_report),
+            None if size_report => Some(create_size_report(&crate_metadata, target)?),
+            None => None,
+        };
 
-            Ok((
-                Some(optimization_result),
-                build_info,
-                crate_metadata.dest_code.clone(),
-                build_steps,
-            ))
+        let optimization_result = OptimizationResult {
+            dest_wasm: crate_metadata.dest_code.clone(),
+            original_size,
+            optimized_size,
         };
+        step.finish()?;
+        BuildEvent::WasmOptResult(&optimization_result).emit(message_format)?;
+
+        hooks::run(
+            Hook::PostWasm,
+            &crate_metadata,
+            target,
+            build_mode,
+            &mut build_steps,
+            verbosity,
+            message_format,
+        )
+        .inspect_err(|_| {
+            // the code is only processed again if it changes, so don't leave behind
+            // code which the hooks might have left in an inconsistent state
+            fs::remove_file(&crate_metadata.dest_code).ok();
+        })?;
+        check_code_host_functions(
+            &crate_metadata.dest_code,
+            target,
+            host_functions.as_ref(),
+        )?;
+        let size_budget = check_size_budget(&crate_metadata, target, verbosity)?;
+
+        Ok((
+            Some(optimization_result),
+            build_info,
+            crate_metadata.dest_code.clone(),
+            build_steps,
+            size_report,
+            size_budget,
+            cache_entry,
+        ))
+    };
 
     let clean_metadata = || {
         fs::remove_file(crate_metadata.metadata_path()).ok();
         fs::remove_file(crate_metadata.contract_bundle_path()).ok();
     };
 
-    let (opt_result, metadata_result, dest_wasm) = match build_artifact {
-        BuildArtifacts::CheckOnly => {
-            let mut build_steps = BuildSteps::new();
-            maybe_lint(&mut build_steps)?;
-
-            maybe_println!(
-                verbosity,
-                " {} {}",
-                format!("{build_steps}").bold(),
-                "Executing `cargo check`".bright_green().bold()
-            );
-            exec_cargo_for_onchain_target(
-                &crate_metadata,
-                "check",
-                &features,
-                BuildMode::Release,
-                network,
-                verbosity,
-                &unstable_flags,
-                target,
-            )?;
-            (None, None, None)
-        }
-        BuildArtifacts::CodeOnly => {
-            // when building only the code metadata will become stale
-            clean_metadata();
-            let (opt_result, _, dest_wasm, _) = build()?;
-            (opt_result, None, Some(dest_wasm))
-        }
-        BuildArtifacts::All => {
-            let (opt_result, build_info, dest_wasm, build_steps) =
-                build().map_err(|e| {
-                    // build error -> bundle is stale
-                    clean_metadata();
-                    e
-                })?;
+    let (opt_result, metadata_result, dest_wasm, size_report, size_budget) =
+        match build_artifact {
+            BuildArtifacts::CheckOnly => {
+                let mut build_steps = BuildSteps::new();
+                maybe_lint(&mut build_steps)?;
+                hooks::run(
+                    Hook::PreBuild,
+                    &crate_metadata,
+                    target,
+                    BuildMode::Release,
+                    &mut build_steps,
+                    verbosity,
+                    message_format,
+                )?;
 
-            let metadata_result = MetadataArtifacts {
-                dest_metadata: crate_metadata.metadata_path(),
-                dest_bundle: crate_metadata.contract_bundle_path(),
-            };
-
-            // skip metadata generation if contract unchanged and all metadata artifacts
-            // exist.
-            if opt_result.is_some()
-                || !metadata_result.dest_metadata.exists()
-                || !metadata_result.dest_bundle.exists()
-            {
-                // if metadata build fails after a code build it might become stale
-                clean_metadata();
-                metadata::execute(
+                let step = Step::start(
+                    build_steps,
+                    "Executing `cargo check`",
+                    verbosity,
+                    message_format,
+                )?;
+                exec_cargo_for_onchain_target(
                     &crate_metadata,
-                    dest_wasm.as_path(),
-                    &metadata_result,
+                    "check",
                     &features,
+                    BuildMode::Release,
                     network,
                     verbosity,
-                    build_steps,
                     &unstable_flags,
-                    build_info,
+                    target,
+                    message_format,
+                    build_image.is_some(),
                 )?;
+                step.finish()?;
+                (None, None, None, None, None)
+            }
+            BuildArtifacts::CodeOnly => {
+                // when building only the code metadata will become stale
+                clean_metadata();
+                let (opt_result, _, dest_wasm, _, size_report, size_budget, _) = build()?;
+                (opt_result, None, Some(dest_wasm), size_report, size_budget)
+            }
+            BuildArtifacts::All => {
+                let (
+                    opt_result,
+                    build_info,
+                    dest_wasm,
+                    mut build_steps,
+                    size_report,
+                    size_budget,
+                    cache_entry,
+                ) = build().inspect_err(|_| {
+                    // build error -> bundle is stale
+                    clean_metadata();
+                })?;
+
+                let metadata_result = MetadataArtifacts {
+                    dest_metadata: crate_metadata.metadata_path(),
+                    dest_bundle: crate_metadata.contract_bundle_path(),
+                };
+
+                // skip metadata generation if contract unchanged and all metadata
+                // artifacts exist.
+                if opt_result.is_some()
+                    || !metadata_result.dest_metadata.exists()
+                    || !metadata_result.dest_bundle.exists()
+                {
+                    // if metadata build fails after a code build it might become stale
+                    clean_metadata();
+                    metadata::execute(
+                        &crate_metadata,
+                        dest_wasm.as_path(),
+                        &metadata_result,
+                        &features,
+                        network,
+                        verbosity,
+                        message_format,
+                        &mut build_steps,
+                        &unstable_flags,
+                        build_info,
+                        cache_entry.as_ref(),
+                    )?;
+                    hooks::run(
+                        Hook::PostMetadata,
+                        &crate_metadata,
+                        target,
+                        build_mode,
+                        &mut build_steps,
+                        verbosity,
+                        message_format,
+                    )
+                    .inspect_err(|_| clean_metadata())?;
+                }
+                BuildEvent::MetadataArtifacts(&metadata_result).emit(message_format)?;
+                (
+                    opt_result,
+                    Some(metadata_result),
+                    Some(dest_wasm),
+                    size_report,
+                    size_budget,
+                )
             }
-            (opt_result, Some(metadata_result), Some(dest_wasm))
+        };
+
+    let size_report_diff = match (&size_report, size_report_baseline) {
+        (Some(size_report), Some(baseline)) => {
+            Some(size_report.diff(&SizeReport::load(baseline)?))
         }
+        _ => None,
     };
+    if let Some(size_report) = size_report.as_ref() {
+        // persist the report, so that it can be used as a baseline for later builds
+        fs::write(
+            crate_metadata.size_report_path(),
+            serde_json::to_string_pretty(size_report)?,
+        )?;
+    }
 
-    Ok(BuildResult {
+    let build_result = BuildResult {
         dest_wasm,
         metadata_result,
         target_directory: crate_metadata.target_directory,
@@ -931,7 +1394,48 @@ pub fn execute(args: ExecuteArgs) -> Result<BuildResult> {
         build_artifact,
         verbosity,
         output_type,
-    })
+        size_report,
+        size_report_diff,
+        size_budget,
+    };
+    BuildEvent::BuildFinished(&build_result).emit(message_format)?;
+    Ok(build_result)
+}
+
+/// Executes the build of all ink! contracts of the workspace of `args.manifest_path`
+/// which are selected by the `packages`.
+///
+/// The contracts are built one after another, sharing the cargo target directory, the
+/// artifacts of each contract are written to a sub-folder named after the contract.
+pub fn execute_workspace(
+    args: ExecuteArgs,
+    packages: &Packages,
+) -> Result<Vec<BuildResult>> {
+    let contracts = crate_metadata::workspace_contracts(&args.manifest_path, packages)?;
+    if args.size_report_baseline.is_some() && contracts.len() > 1 {
+        anyhow::bail!(
+            "A size report baseline can only be supplied when building a single contract."
+        )
+    }
+    let total = contracts.len();
+    contracts
+        .into_iter()
+        .enumerate()
+        .map(|(index, manifest_path)| {
+            maybe_println!(
+                args.verbosity,
+                "\n {} {}",
+                format!("({}/{})", index + 1, total).bold(),
+                format!("Building contract at {}", manifest_path.as_ref().display())
+                    .bright_cyan()
+                    .bold()
+            );
+            execute(ExecuteArgs {
+                manifest_path,
+                ..args.clone()
+            })
+        })
+        .collect()
 }
 
 /// Unique fingerprint for a file to detect whether it has changed.
@@ -1099,6 +1603,9 @@ mod unit_tests {
             build_artifact: Default::default(),
             verbosity: Verbosity::Quiet,
             output_type: OutputType::Json,
+            size_report: None,
+            size_report_diff: None,
+            size_budget: None,
         };
 
         // when
@@ -1108,4 +1615,41 @@ mod unit_tests {
         assert!(serialized_result.is_ok());
         assert_eq!(serialized_result.unwrap(), raw_result);
     }
+
+    #[test]
+    fn build_result_paths_of_verifiable_build_are_mapped_to_host() {
+        // given
+        let raw_result = r#"{
+  "dest_wasm": "/target/ink/contract.wasm",
+  "metadata_result": {
+    "dest_metadata": "/target/ink/contract.json",
+    "dest_bundle": "/target/ink/contract.contract"
+  },
+  "target_directory": "/target/ink",
+  "optimization_result": null,
+  "build_mode": "Release",
+  "build_artifact": "All",
+  "verbosity": "Quiet"
+}"#;
+        let mut build_result: BuildResult =
+            serde_json::from_str(raw_result).expect("deserialization must work");
+
+        // when
+        build_result.map_paths(Path::new("/target"), Path::new("/host/target"));
+
+        // then
+        assert_eq!(
+            build_result.dest_wasm,
+            Some(PathBuf::from("/host/target/ink/contract.wasm"))
+        );
+        let metadata_result = build_result.metadata_result.unwrap();
+        assert_eq!(
+            metadata_result.dest_bundle,
+            PathBuf::from("/host/target/ink/contract.contract")
+        );
+        assert_eq!(
+            build_result.target_directory,
+            PathBuf::from("/host/target/ink")
+        );
+    }
 }
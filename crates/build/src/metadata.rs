@@ -15,9 +15,10 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::{
+    cache::CacheEntry,
     code_hash,
     crate_metadata::CrateMetadata,
-    maybe_println,
+    events::Step,
     util,
     workspace::{
         ManifestPath,
@@ -26,26 +27,34 @@ use crate::{
     BuildMode,
     BuildSteps,
     Features,
+    MessageFormat,
     Network,
     OptimizationPasses,
+    Target,
     UnstableFlags,
     Verbosity,
 };
 
-use anyhow::Result;
-use colored::Colorize;
+use anyhow::{
+    Context,
+    Result,
+};
 use contract_metadata::{
     Compiler,
     Contract,
     ContractMetadata,
     Language,
     Source,
+    SourceCode,
     SourceCompiler,
     SourceLanguage,
-    SourceWasm,
     User,
 };
 use semver::Version;
+use serde_json::{
+    Map,
+    Value,
+};
 use std::{
     fs,
     path::{
@@ -54,9 +63,16 @@ use std::{
     },
 };
 use url::Url;
+use wasmparser::{
+    Parser,
+    Payload,
+};
+
+/// The name of the custom section a contract can embed its ink! metadata in.
+pub const INK_METADATA_SECTION: &str = "ink_metadata";
 
 /// Artifacts resulting from metadata generation.
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, serde::Deserialize)]
 pub struct MetadataArtifacts {
     /// Path to the resulting metadata file.
     pub dest_metadata: PathBuf,
@@ -85,6 +101,16 @@ pub struct BuildInfo {
     pub build_mode: BuildMode,
     /// Information about the `wasm-opt` optimization settings.
     pub wasm_opt_settings: WasmOptSettings,
+    /// The repository digest of the image used for a verifiable build.
+    ///
+    /// Only present if the contract was built with `--verifiable`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    /// The target the contract was built for.
+    ///
+    /// Contracts built before the target was recorded are Wasm contracts.
+    #[serde(default)]
+    pub target: Target,
 }
 
 impl TryFrom<BuildInfo> for serde_json::Map<String, serde_json::Value> {
@@ -107,7 +133,8 @@ pub struct WasmOptSettings {
 
 /// Generates a file with metadata describing the ABI of the smart contract.
 ///
-/// It does so by generating and invoking a temporary workspace member.
+/// It does so by generating and invoking a temporary workspace member, unless the ink!
+/// metadata is found in the build `cache`.
 #[allow(clippy::too_many_arguments)]
 pub(crate) fn execute(
     crate_metadata: &CrateMetadata,
@@ -116,9 +143,11 @@ pub(crate) fn execute(
     features: &Features,
     network: Network,
     verbosity: Verbosity,
-    mut build_steps: BuildSteps,
+    message_format: MessageFormat,
+    build_steps: &mut BuildSteps,
     unstable_options: &UnstableFlags,
     build_info: BuildInfo,
+    cache: Option<&CacheEntry>,
 ) -> Result<()> {
     // build the extended contract project metadata
     let ExtendedMetadataResult {
@@ -127,15 +156,107 @@ pub(crate) fn execute(
         user,
     } = extended_metadata(crate_metadata, final_contract_wasm, build_info)?;
 
-    let generate_metadata = |manifest_path: &ManifestPath| -> Result<()> {
-        maybe_println!(
+    let step = Step::start(
+        *build_steps,
+        "Generating metadata",
+        verbosity,
+        message_format,
+    )?;
+    let extracted = crate_metadata.extracted_ink_metadata_path();
+    let ink_meta = if extracted.exists() {
+        tracing::debug!("Using the ink! metadata extracted from the contract code");
+        serde_json::from_slice(&fs::read(&extracted)?)?
+    } else if let Some(ink_meta) =
+        cache.map(CacheEntry::ink_metadata).transpose()?.flatten()
+    {
+        ink_meta
+    } else {
+        let ink_meta = generate_ink_metadata(
+            crate_metadata,
+            features,
+            network,
             verbosity,
-            " {} {}",
-            format!("{build_steps}").bold(),
-            "Generating metadata".bright_green().bold()
-        );
+            unstable_options,
+        )?;
+        if let Some(cache) = cache {
+            cache.store_ink_metadata(&ink_meta)?;
+        }
+        ink_meta
+    };
+    let metadata = ContractMetadata::new(source, contract, user, ink_meta);
+    {
+        let mut metadata = metadata.clone();
+        metadata.remove_source_code();
+        let contents = serde_json::to_string_pretty(&metadata)?;
+        fs::write(&metadata_artifacts.dest_metadata, contents)?;
+        build_steps.increment_current();
+    }
+    step.finish()?;
+
+    let step = Step::start(*build_steps, "Generating bundle", verbosity, message_format)?;
+    build_steps.increment_current();
+    let contents = serde_json::to_string(&metadata)?;
+    fs::write(&metadata_artifacts.dest_bundle, contents)?;
+    step.finish()
+}
+
+/// Extracts the ink! metadata embedded in the [`INK_METADATA_SECTION`] of the original
+/// code to [`CrateMetadata::extracted_ink_metadata_path`], returns `false` if the
+/// contract does not embed it.
+///
+/// This spares the compilation of the metadata generator, contracts built with older
+/// ink! versions don't embed the metadata though. The section itself is removed when
+/// the code is post processed.
+pub(crate) fn extract_ink_metadata(crate_metadata: &CrateMetadata) -> Result<bool> {
+    let code = fs::read(&crate_metadata.original_code)?;
+    let Some(ink_meta) = embedded_ink_metadata(&code)? else {
+        return Ok(false)
+    };
+    fs::write(crate_metadata.extracted_ink_metadata_path(), ink_meta)?;
+    Ok(true)
+}
+
+/// Returns the contents of the [`INK_METADATA_SECTION`] of the Wasm `code`, if any.
+///
+/// Only the sections are read, not the function bodies, so that code which is not
+/// supported by `pallet-contracts` is left for the validation to report.
+fn embedded_ink_metadata(code: &[u8]) -> Result<Option<&[u8]>> {
+    let mut payload = None;
+    for section in Parser::new(0).parse_all(code) {
+        match section {
+            Ok(Payload::CustomSection(section))
+                if section.name() == INK_METADATA_SECTION =>
+            {
+                payload = Some(section.data());
+                break
+            }
+            Ok(_) => {}
+            // malformed code is reported by the validation
+            Err(_) => return Ok(None),
+        }
+    }
+    let Some(payload) = payload else {
+        return Ok(None)
+    };
+    serde_json::from_slice::<Map<String, Value>>(payload).with_context(|| {
+        format!("The `{INK_METADATA_SECTION}` section of the contract is no valid JSON")
+    })?;
+    Ok(Some(payload))
+}
+
+/// Generates the ink! metadata by invoking the `metadata-gen` package of a temporary
+/// workspace.
+fn generate_ink_metadata(
+    crate_metadata: &CrateMetadata,
+    features: &Features,
+    network: Network,
+    verbosity: Verbosity,
+    unstable_options: &UnstableFlags,
+) -> Result<Map<String, Value>> {
+    let mut ink_meta = None;
+    let mut generate_metadata = |manifest_path: &ManifestPath| -> Result<()> {
         let target_dir = crate_metadata
-            .target_directory
+            .cargo_target_directory
             .to_string_lossy()
             .to_string();
         let mut args = vec![
@@ -158,26 +279,7 @@ pub(crate) fn execute(
         );
         let output = cmd.stdout_capture().run()?;
 
-        let ink_meta: serde_json::Map<String, serde_json::Value> =
-            serde_json::from_slice(&output.stdout)?;
-        let metadata = ContractMetadata::new(source, contract, user, ink_meta);
-        {
-            let mut metadata = metadata.clone();
-            metadata.remove_source_wasm_attribute();
-            let contents = serde_json::to_string_pretty(&metadata)?;
-            fs::write(&metadata_artifacts.dest_metadata, contents)?;
-            build_steps.increment_current();
-        }
-
-        maybe_println!(
-            verbosity,
-            " {} {}",
-            format!("{build_steps}").bold(),
-            "Generating bundle".bright_green().bold()
-        );
-        let contents = serde_json::to_string(&metadata)?;
-        fs::write(&metadata_artifacts.dest_bundle, contents)?;
-
+        ink_meta = Some(serde_json::from_slice(&output.stdout)?);
         Ok(())
     };
 
@@ -196,7 +298,7 @@ pub(crate) fn execute(
             .using_temp(generate_metadata)?;
     }
 
-    Ok(())
+    ink_meta.ok_or_else(|| anyhow::anyhow!("No ink! metadata has been generated"))
 }
 
 /// Generate the extended contract project metadata
@@ -224,10 +326,10 @@ fn extended_metadata(
     let source = {
         let lang = SourceLanguage::new(Language::Ink, ink_version.clone());
         let compiler = SourceCompiler::new(Compiler::RustC, rust_version);
-        let wasm = fs::read(final_contract_wasm)?;
-        let hash = code_hash(wasm.as_slice());
+        let code = fs::read(final_contract_wasm)?;
+        let hash = code_hash(code.as_slice());
         Source::new(
-            Some(SourceWasm::new(wasm)),
+            Some(SourceCode::new(build_info.target.into(), code)),
             hash.into(),
             lang,
             compiler,
@@ -275,3 +377,85 @@ fn extended_metadata(
         user,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate_wasm::validate_module;
+    use parity_wasm::{
+        builder,
+        elements::{
+            CustomSection,
+            Module,
+            Section,
+        },
+    };
+    use pretty_assertions::assert_eq;
+
+    fn code_with_section(name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut module: Module = builder::module().build();
+        module
+            .sections_mut()
+            .push(Section::Custom(CustomSection::new(
+                name.into(),
+                payload.to_vec(),
+            )));
+        parity_wasm::serialize(module).unwrap()
+    }
+
+    #[test]
+    fn embedded_ink_metadata_is_found() {
+        // given
+        let payload = br#"{"version":"4","spec":{}}"#;
+        let code = code_with_section(INK_METADATA_SECTION, payload);
+
+        // when
+        let ink_meta = embedded_ink_metadata(&code).unwrap();
+
+        // then
+        assert_eq!(ink_meta, Some(&payload[..]));
+    }
+
+    #[test]
+    fn other_custom_sections_are_ignored() {
+        let code = code_with_section("producers", b"rustc");
+        assert_eq!(embedded_ink_metadata(&code).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_embedded_ink_metadata_is_rejected() {
+        let code = code_with_section(INK_METADATA_SECTION, b"{");
+        let err = embedded_ink_metadata(&code).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "The `ink_metadata` section of the contract is no valid JSON"
+        );
+    }
+
+    #[test]
+    fn unsupported_instructions_are_left_to_the_validation() {
+        // given
+        // a single function of type `[] -> []` executing `v128.const 0`, followed by
+        // an `ink_metadata` section containing `{}`
+        let code = b"\0asm\x01\0\0\0\
+            \x01\x04\x01\x60\0\0\
+            \x03\x02\x01\0\
+            \x0a\x17\x01\x15\0\xfd\x0c\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x1a\x0b\
+            \0\x0f\x0cink_metadata{}";
+
+        // when
+        let ink_meta = embedded_ink_metadata(code).unwrap();
+        let res = validate_module(code, 16);
+
+        // then
+        assert_eq!(ink_meta, Some(&b"{}"[..]));
+        let err = res.unwrap_err().to_string();
+        assert!(
+            err.contains(
+                "The function #0 is not supported by `pallet-contracts`: \
+                SIMD support is not enabled."
+            ),
+            "{err}"
+        );
+    }
+}
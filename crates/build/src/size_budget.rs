@@ -0,0 +1,307 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Enforcement of a size budget for the final contract code.
+//!
+//! The budget is declared in the `[package.metadata.contract.size-budget]` section of
+//! the contract's `Cargo.toml`:
+//!
+//! ```toml
+//! [package.metadata.contract.size-budget]
+//! max-code-size = 16384
+//! max-imports = 24
+//! max-exports = 2
+//! # either "error" (the default) or "warn"
+//! enforcement = "warn"
+//! ```
+
+use anyhow::Result;
+use colored::Colorize;
+use parity_wasm::elements::Module;
+use std::fmt::Write as _;
+
+/// How an exceeded size budget is treated.
+#[derive(
+    Clone, Copy, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum BudgetEnforcement {
+    /// The build fails.
+    #[default]
+    Error,
+    /// A warning is printed, the build succeeds.
+    Warn,
+}
+
+/// The size budget of a contract, as declared in its `Cargo.toml`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SizeBudget {
+    /// The maximum size of the optimized contract code in bytes.
+    pub max_code_size: Option<u64>,
+    /// The maximum number of imports of the contract code.
+    pub max_imports: Option<u64>,
+    /// The maximum number of exports of the contract code.
+    pub max_exports: Option<u64>,
+    /// How an exceeded budget is treated.
+    #[serde(default)]
+    pub enforcement: BudgetEnforcement,
+}
+
+impl SizeBudget {
+    /// Checks the contract code against the budget.
+    ///
+    /// The `module` is only available for Wasm contracts, the number of imports and
+    /// exports is not checked otherwise.
+    pub fn check(&self, code_size: u64, module: Option<&Module>) -> SizeBudgetResult {
+        let usage = |current: Option<u64>, allowed: Option<u64>| {
+            Some(BudgetUsage::new(current?, allowed?))
+        };
+        let imports = module.map(|module| {
+            module
+                .import_section()
+                .map_or(0, |section| section.entries().len() as u64)
+        });
+        let exports = module.map(|module| {
+            module
+                .export_section()
+                .map_or(0, |section| section.entries().len() as u64)
+        });
+        SizeBudgetResult {
+            code_size: usage(Some(code_size), self.max_code_size),
+            imports: usage(imports, self.max_imports),
+            exports: usage(exports, self.max_exports),
+            enforcement: self.enforcement,
+        }
+    }
+}
+
+/// The current value of a budgeted quantity compared to the allowed one.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BudgetUsage {
+    /// The current value.
+    pub current: u64,
+    /// The maximum allowed value.
+    pub allowed: u64,
+    /// The remaining headroom, negative if the budget is exceeded.
+    pub headroom: i128,
+}
+
+impl BudgetUsage {
+    fn new(current: u64, allowed: u64) -> Self {
+        Self {
+            current,
+            allowed,
+            headroom: i128::from(allowed) - i128::from(current),
+        }
+    }
+
+    /// Returns `true` if the current value exceeds the allowed one.
+    pub fn is_exceeded(&self) -> bool {
+        self.current > self.allowed
+    }
+}
+
+/// Result of checking the contract code against its size budget.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SizeBudgetResult {
+    /// The size of the optimized contract code in bytes, if budgeted.
+    pub code_size: Option<BudgetUsage>,
+    /// The number of imports, if budgeted.
+    pub imports: Option<BudgetUsage>,
+    /// The number of exports, if budgeted.
+    pub exports: Option<BudgetUsage>,
+    /// How an exceeded budget is treated.
+    pub enforcement: BudgetEnforcement,
+}
+
+impl SizeBudgetResult {
+    /// Returns all budgeted quantities with their name.
+    fn entries(&self) -> impl Iterator<Item = (&'static str, &BudgetUsage)> {
+        [
+            ("code size", self.code_size.as_ref()),
+            ("imports", self.imports.as_ref()),
+            ("exports", self.exports.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, usage)| Some((name, usage?)))
+    }
+
+    /// Returns `true` if any of the budgeted quantities is exceeded.
+    pub fn is_exceeded(&self) -> bool {
+        self.entries().any(|(_, usage)| usage.is_exceeded())
+    }
+
+    /// Fails if the budget is exceeded and enforced as an error, returns the description
+    /// of the exceeded quantities if it is only enforced as a warning.
+    pub fn enforce(&self) -> Result<Option<String>> {
+        if !self.is_exceeded() {
+            return Ok(None)
+        }
+        let mut exceeded = String::from("The contract exceeds its size budget:");
+        for (name, usage) in self.entries().filter(|(_, usage)| usage.is_exceeded()) {
+            write!(
+                exceeded,
+                "\n  - {name}: {} (allowed: {})",
+                usage.current, usage.allowed
+            )?;
+        }
+        match self.enforcement {
+            BudgetEnforcement::Error => anyhow::bail!(exceeded),
+            BudgetEnforcement::Warn => Ok(Some(exceeded)),
+        }
+    }
+
+    /// Display the usage of the budget in a human readable format.
+    pub fn display(&self) -> String {
+        let mut out = format!("{}\n", "Size budget".bright_purple().bold());
+        for (name, usage) in self.entries() {
+            let headroom = if usage.is_exceeded() {
+                format!("exceeded by {}", -usage.headroom).red().bold()
+            } else {
+                format!("{} left", usage.headroom).green()
+            };
+            let _ = writeln!(
+                out,
+                "  {name:<9} {} of {} ({headroom})",
+                usage.current, usage.allowed
+            );
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parity_wasm::builder;
+    use pretty_assertions::assert_eq;
+
+    fn create_module() -> Module {
+        builder::module()
+            .import()
+            .module("seal0")
+            .field("seal_input")
+            .external()
+            .func(0)
+            .build()
+            .import()
+            .module("env")
+            .field("memory")
+            .external()
+            .memory(2, Some(16))
+            .build()
+            .build()
+    }
+
+    #[test]
+    fn budget_is_parsed_from_toml() {
+        // given
+        let toml = r#"
+            max-code-size = 16384
+            max-exports = 2
+            enforcement = "warn"
+        "#;
+
+        // when
+        let budget: SizeBudget = toml::from_str(toml).unwrap();
+
+        // then
+        assert_eq!(
+            budget,
+            SizeBudget {
+                max_code_size: Some(16384),
+                max_imports: None,
+                max_exports: Some(2),
+                enforcement: BudgetEnforcement::Warn,
+            }
+        );
+    }
+
+    #[test]
+    fn only_budgeted_quantities_are_checked() {
+        // given
+        let budget = SizeBudget {
+            max_imports: Some(4),
+            ..Default::default()
+        };
+
+        // when
+        let result = budget.check(1000, Some(&create_module()));
+
+        // then
+        assert_eq!(result.code_size, None);
+        assert_eq!(
+            result.imports,
+            Some(BudgetUsage {
+                current: 2,
+                allowed: 4,
+                headroom: 2,
+            })
+        );
+        assert_eq!(result.exports, None);
+        assert!(result.enforce().unwrap().is_none());
+    }
+
+    #[test]
+    fn exceeded_budget_fails() {
+        // given
+        let budget = SizeBudget {
+            max_code_size: Some(512),
+            max_imports: Some(1),
+            ..Default::default()
+        };
+
+        // when
+        let result = budget.check(1000, Some(&create_module()));
+
+        // then
+        assert_eq!(result.code_size.as_ref().unwrap().headroom, -488);
+        let err = result.enforce().unwrap_err().to_string();
+        assert!(err.contains("code size: 1000 (allowed: 512)"));
+        assert!(err.contains("imports: 2 (allowed: 1)"));
+    }
+
+    #[test]
+    fn usage_of_large_values_does_not_wrap() {
+        // when
+        let exceeded = BudgetUsage::new(u64::MAX, 0);
+        let within = BudgetUsage::new(0, u64::MAX);
+
+        // then
+        assert!(exceeded.is_exceeded());
+        assert_eq!(exceeded.headroom, -i128::from(u64::MAX));
+        assert!(!within.is_exceeded());
+        assert_eq!(within.headroom, i128::from(u64::MAX));
+    }
+
+    #[test]
+    fn exceeded_budget_only_warns_if_configured() {
+        // given
+        let budget = SizeBudget {
+            max_code_size: Some(512),
+            enforcement: BudgetEnforcement::Warn,
+            ..Default::default()
+        };
+
+        // when
+        let result = budget.check(1000, None);
+
+        // then
+        let warning = result.enforce().unwrap().expect("budget must be exceeded");
+        assert!(warning.contains("code size: 1000 (allowed: 512)"));
+    }
+}
@@ -0,0 +1,811 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Attribution of the size of a Wasm contract to its functions, data segments and the
+//! crates the functions originate from.
+//!
+//! Function names are resolved using the Wasm name section and demangled, functions of
+//! a module without a name section are reported by their index.
+//!
+//! Contracts are usually optimized without their name section. For the size report the
+//! name section is retained by the optimization, the report is created from the
+//! optimized code with its names and the name section is stripped afterwards.
+//!
+//! For contracts built for RISC-V the functions are taken from the symbol table of the
+//! ELF before it is stripped, the data segments are its loaded data sections.
+
+use crate::{
+    elf::{
+        self,
+        LE,
+    },
+    load_module,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use colored::Colorize;
+use object::read::elf::{
+    FileHeader as _,
+    SectionHeader as _,
+    Sym as _,
+};
+use parity_wasm::elements::{
+    ImportCountType,
+    Instruction,
+    Module,
+    NameMap,
+    Section,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fmt::Write as _,
+    fs,
+    path::Path,
+};
+
+/// Name under which the size of functions without a resolvable crate is reported.
+const UNKNOWN_CRATE: &str = "[unknown]";
+
+/// Size of a single function body of the Wasm module.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FunctionSize {
+    /// Index of the function in the function index space, including imports.
+    pub index: u32,
+    /// Name of the function from the name section, `func[<index>]` if not available.
+    pub name: String,
+    /// The crate the function originates from, if it could be determined.
+    pub krate: Option<String>,
+    /// Size of the encoded function body in bytes.
+    pub size: u64,
+}
+
+/// Size of a single data segment of the Wasm module.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DataSegmentSize {
+    /// Index of the segment in the data section.
+    pub index: u32,
+    /// The constant memory offset of the segment, if any.
+    pub offset: Option<i32>,
+    /// Size of the segment's data in bytes.
+    pub size: u64,
+}
+
+/// Accumulated size of all functions originating from a crate.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CrateSize {
+    /// Name of the crate, [`UNKNOWN_CRATE`] for functions without a resolvable crate.
+    pub name: String,
+    /// Number of functions originating from the crate.
+    pub functions: u64,
+    /// Accumulated size of the function bodies in bytes.
+    pub size: u64,
+}
+
+/// Size attribution of a contract.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SizeReport {
+    /// Size of the whole contract code in bytes.
+    pub total_size: u64,
+    /// Accumulated size of all function bodies in bytes.
+    pub code_size: u64,
+    /// Accumulated size of all data segments in bytes.
+    pub data_size: u64,
+    /// All function bodies, largest first.
+    pub functions: Vec<FunctionSize>,
+    /// All data segments, largest first.
+    pub data_segments: Vec<DataSegmentSize>,
+    /// Function sizes grouped by originating crate, largest first.
+    pub crates: Vec<CrateSize>,
+}
+
+impl SizeReport {
+    /// Creates the size report for the Wasm module at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let module = load_module(path)?;
+        let total_size = fs::metadata(path)?.len();
+        Self::from_module(module, total_size)
+    }
+
+    /// Creates the size report for the Wasm module at `path`, resolving the function
+    /// names using its own name section, which is stripped from the module afterwards.
+    ///
+    /// Fails if the module has no name section, or if the name section is invalid, e.g.
+    /// because it names functions the module does not have.
+    pub fn from_path_stripping_names<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let module = parse_names(load_module(path)?);
+        let function_names = function_names(&module)
+            .filter(|names| !names.is_empty())
+            .ok_or_else(|| {
+                anyhow!(
+                "The optimized code at '{}' has no valid name section, unable to resolve \
+                the function names.",
+                path.display()
+            )
+            })?;
+        let mut stripped = module.clone();
+        stripped
+            .sections_mut()
+            .retain(|section| !matches!(section, Section::Name(_)));
+        parity_wasm::serialize_to_file(path, stripped).with_context(|| {
+            format!("Failed to write the stripped code to '{}'", path.display())
+        })?;
+        let total_size = fs::metadata(path)?.len();
+        Self::attribute(&module, total_size, Some(function_names))
+    }
+
+    /// Creates the size report for the RISC-V ELF at `path`, resolving the functions
+    /// using the symbol table of the ELF at `symbols_path`.
+    ///
+    /// The ELF at `symbols_path` must be the same code before it was stripped, all sizes
+    /// are still taken from the ELF at `path`. Functions are reported by the index of
+    /// their symbol, aliases of an already reported function are skipped.
+    pub fn from_elf_with_symbols<P, S>(path: P, symbols_path: S) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        S: AsRef<Path>,
+    {
+        let total_size = fs::metadata(path)?.len();
+        let data = fs::read(symbols_path)?;
+        let header = elf::parse(&data)?;
+        let sections = header.sections(LE, &data[..])?;
+        let symbols = sections.symbols(LE, &data[..], object::elf::SHT_SYMTAB)?;
+
+        let mut addresses = BTreeSet::new();
+        let mut functions = Vec::new();
+        for (index, symbol) in symbols.iter().enumerate() {
+            if symbol.st_type() != object::elf::STT_FUNC
+                || symbol.is_undefined(LE)
+                || symbol.st_size(LE) == 0
+                || !addresses.insert(symbol.st_value(LE))
+            {
+                continue
+            }
+            let name = symbols.symbol_name(LE, symbol)?;
+            let name = demangle(&String::from_utf8_lossy(name));
+            functions.push(FunctionSize {
+                index: index as u32,
+                krate: crate_name(&name),
+                name,
+                size: symbol.st_size(LE) as u64,
+            })
+        }
+
+        let mut data_segments = Vec::new();
+        for (index, section) in sections.iter().enumerate() {
+            let flags = section.sh_flags(LE);
+            if section.sh_type(LE) != object::elf::SHT_PROGBITS
+                || flags & object::elf::SHF_ALLOC == 0
+                || flags & object::elf::SHF_EXECINSTR != 0
+            {
+                continue
+            }
+            data_segments.push(DataSegmentSize {
+                index: index as u32,
+                offset: Some(section.sh_addr(LE) as i32),
+                size: section.sh_size(LE) as u64,
+            })
+        }
+        Ok(Self::from_sizes(total_size, functions, data_segments))
+    }
+
+    /// Creates the size report for an already loaded Wasm `module`, `total_size` is the
+    /// size of the serialized module.
+    pub fn from_module(module: Module, total_size: u64) -> Result<Self> {
+        let module = parse_names(module);
+        Self::attribute(&module, total_size, function_names(&module))
+    }
+
+    /// Attributes the size of the `module` to its functions and data segments.
+    fn attribute(
+        module: &Module,
+        total_size: u64,
+        function_names: Option<&NameMap>,
+    ) -> Result<Self> {
+        let imported_functions = module.import_count(ImportCountType::Function) as u32;
+
+        let mut functions = Vec::new();
+        if let Some(code_section) = module.code_section() {
+            for (i, body) in code_section.bodies().iter().enumerate() {
+                let index = imported_functions + i as u32;
+                let name = function_names
+                    .and_then(|names| names.get(index))
+                    .map(|name| demangle(name))
+                    .unwrap_or_else(|| format!("func[{index}]"));
+                let size = parity_wasm::serialize(body.clone())
+                    .context("Serializing a function body failed")?
+                    .len() as u64;
+                functions.push(FunctionSize {
+                    index,
+                    krate: crate_name(&name),
+                    name,
+                    size,
+                })
+            }
+        }
+
+        let mut data_segments = Vec::new();
+        if let Some(data_section) = module.data_section() {
+            for (index, segment) in data_section.entries().iter().enumerate() {
+                let offset = segment.offset().as_ref().and_then(|offset| {
+                    match offset.code() {
+                        [Instruction::I32Const(offset), Instruction::End] => {
+                            Some(*offset)
+                        }
+                        _ => None,
+                    }
+                });
+                data_segments.push(DataSegmentSize {
+                    index: index as u32,
+                    offset,
+                    size: segment.value().len() as u64,
+                })
+            }
+        }
+
+        Ok(Self::from_sizes(total_size, functions, data_segments))
+    }
+
+    /// Groups the function sizes by crate and sorts all entries by their size.
+    fn from_sizes(
+        total_size: u64,
+        mut functions: Vec<FunctionSize>,
+        mut data_segments: Vec<DataSegmentSize>,
+    ) -> Self {
+        let mut crates = BTreeMap::<&str, CrateSize>::new();
+        for function in &functions {
+            let name = function.krate.as_deref().unwrap_or(UNKNOWN_CRATE);
+            let entry = crates.entry(name).or_insert_with(|| {
+                CrateSize {
+                    name: name.to_string(),
+                    functions: 0,
+                    size: 0,
+                }
+            });
+            entry.functions += 1;
+            entry.size += function.size;
+        }
+        let mut crates: Vec<_> = crates.into_values().collect();
+
+        functions.sort_by(|a, b| b.size.cmp(&a.size).then(a.index.cmp(&b.index)));
+        data_segments.sort_by(|a, b| b.size.cmp(&a.size).then(a.index.cmp(&b.index)));
+        crates.sort_by(|a, b| b.size.cmp(&a.size).then(a.name.cmp(&b.name)));
+
+        Self {
+            total_size,
+            code_size: functions.iter().map(|f| f.size).sum(),
+            data_size: data_segments.iter().map(|d| d.size).sum(),
+            functions,
+            data_segments,
+            crates,
+        }
+    }
+
+    /// Loads a size report previously serialized as JSON.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = fs::File::open(path)
+            .with_context(|| format!("Failed to open size report {}", path.display()))?;
+        serde_json::from_reader(file).with_context(|| {
+            format!("Failed to deserialize size report {}", path.display())
+        })
+    }
+
+    /// Compares the report against an `old` report, returning all entries which have
+    /// changed in size.
+    pub fn diff(&self, old: &SizeReport) -> SizeReportDiff {
+        let functions = diff_entries(
+            old.functions.iter().map(|f| (f.name.as_str(), f.size)),
+            self.functions.iter().map(|f| (f.name.as_str(), f.size)),
+        );
+        let crates = diff_entries(
+            old.crates.iter().map(|c| (c.name.as_str(), c.size)),
+            self.crates.iter().map(|c| (c.name.as_str(), c.size)),
+        );
+        SizeReportDiff {
+            total_size: SizeDiff::new(
+                "total".to_string(),
+                old.total_size,
+                self.total_size,
+            ),
+            code_size: SizeDiff::new("code".to_string(), old.code_size, self.code_size),
+            data_size: SizeDiff::new("data".to_string(), old.data_size, self.data_size),
+            functions,
+            crates,
+        }
+    }
+
+    /// Display the report as human readable tables.
+    pub fn display(&self) -> String {
+        let mut out = format!(
+            "{}\n  total: {}, code: {}, data: {}\n",
+            "Size report".bold(),
+            format!("{} B", self.total_size).bold(),
+            format!("{} B", self.code_size).bold(),
+            format!("{} B", self.data_size).bold(),
+        );
+
+        let _ = writeln!(
+            out,
+            "\n{:>10} {:>7} {:>9}  Crate",
+            "Size", "Share", "Functions"
+        );
+        for krate in &self.crates {
+            let _ = writeln!(
+                out,
+                "{:>10} {:>7} {:>9}  {}",
+                krate.size,
+                share(krate.size, self.total_size),
+                krate.functions,
+                krate.name
+            );
+        }
+
+        let _ = writeln!(out, "\n{:>10} {:>7}  Function", "Size", "Share");
+        for function in &self.functions {
+            let _ = writeln!(
+                out,
+                "{:>10} {:>7}  {}",
+                function.size,
+                share(function.size, self.total_size),
+                function.name
+            );
+        }
+
+        let _ = writeln!(out, "\n{:>10} {:>7}  Data segment", "Size", "Share");
+        for segment in &self.data_segments {
+            let offset = segment
+                .offset
+                .map(|offset| format!(" (offset {offset})"))
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{:>10} {:>7}  data[{}]{offset}",
+                segment.size,
+                share(segment.size, self.total_size),
+                segment.index,
+            );
+        }
+        out
+    }
+}
+
+/// The change in size of a single entry between two reports.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SizeDiff {
+    /// Name of the entry.
+    pub name: String,
+    /// Size in the old report in bytes, `0` if the entry did not exist.
+    pub old_size: u64,
+    /// Size in the new report in bytes, `0` if the entry was removed.
+    pub new_size: u64,
+    /// The difference in bytes, positive if the entry grew.
+    pub delta: i128,
+}
+
+impl SizeDiff {
+    fn new(name: String, old_size: u64, new_size: u64) -> Self {
+        Self {
+            name,
+            old_size,
+            new_size,
+            delta: i128::from(new_size) - i128::from(old_size),
+        }
+    }
+}
+
+/// The difference between two size reports.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SizeReportDiff {
+    pub total_size: SizeDiff,
+    pub code_size: SizeDiff,
+    pub data_size: SizeDiff,
+    /// All functions which changed in size, the ones which grew the most first.
+    pub functions: Vec<SizeDiff>,
+    /// All crates which changed in size, the ones which grew the most first.
+    pub crates: Vec<SizeDiff>,
+}
+
+impl SizeReportDiff {
+    /// Display the diff as human readable tables.
+    pub fn display(&self) -> String {
+        let mut out = format!("{}\n", "Size difference".bold());
+        for total in [&self.total_size, &self.code_size, &self.data_size] {
+            let _ = writeln!(
+                out,
+                "  {}: {} -> {} ({})",
+                total.name,
+                total.old_size,
+                total.new_size,
+                delta(total.delta)
+            );
+        }
+        for (title, entries) in [("Crate", &self.crates), ("Function", &self.functions)] {
+            if entries.is_empty() {
+                continue
+            }
+            let _ = writeln!(
+                out,
+                "\n{:>10} {:>10} {:>10}  {title}",
+                "Delta", "Old", "New"
+            );
+            for entry in entries {
+                let _ = writeln!(
+                    out,
+                    "{:>10} {:>10} {:>10}  {}",
+                    delta(entry.delta),
+                    entry.old_size,
+                    entry.new_size,
+                    entry.name
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Returns the entries which changed in size, sorted by their growth.
+///
+/// Sizes of entries with the same name, e.g. unnamed functions, are accumulated.
+fn diff_entries<'a>(
+    old: impl Iterator<Item = (&'a str, u64)>,
+    new: impl Iterator<Item = (&'a str, u64)>,
+) -> Vec<SizeDiff> {
+    let mut sizes = BTreeMap::<&str, (u64, u64)>::new();
+    for (name, size) in old {
+        sizes.entry(name).or_default().0 += size;
+    }
+    for (name, size) in new {
+        sizes.entry(name).or_default().1 += size;
+    }
+    let mut diff: Vec<_> = sizes
+        .into_iter()
+        .filter(|(_, (old, new))| old != new)
+        .map(|(name, (old, new))| SizeDiff::new(name.to_string(), old, new))
+        .collect();
+    diff.sort_by(|a, b| b.delta.cmp(&a.delta).then(a.name.cmp(&b.name)));
+    diff
+}
+
+/// Parses the name section of the `module`.
+///
+/// A malformed name section must not prevent the size attribution, the module is used
+/// as is in that case.
+fn parse_names(module: Module) -> Module {
+    module.parse_names().unwrap_or_else(|(_, module)| module)
+}
+
+/// Returns the names of the functions from the name section of the `module`.
+fn function_names(module: &Module) -> Option<&NameMap> {
+    module
+        .names_section()
+        .and_then(|section| section.functions())
+        .map(|functions| functions.names())
+}
+
+/// Demangles a Rust symbol name, omitting the hash. Other symbols are left untouched.
+fn demangle(symbol: &str) -> String {
+    format!("{:#}", rustc_demangle::demangle(symbol))
+}
+
+/// Returns the crate a function originates from, based on its demangled symbol name.
+fn crate_name(symbol: &str) -> Option<String> {
+    // for trait implementations, e.g. `<alloc::vec::Vec<T> as core::fmt::Debug>::fmt`,
+    // the code originates from the crate of the implementing type. Implementations
+    // for primitive types are attributed to the crate of the trait.
+    if let Some(qualified) = symbol.strip_prefix('<') {
+        let (self_ty, trait_path) =
+            qualified.split_once(" as ").unwrap_or((qualified, ""));
+        return path_crate(self_ty).or_else(|| path_crate(trait_path))
+    }
+    path_crate(symbol)
+}
+
+/// Returns the first segment of `path`, if it is followed by further segments.
+fn path_crate(path: &str) -> Option<String> {
+    let path = path.trim_start_matches(['<', '&', '*', '(', '[']);
+    let path = ["mut ", "const ", "dyn "]
+        .iter()
+        .find_map(|prefix| path.strip_prefix(prefix))
+        .unwrap_or(path);
+    let (krate, _) = path.split_once("::")?;
+    let is_ident =
+        !krate.is_empty() && krate.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_ident.then(|| krate.to_string())
+}
+
+fn share(size: u64, total: u64) -> String {
+    if total == 0 {
+        return "-".to_string()
+    }
+    format!("{:.1}%", size as f64 * 100.0 / total as f64)
+}
+
+fn delta(delta: i128) -> String {
+    match delta {
+        d if d > 0 => format!("+{d}").red().to_string(),
+        d if d < 0 => d.to_string().green().to_string(),
+        d => d.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::tests::{
+        create_contract,
+        GLOBAL_FUNC,
+    };
+    use parity_wasm::{
+        builder,
+        elements::{
+            DataSegment,
+            FunctionNameSubsection,
+            InitExpr,
+            Instructions,
+            NameSection,
+        },
+    };
+
+    fn function(mut instructions: Vec<Instruction>) -> builder::FunctionDefinition {
+        // a function body must be terminated for the module to be deserializable
+        instructions.push(Instruction::End);
+        builder::function()
+            .signature()
+            .build()
+            .body()
+            .with_instructions(Instructions::new(instructions))
+            .build()
+            .build()
+    }
+
+    fn create_module(names: &[&str]) -> Module {
+        let mut module = builder::module();
+        for nops in [0, 8, 4] {
+            module.push_function(function(vec![Instruction::Nop; nops]));
+        }
+        let mut module = module
+            .with_data_segment(DataSegment::new(
+                0,
+                Some(InitExpr::new(vec![
+                    Instruction::I32Const(1024),
+                    Instruction::End,
+                ])),
+                vec![0; 16],
+            ))
+            .build();
+
+        let mut functions = FunctionNameSubsection::default();
+        for (index, name) in names.iter().enumerate() {
+            functions.names_mut().insert(index as u32, name.to_string());
+        }
+        module.sections_mut().push(Section::Name(NameSection::new(
+            None,
+            Some(functions),
+            None,
+        )));
+        module
+    }
+
+    #[test]
+    fn crate_names_are_resolved() {
+        assert_eq!(
+            crate_name("ink_env::engine::on_chain::ext::set_storage"),
+            Some("ink_env".into())
+        );
+        assert_eq!(
+            crate_name("<alloc::vec::Vec<T> as core::fmt::Debug>::fmt"),
+            Some("alloc".into())
+        );
+        assert_eq!(
+            crate_name("<&mut parity_scale_codec::Ref as core::ops::Drop>::drop"),
+            Some("parity_scale_codec".into())
+        );
+        assert_eq!(
+            crate_name("<i32 as core::fmt::Display>::fmt"),
+            Some("core".into())
+        );
+        assert_eq!(crate_name("deploy"), None);
+    }
+
+    #[test]
+    fn symbol_names_are_demangled() {
+        assert_eq!(
+            demangle("_ZN4core9panicking5panic17h00e3acdd8048cb7cE"),
+            "core::panicking::panic"
+        );
+        assert_eq!(
+            demangle("_RNvNtCsicPXn3uX6YO_4core3fmt5write"),
+            "core::fmt::write"
+        );
+        assert_eq!(demangle("memcpy"), "memcpy");
+    }
+
+    #[test]
+    fn sizes_are_attributed_to_functions_crates_and_data_segments() {
+        // given
+        let module = create_module(&["call", "flipper::flip", "flipper::get"]);
+
+        // when
+        let report = SizeReport::from_module(module, 100).unwrap();
+
+        // then
+        let names: Vec<_> = report.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["flipper::flip", "flipper::get", "call"]);
+        assert_eq!(report.crates[0].name, "flipper");
+        assert_eq!(report.crates[0].functions, 2);
+        assert_eq!(report.crates[1].name, UNKNOWN_CRATE);
+        assert_eq!(
+            report.code_size,
+            report.functions.iter().map(|f| f.size).sum::<u64>()
+        );
+        assert_eq!(
+            report.data_segments,
+            vec![DataSegmentSize {
+                index: 0,
+                offset: Some(1024),
+                size: 16
+            }]
+        );
+    }
+
+    #[test]
+    fn functions_without_names_are_reported_by_index() {
+        // given
+        let module = create_module(&[]);
+
+        // when
+        let report = SizeReport::from_module(module, 100).unwrap();
+
+        // then
+        assert_eq!(report.functions[0].name, "func[1]");
+        assert_eq!(report.crates.len(), 1);
+        assert_eq!(report.crates[0].name, UNKNOWN_CRATE);
+    }
+
+    #[test]
+    fn names_are_resolved_using_the_name_section_of_the_measured_code() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let code = dir.path().join("flipper.wasm");
+        parity_wasm::serialize_to_file(
+            &code,
+            create_module(&["call", "flipper::flip", "flipper::get"]),
+        )
+        .unwrap();
+
+        // when
+        let report = SizeReport::from_path_stripping_names(&code).unwrap();
+
+        // then
+        let names: Vec<_> = report.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["flipper::flip", "flipper::get", "call"]);
+        let stripped = parse_names(load_module(&code).unwrap());
+        assert!(function_names(&stripped).is_none());
+        assert_eq!(report.total_size, fs::metadata(&code).unwrap().len());
+    }
+
+    #[test]
+    fn elf_functions_are_resolved_using_the_symbol_table() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let code = dir.path().join("flipper.riscv");
+        let elf = create_contract(&[
+            ("_start", 84, 2, GLOBAL_FUNC, 1),
+            (
+                "_ZN7flipper4flip17h0123456789abcdefE",
+                86,
+                6,
+                GLOBAL_FUNC,
+                1,
+            ),
+            ("flip_alias", 86, 6, GLOBAL_FUNC, 1),
+        ]);
+        fs::write(&code, crate::elf::strip(&elf).unwrap()).unwrap();
+        let symbols = dir.path().join("flipper");
+        fs::write(&symbols, elf).unwrap();
+
+        // when
+        let report = SizeReport::from_elf_with_symbols(&code, &symbols).unwrap();
+
+        // then
+        let names: Vec<_> = report.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["flipper::flip", "_start"]);
+        assert_eq!(report.crates[0].name, "flipper");
+        assert_eq!(
+            report.data_segments,
+            vec![DataSegmentSize {
+                index: 2,
+                offset: Some(92),
+                size: 7
+            }]
+        );
+        assert_eq!(report.total_size, fs::metadata(&code).unwrap().len());
+    }
+
+    #[test]
+    fn code_without_or_with_inconsistent_names_is_rejected() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let unnamed = dir.path().join("unnamed.wasm");
+        parity_wasm::serialize_to_file(&unnamed, create_module(&[])).unwrap();
+        let inconsistent = dir.path().join("inconsistent.wasm");
+        parity_wasm::serialize_to_file(
+            &inconsistent,
+            create_module(&["call", "flipper::flip", "flipper::get", "removed"]),
+        )
+        .unwrap();
+
+        // when
+        let unnamed = SizeReport::from_path_stripping_names(&unnamed);
+        let inconsistent = SizeReport::from_path_stripping_names(&inconsistent);
+
+        // then
+        assert!(unnamed
+            .unwrap_err()
+            .to_string()
+            .contains("has no valid name section"));
+        assert!(inconsistent
+            .unwrap_err()
+            .to_string()
+            .contains("has no valid name section"));
+    }
+
+    #[test]
+    fn diff_reports_grown_entries_first() {
+        // given
+        let old = SizeReport::from_module(
+            create_module(&["call", "flipper::flip", "flipper::get"]),
+            100,
+        )
+        .unwrap();
+        let new = SizeReport::from_module(
+            create_module(&["call", "flipper::get", "flipper::flip"]),
+            120,
+        )
+        .unwrap();
+
+        // when
+        let diff = new.diff(&old);
+
+        // then
+        assert_eq!(diff.total_size.delta, 20);
+        assert_eq!(diff.code_size.delta, 0);
+        assert!(diff.crates.is_empty());
+        let names: Vec<_> = diff.functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["flipper::get", "flipper::flip"]);
+        assert!(diff.functions[0].delta > 0);
+        assert_eq!(diff.functions[0].delta, -diff.functions[1].delta);
+    }
+
+    #[test]
+    fn diff_of_large_sizes_does_not_wrap() {
+        // when
+        let grown = SizeDiff::new("grown".to_string(), 0, u64::MAX);
+        let shrunk = SizeDiff::new("shrunk".to_string(), u64::MAX, 0);
+
+        // then
+        assert_eq!(grown.delta, i128::from(u64::MAX));
+        assert_eq!(shrunk.delta, -i128::from(u64::MAX));
+    }
+}
@@ -79,7 +79,12 @@ build_tests!(
     missing_cargo_dylint_installation_must_be_detected,
     generates_metadata,
     unchanged_contract_skips_optimization_and_metadata_steps,
-    unchanged_contract_no_metadata_artifacts_generates_metadata
+    unchanged_contract_no_metadata_artifacts_generates_metadata,
+    exceeded_size_budget_must_fail_the_build,
+    exceeded_size_budget_must_only_warn_if_configured,
+    post_wasm_hook_must_receive_the_code_path,
+    failing_hook_must_fail_the_build,
+    build_cache_must_be_reused_for_unchanged_code
 );
 
 fn build_code_only(manifest_path: &ManifestPath) -> Result<()> {
@@ -628,6 +633,155 @@ fn unchanged_contract_no_metadata_artifacts_generates_metadata(
     Ok(())
 }
 
+fn exceeded_size_budget_must_fail_the_build(manifest_path: &ManifestPath) -> Result<()> {
+    // given
+    let mut test_manifest = TestContractManifest::new(manifest_path.clone())?;
+    test_manifest.add_size_budget_value("max-code-size", 1000.into())?;
+    test_manifest.write()?;
+
+    let args = ExecuteArgs {
+        manifest_path: manifest_path.clone(),
+        build_mode: BuildMode::Release,
+        build_artifact: BuildArtifacts::CodeOnly,
+        ..Default::default()
+    };
+
+    // when
+    let res = super::execute(args);
+
+    // then
+    let err = res.err().expect("build must fail").to_string();
+    assert!(
+        err.contains("The contract exceeds its size budget"),
+        "unexpected error: {err}"
+    );
+    assert!(err.contains("(allowed: 1000)"), "unexpected error: {err}");
+    Ok(())
+}
+
+fn exceeded_size_budget_must_only_warn_if_configured(
+    manifest_path: &ManifestPath,
+) -> Result<()> {
+    // given
+    let mut test_manifest = TestContractManifest::new(manifest_path.clone())?;
+    test_manifest.add_size_budget_value("max-code-size", 1000.into())?;
+    test_manifest.add_size_budget_value("max-exports", 2.into())?;
+    test_manifest.add_size_budget_value("enforcement", "warn".into())?;
+    test_manifest.write()?;
+
+    let args = ExecuteArgs {
+        manifest_path: manifest_path.clone(),
+        build_mode: BuildMode::Release,
+        build_artifact: BuildArtifacts::CodeOnly,
+        ..Default::default()
+    };
+
+    // when
+    let res = super::execute(args).expect("build failed");
+
+    // then
+    let size_budget = res.size_budget.expect("size budget must be checked");
+    let code_size = size_budget.code_size.expect("code size must be budgeted");
+    assert!(code_size.is_exceeded());
+    assert_eq!(code_size.allowed, 1000);
+    let exports = size_budget.exports.expect("exports must be budgeted");
+    assert_eq!(exports.current, 2);
+    assert_eq!(exports.headroom, 0);
+    assert!(size_budget.imports.is_none());
+    Ok(())
+}
+
+fn post_wasm_hook_must_receive_the_code_path(manifest_path: &ManifestPath) -> Result<()> {
+    // given
+    let mut test_manifest = TestContractManifest::new(manifest_path.clone())?;
+    test_manifest.add_hook(
+        "post-wasm",
+        r#"cp "$CONTRACT_CODE_PATH" "$CONTRACT_TARGET_DIR/hooked.wasm""#.into(),
+    )?;
+    test_manifest.write()?;
+
+    let args = ExecuteArgs {
+        manifest_path: manifest_path.clone(),
+        build_mode: BuildMode::Release,
+        build_artifact: BuildArtifacts::CodeOnly,
+        ..Default::default()
+    };
+
+    // when
+    let res = super::execute(args).expect("build failed");
+
+    // then
+    let hooked = fs::read(res.target_directory.join("hooked.wasm"))?;
+    assert_eq!(
+        hooked,
+        fs::read(res.dest_wasm.expect("code must be built"))?
+    );
+    Ok(())
+}
+
+fn failing_hook_must_fail_the_build(manifest_path: &ManifestPath) -> Result<()> {
+    // given
+    let mut test_manifest = TestContractManifest::new(manifest_path.clone())?;
+    test_manifest.add_hook("pre-build", vec!["true", "exit 1"].into())?;
+    test_manifest.write()?;
+
+    let args = ExecuteArgs {
+        manifest_path: manifest_path.clone(),
+        build_mode: BuildMode::Release,
+        build_artifact: BuildArtifacts::CodeOnly,
+        ..Default::default()
+    };
+
+    // when
+    let res = super::execute(args);
+
+    // then
+    let err = res.err().expect("build must fail").to_string();
+    assert!(
+        err.starts_with("The `pre-build` hook `exit 1` failed"),
+        "unexpected error: {err}"
+    );
+    Ok(())
+}
+
+fn build_cache_must_be_reused_for_unchanged_code(
+    manifest_path: &ManifestPath,
+) -> Result<()> {
+    // given
+    let cache_dir = tempfile::Builder::new()
+        .prefix("cargo-contract.cache.")
+        .tempdir()?;
+    let args = ExecuteArgs {
+        manifest_path: manifest_path.clone(),
+        build_artifact: BuildArtifacts::All,
+        cache_dir: Some(cache_dir.path().to_path_buf()),
+        ..Default::default()
+    };
+    let res1 = super::execute(args.clone()).expect("build failed");
+    let dest_wasm = res1.dest_wasm.expect("code must be built");
+    let dest_metadata = res1.metadata_result.expect("metadata must be built");
+    let code = fs::read(&dest_wasm)?;
+    let metadata = fs::read_to_string(&dest_metadata.dest_metadata)?;
+
+    // when
+    // the artifacts are removed, as in a fresh checkout
+    fs::remove_file(&dest_wasm)?;
+    fs::remove_file(&dest_metadata.dest_metadata)?;
+    fs::remove_file(&dest_metadata.dest_bundle)?;
+    let res2 = super::execute(args).expect("build failed");
+
+    // then
+    let entries = fs::read_dir(cache_dir.path())?.collect::<Vec<_>>();
+    assert_eq!(entries.len(), 1, "only one cache entry must be written");
+    let entry = entries[0].as_ref().unwrap().path();
+    assert!(entry.join("code.wasm").exists());
+    assert!(entry.join("ink-metadata.json").exists());
+    assert_eq!(fs::read(res2.dest_wasm.unwrap())?, code);
+    let dest_metadata = res2.metadata_result.unwrap().dest_metadata;
+    assert_eq!(fs::read_to_string(dest_metadata)?, metadata);
+    Ok(())
+}
+
 /// Get the last modified date of the given file.
 /// Panics if the file does not exist.
 fn file_last_modified(path: &Path) -> SystemTime {
@@ -193,6 +193,48 @@ impl TestContractManifest {
             ))
     }
 
+    /// Add a key/value to the `[package.metadata.contract.size-budget]` section
+    pub fn add_size_budget_value(
+        &mut self,
+        key: &'static str,
+        value: value::Value,
+    ) -> Result<()> {
+        self.package_mut()?
+            .entry("metadata")
+            .or_insert(value::Value::Table(Default::default()))
+            .as_table_mut()
+            .context("metadata section should be a table")?
+            .entry("contract")
+            .or_insert(value::Value::Table(Default::default()))
+            .as_table_mut()
+            .context("metadata.contract section should be a table")?
+            .entry("size-budget")
+            .or_insert(value::Value::Table(Default::default()))
+            .as_table_mut()
+            .context("metadata.contract.size-budget section should be a table")?
+            .insert(key.into(), value);
+        Ok(())
+    }
+
+    /// Add a hook to the `[package.metadata.contract.hooks]` section
+    pub fn add_hook(&mut self, hook: &'static str, commands: value::Value) -> Result<()> {
+        self.package_mut()?
+            .entry("metadata")
+            .or_insert(value::Value::Table(Default::default()))
+            .as_table_mut()
+            .context("metadata section should be a table")?
+            .entry("contract")
+            .or_insert(value::Value::Table(Default::default()))
+            .as_table_mut()
+            .context("metadata.contract section should be a table")?
+            .entry("hooks")
+            .or_insert(value::Value::Table(Default::default()))
+            .as_table_mut()
+            .context("metadata.contract.hooks section should be a table")?
+            .insert(hook.into(), commands);
+        Ok(())
+    }
+
     /// Set the dependency version of `package` to `version`.
     pub fn set_dependency_version(
         &mut self,
@@ -18,11 +18,55 @@ use anyhow::Result;
 use colored::Colorize;
 use impl_serde::serialize as serde_hex;
 use parity_wasm::elements::Module;
+use std::collections::HashMap;
+use wasmparser::{
+    DataKind,
+    ExternalKind,
+    Name,
+    NameSectionReader,
+    Operator,
+    Parser,
+    Payload,
+    ValidPayload,
+    Validator,
+    WasmFeatures,
+};
 
 /// Marker inserted by the ink! codegen for an error which can't
 /// be checked at compile time.
 const INK_ENFORCE_ERR: &str = "__ink_enforce_error_";
 
+/// The maximum number of elements of the table of a contract.
+///
+/// This is the default `table_size` limit of the `pallet-contracts` schedule.
+const MAX_TABLE_SIZE: u32 = 4096;
+
+/// The size of a page of the Wasm memory in bytes.
+const PAGE_SIZE: u64 = 64 * 1024;
+
+/// The Wasm features `pallet-contracts` accepts in contract code.
+///
+/// Floating point instructions as well as all proposals beyond the MVP are rejected.
+const PALLET_CONTRACTS_FEATURES: WasmFeatures = WasmFeatures {
+    mutable_global: false,
+    saturating_float_to_int: false,
+    sign_extension: false,
+    reference_types: false,
+    multi_value: false,
+    bulk_memory: false,
+    simd: false,
+    relaxed_simd: false,
+    threads: false,
+    tail_call: false,
+    floats: false,
+    multi_memory: false,
+    exceptions: false,
+    memory64: false,
+    extended_const: false,
+    component_model: false,
+    memory_control: false,
+};
+
 /// Errors which may occur when forwarding a call is not allowed.
 ///
 /// We insert markers for these errors in the generated contract code.
@@ -89,18 +133,7 @@ pub fn validate_import_section(module: &Module) -> Result<()> {
 
     let filtered_imports = imports.filter(|section| {
         let field = section.field();
-        if field.contains("panic") {
-            errs.push(String::from(
-                "An unexpected panic function import was found in the contract Wasm.\n\
-                This typically goes back to a known bug in the Rust compiler:\n\
-                https://github.com/rust-lang/rust/issues/78744\n\n\
-                As a workaround try to insert `overflow-checks = false` into your `Cargo.toml`.\n\
-                This will disable safe math operations, but unfortunately we are currently not \n\
-                aware of a better workaround until the bug in the compiler is fixed.",
-            ));
-        } else if field.starts_with(INK_ENFORCE_ERR) {
-            errs.push(parse_linker_error(field));
-        }
+        check_known_errors(field, "Wasm", &mut errs);
 
         match check_import(section.module(), field) {
             Ok(_) => true,
@@ -112,17 +145,236 @@ pub fn validate_import_section(module: &Module) -> Result<()> {
     });
 
     if original_imports_len != filtered_imports.count() {
-        anyhow::bail!(format!(
-            "Validation of the Wasm failed.\n\n\n{}\n\nIgnore with `--skip-wasm-validation`",
-            errs.into_iter()
-                .map(|err| format!("{} {}", "ERROR:".to_string().bold(), err))
-                .collect::<Vec<String>>()
-                .join("\n\n\n")
-        ));
+        anyhow::bail!(validation_failed("Wasm", errs));
+    }
+    Ok(())
+}
+
+/// Validates the Wasm `code` against what `pallet-contracts` accepts.
+///
+/// Other than [`validate_import_section`] this checks the whole module:
+/// - no floating point instructions and no instructions of unsupported proposals, e.g.
+///   bulk memory, SIMD, reference types or sign extension,
+/// - the exported `call` and `deploy` functions without parameters and results,
+/// - the size of the table and the bounds of the data segments, which must fit into
+///   `max_memory_pages`.
+///
+/// Violations in the body of a function are reported with the name of the function,
+/// if the code contains a name section.
+pub fn validate_module(code: &[u8], max_memory_pages: u32) -> Result<()> {
+    let names = function_names(code);
+    let function = |index: u32| {
+        match names.get(&index) {
+            Some(name) => format!("`{:#}`", rustc_demangle::demangle(name)),
+            None => format!("#{index}"),
+        }
+    };
+
+    let mut validator = Validator::new_with_features(PALLET_CONTRACTS_FEATURES);
+    let mut errs = Vec::new();
+    let mut exports = Vec::new();
+    let mut types = None;
+    for payload in Parser::new(0).parse_all(code) {
+        let payload = payload?;
+        match validator.payload(&payload) {
+            Ok(ValidPayload::Func(func, body)) => {
+                let mut func = func.into_validator(Default::default());
+                if let Err(err) = func.validate(&body) {
+                    errs.push(format!(
+                        "The function {} is not supported by `pallet-contracts`: {}.",
+                        function(func.index()),
+                        err.message()
+                    ));
+                }
+            }
+            Ok(ValidPayload::End(module_types)) => types = Some(module_types),
+            Ok(_) => {}
+            Err(err) => {
+                // the validation can't be continued after an invalid section
+                errs.push(format!(
+                    "The contract Wasm is not supported by `pallet-contracts`: {} \
+                    (at offset {:#x}).",
+                    err.message(),
+                    err.offset()
+                ));
+                break
+            }
+        }
+
+        match payload {
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if export.kind == ExternalKind::Func {
+                        exports.push((export.name, export.index));
+                    }
+                }
+            }
+            Payload::TableSection(reader) => {
+                for table in reader {
+                    let table = table?;
+                    if table.initial > MAX_TABLE_SIZE {
+                        errs.push(format!(
+                            "The table of the contract Wasm has {} elements, but \
+                            `pallet-contracts` allows at most {MAX_TABLE_SIZE}.",
+                            table.initial
+                        ));
+                    }
+                }
+            }
+            Payload::DataSection(reader) => {
+                let memory_size = u64::from(max_memory_pages) * PAGE_SIZE;
+                for data in reader {
+                    let data = data?;
+                    let DataKind::Active { offset_expr, .. } = data.kind else {
+                        continue
+                    };
+                    let Ok(Operator::I32Const { value }) =
+                        offset_expr.get_operators_reader().read()
+                    else {
+                        continue
+                    };
+                    let end = u64::from(value as u32) + data.data.len() as u64;
+                    if end > memory_size {
+                        errs.push(format!(
+                            "A data segment of the contract Wasm ends at byte {end}, \
+                            beyond the maximum memory of {max_memory_pages} pages. \
+                            Increase the memory with `--max-memory-pages`."
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // the types of the functions are only known if the whole module has been validated
+    if let Some(types) = types {
+        for name in ["call", "deploy"] {
+            let ty = exports
+                .iter()
+                .find(|(export, _)| *export == name)
+                .and_then(|(_, index)| types.function_at(*index));
+            match ty {
+                None => {
+                    errs.push(format!(
+                        "The contract Wasm does not export the function `{name}`."
+                    ))
+                }
+                Some(ty) if !ty.params().is_empty() || !ty.results().is_empty() => {
+                    errs.push(format!(
+                        "The exported function `{name}` of the contract Wasm must not \
+                        have any parameters or results, but has {} parameters and {} \
+                        results.",
+                        ty.params().len(),
+                        ty.results().len()
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+    }
+
+    if !errs.is_empty() {
+        anyhow::bail!(validation_failed("Wasm", errs));
+    }
+    Ok(())
+}
+
+/// Returns the names of the functions found in the name section of the Wasm `code`.
+///
+/// The name section is optional, a malformed one is ignored.
+fn function_names(code: &[u8]) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for payload in Parser::new(0).parse_all(code) {
+        let Ok(Payload::CustomSection(reader)) = payload else {
+            continue
+        };
+        if reader.name() != "name" {
+            continue
+        }
+        let section = NameSectionReader::new(reader.data(), reader.data_offset());
+        for name in section.into_iter().flatten() {
+            if let Name::Function(map) = name {
+                for naming in map.into_iter().flatten() {
+                    names.insert(naming.index, naming.name.to_string());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// Validates the undefined symbols of a contract built for RISC-V.
+///
+/// Host functions are imported as undefined symbols named `<module>::<function>`, which
+/// must be part of a module prefixed with 'seal' like the imports of a Wasm contract.
+/// All other symbols must be resolved when linking the contract. The same known bugs
+/// and ink! error markers as for the imports of a Wasm contract are reported.
+pub fn validate_undefined_symbols<'a, I>(symbols: I) -> Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut errs = Vec::new();
+    for symbol in symbols {
+        if check_known_errors(symbol, "ELF", &mut errs) {
+            continue
+        }
+        match symbol.split_once("::") {
+            Some((module, _)) if module.starts_with("seal") => (),
+            Some(_) => {
+                errs.push(format!(
+                    "An unexpected host function import was found in the contract ELF: \
+                    {symbol}.\n\
+                    Host functions must be imported from a module prefixed with 'seal'."
+                ))
+            }
+            None => {
+                errs.push(format!(
+                    "An undefined symbol was found in the contract ELF: {symbol}.\n\
+                    Host functions must be imported as `<module>::<function>`, all other \
+                    symbols must be resolved when linking the contract."
+                ))
+            }
+        }
+    }
+    if !errs.is_empty() {
+        anyhow::bail!(validation_failed("ELF", errs));
     }
     Ok(())
 }
 
+/// Pushes an error for known bugs and for ink! error markers found in the `name` of an
+/// imported function of the contract `code`, returns `true` if an error was pushed.
+fn check_known_errors(name: &str, code: &str, errs: &mut Vec<String>) -> bool {
+    if name.contains("panic") {
+        errs.push(format!(
+            "An unexpected panic function import was found in the contract {code}.\n\
+            This typically goes back to a known bug in the Rust compiler:\n\
+            https://github.com/rust-lang/rust/issues/78744\n\n\
+            As a workaround try to insert `overflow-checks = false` into your `Cargo.toml`.\n\
+            This will disable safe math operations, but unfortunately we are currently not \n\
+            aware of a better workaround until the bug in the compiler is fixed.",
+        ));
+    } else if name.starts_with(INK_ENFORCE_ERR) {
+        errs.push(parse_linker_error(name));
+    } else {
+        return false
+    }
+    true
+}
+
+/// Returns the error for a failed validation of the contract `code`.
+fn validation_failed(code: &str, errs: Vec<String>) -> String {
+    format!(
+        "Validation of the {code} failed.\n\n\n{}\n\nIgnore with `--skip-wasm-validation`",
+        errs.into_iter()
+            .map(|err| format!("{} {}", "ERROR:".to_string().bold(), err))
+            .collect::<Vec<String>>()
+            .join("\n\n\n")
+    )
+}
+
 /// Returns `Ok` if the import is allowed.
 fn check_import(module: &str, field: &str) -> Result<(), String> {
     if module.starts_with("seal") || field.starts_with("memory") {
@@ -190,8 +442,25 @@ fn parse_linker_error(field: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::validate_import_section;
-    use parity_wasm::elements::Module;
+    use super::{
+        validate_import_section,
+        validate_module,
+    };
+    use parity_wasm::{
+        builder,
+        elements::{
+            FuncBody,
+            FunctionNameSubsection,
+            FunctionType,
+            Instruction,
+            Instructions,
+            Module,
+            NameSection,
+            Section,
+            Type,
+            ValueType,
+        },
+    };
 
     fn create_module(contract: &str) -> Module {
         let wasm = wabt::wat2wasm(contract).expect("invalid wabt");
@@ -303,6 +572,146 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    /// Creates a contract exporting `call` and `deploy` with the `call_body`, the name of
+    /// `call` is the mangled name of `flipper::call`.
+    fn create_contract(call_body: Vec<Instruction>) -> Vec<u8> {
+        let mut module = builder::module()
+            .import()
+            .module("env")
+            .field("memory")
+            .external()
+            .memory(2, Some(16))
+            .build()
+            .function()
+            .signature()
+            .build()
+            .with_body(FuncBody::new(vec![], Instructions::new(call_body)))
+            .build()
+            .function()
+            .signature()
+            .build()
+            .body()
+            .build()
+            .build()
+            .export()
+            .field("call")
+            .internal()
+            .func(0)
+            .build()
+            .export()
+            .field("deploy")
+            .internal()
+            .func(1)
+            .build()
+            .data()
+            .offset(Instruction::I32Const(1024))
+            .value(b"flipper".to_vec())
+            .build()
+            .build();
+        let mut names = FunctionNameSubsection::default();
+        names
+            .names_mut()
+            .insert(0, "_ZN7flipper4call17h0123456789abcdefE".to_string());
+        module.sections_mut().push(Section::Name(NameSection::new(
+            None,
+            Some(names),
+            None,
+        )));
+        parity_wasm::serialize(module).expect("serializing must work")
+    }
+
+    #[test]
+    fn must_accept_mvp_contract() {
+        let code = create_contract(vec![Instruction::End]);
+        assert!(validate_module(&code, 16).is_ok());
+    }
+
+    #[test]
+    fn must_catch_floating_point_instructions() {
+        // given
+        let code = create_contract(vec![
+            Instruction::F32Const(0),
+            Instruction::Drop,
+            Instruction::End,
+        ]);
+
+        // when
+        let res = validate_module(&code, 16);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(
+            err.contains(
+                "The function `flipper::call` is not supported by `pallet-contracts`: \
+                floating-point instruction disallowed."
+            ),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn must_catch_unsupported_proposals() {
+        // given
+        // a single function of type `[] -> []` executing `i32.extend8_s`
+        let code = b"\0asm\x01\0\0\0\
+            \x01\x04\x01\x60\0\0\
+            \x03\x02\x01\0\
+            \x0a\x08\x01\x06\0\x41\0\xc0\x1a\x0b";
+
+        // when
+        let res = validate_module(code, 16);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(
+            err.contains(
+                "The function #0 is not supported by `pallet-contracts`: \
+                sign extension operations support is not enabled."
+            ),
+            "{err}"
+        );
+        assert!(err.contains("The contract Wasm does not export the function `call`."));
+    }
+
+    #[test]
+    fn must_catch_invalid_exports() {
+        // given
+        let mut module: Module =
+            parity_wasm::deserialize_buffer(&create_contract(vec![Instruction::End]))
+                .unwrap();
+        module.export_section_mut().unwrap().entries_mut().pop();
+        module.type_section_mut().unwrap().types_mut()[0] =
+            Type::Function(FunctionType::new(vec![ValueType::I32], vec![]));
+        let code = parity_wasm::serialize(module).unwrap();
+
+        // when
+        let res = validate_module(&code, 16);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains(
+            "The exported function `call` of the contract Wasm must not have any \
+            parameters or results, but has 1 parameters and 0 results."
+        ));
+        assert!(err.contains("The contract Wasm does not export the function `deploy`."));
+    }
+
+    #[test]
+    fn must_catch_data_segments_exceeding_the_memory() {
+        // given
+        let code = create_contract(vec![Instruction::End]);
+
+        // when
+        let res = validate_module(&code, 0);
+
+        // then
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains(
+            "A data segment of the contract Wasm ends at byte 1031, beyond the maximum \
+            memory of 0 pages."
+        ));
+    }
+
     #[test]
     fn must_validate_successfully_if_no_import_section_found() {
         // given
@@ -0,0 +1,209 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::extrinsics::{
+    prompt_secret,
+    CryptoScheme,
+    Keypair,
+    Keystore,
+    KeystoreJson,
+};
+use crate::name_value_println;
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use colored::Colorize as _;
+use std::{
+    io::Write as _,
+    path::PathBuf,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "account", about = "Manage the accounts of the local keystore")]
+pub struct AccountCommand {
+    #[clap(subcommand)]
+    action: AccountAction,
+    /// Directory of the keystore, `cargo-contract/keystore` in the data directory of the
+    /// user by default.
+    #[clap(long, value_parser, env = "CARGO_CONTRACT_KEYSTORE", global = true)]
+    keystore: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Subcommand)]
+enum AccountAction {
+    /// Add an account to the keystore, from a secret key URI or an account exported
+    /// from polkadot-js
+    #[clap(name = "add")]
+    Add(AddAccount),
+    /// List the accounts of the keystore
+    #[clap(name = "list")]
+    List {
+        /// Export the accounts in JSON format.
+        #[clap(long)]
+        output_json: bool,
+    },
+    /// Remove an account from the keystore
+    #[clap(name = "remove")]
+    Remove {
+        /// Name of the account.
+        name: String,
+        /// Do not ask the user for confirmation.
+        #[clap(long)]
+        skip_confirm: bool,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+struct AddAccount {
+    /// Name of the account, which selects it with `--account`.
+    name: String,
+    /// Secret key URI of the account, prompted for on a terminal if neither `--suri`
+    /// nor `--json` is specified.
+    #[clap(long, short, conflicts_with = "json")]
+    suri: Option<String>,
+    /// The signature scheme of the secret key URI.
+    #[clap(long, value_enum, default_value = "sr25519", conflicts_with = "json")]
+    scheme: CryptoScheme,
+    /// Path to the JSON file of an account exported from polkadot-js, which is kept
+    /// encrypted with its password.
+    #[clap(long, value_parser)]
+    json: Option<PathBuf>,
+    /// Password encrypting the account, prompted for on a terminal if not specified.
+    #[clap(long, short)]
+    password: Option<String>,
+}
+
+/// An account of the keystore, as listed in JSON format.
+#[derive(serde::Serialize)]
+struct AccountJson {
+    name: String,
+    address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scheme: Option<String>,
+}
+
+impl AccountCommand {
+    pub fn run(&self) -> Result<()> {
+        let keystore = Keystore::open(self.keystore.as_deref())?;
+        match &self.action {
+            AccountAction::Add(add) => add.run(&keystore),
+            AccountAction::List { output_json } => {
+                let accounts = keystore.list()?;
+                if *output_json {
+                    let accounts = accounts
+                        .into_iter()
+                        .map(|(name, json)| {
+                            AccountJson {
+                                name,
+                                scheme: json.scheme().ok().map(|s| s.to_string()),
+                                address: json.address,
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    println!("{}", serde_json::to_string_pretty(&accounts)?);
+                } else if accounts.is_empty() {
+                    println!("No accounts in the keystore {}", keystore.dir().display());
+                } else {
+                    for (name, json) in accounts {
+                        let scheme = json
+                            .scheme()
+                            .map(|scheme| scheme.to_string())
+                            .unwrap_or_else(|_| "unsupported".to_string());
+                        name_value_println!(name, format!("{} ({scheme})", json.address));
+                    }
+                }
+                Ok(())
+            }
+            AccountAction::Remove { name, skip_confirm } => {
+                let json = keystore.load(name)?;
+                if !skip_confirm {
+                    print!(
+                        "{} `{name}` with the address {}? (y/{}): ",
+                        "Remove the account".bright_white().bold(),
+                        json.address,
+                        "N".bright_white().bold()
+                    );
+                    let mut buf = String::new();
+                    std::io::stdout().flush()?;
+                    std::io::stdin().read_line(&mut buf)?;
+                    if buf.trim().to_lowercase() != "y" {
+                        anyhow::bail!("The account `{name}` was not removed")
+                    }
+                }
+                keystore.remove(name)?;
+                name_value_println!("Removed", format!("{name} {}", json.address));
+                Ok(())
+            }
+        }
+    }
+}
+
+impl AddAccount {
+    fn run(&self, keystore: &Keystore) -> Result<()> {
+        let json = match &self.json {
+            Some(path) => {
+                let json = std::fs::read_to_string(path).with_context(|| {
+                    format!("Failed to read account {}", path.display())
+                })?;
+                let json: KeystoreJson =
+                    serde_json::from_str(&json).with_context(|| {
+                        format!("Failed to parse account {}", path.display())
+                    })?;
+                let password = match &self.password {
+                    Some(password) => password.clone(),
+                    None => {
+                        prompt_secret(
+                            "Password of the exported account",
+                            "specify it with `--password`",
+                        )?
+                    }
+                };
+                // checks the password before the account is added
+                json.decrypt(&password)?;
+                json
+            }
+            None => {
+                let suri = match &self.suri {
+                    Some(suri) => suri.clone(),
+                    None => prompt_secret("Secret key URI", "specify it with `--suri`")?,
+                };
+                let keypair = Keypair::from_suri(self.scheme, &suri, None)?;
+                let password = match &self.password {
+                    Some(password) => password.clone(),
+                    None => prompt_new_password()?,
+                };
+                KeystoreJson::encrypt(&keypair, &self.name, &password)?
+            }
+        };
+        let address = json.address.clone();
+        keystore.add(&self.name, json)?;
+        name_value_println!("Added", format!("{} {address}", self.name));
+        Ok(())
+    }
+}
+
+/// Prompts for the password of a new account twice.
+fn prompt_new_password() -> Result<String> {
+    let alternative = "specify it with `--password`";
+    let password = prompt_secret("Password of the account", alternative)?;
+    let repeated = prompt_secret("Repeat the password", alternative)?;
+    if password != repeated {
+        return Err(anyhow!("The passwords do not match"))
+    }
+    Ok(password)
+}
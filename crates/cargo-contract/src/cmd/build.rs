@@ -21,10 +21,14 @@ use contract_build::{
     BuildResult,
     ExecuteArgs,
     Features,
+    HostFunctions,
+    ImageVariant,
     ManifestPath,
+    MessageFormat,
     Network,
     OptimizationPasses,
     OutputType,
+    Packages,
     Target,
     UnstableFlags,
     UnstableOptions,
@@ -46,6 +50,8 @@ pub struct BuildCommand {
     /// Path to the `Cargo.toml` of the contract to build
     #[clap(long, value_parser)]
     manifest_path: Option<PathBuf>,
+    #[clap(flatten)]
+    packages: Packages,
     /// By default the contract is compiled with debug functionality
     /// included. This enables the contract to output debug messages,
     /// but increases the contract size and the amount of gas used.
@@ -106,13 +112,32 @@ pub struct BuildCommand {
     optimization_passes: Option<OptimizationPasses>,
     /// Do not remove symbols (Wasm name section) when optimizing.
     ///
+    /// For RISC-V contracts the symbol table and debug sections of the ELF are kept.
+    ///
     /// This is useful if one wants to analyze or debug the optimized binary.
     #[clap(long)]
     keep_debug_symbols: bool,
     /// Export the build output in JSON format.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
-    /// Don't perform wasm validation checks e.g. for permitted imports.
+    /// The format of the messages printed while building.
+    ///
+    /// - `human`: coloured progress output.
+    ///
+    /// - `json`: stream the build progress, compiler diagnostics and the final build
+    ///   result as newline delimited JSON events.
+    #[clap(
+        long,
+        value_enum,
+        default_value = "human",
+        conflicts_with_all = ["output_json", "verbose"]
+    )]
+    message_format: MessageFormat,
+    /// Don't perform wasm validation checks e.g. for permitted imports, floating point
+    /// instructions or Wasm proposals not supported by `pallet-contracts`.
+    ///
+    /// For RISC-V contracts this skips the checks of the ELF, e.g. for undefined
+    /// symbols.
     #[clap(long)]
     skip_wasm_validation: bool,
     /// Which bytecode to build the contract into.
@@ -121,16 +146,79 @@ pub struct BuildCommand {
     /// The maximum number of pages available for a wasm contract to allocate.
     #[clap(long, default_value_t = contract_build::DEFAULT_MAX_MEMORY_PAGES)]
     max_memory_pages: u32,
+    /// Executes the build inside of a pinned container image, so that the resulting
+    /// contract can be reproduced byte-for-byte. Implies `--release`.
+    ///
+    /// The image must be available locally, the digest of the image is recorded in
+    /// the contract's metadata.
+    #[clap(long, conflicts_with = "lint")]
+    verifiable: bool,
+    /// The image to use for a verifiable build, defaults to
+    /// `paritytech/contracts-verifiable:<cargo-contract version>`.
+    #[clap(long, requires = "verifiable")]
+    image: Option<String>,
+    /// The repository digest of the image the build is executed in, passed to the
+    /// build inside of the container of a verifiable build. It is rejected by other
+    /// builds.
+    #[clap(long, hide = true, conflicts_with = "verifiable")]
+    build_image: Option<String>,
+    /// Report the size of the contract's code per function, data segment and crate.
+    ///
+    /// The report is also written to `<name>.size.json` in the target directory. The
+    /// function names are resolved from the name section kept by `wasm-opt`, which is
+    /// stripped afterwards unless `--keep-debug-symbols` is passed. For RISC-V contracts
+    /// the functions are taken from the symbol table of the ELF emitted by the linker.
+    #[clap(long)]
+    size_report: bool,
+    /// A previously written size report (`<name>.size.json`) to compare the size
+    /// report against, e.g. to find out what grew between two commits.
+    #[clap(long, requires = "size_report")]
+    size_report_baseline: Option<PathBuf>,
+    /// A directory to cache the post processed code and the ink! metadata in.
+    ///
+    /// The entries are keyed by the hash of the code emitted by cargo, the sources of the
+    /// contract and its path dependencies and the settings of the post processing, so
+    /// the directory can be shared by CI runners or worktrees. On a hit `wasm-opt` and
+    /// the metadata generation are skipped.
+    #[clap(long, env = "CARGO_CONTRACT_CACHE_DIR")]
+    cache_dir: Option<PathBuf>,
+    /// A JSON file listing the host functions provided by the target chain, the
+    /// imports of the contract are checked against it.
+    ///
+    /// The file maps each module to the names of its functions, e.g.
+    /// `{"seal0": ["seal_input"], "seal1": ["seal_call"]}`.
+    #[clap(long, value_name = "FILE")]
+    check_host_fns: Option<PathBuf>,
 }
 
 impl BuildCommand {
     pub fn exec(&self) -> Result<BuildResult> {
+        contract_build::execute(self.execute_args()?)
+    }
+
+    /// Builds all contracts of the workspace selected by `--workspace`, `--package` and
+    /// `--exclude`.
+    pub fn exec_workspace(&self) -> Result<Vec<BuildResult>> {
+        contract_build::execute_workspace(self.execute_args()?, &self.packages)
+    }
+
+    /// Returns `true` if more than the contract of the manifest should be built.
+    pub fn is_workspace_build(&self) -> bool {
+        self.packages.is_workspace_build()
+    }
+
+    /// Returns `true` if the build results should be printed as JSON.
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    fn execute_args(&self) -> Result<ExecuteArgs> {
         let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
         let unstable_flags: UnstableFlags =
             TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
         let mut verbosity = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
 
-        let build_mode = match self.build_release {
+        let build_mode = match self.build_release || self.verifiable {
             true => BuildMode::Release,
             false => BuildMode::Debug,
         };
@@ -146,7 +234,9 @@ impl BuildCommand {
         };
 
         // We want to ensure that the only thing in `STDOUT` is our JSON formatted string.
-        if matches!(output_type, OutputType::Json) {
+        if matches!(output_type, OutputType::Json)
+            || self.message_format == MessageFormat::Json
+        {
             verbosity = Verbosity::Quiet;
         }
 
@@ -162,12 +252,24 @@ impl BuildCommand {
             keep_debug_symbols: self.keep_debug_symbols,
             lint: self.lint,
             output_type,
+            message_format: self.message_format,
             skip_wasm_validation: self.skip_wasm_validation,
             target: self.target,
             max_memory_pages: self.max_memory_pages,
+            verifiable: self.verifiable,
+            image: ImageVariant::from(self.image.clone()),
+            build_image: self.build_image.clone(),
+            size_report: self.size_report,
+            size_report_baseline: self.size_report_baseline.clone(),
+            cache_dir: self.cache_dir.clone(),
+            host_functions: self
+                .check_host_fns
+                .as_ref()
+                .map(HostFunctions::load)
+                .transpose()?,
         };
 
-        contract_build::execute(args)
+        Ok(args)
     }
 }
 
@@ -185,6 +287,9 @@ pub struct CheckCommand {
     unstable_options: UnstableOptions,
     #[clap(long, default_value = "wasm")]
     target: Target,
+    /// The format of the messages printed while checking, see `build`.
+    #[clap(long, value_enum, default_value = "human", conflicts_with = "verbose")]
+    message_format: MessageFormat,
 }
 
 impl CheckCommand {
@@ -192,7 +297,11 @@ impl CheckCommand {
         let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
         let unstable_flags: UnstableFlags =
             TryFrom::<&UnstableOptions>::try_from(&self.unstable_options)?;
-        let verbosity: Verbosity = TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+        let mut verbosity: Verbosity =
+            TryFrom::<&VerbosityFlags>::try_from(&self.verbosity)?;
+        if self.message_format == MessageFormat::Json {
+            verbosity = Verbosity::Quiet;
+        }
 
         let args = ExecuteArgs {
             manifest_path,
@@ -206,9 +315,17 @@ impl CheckCommand {
             keep_debug_symbols: false,
             lint: false,
             output_type: OutputType::default(),
+            message_format: self.message_format,
             skip_wasm_validation: false,
             target: self.target,
             max_memory_pages: 0,
+            verifiable: false,
+            image: ImageVariant::default(),
+            build_image: None,
+            size_report: false,
+            size_report_baseline: None,
+            cache_dir: None,
+            host_functions: None,
         };
 
         contract_build::execute(args)
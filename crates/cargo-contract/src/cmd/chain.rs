@@ -0,0 +1,794 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The configuration of the chain the commands connect to.
+//!
+//! The types of `subxt` are fixed at compile time by [`ChainConfig`], their encoding
+//! follows the [`ChainProfile`] installed at start up, `PolkadotConfig` by default.
+
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use contract_transcode::AccountIdFormat;
+use scale::{
+    Compact,
+    Decode,
+    Encode,
+    Input,
+    Output,
+};
+use scale_info::{
+    PortableRegistry,
+    TypeDef,
+};
+use serde::{
+    Deserialize,
+    Deserializer,
+    Serialize,
+    Serializer,
+};
+use std::{
+    fmt,
+    path::Path,
+    str::FromStr,
+    sync::OnceLock,
+};
+use subxt::{
+    config::{
+        polkadot::Era,
+        substrate::{
+            BlakeTwo256,
+            SubstrateHeader,
+        },
+        ExtrinsicParams,
+    },
+    utils::{
+        AccountId32,
+        MultiSignature,
+        H256,
+    },
+    Config,
+    Metadata,
+};
+
+static PROFILE: OnceLock<ChainProfile> = OnceLock::new();
+
+/// Returns the profile of the chain, the one of Polkadot like chains unless another one
+/// is installed.
+pub fn profile() -> &'static ChainProfile {
+    PROFILE.get_or_init(ChainProfile::default)
+}
+
+/// A TOML profile describing the types of a chain which differ between chains.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ChainProfile {
+    /// The accounts of the chain, which determine how extrinsics are signed.
+    pub account_id: AccountFormat,
+    /// The address of the signer included in extrinsics.
+    pub address: AddressFormat,
+    /// The signed extensions of the runtime, in the order of the runtime.
+    pub signed_extensions: Vec<SignedExtension>,
+    /// The SCALE encoded asset fees are paid in with `ChargeAssetTxPayment`, the native
+    /// currency if not specified.
+    #[serde(deserialize_with = "deserialize_hex")]
+    pub asset_id: Option<Vec<u8>>,
+}
+
+impl Default for ChainProfile {
+    fn default() -> Self {
+        Self {
+            account_id: AccountFormat::Ss58,
+            address: AddressFormat::Id,
+            signed_extensions: vec![
+                SignedExtension::CheckNonZeroSender,
+                SignedExtension::CheckSpecVersion,
+                SignedExtension::CheckTxVersion,
+                SignedExtension::CheckGenesis,
+                SignedExtension::CheckMortality,
+                SignedExtension::CheckNonce,
+                SignedExtension::CheckWeight,
+                SignedExtension::ChargeTransactionPayment,
+            ],
+            asset_id: None,
+        }
+    }
+}
+
+impl ChainProfile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let toml = std::fs::read_to_string(path).with_context(|| {
+            format!("Failed to read the chain profile {}", path.display())
+        })?;
+        let profile: Self = toml::from_str(&toml).with_context(|| {
+            format!("Failed to parse the chain profile {}", path.display())
+        })?;
+        profile
+            .validate()
+            .with_context(|| format!("Invalid chain profile {}", path.display()))?;
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.address == AddressFormat::Address20
+            && self.account_id != AccountFormat::H160
+        {
+            anyhow::bail!("The `address20` address requires `h160` accounts")
+        }
+        if self.asset_id.is_some()
+            && !self
+                .signed_extensions
+                .contains(&SignedExtension::ChargeAssetTxPayment)
+        {
+            anyhow::bail!("The `asset-id` requires the `ChargeAssetTxPayment` extension")
+        }
+        Ok(())
+    }
+
+    /// Checks that the `account` given with the argument `arg` is an account of the
+    /// chain.
+    pub fn check_account(&self, account: &AccountId, arg: &str) -> Result<()> {
+        match (self.account_id, account) {
+            (AccountFormat::Ss58, AccountId::Id32(_))
+            | (AccountFormat::H160, AccountId::Id20(_)) => Ok(()),
+            (format, account) => {
+                Err(anyhow!(
+                "The account {account} of `{arg}` has {} bytes, but the chain profile \
+                    expects {}",
+                account.as_ref().len(),
+                match format {
+                    AccountFormat::Ss58 => "32 byte SS58 accounts",
+                    AccountFormat::H160 => "20 byte H160 accounts",
+                }
+            ))
+            }
+        }
+    }
+
+    /// Checks the signed extensions against the ones of the runtime in the `metadata`.
+    ///
+    /// Only the extensions adding data to the extrinsic or to the signed payload have to
+    /// match, others don't change the encoding of the extrinsic.
+    pub fn check_signed_extensions(&self, metadata: &Metadata) -> Result<()> {
+        let types = metadata.types();
+        let runtime = metadata
+            .runtime_metadata()
+            .extrinsic
+            .signed_extensions
+            .iter()
+            .filter(|extension| {
+                !is_zero_sized(extension.ty.id, types)
+                    || !is_zero_sized(extension.additional_signed.id, types)
+            })
+            .map(|extension| {
+                match extension.identifier.as_str() {
+                    "CheckEra" => "CheckMortality".to_string(),
+                    identifier => identifier.to_string(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let profile = self
+            .signed_extensions
+            .iter()
+            .filter(|extension| extension.adds_data())
+            .map(|extension| format!("{extension:?}"))
+            .collect::<Vec<_>>();
+        if runtime != profile {
+            anyhow::bail!(
+                "The signed extensions of the chain profile ({}) do not match the ones of \
+                the chain ({}), pass a `--chain-profile` describing the chain",
+                profile.join(", "),
+                runtime.join(", ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Makes the profile the one of the chain for the rest of the process.
+    pub fn install(self) -> Result<()> {
+        PROFILE
+            .set(self)
+            .map_err(|_| anyhow!("The chain profile is already installed"))
+    }
+}
+
+/// The accounts of a chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccountFormat {
+    /// 32 byte accounts of any key, given as SS58 addresses, like on Polkadot.
+    #[default]
+    Ss58,
+    /// 20 byte Ethereum style accounts of ecdsa keys, given as hex encoded addresses.
+    /// The extrinsics are signed with Ethereum signatures.
+    H160,
+}
+
+impl AccountFormat {
+    /// The format of the `AccountId` of contracts, for their transcoder.
+    pub fn transcoder_format(&self) -> AccountIdFormat {
+        match self {
+            AccountFormat::Ss58 => AccountIdFormat::Ss58,
+            AccountFormat::H160 => AccountIdFormat::H160,
+        }
+    }
+}
+
+/// The address of the signer included in extrinsics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressFormat {
+    /// `MultiAddress::Id` holding the account.
+    #[default]
+    Id,
+    /// `MultiAddress::Address20` holding the 20 byte account.
+    Address20,
+    /// The account itself.
+    AccountId,
+}
+
+/// A signed extension of the runtime, which adds data to signed extrinsics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum SignedExtension {
+    CheckNonZeroSender,
+    CheckSpecVersion,
+    CheckTxVersion,
+    CheckGenesis,
+    #[serde(alias = "CheckEra")]
+    CheckMortality,
+    CheckNonce,
+    CheckWeight,
+    ChargeTransactionPayment,
+    ChargeAssetTxPayment,
+}
+
+impl SignedExtension {
+    /// Whether the extension adds data to the extrinsic or to the signed payload.
+    fn adds_data(&self) -> bool {
+        !matches!(
+            self,
+            SignedExtension::CheckNonZeroSender | SignedExtension::CheckWeight
+        )
+    }
+}
+
+/// Whether the type is encoded without any data, e.g. `()` or a struct without fields.
+fn is_zero_sized(id: u32, types: &PortableRegistry) -> bool {
+    match types.resolve(id).map(|ty| &ty.type_def) {
+        Some(TypeDef::Composite(composite)) => {
+            composite
+                .fields
+                .iter()
+                .all(|field| is_zero_sized(field.ty.id, types))
+        }
+        Some(TypeDef::Tuple(tuple)) => {
+            tuple
+                .fields
+                .iter()
+                .all(|field| is_zero_sized(field.id, types))
+        }
+        Some(TypeDef::Array(array)) => {
+            array.len == 0 || is_zero_sized(array.type_param.id, types)
+        }
+        _ => false,
+    }
+}
+
+fn deserialize_hex<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let hex = String::deserialize(deserializer)?;
+    contract_build::util::decode_hex(&hex)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// The `subxt` configuration of the chain, whose types are encoded as described by the
+/// installed [`ChainProfile`].
+pub enum ChainConfig {}
+
+impl Config for ChainConfig {
+    type Index = u32;
+    type Hash = H256;
+    type AccountId = AccountId;
+    type Address = Address;
+    type Signature = Signature;
+    type Hasher = BlakeTwo256;
+    type Header = SubstrateHeader<u32, BlakeTwo256>;
+    type ExtrinsicParams = ChainExtrinsicParams;
+}
+
+/// An account of the chain, of 32 or 20 bytes.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AccountId {
+    Id32(AccountId32),
+    Id20([u8; 20]),
+}
+
+impl AccountId {
+    /// Returns the account of the `format` derived from the 32 byte `entropy`, e.g. for
+    /// multisig accounts.
+    pub fn from_entropy(entropy: [u8; 32], format: AccountFormat) -> Self {
+        match format {
+            AccountFormat::Ss58 => AccountId::Id32(AccountId32(entropy)),
+            AccountFormat::H160 => {
+                let mut account = [0; 20];
+                account.copy_from_slice(&entropy[..20]);
+                AccountId::Id20(account)
+            }
+        }
+    }
+
+    /// Returns the account holding the `bytes` of an account of the chain.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        match bytes.len() {
+            32 => Ok(AccountId::Id32(AccountId32(bytes.try_into()?))),
+            20 => Ok(AccountId::Id20(bytes.try_into()?)),
+            len => Err(anyhow!("Expected an account of 32 or 20 bytes, got {len}")),
+        }
+    }
+}
+
+impl AsRef<[u8]> for AccountId {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            AccountId::Id32(account) => account.as_ref(),
+            AccountId::Id20(account) => account,
+        }
+    }
+}
+
+impl From<AccountId32> for AccountId {
+    fn from(account: AccountId32) -> Self {
+        AccountId::Id32(account)
+    }
+}
+
+impl Encode for AccountId {
+    fn size_hint(&self) -> usize {
+        self.as_ref().len()
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        dest.write(self.as_ref())
+    }
+}
+
+impl Decode for AccountId {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, scale::Error> {
+        match profile().account_id {
+            AccountFormat::Ss58 => Ok(AccountId::Id32(AccountId32::decode(input)?)),
+            AccountFormat::H160 => Ok(AccountId::Id20(<[u8; 20]>::decode(input)?)),
+        }
+    }
+}
+
+impl FromStr for AccountId {
+    type Err = anyhow::Error;
+
+    /// Parses SS58 addresses of 32 byte accounts, and hex encoded addresses of 20 or 32
+    /// byte accounts.
+    fn from_str(s: &str) -> Result<Self> {
+        if s.starts_with("0x") {
+            let bytes = contract_build::util::decode_hex(s)?;
+            Self::from_bytes(&bytes)
+        } else {
+            let account = AccountId32::from_str(s)
+                .map_err(|err| anyhow!("Invalid SS58 address `{s}`: {err}"))?;
+            Ok(AccountId::Id32(account))
+        }
+    }
+}
+
+impl fmt::Display for AccountId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AccountId::Id32(account) => write!(f, "{account}"),
+            AccountId::Id20(account) => write!(f, "0x{}", hex::encode(account)),
+        }
+    }
+}
+
+impl Serialize for AccountId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for AccountId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let address = String::deserialize(deserializer)?;
+        Self::from_str(&address).map_err(serde::de::Error::custom)
+    }
+}
+
+/// The address of the signer of an extrinsic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Address(pub AccountId);
+
+impl Address {
+    pub fn encode_as(&self, format: AddressFormat, dest: &mut Vec<u8>) {
+        match format {
+            AddressFormat::Id => 0u8.encode_to(dest),
+            AddressFormat::Address20 => 4u8.encode_to(dest),
+            AddressFormat::AccountId => (),
+        }
+        self.0.encode_to(dest)
+    }
+}
+
+impl From<AccountId> for Address {
+    fn from(account: AccountId) -> Self {
+        Address(account)
+    }
+}
+
+impl Encode for Address {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        self.encode_as(profile().address, &mut encoded);
+        encoded
+    }
+}
+
+/// The signature of an extrinsic.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Signature {
+    Multi(MultiSignature),
+    /// A recoverable ecdsa signature of the keccak hash of the payload.
+    Ethereum([u8; 65]),
+}
+
+impl Encode for Signature {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            Signature::Multi(signature) => signature.encode_to(dest),
+            Signature::Ethereum(signature) => dest.write(signature),
+        }
+    }
+}
+
+/// The signed extra and additional data of extrinsics, as listed by the signed
+/// extensions of the [`ChainProfile`].
+#[derive(Debug)]
+pub struct ChainExtrinsicParams {
+    signed_extensions: Vec<SignedExtension>,
+    asset_id: Option<Vec<u8>>,
+    era: Era,
+    mortality_checkpoint: H256,
+    nonce: u32,
+    tip: u128,
+    spec_version: u32,
+    transaction_version: u32,
+    genesis_hash: H256,
+}
+
+/// The parameters of [`ChainExtrinsicParams`] which are not provided by `subxt`.
+#[derive(Clone, Debug)]
+pub struct ChainExtrinsicParamsBuilder {
+    profile: &'static ChainProfile,
+    era: Era,
+    mortality_checkpoint: Option<H256>,
+    tip: u128,
+}
+
+impl ChainExtrinsicParamsBuilder {
+    /// Immortal extrinsics without tip, for the installed [`ChainProfile`].
+    pub fn new() -> Self {
+        Self::for_profile(profile())
+    }
+
+    pub fn for_profile(profile: &'static ChainProfile) -> Self {
+        Self {
+            profile,
+            era: Era::Immortal,
+            mortality_checkpoint: None,
+            tip: 0,
+        }
+    }
+
+    /// The period the extrinsic is valid for, starting at the block `checkpoint`.
+    pub fn era(mut self, era: Era, checkpoint: H256) -> Self {
+        self.era = era;
+        self.mortality_checkpoint = Some(checkpoint);
+        self
+    }
+}
+
+impl Default for ChainExtrinsicParamsBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExtrinsicParams<u32, H256> for ChainExtrinsicParams {
+    type OtherParams = ChainExtrinsicParamsBuilder;
+
+    fn new(
+        spec_version: u32,
+        transaction_version: u32,
+        nonce: u32,
+        genesis_hash: H256,
+        other_params: Self::OtherParams,
+    ) -> Self {
+        Self {
+            signed_extensions: other_params.profile.signed_extensions.clone(),
+            asset_id: other_params.profile.asset_id.clone(),
+            era: other_params.era,
+            mortality_checkpoint: other_params
+                .mortality_checkpoint
+                .unwrap_or(genesis_hash),
+            nonce,
+            tip: other_params.tip,
+            spec_version,
+            transaction_version,
+            genesis_hash,
+        }
+    }
+
+    fn encode_extra_to(&self, v: &mut Vec<u8>) {
+        for extension in &self.signed_extensions {
+            match extension {
+                SignedExtension::CheckMortality => self.era.encode_to(v),
+                SignedExtension::CheckNonce => Compact(self.nonce).encode_to(v),
+                SignedExtension::ChargeTransactionPayment => {
+                    Compact(self.tip).encode_to(v)
+                }
+                SignedExtension::ChargeAssetTxPayment => {
+                    Compact(self.tip).encode_to(v);
+                    match self.asset_id {
+                        Some(ref asset_id) => {
+                            1u8.encode_to(v);
+                            v.extend_from_slice(asset_id);
+                        }
+                        None => 0u8.encode_to(v),
+                    }
+                }
+                SignedExtension::CheckNonZeroSender
+                | SignedExtension::CheckSpecVersion
+                | SignedExtension::CheckTxVersion
+                | SignedExtension::CheckGenesis
+                | SignedExtension::CheckWeight => (),
+            }
+        }
+    }
+
+    fn encode_additional_to(&self, v: &mut Vec<u8>) {
+        for extension in &self.signed_extensions {
+            match extension {
+                SignedExtension::CheckSpecVersion => self.spec_version.encode_to(v),
+                SignedExtension::CheckTxVersion => self.transaction_version.encode_to(v),
+                SignedExtension::CheckGenesis => self.genesis_hash.encode_to(v),
+                SignedExtension::CheckMortality => self.mortality_checkpoint.encode_to(v),
+                SignedExtension::CheckNonZeroSender
+                | SignedExtension::CheckNonce
+                | SignedExtension::CheckWeight
+                | SignedExtension::ChargeTransactionPayment
+                | SignedExtension::ChargeAssetTxPayment => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use subxt::config::polkadot::PolkadotExtrinsicParamsBuilder;
+
+    #[test]
+    fn default_profile_encodes_extrinsics_like_polkadot() {
+        // given
+        let genesis_hash = H256::repeat_byte(1);
+        let checkpoint = H256::repeat_byte(2);
+        let era = Era::mortal(64, 7);
+        static PROFILE: OnceLock<ChainProfile> = OnceLock::new();
+        let profile = PROFILE.get_or_init(ChainProfile::default);
+
+        // when
+        let params = ChainExtrinsicParams::new(
+            100,
+            1,
+            7,
+            genesis_hash,
+            ChainExtrinsicParamsBuilder::for_profile(profile).era(era, checkpoint),
+        );
+
+        // then
+        let polkadot = <<subxt::PolkadotConfig as Config>::ExtrinsicParams>::new(
+            100,
+            1,
+            7,
+            genesis_hash,
+            PolkadotExtrinsicParamsBuilder::new().era(era, checkpoint),
+        );
+        let encode = |params: &dyn Fn(&mut Vec<u8>)| {
+            let mut encoded = Vec::new();
+            params(&mut encoded);
+            encoded
+        };
+        assert_eq!(
+            encode(&|v| params.encode_extra_to(v)),
+            encode(&|v| polkadot.encode_extra_to(v))
+        );
+        assert_eq!(
+            encode(&|v| params.encode_additional_to(v)),
+            encode(&|v| polkadot.encode_additional_to(v))
+        );
+    }
+
+    #[test]
+    fn profiles_are_parsed_from_toml() {
+        // given
+        let toml = r#"
+            account-id = "h160"
+            address = "account-id"
+            signed-extensions = [
+                "CheckSpecVersion",
+                "CheckTxVersion",
+                "CheckGenesis",
+                "CheckEra",
+                "CheckNonce",
+                "CheckWeight",
+                "ChargeAssetTxPayment",
+            ]
+            asset-id = "0x01000000"
+        "#;
+
+        // when
+        let profile: ChainProfile = toml::from_str(toml).unwrap();
+
+        // then
+        assert_eq!(profile.account_id, AccountFormat::H160);
+        assert_eq!(profile.address, AddressFormat::AccountId);
+        assert_eq!(
+            profile.signed_extensions[3],
+            SignedExtension::CheckMortality
+        );
+        assert_eq!(profile.asset_id, Some(vec![1, 0, 0, 0]));
+        assert!(profile.validate().is_ok());
+    }
+
+    #[test]
+    fn accounts_are_checked_against_the_profile() {
+        // given
+        let account32 = AccountId::Id32(AccountId32([1; 32]));
+        let account20 = AccountId::Id20([1; 20]);
+        let h160 = ChainProfile {
+            account_id: AccountFormat::H160,
+            ..Default::default()
+        };
+
+        // when
+        let err = h160.check_account(&account32, "--contract").unwrap_err();
+
+        // then
+        assert!(ChainProfile::default()
+            .check_account(&account32, "--origin")
+            .is_ok());
+        assert!(h160.check_account(&account20, "--origin").is_ok());
+        assert!(ChainProfile::default()
+            .check_account(&account20, "--origin")
+            .is_err());
+        assert_eq!(
+            err.to_string(),
+            "The account 5C62Ck4UrFPiBtoCmeSrgF7x9yv9mn38446dhCpsi2mLHiFT of \
+            `--contract` has 32 bytes, but the chain profile expects 20 byte H160 accounts"
+        );
+    }
+
+    #[test]
+    fn signed_extensions_are_checked_against_the_metadata() {
+        // given
+        let metadata = include_bytes!("runtime_api/contracts_runtime.scale");
+        let metadata =
+            <subxt::ext::frame_metadata::RuntimeMetadataPrefixed as Decode>::decode(
+                &mut &metadata[..],
+            )
+            .unwrap();
+        let metadata = Metadata::try_from(metadata).unwrap();
+        let asset_tx_payment = ChainProfile {
+            signed_extensions: vec![
+                SignedExtension::CheckSpecVersion,
+                SignedExtension::CheckTxVersion,
+                SignedExtension::CheckGenesis,
+                SignedExtension::CheckMortality,
+                SignedExtension::CheckNonce,
+                SignedExtension::ChargeAssetTxPayment,
+            ],
+            ..Default::default()
+        };
+
+        // when
+        let err = asset_tx_payment
+            .check_signed_extensions(&metadata)
+            .unwrap_err();
+
+        // then
+        assert!(ChainProfile::default()
+            .check_signed_extensions(&metadata)
+            .is_ok());
+        assert!(err
+            .to_string()
+            .contains("ChargeAssetTxPayment) do not match the ones of the chain"));
+    }
+
+    #[test]
+    fn asset_ids_are_encoded_with_the_tip() {
+        // given
+        static PROFILE: OnceLock<ChainProfile> = OnceLock::new();
+        let profile = PROFILE.get_or_init(|| {
+            ChainProfile {
+                signed_extensions: vec![
+                    SignedExtension::CheckNonce,
+                    SignedExtension::ChargeAssetTxPayment,
+                ],
+                asset_id: Some(vec![1, 0, 0, 0]),
+                ..Default::default()
+            }
+        });
+
+        // when
+        let params = ChainExtrinsicParams::new(
+            100,
+            1,
+            7,
+            H256::zero(),
+            ChainExtrinsicParamsBuilder::for_profile(profile),
+        );
+        let mut extra = Vec::new();
+        params.encode_extra_to(&mut extra);
+
+        // then
+        // the compact nonce and tip, followed by `Some` asset id
+        assert_eq!(extra, vec![28, 0, 1, 1, 0, 0, 0]);
+    }
+
+    #[test]
+    fn accounts_are_parsed_and_displayed_in_their_format() {
+        let ss58 = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let h160 = "0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac";
+
+        let account = AccountId::from_str(ss58).unwrap();
+        assert!(matches!(account, AccountId::Id32(_)));
+        assert_eq!(account.to_string(), ss58);
+        let account = AccountId::from_str(h160).unwrap();
+        assert!(matches!(account, AccountId::Id20(_)));
+        assert_eq!(account.to_string(), h160);
+        assert_eq!(account.encode(), hex::decode(&h160[2..]).unwrap());
+        assert!(AccountId::from_str("0x0102").is_err());
+    }
+
+    #[test]
+    fn addresses_are_encoded_in_their_format() {
+        let account = AccountId::Id20([7; 20]);
+        let address = Address(account.clone());
+        let encode = |format| {
+            let mut encoded = Vec::new();
+            address.encode_as(format, &mut encoded);
+            encoded
+        };
+
+        assert_eq!(encode(AddressFormat::Id), [&[0], &[7; 20][..]].concat());
+        assert_eq!(
+            encode(AddressFormat::Address20),
+            [&[4], &[7; 20][..]].concat()
+        );
+        assert_eq!(encode(AddressFormat::AccountId), account.encode());
+    }
+}
@@ -29,8 +29,8 @@ use colored::Colorize as _;
     about = "Encodes a contracts input calls and their arguments"
 )]
 pub struct EncodeCommand {
-    /// Path to a contract build artifact file: a raw `.wasm` file, a `.contract` bundle,
-    /// or a `.json` metadata file.
+    /// Path to a contract build artifact file: a raw `.wasm` or `.riscv` file, a
+    /// `.contract` bundle, or a `.json` metadata file.
     #[clap(value_parser, conflicts_with = "manifest_path")]
     file: Option<PathBuf>,
     /// Path to the `Cargo.toml` of the contract.
@@ -49,6 +49,7 @@ impl EncodeCommand {
         let artifacts = ContractArtifacts::from_manifest_or_file(
             self.manifest_path.as_ref(),
             self.file.as_ref(),
+            None,
         )?;
         let transcoder = artifacts.contract_transcoder()?;
 
@@ -0,0 +1,845 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    contracts_pallet::{
+        CodeStored,
+        ContractsEvent,
+        Fields,
+        Instantiated,
+    },
+    display_dry_run_result_warning,
+    events::{
+        DisplayEvents,
+        ExtrinsicDetails,
+    },
+    instantiate::Code,
+    prompt_confirm_tx,
+    step::{
+        call_description,
+        default_constructor,
+        step_artifacts,
+        step_value,
+        StepCall,
+        StepDryRunOutput,
+    },
+    Client,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    MultisigOpts,
+    OfflineOpts,
+    Origin,
+    ProxyOpts,
+    Submission,
+    TokenMetadata,
+};
+use crate::{
+    cmd::{
+        chain::profile,
+        Balance,
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use colored::Colorize as _;
+use contract_build::util::decode_hex;
+use sp_weights::Weight;
+use std::{
+    fmt::Debug,
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+use subxt::{
+    dynamic::Value,
+    events::EventDetails,
+    Config,
+    OnlineClient,
+};
+
+/// The name of `pallet-utility` in the runtime.
+const UTILITY_PALLET: &str = "Utility";
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "batch",
+    about = "Submit the steps of a batch file in a single extrinsic"
+)]
+pub struct BatchCommand {
+    /// Path to the JSON file listing the upload, instantiate and call steps of the
+    /// batch.
+    #[clap(value_parser)]
+    batch_file: PathBuf,
+    /// The contract artifacts of `extrinsic_opts` are used by the steps which specify
+    /// neither a `file` nor a `manifest_path`.
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[clap(flatten)]
+    offline_opts: OfflineOpts,
+    #[clap(flatten)]
+    multisig_opts: MultisigOpts,
+    #[clap(flatten)]
+    proxy_opts: ProxyOpts,
+    /// Submit the steps with `utility.batch`, which stops at the first failing step but
+    /// keeps the effects of the preceding ones, instead of `utility.batch_all`, which
+    /// reverts all of them.
+    #[clap(long)]
+    non_atomic: bool,
+    /// Export the batch output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// A step of a batch file.
+///
+/// Relative paths are resolved against the directory of the batch file.
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum Step {
+    /// Upload the code of a contract.
+    Upload {
+        file: Option<PathBuf>,
+        manifest_path: Option<PathBuf>,
+    },
+    /// Instantiate a contract, uploading its code if the artifact contains it.
+    Instantiate {
+        file: Option<PathBuf>,
+        manifest_path: Option<PathBuf>,
+        #[serde(default = "default_constructor")]
+        constructor: String,
+        #[serde(default)]
+        args: Vec<String>,
+        value: Option<String>,
+        gas_limit: Option<u64>,
+        proof_size: Option<u64>,
+        salt: Option<String>,
+    },
+    /// Call a message of a contract.
+    Call {
+        file: Option<PathBuf>,
+        manifest_path: Option<PathBuf>,
+        contract: String,
+        message: String,
+        #[serde(default)]
+        args: Vec<String>,
+        value: Option<String>,
+        gas_limit: Option<u64>,
+        proof_size: Option<u64>,
+    },
+}
+
+/// A step of the batch, encoded and ready to be dry run and submitted.
+struct PreparedStep {
+    /// Describes the step to the user.
+    description: String,
+    call: StepCall,
+    /// The gas limit given in the batch file, estimated by a dry run if not given
+    /// together with the `proof_size`.
+    gas_limit: Option<u64>,
+    proof_size: Option<u64>,
+    /// Decodes the contract events emitted by the step.
+    transcoder: Option<ContractMessageTranscoder>,
+}
+
+impl BatchCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    pub fn run(&self) -> Result<(), ErrorVariant> {
+        let steps = self.load_steps()?;
+        let signer = self.offline_opts.signer(&self.extrinsic_opts)?;
+        let origin = self.proxy_opts.origin(self.multisig_opts.origin(signer)?)?;
+        let url = self.extrinsic_opts.url_to_string();
+
+        async_std::task::block_on(async {
+            let client = OnlineClient::from_url(url.clone()).await?;
+            let token_metadata = TokenMetadata::query(&client).await?;
+            let steps = steps
+                .into_iter()
+                .map(|step| self.prepare_step(step, &token_metadata))
+                .collect::<Result<Vec<_>>>()?;
+            let storage_deposit_limit = self
+                .extrinsic_opts
+                .storage_deposit_limit
+                .as_ref()
+                .map(|bv| bv.denominate_balance(&token_metadata))
+                .transpose()?;
+
+            if !self.extrinsic_opts.execute {
+                return self
+                    .dry_run(&steps, &url, &client, &origin, storage_deposit_limit)
+                    .await
+            }
+
+            let mut gas_limits = Vec::new();
+            for (index, step) in steps.iter().enumerate() {
+                let gas_limit = self
+                    .pre_submit_dry_run_gas_estimate(
+                        index,
+                        step,
+                        &url,
+                        &client,
+                        &origin,
+                        storage_deposit_limit,
+                    )
+                    .await?;
+                gas_limits.push(gas_limit);
+            }
+
+            if !self.extrinsic_opts.skip_confirm {
+                prompt_confirm_tx(|| {
+                    for (index, (step, gas_limit)) in
+                        steps.iter().zip(&gas_limits).enumerate()
+                    {
+                        print_step_header(index, step);
+                        if !matches!(step.call, StepCall::Upload { .. }) {
+                            name_value_println!(
+                                "Gas limit",
+                                gas_limit.to_string(),
+                                DEFAULT_KEY_COL_WIDTH
+                            );
+                        }
+                    }
+                })?;
+            }
+
+            self.submit(&steps, &gas_limits, &client, &origin, &token_metadata)
+                .await
+        })
+    }
+
+    /// Loads the steps of the batch file.
+    fn load_steps(&self) -> Result<Vec<Step>> {
+        let json = std::fs::read_to_string(&self.batch_file).with_context(|| {
+            format!("Failed to read batch file {}", self.batch_file.display())
+        })?;
+        let steps: Vec<Step> = serde_json::from_str(&json).with_context(|| {
+            format!("Failed to parse batch file {}", self.batch_file.display())
+        })?;
+        if steps.is_empty() {
+            anyhow::bail!("The batch file {} has no steps", self.batch_file.display())
+        }
+        Ok(steps)
+    }
+
+    /// Encodes the step.
+    fn prepare_step(
+        &self,
+        step: Step,
+        token_metadata: &TokenMetadata,
+    ) -> Result<PreparedStep> {
+        let dir = self.batch_file.parent().unwrap_or_else(|| Path::new(""));
+        let artifacts = |file: Option<PathBuf>, manifest_path: Option<PathBuf>| {
+            step_artifacts(
+                &self.extrinsic_opts,
+                dir,
+                file.as_ref(),
+                manifest_path.as_ref(),
+            )
+        };
+        match step {
+            Step::Upload {
+                file,
+                manifest_path,
+            } => {
+                let artifacts = artifacts(file, manifest_path)?;
+                let description =
+                    format!("upload {}", artifacts.artifact_path().display());
+                let code = artifacts.code.ok_or_else(|| {
+                    anyhow!("Contract code not found for the step `{description}`")
+                })?;
+                Ok(PreparedStep {
+                    description,
+                    call: StepCall::Upload { code: code.code },
+                    gas_limit: None,
+                    proof_size: None,
+                    transcoder: None,
+                })
+            }
+            Step::Instantiate {
+                file,
+                manifest_path,
+                constructor,
+                args,
+                value: balance,
+                gas_limit: ref_time,
+                proof_size,
+                salt,
+            } => {
+                let artifacts = artifacts(file, manifest_path)?;
+                let transcoder = artifacts.contract_transcoder()?;
+                let data = transcoder.encode(&constructor, &args)?;
+                let description = format!(
+                    "instantiate {} {}",
+                    artifacts.artifact_path().display(),
+                    call_description(&constructor, &args)
+                );
+                let code = match artifacts.code {
+                    Some(code) => Code::Upload(code.code),
+                    None => Code::Existing(artifacts.code_hash()?.into()),
+                };
+                let salt = salt.map(|salt| decode_hex(&salt)).transpose()?;
+                Ok(PreparedStep {
+                    description,
+                    call: StepCall::Instantiate {
+                        code,
+                        value: step_value(balance.as_deref(), token_metadata)?,
+                        data,
+                        salt: salt.unwrap_or_default(),
+                    },
+                    gas_limit: ref_time,
+                    proof_size,
+                    transcoder: Some(transcoder),
+                })
+            }
+            Step::Call {
+                file,
+                manifest_path,
+                contract,
+                message,
+                args,
+                value: balance,
+                gas_limit,
+                proof_size,
+            } => {
+                let artifacts = artifacts(file, manifest_path)?;
+                let transcoder = artifacts.contract_transcoder()?;
+                let data = transcoder.encode(&message, &args)?;
+                let contract = <DefaultConfig as Config>::AccountId::from_str(&contract)
+                    .map_err(|err| {
+                        anyhow!("Invalid contract address {contract}: {err}")
+                    })?;
+                profile().check_account(&contract, "contract")?;
+                Ok(PreparedStep {
+                    description: format!(
+                        "call {contract} {}",
+                        call_description(&message, &args)
+                    ),
+                    call: StepCall::Call {
+                        contract,
+                        message,
+                        value: step_value(balance.as_deref(), token_metadata)?,
+                        data,
+                    },
+                    gas_limit,
+                    proof_size,
+                    transcoder: Some(transcoder),
+                })
+            }
+        }
+    }
+
+    /// Dry runs the steps on behalf of the origin and displays their results.
+    async fn dry_run(
+        &self,
+        steps: &[PreparedStep],
+        url: &str,
+        client: &Client,
+        origin: &Origin,
+        storage_deposit_limit: Option<Balance>,
+    ) -> Result<(), ErrorVariant> {
+        let metadata = client.metadata();
+        let mut results = BatchDryRunResult { steps: Vec::new() };
+        for (index, step) in steps.iter().enumerate() {
+            let result = step
+                .call
+                .dry_run(url, origin.account_id(), storage_deposit_limit)
+                .await?;
+            let transcoder = step.transcoder.as_ref();
+            if self.output_json {
+                let (result, error) = match result.output(transcoder, &metadata)? {
+                    Ok(output) => (Some(output), None),
+                    Err(err) => (None, Some(err)),
+                };
+                results.steps.push(StepDryRunResult {
+                    step: step.description.clone(),
+                    result,
+                    error,
+                });
+            } else {
+                print_step_header(index, step);
+                result.display(transcoder, &metadata)?;
+            }
+        }
+        if self.output_json {
+            println!("{}", results.to_json()?);
+        } else {
+            display_dry_run_result_warning("batch");
+        }
+        Ok(())
+    }
+
+    /// Returns the gas limit of the step, estimated by a dry run unless given in the
+    /// batch file.
+    async fn pre_submit_dry_run_gas_estimate(
+        &self,
+        index: usize,
+        step: &PreparedStep,
+        url: &str,
+        client: &Client,
+        origin: &Origin,
+        storage_deposit_limit: Option<Balance>,
+    ) -> Result<Weight> {
+        if let Some(gas_limit) = step.call.gas_limit(step.gas_limit, step.proof_size) {
+            return Ok(gas_limit)
+        }
+        if self.extrinsic_opts.skip_dry_run {
+            anyhow::bail!(
+                "Step {} requires `gas_limit` and `proof_size` if `--skip-dry-run` is specified",
+                index + 1
+            )
+        }
+        if !self.output_json {
+            super::print_dry_running_status(&format!("step {}", index + 1));
+        }
+        step.call
+            .dry_run(url, origin.account_id(), storage_deposit_limit)
+            .await?
+            .gas_limit(
+                step.gas_limit,
+                step.proof_size,
+                self.output_json,
+                &client.metadata(),
+            )
+            .with_context(|| {
+                format!(
+                    "Pre-submission dry-run of step {} `{}` failed. Steps depending on the \
+                    preceding steps of the batch require `gas_limit` and `proof_size`.",
+                    index + 1,
+                    step.description
+                )
+            })
+    }
+
+    /// Submits the steps in a single `utility.batch_all` or `utility.batch` extrinsic.
+    async fn submit(
+        &self,
+        steps: &[PreparedStep],
+        gas_limits: &[Weight],
+        client: &Client,
+        origin: &Origin,
+        token_metadata: &TokenMetadata,
+    ) -> Result<(), ErrorVariant> {
+        let storage_deposit_limit =
+            self.extrinsic_opts.storage_deposit_limit(token_metadata)?;
+        let metadata = client.metadata();
+        let calls = steps
+            .iter()
+            .zip(gas_limits)
+            .map(|(step, gas_limit)| {
+                step.call
+                    .contracts_call(*gas_limit, storage_deposit_limit)
+                    .runtime_call(&metadata)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let batch = if self.non_atomic {
+            "batch"
+        } else {
+            "batch_all"
+        };
+        let call = subxt::dynamic::tx(
+            UTILITY_PALLET,
+            batch,
+            vec![Value::unnamed_composite(calls)],
+        );
+        let submitted = match origin
+            .submit(client, &call, self.extrinsic_opts.wait_for())
+            .await?
+        {
+            Submission::Dispatched(submitted) => submitted,
+            // the steps are only executed once the approvals of the multisig account
+            // reach the threshold
+            Submission::Approved(submitted) => {
+                let events = DisplayEvents::from_events(&submitted, None, &metadata)?;
+                let output = if self.output_json {
+                    events.to_json(self.extrinsic_opts.output_extrinsic())?
+                } else {
+                    events.display_events(
+                        self.extrinsic_opts.verbosity()?,
+                        token_metadata,
+                    )?
+                };
+                println!("{output}");
+                return Ok(())
+            }
+            Submission::Unsigned => return Ok(()),
+        };
+
+        let transcoders = steps
+            .iter()
+            .map(|step| step.transcoder.as_ref())
+            .collect::<Vec<_>>();
+        let events = DisplayEvents::from_batch_events(
+            &submitted,
+            &transcoders,
+            &client.metadata(),
+        )?;
+        let (outputs, interrupted) = match &submitted.events {
+            Some(result) => {
+                let events = result
+                    .iter()
+                    .map(|event| BatchEvent::from_event(&event?))
+                    .filter_map(Result::transpose)
+                    .collect::<Result<Vec<_>>>()?;
+                step_outputs(events, steps.len())
+            }
+            None => (Vec::new(), None),
+        };
+        let results = steps
+            .iter()
+            .zip(events.items)
+            .zip(outputs)
+            .map(|((step, events), output)| {
+                StepResult {
+                    step: step.description.clone(),
+                    code_hash: output.code_hash.map(|code_hash| format!("{code_hash:?}")),
+                    contract: output.contract.map(|contract| contract.to_string()),
+                    events,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if self.output_json {
+            let batch_result = BatchResult {
+                extrinsic: events
+                    .extrinsic
+                    .extrinsic()
+                    .filter(|_| self.extrinsic_opts.output_extrinsic())
+                    .cloned(),
+                events: events.extrinsic,
+                steps: results,
+            };
+            println!("{}", batch_result.to_json()?);
+        } else {
+            let verbosity = self.extrinsic_opts.verbosity()?;
+            println!(
+                "{}",
+                events.extrinsic.display_events(verbosity, token_metadata)?
+            );
+            for (index, (step, result)) in steps.iter().zip(&results).enumerate() {
+                print_step_header(index, step);
+                println!(
+                    "{}",
+                    result.events.display_events(verbosity, token_metadata)?
+                );
+                if let Some(code_hash) = &result.code_hash {
+                    name_value_println!("Code hash", code_hash);
+                }
+                if let Some(contract) = &result.contract {
+                    name_value_println!("Contract", contract);
+                }
+            }
+        }
+
+        match interrupted {
+            Some(index) => {
+                let index = index as usize;
+                Err(anyhow!(
+                    "Step {} `{}` of the batch failed, the steps after it were not executed",
+                    index + 1,
+                    steps.get(index).map_or("", |step| step.description.as_str())
+                )
+                .into())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// The code stored and the contract instantiated by a step of the batch.
+#[derive(Debug, Default, PartialEq)]
+struct StepOutput {
+    code_hash: Option<CodeHash>,
+    contract: Option<<DefaultConfig as Config>::AccountId>,
+}
+
+/// An event of a batch determining the outputs of its steps.
+#[derive(Debug)]
+enum BatchEvent {
+    ItemCompleted,
+    BatchInterrupted { index: u32 },
+    CodeStored(CodeHash),
+    Instantiated(<DefaultConfig as Config>::AccountId),
+}
+
+impl BatchEvent {
+    /// Decodes the `event` if it is one of the events of a batch.
+    fn from_event(event: &EventDetails) -> Result<Option<Self>> {
+        if Fields::of_event(UTILITY_PALLET, "ItemCompleted", event)?.is_some() {
+            Ok(Some(BatchEvent::ItemCompleted))
+        } else if let Some(fields) =
+            Fields::of_event(UTILITY_PALLET, "BatchInterrupted", event)?
+        {
+            let index = fields.u32("index")?;
+            Ok(Some(BatchEvent::BatchInterrupted { index }))
+        } else if let Some(code_stored) = CodeStored::from_event(event)? {
+            Ok(Some(BatchEvent::CodeStored(code_stored.code_hash)))
+        } else if let Some(instantiated) = Instantiated::from_event(event)? {
+            Ok(Some(BatchEvent::Instantiated(instantiated.contract)))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Returns the outputs of the steps of the batch, and the index of the step which
+/// interrupted a `utility.batch`, if any.
+///
+/// The events of the steps are terminated by an `ItemCompleted` event each.
+fn step_outputs(
+    events: impl IntoIterator<Item = BatchEvent>,
+    steps: usize,
+) -> (Vec<StepOutput>, Option<u32>) {
+    let mut outputs = Vec::new();
+    let mut output = StepOutput::default();
+    let mut interrupted = None;
+    for event in events {
+        match event {
+            BatchEvent::ItemCompleted => outputs.push(std::mem::take(&mut output)),
+            BatchEvent::CodeStored(code_hash) => {
+                output.code_hash.get_or_insert(code_hash);
+            }
+            // contracts instantiated by the constructor are reported before
+            BatchEvent::Instantiated(contract) => output.contract = Some(contract),
+            BatchEvent::BatchInterrupted { index } => interrupted = Some(index),
+        }
+    }
+    outputs.truncate(steps);
+    (outputs, interrupted)
+}
+
+fn print_step_header(index: usize, step: &PreparedStep) {
+    println!(
+        "{:>width$} {}",
+        format!("Step {}", index + 1).bright_green().bold(),
+        step.description.bright_white(),
+        width = DEFAULT_KEY_COL_WIDTH
+    );
+}
+
+/// Result of the dry run of a step of the batch.
+#[derive(serde::Serialize)]
+struct StepDryRunResult {
+    step: String,
+    #[serde(flatten)]
+    result: Option<StepDryRunOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorVariant>,
+}
+
+/// Result of the dry run of the batch.
+#[derive(serde::Serialize)]
+struct BatchDryRunResult {
+    steps: Vec<StepDryRunResult>,
+}
+
+impl BatchDryRunResult {
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Result of a step of the submitted batch.
+#[derive(serde::Serialize)]
+struct StepResult {
+    step: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contract: Option<String>,
+    events: DisplayEvents,
+}
+
+/// Result of the submitted batch.
+#[derive(serde::Serialize)]
+struct BatchResult {
+    /// The details of the batch extrinsic, if requested with `--output-extrinsic`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extrinsic: Option<ExtrinsicDetails>,
+    /// The events of the extrinsic itself, e.g. for the payment of its fee.
+    events: DisplayEvents,
+    steps: Vec<StepResult>,
+}
+
+impl BatchResult {
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::{
+        chain::AccountId,
+        extrinsics::upload::UploadDryRunResult,
+    };
+    use pallet_contracts_primitives::CodeUploadReturnValue;
+    use subxt::utils::AccountId32;
+
+    #[test]
+    fn steps_are_parsed() {
+        let json = r#"[
+            { "type": "upload", "file": "flipper.contract" },
+            { "type": "instantiate", "args": ["false"], "salt": "0x01" },
+            {
+                "type": "call",
+                "contract": "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+                "message": "flip",
+                "gas_limit": 1000,
+                "proof_size": 100
+            }
+        ]"#;
+
+        let steps: Vec<Step> = serde_json::from_str(json).unwrap();
+
+        assert!(matches!(
+            &steps[0],
+            Step::Upload { file: Some(file), manifest_path: None }
+                if file == Path::new("flipper.contract")
+        ));
+        assert!(matches!(
+            &steps[1],
+            Step::Instantiate { constructor, args, salt: Some(_), gas_limit: None, .. }
+                if constructor == "new" && args == &["false"]
+        ));
+        assert!(matches!(
+            &steps[2],
+            Step::Call { message, args, gas_limit: Some(1000), proof_size: Some(100), .. }
+                if message == "flip" && args.is_empty()
+        ));
+    }
+
+    #[test]
+    fn events_of_a_completed_batch_are_split_into_steps() {
+        // given
+        let code_hash = CodeHash::repeat_byte(1);
+        let contract = |byte| AccountId::Id32(AccountId32([byte; 32]));
+        let events = vec![
+            BatchEvent::CodeStored(code_hash),
+            BatchEvent::ItemCompleted,
+            // instantiated by the constructor of the instantiated contract
+            BatchEvent::Instantiated(contract(2)),
+            BatchEvent::Instantiated(contract(3)),
+            BatchEvent::ItemCompleted,
+            BatchEvent::ItemCompleted,
+        ];
+
+        // when
+        let (outputs, interrupted) = step_outputs(events, 3);
+
+        // then
+        assert_eq!(
+            outputs,
+            [
+                StepOutput {
+                    code_hash: Some(code_hash),
+                    contract: None,
+                },
+                StepOutput {
+                    code_hash: None,
+                    contract: Some(contract(3)),
+                },
+                StepOutput::default(),
+            ]
+        );
+        assert_eq!(interrupted, None);
+    }
+
+    #[test]
+    fn events_of_an_interrupted_batch_are_split_into_the_completed_steps() {
+        // given
+        let code_hash = CodeHash::repeat_byte(1);
+        let events = vec![
+            BatchEvent::CodeStored(code_hash),
+            BatchEvent::ItemCompleted,
+            BatchEvent::BatchInterrupted { index: 1 },
+        ];
+
+        // when
+        let (outputs, interrupted) = step_outputs(events, 3);
+
+        // then
+        assert_eq!(
+            outputs,
+            [StepOutput {
+                code_hash: Some(code_hash),
+                contract: None,
+            }]
+        );
+        assert_eq!(interrupted, Some(1));
+    }
+
+    #[test]
+    fn dry_run_results_are_flattened_into_the_steps() {
+        // given
+        let upload = UploadDryRunResult::new(&CodeUploadReturnValue {
+            code_hash: CodeHash::repeat_byte(1),
+            deposit: 100,
+        });
+        let results = BatchDryRunResult {
+            steps: vec![
+                StepDryRunResult {
+                    step: String::from("upload flipper.contract"),
+                    result: Some(StepDryRunOutput::Upload(upload)),
+                    error: None,
+                },
+                StepDryRunResult {
+                    step: String::from("call flip"),
+                    result: None,
+                    error: Some(ErrorVariant::from("ContractTrapped")),
+                },
+            ],
+        };
+
+        // when
+        let json: serde_json::Value =
+            serde_json::from_str(&results.to_json().unwrap()).unwrap();
+
+        // then
+        assert_eq!(json["steps"][0]["step"], "upload flipper.contract");
+        assert_eq!(json["steps"][0]["result"], "Success!");
+        assert_eq!(json["steps"][0]["deposit"], 100);
+        assert!(json["steps"][0].get("error").is_none());
+        assert_eq!(json["steps"][1]["step"], "call flip");
+        assert!(json["steps"][1].get("result").is_none());
+        assert!(json["steps"][1]["error"].is_object());
+    }
+
+    #[test]
+    fn unknown_step_fields_are_rejected() {
+        let json = r#"[{ "type": "call", "contract": "5G", "messsage": "flip" }]"#;
+
+        let res = serde_json::from_str::<Vec<Step>>(json);
+
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("unknown field `messsage`"));
+    }
+}
@@ -18,28 +18,37 @@ use super::{
     display_contract_exec_result,
     prompt_confirm_tx,
     state_call,
-    submit_extrinsic,
     BalanceVariant,
     Client,
     ContractMessageTranscoder,
     DefaultConfig,
     ExtrinsicOpts,
-    PairSigner,
+    MultisigOpts,
+    OfflineOpts,
+    Origin,
+    ProxyOpts,
+    SandboxOpts,
     StorageDeposit,
+    Submission,
     TokenMetadata,
     MAX_KEY_COL_WIDTH,
 };
 
 use crate::{
     cmd::{
+        block_hash,
+        chain::profile,
         extrinsics::{
+            contracts_pallet::ContractsCall,
             display_contract_exec_result_debug,
             display_dry_run_result_warning,
             events::DisplayEvents,
+            sandbox,
             ErrorVariant,
         },
-        runtime_api::api,
         Balance,
+        BlockHash,
+        BlockRef,
     },
     DEFAULT_KEY_COL_WIDTH,
 };
@@ -52,7 +61,11 @@ use anyhow::{
 };
 
 use contract_transcode::Value;
-use pallet_contracts_primitives::ContractExecResult;
+use pallet_contracts_primitives::{
+    ContractExecResult,
+    ContractResult,
+    ExecReturnValue,
+};
 use scale::Encode;
 use sp_weights::Weight;
 
@@ -76,6 +89,14 @@ pub struct CallCommand {
     args: Vec<String>,
     #[clap(flatten)]
     extrinsic_opts: ExtrinsicOpts,
+    #[clap(flatten)]
+    sandbox_opts: SandboxOpts,
+    #[clap(flatten)]
+    offline_opts: OfflineOpts,
+    #[clap(flatten)]
+    multisig_opts: MultisigOpts,
+    #[clap(flatten)]
+    proxy_opts: ProxyOpts,
     /// Maximum amount of gas (execution time) to be used for this command.
     /// If not specified will perform a dry-run to estimate the gas consumed for the
     /// call.
@@ -89,6 +110,10 @@ pub struct CallCommand {
     /// The value to be transferred as part of the call.
     #[clap(name = "value", long, default_value = "0")]
     value: BalanceVariant,
+    /// The hash or number of the block to dry run the call at, the best block by
+    /// default.
+    #[clap(long, value_name = "BLOCK", conflicts_with_all = ["execute", "sandbox"])]
+    at: Option<BlockRef>,
     /// Export the call output in JSON format.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
@@ -106,7 +131,13 @@ impl CallCommand {
         let call_data = transcoder.encode(&self.message, &self.args)?;
         tracing::debug!("Message data: {:?}", hex::encode(&call_data));
 
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
+        profile().check_account(&self.contract, "--contract")?;
+        let signer = self.offline_opts.signer(&self.extrinsic_opts)?;
+        let origin = self.proxy_opts.origin(self.multisig_opts.origin(signer)?)?;
+
+        if self.sandbox_opts.is_enabled() {
+            return self.run_in_sandbox(call_data, &origin, &transcoder)
+        }
 
         async_std::task::block_on(async {
             let url = self.extrinsic_opts.url_to_string();
@@ -114,61 +145,97 @@ impl CallCommand {
 
             if !self.extrinsic_opts.execute {
                 let result = self
-                    .call_dry_run(call_data.clone(), &client, &signer)
+                    .call_dry_run(call_data.clone(), &client, &origin)
                     .await?;
-                match result.result {
-                    Ok(ref ret_val) => {
-                        let value = transcoder
-                            .decode_return(&self.message, &mut &ret_val.data[..])
-                            .context(format!(
-                                "Failed to decode return value {:?}",
-                                &ret_val
-                            ))?;
-                        let dry_run_result = CallDryRunResult {
-                            result: String::from("Success!"),
-                            reverted: ret_val.did_revert(),
-                            data: value,
-                            gas_consumed: result.gas_consumed,
-                            gas_required: result.gas_required,
-                            storage_deposit: StorageDeposit::from(
-                                &result.storage_deposit,
-                            ),
-                        };
-                        if self.output_json {
-                            println!("{}", dry_run_result.to_json()?);
-                        } else {
-                            dry_run_result.print();
-                            display_contract_exec_result_debug::<_, DEFAULT_KEY_COL_WIDTH>(
-                                &result,
-                            )?;
-                            display_dry_run_result_warning("message");
-                        };
-                    }
-                    Err(ref err) => {
-                        let metadata = client.metadata();
-                        let object = ErrorVariant::from_dispatch_error(err, &metadata)?;
-                        if self.output_json {
-                            return Err(object)
-                        } else {
-                            name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
-                            display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(
-                                &result,
-                            )?;
-                        }
-                    }
-                }
+                self.display_dry_run_result(&result, &transcoder, |err| {
+                    ErrorVariant::from_dispatch_error(err, &client.metadata())
+                })?;
             } else {
-                self.call(&client, call_data, &signer, &transcoder).await?;
+                self.call(&client, call_data, &origin, &transcoder).await?;
             }
             Ok(())
         })
     }
 
+    /// Calls the contract in the sandbox instead of on a node. Its state is only changed
+    /// if the call is executed with `-x`.
+    fn run_in_sandbox(
+        &self,
+        input_data: Vec<u8>,
+        origin: &Origin,
+        transcoder: &ContractMessageTranscoder,
+    ) -> Result<(), ErrorVariant> {
+        let token_metadata = sandbox::token_metadata();
+        let mut sandbox = self.sandbox_opts.load()?;
+        let gas_limit = self.gas_limit.map(|ref_time| {
+            Weight::from_parts(ref_time, self.proof_size.unwrap_or_default())
+        });
+        let storage_deposit_limit = self
+            .extrinsic_opts
+            .storage_deposit_limit
+            .as_ref()
+            .map(|bv| bv.denominate_balance(&token_metadata))
+            .transpose()?;
+        let outcome = sandbox.call(
+            sandbox::account_id(origin.account_id())?,
+            sandbox::account_id(&self.contract)?,
+            self.value.denominate_balance(&token_metadata)?,
+            gas_limit,
+            storage_deposit_limit,
+            input_data,
+        );
+
+        if !self.extrinsic_opts.execute {
+            return self.display_dry_run_result(&outcome.result, transcoder, |err| {
+                Ok(ErrorVariant::from(err))
+            })
+        }
+        match outcome.result.result {
+            Ok(ref ret_val) if !ret_val.did_revert() => {
+                sandbox.advance_block();
+                self.sandbox_opts.save(&sandbox)?;
+            }
+            Ok(_) => return Err("The contract reverted the call".into()),
+            Err(ref err) => return Err(ErrorVariant::from(err)),
+        }
+        let display_events =
+            DisplayEvents::from_sandbox_events(&outcome.events, Some(transcoder))?;
+        let output = if self.output_json {
+            display_events.to_json(self.extrinsic_opts.output_extrinsic())?
+        } else {
+            display_events
+                .display_events(self.extrinsic_opts.verbosity()?, &token_metadata)?
+        };
+        println!("{output}");
+        Ok(())
+    }
+
+    /// Displays the result of a dry run, the error of a failed call is converted by
+    /// `error_variant`.
+    fn display_dry_run_result<E>(
+        &self,
+        result: &ContractResult<Result<ExecReturnValue, E>, Balance>,
+        transcoder: &ContractMessageTranscoder,
+        error_variant: impl FnOnce(&E) -> Result<ErrorVariant>,
+    ) -> Result<(), ErrorVariant> {
+        display_dry_run_result(
+            result,
+            transcoder,
+            &self.message,
+            self.output_json,
+            error_variant,
+        )?;
+        if result.result.is_ok() && !self.output_json {
+            display_dry_run_result_warning("message");
+        }
+        Ok(())
+    }
+
     async fn call_dry_run(
         &self,
         input_data: Vec<u8>,
         client: &Client,
-        signer: &PairSigner,
+        origin: &Origin,
     ) -> Result<ContractExecResult<Balance>> {
         let url = self.extrinsic_opts.url_to_string();
         let token_metadata = TokenMetadata::query(client).await?;
@@ -179,27 +246,31 @@ impl CallCommand {
             .map(|bv| bv.denominate_balance(&token_metadata))
             .transpose()?;
         let call_request = CallRequest {
-            origin: signer.account_id().clone(),
+            origin: origin.account_id().clone(),
             dest: self.contract.clone(),
             value: self.value.denominate_balance(&token_metadata)?,
             gas_limit: None,
             storage_deposit_limit,
             input_data,
         };
-        state_call(&url, "ContractsApi_call", call_request).await
+        let at = match self.at {
+            Some(ref at) => Some(block_hash(client, Some(at)).await?),
+            None => None,
+        };
+        call_dry_run(&url, &call_request, at).await
     }
 
     async fn call(
         &self,
         client: &Client,
         data: Vec<u8>,
-        signer: &PairSigner,
+        origin: &Origin,
         transcoder: &ContractMessageTranscoder,
     ) -> Result<(), ErrorVariant> {
         tracing::debug!("calling contract {:?}", self.contract);
 
         let gas_limit = self
-            .pre_submit_dry_run_gas_estimate(client, data.clone(), signer)
+            .pre_submit_dry_run_gas_estimate(client, data.clone(), origin)
             .await?;
 
         if !self.extrinsic_opts.skip_confirm {
@@ -216,21 +287,28 @@ impl CallCommand {
 
         let token_metadata = TokenMetadata::query(client).await?;
 
-        let call = api::tx().contracts().call(
-            self.contract.clone().into(),
+        let call = ContractsCall::call(
+            self.contract.clone(),
             self.value.denominate_balance(&token_metadata)?,
-            gas_limit.into(),
+            gas_limit,
             self.extrinsic_opts.storage_deposit_limit(&token_metadata)?,
             data,
         );
-
-        let result = submit_extrinsic(client, &call, signer).await?;
+        let result = match origin
+            .submit(client, &call, self.extrinsic_opts.wait_for())
+            .await?
+        {
+            Submission::Approved(submitted) | Submission::Dispatched(submitted) => {
+                submitted
+            }
+            Submission::Unsigned => return Ok(()),
+        };
 
         let display_events =
             DisplayEvents::from_events(&result, Some(transcoder), &client.metadata())?;
 
         let output = if self.output_json {
-            display_events.to_json()?
+            display_events.to_json(self.extrinsic_opts.output_extrinsic())?
         } else {
             display_events
                 .display_events(self.extrinsic_opts.verbosity()?, &token_metadata)?
@@ -245,7 +323,7 @@ impl CallCommand {
         &self,
         client: &Client,
         data: Vec<u8>,
-        signer: &PairSigner,
+        origin: &Origin,
     ) -> Result<Weight> {
         if self.extrinsic_opts.skip_dry_run {
             return match (self.gas_limit, self.proof_size) {
@@ -260,33 +338,58 @@ impl CallCommand {
         if !self.output_json {
             super::print_dry_running_status(&self.message);
         }
-        let call_result = self.call_dry_run(data, client, signer).await?;
-        match call_result.result {
-            Ok(_) => {
-                if !self.output_json {
-                    super::print_gas_required_success(call_result.gas_required);
-                }
-                // use user specified values where provided, otherwise use the estimates
-                let ref_time = self
-                    .gas_limit
-                    .unwrap_or_else(|| call_result.gas_required.ref_time());
-                let proof_size = self
-                    .proof_size
-                    .unwrap_or_else(|| call_result.gas_required.proof_size());
-                Ok(Weight::from_parts(ref_time, proof_size))
-            }
-            Err(ref err) => {
-                let object = ErrorVariant::from_dispatch_error(err, &client.metadata())?;
-                if self.output_json {
-                    Err(anyhow!("{}", serde_json::to_string_pretty(&object)?))
-                } else {
-                    name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
-                    display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(&call_result)?;
-                    Err(anyhow!("Pre-submission dry-run failed. Use --skip-dry-run to skip this step."))
-                }
+        let call_result = self.call_dry_run(data, client, origin).await?;
+        super::dry_run_gas_limit(
+            &call_result,
+            self.gas_limit,
+            self.proof_size,
+            self.output_json,
+            &client.metadata(),
+        )
+    }
+}
+
+/// Dry runs the call of `request` at the block with the hash `at`, the best block if
+/// `None`.
+pub async fn call_dry_run(
+    url: &str,
+    request: &CallRequest,
+    at: Option<BlockHash>,
+) -> Result<ContractExecResult<Balance>> {
+    state_call(url, "ContractsApi_call", request, at).await
+}
+
+/// Displays the result of a dry run of `message`, the error of a failed call is
+/// converted by `error_variant`.
+pub fn display_dry_run_result<E>(
+    result: &ContractResult<Result<ExecReturnValue, E>, Balance>,
+    transcoder: &ContractMessageTranscoder,
+    message: &str,
+    output_json: bool,
+    error_variant: impl FnOnce(&E) -> Result<ErrorVariant>,
+) -> Result<(), ErrorVariant> {
+    match result.result {
+        Ok(ref ret_val) => {
+            let dry_run_result =
+                CallDryRunResult::new(ret_val, result, transcoder, message)?;
+            if output_json {
+                println!("{}", dry_run_result.to_json()?);
+            } else {
+                dry_run_result.print();
+                display_contract_exec_result_debug::<_, DEFAULT_KEY_COL_WIDTH>(result)?;
+            };
+        }
+        Err(ref err) => {
+            let object = error_variant(err)?;
+            if output_json {
+                return Err(object)
+            } else {
+                name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
+                display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(result)?;
             }
         }
     }
+    Ok(())
 }
 
 /// A struct that encodes RPC parameters required for a call to a smart contract.
@@ -294,12 +397,12 @@ impl CallCommand {
 /// Copied from `pallet-contracts-rpc-runtime-api`.
 #[derive(Encode)]
 pub struct CallRequest {
-    origin: <DefaultConfig as Config>::AccountId,
-    dest: <DefaultConfig as Config>::AccountId,
-    value: Balance,
-    gas_limit: Option<Weight>,
-    storage_deposit_limit: Option<Balance>,
-    input_data: Vec<u8>,
+    pub origin: <DefaultConfig as Config>::AccountId,
+    pub dest: <DefaultConfig as Config>::AccountId,
+    pub value: Balance,
+    pub gas_limit: Option<Weight>,
+    pub storage_deposit_limit: Option<Balance>,
+    pub input_data: Vec<u8>,
 }
 
 /// Result of the contract call
@@ -317,6 +420,26 @@ pub struct CallDryRunResult {
 }
 
 impl CallDryRunResult {
+    /// Returns the result of a dry run of `message`, decoding its return value.
+    pub fn new<R>(
+        ret_val: &ExecReturnValue,
+        result: &ContractResult<R, Balance>,
+        transcoder: &ContractMessageTranscoder,
+        message: &str,
+    ) -> Result<Self> {
+        let value = transcoder
+            .decode_return(message, &mut &ret_val.data[..])
+            .context(format!("Failed to decode return value {:?}", &ret_val))?;
+        Ok(Self {
+            result: String::from("Success!"),
+            reverted: ret_val.did_revert(),
+            data: value,
+            gas_consumed: result.gas_consumed,
+            gas_required: result.gas_required,
+            storage_deposit: StorageDeposit::from(&result.storage_deposit),
+        })
+    }
+
     /// Returns a result in json format
     pub fn to_json(&self) -> Result<String> {
         Ok(serde_json::to_string_pretty(self)?)
@@ -0,0 +1,762 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The calls and events of `pallet-contracts`, encoded and decoded dynamically with the
+//! metadata of the chain instead of the bundled runtime metadata.
+
+use super::DefaultConfig;
+use crate::cmd::{
+    chain::{
+        profile,
+        AddressFormat,
+    },
+    Balance,
+    BlockHash,
+    Client,
+    CodeHash,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use sp_weights::Weight;
+use subxt::{
+    blocks::ExtrinsicEvents,
+    dynamic::Value,
+    events::EventDetails,
+    ext::scale_value::{
+        self,
+        scale::TypeId,
+        Composite,
+        Primitive,
+        ValueDef,
+    },
+    tx::{
+        DynamicPayload,
+        TxPayload,
+    },
+    utils::Static,
+    Config,
+    Metadata,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The name of `pallet-contracts` in the runtime.
+pub const CONTRACTS_PALLET: &str = "Contracts";
+
+/// Arguments the calls of older versions of the pallet do not take, they are left out
+/// for those.
+const OPTIONAL_ARGS: &[&str] = &["determinism"];
+
+/// A call of `pallet-contracts`, encoded with the call signature of the chain.
+#[derive(Clone, Debug)]
+pub struct ContractsCall {
+    name: &'static str,
+    args: Vec<(&'static str, Value)>,
+}
+
+impl ContractsCall {
+    pub fn upload_code(
+        code: Vec<u8>,
+        storage_deposit_limit: Option<scale::Compact<Balance>>,
+    ) -> Self {
+        Self {
+            name: "upload_code",
+            args: vec![
+                ("code", Value::from_bytes(code)),
+                (
+                    "storage_deposit_limit",
+                    optional_balance(storage_deposit_limit),
+                ),
+                ("determinism", Value::unnamed_variant("Enforced", [])),
+            ],
+        }
+    }
+
+    pub fn instantiate_with_code(
+        value: Balance,
+        gas_limit: Weight,
+        storage_deposit_limit: Option<scale::Compact<Balance>>,
+        code: Vec<u8>,
+        data: Vec<u8>,
+        salt: Vec<u8>,
+    ) -> Self {
+        Self {
+            name: "instantiate_with_code",
+            args: vec![
+                ("value", Value::u128(value)),
+                ("gas_limit", weight(gas_limit)),
+                (
+                    "storage_deposit_limit",
+                    optional_balance(storage_deposit_limit),
+                ),
+                ("code", Value::from_bytes(code)),
+                ("data", Value::from_bytes(data)),
+                ("salt", Value::from_bytes(salt)),
+            ],
+        }
+    }
+
+    pub fn instantiate(
+        value: Balance,
+        gas_limit: Weight,
+        storage_deposit_limit: Option<scale::Compact<Balance>>,
+        code_hash: CodeHash,
+        data: Vec<u8>,
+        salt: Vec<u8>,
+    ) -> Self {
+        Self {
+            name: "instantiate",
+            args: vec![
+                ("value", Value::u128(value)),
+                ("gas_limit", weight(gas_limit)),
+                (
+                    "storage_deposit_limit",
+                    optional_balance(storage_deposit_limit),
+                ),
+                ("code_hash", Value::from_bytes(code_hash)),
+                ("data", Value::from_bytes(data)),
+                ("salt", Value::from_bytes(salt)),
+            ],
+        }
+    }
+
+    pub fn call(
+        dest: AccountId,
+        value: Balance,
+        gas_limit: Weight,
+        storage_deposit_limit: Option<scale::Compact<Balance>>,
+        data: Vec<u8>,
+    ) -> Self {
+        Self {
+            name: "call",
+            args: vec![
+                ("dest", address(dest, profile().address)),
+                ("value", Value::u128(value)),
+                ("gas_limit", weight(gas_limit)),
+                (
+                    "storage_deposit_limit",
+                    optional_balance(storage_deposit_limit),
+                ),
+                ("data", Value::from_bytes(data)),
+            ],
+        }
+    }
+
+    pub fn remove_code(code_hash: CodeHash) -> Self {
+        Self {
+            name: "remove_code",
+            args: vec![("code_hash", Value::from_bytes(code_hash))],
+        }
+    }
+
+    /// Returns the dynamic payload of the call, after checking that the call of the
+    /// chain takes the arguments of the call.
+    pub fn payload(&self, metadata: &Metadata) -> Result<DynamicPayload> {
+        let call = metadata
+            .pallet(CONTRACTS_PALLET)
+            .and_then(|pallet| pallet.call(self.name))
+            .map_err(|_| {
+                anyhow!(
+                    "The runtime of the chain has no `{CONTRACTS_PALLET}::{}` call",
+                    self.name
+                )
+            })?;
+        let params = call
+            .fields()
+            .iter()
+            .map(|field| field.name.as_deref().unwrap_or_default())
+            .collect::<Vec<_>>();
+        let compatible = params
+            .iter()
+            .all(|param| self.args.iter().any(|(name, _)| name == param))
+            && self
+                .args
+                .iter()
+                .all(|(name, _)| params.contains(name) || OPTIONAL_ARGS.contains(name));
+        if !compatible {
+            anyhow::bail!(
+                "The `{CONTRACTS_PALLET}::{}` call of the chain is incompatible with \
+                cargo-contract: it takes the arguments ({}), instead of ({})",
+                self.name,
+                params.join(", "),
+                self.args
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+        let args = self
+            .args
+            .iter()
+            .filter(|(name, _)| params.contains(name))
+            .map(|(name, value)| (*name, value.clone()));
+        Ok(subxt::dynamic::tx(
+            CONTRACTS_PALLET,
+            self.name,
+            Composite::named(args),
+        ))
+    }
+
+    /// Returns the call as a call of the runtime, e.g. to be wrapped into a
+    /// `utility.batch_all`.
+    pub fn runtime_call(&self, metadata: &Metadata) -> Result<Value> {
+        Ok(self.payload(metadata)?.into_value())
+    }
+}
+
+impl TxPayload for ContractsCall {
+    fn encode_call_data_to(
+        &self,
+        metadata: &Metadata,
+        out: &mut Vec<u8>,
+    ) -> Result<(), subxt::Error> {
+        let payload = self
+            .payload(metadata)
+            .map_err(|err| subxt::Error::Other(err.to_string()))?;
+        payload.encode_call_data_to(metadata, out).map_err(|err| {
+            subxt::Error::Other(format!(
+                "The `{CONTRACTS_PALLET}::{}` call of the chain is incompatible with \
+                cargo-contract: {err}",
+                self.name
+            ))
+        })
+    }
+}
+
+fn weight(weight: Weight) -> Value {
+    Value::named_composite([
+        ("ref_time", Value::u128(weight.ref_time().into())),
+        ("proof_size", Value::u128(weight.proof_size().into())),
+    ])
+}
+
+/// The account as the address of the chain, e.g. `MultiAddress::Id(account)`.
+fn address(account: AccountId, format: AddressFormat) -> Value {
+    match format {
+        AddressFormat::Id => Value::unnamed_variant("Id", [Value::from_bytes(account)]),
+        AddressFormat::Address20 => {
+            Value::unnamed_variant("Address20", [Value::from_bytes(account)])
+        }
+        AddressFormat::AccountId => Value::from_bytes(account),
+    }
+}
+
+fn optional_balance(balance: Option<scale::Compact<Balance>>) -> Value {
+    match balance {
+        Some(balance) => Value::unnamed_variant("Some", [Value::u128(balance.0)]),
+        None => Value::unnamed_variant("None", []),
+    }
+}
+
+/// An event of `pallet-contracts`, decoded from its named fields with the metadata of
+/// the chain.
+pub trait ContractsEvent: Sized {
+    const EVENT: &'static str;
+
+    fn from_fields(fields: &Fields) -> Result<Self>;
+
+    /// Decodes the `event` if it is this event.
+    fn from_event(event: &EventDetails) -> Result<Option<Self>> {
+        match Fields::of_event(CONTRACTS_PALLET, Self::EVENT, event)? {
+            Some(fields) => Self::from_fields(&fields).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn find_first(events: &ExtrinsicEvents<DefaultConfig>) -> Result<Option<Self>> {
+        for event in events.iter() {
+            if let Some(event) = Self::from_event(&event?)? {
+                return Ok(Some(event))
+            }
+        }
+        Ok(None)
+    }
+
+    fn find_last(events: &ExtrinsicEvents<DefaultConfig>) -> Result<Option<Self>> {
+        let mut last = None;
+        for event in events.iter() {
+            if let Some(event) = Self::from_event(&event?)? {
+                last = Some(event);
+            }
+        }
+        Ok(last)
+    }
+}
+
+/// The decoded fields of an event or a storage item.
+pub struct Fields {
+    pallet: &'static str,
+    item: &'static str,
+    name: &'static str,
+    values: Composite<TypeId>,
+}
+
+impl Fields {
+    /// Returns the fields of the `event`, if it is the event `name` of the `pallet`.
+    pub fn of_event(
+        pallet: &'static str,
+        name: &'static str,
+        event: &EventDetails,
+    ) -> Result<Option<Self>> {
+        if event.pallet_name() != pallet || event.variant_name() != name {
+            return Ok(None)
+        }
+        Ok(Some(Self {
+            pallet,
+            item: "event",
+            name,
+            values: event.field_values()?,
+        }))
+    }
+
+    fn field(&self, field: &str, expected: &str) -> Result<&scale_value::Value<TypeId>> {
+        let incompatible = || self.incompatible(field, expected);
+        let Composite::Named(ref fields) = self.values else {
+            return Err(incompatible())
+        };
+        fields
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, value)| value)
+            .ok_or_else(incompatible)
+    }
+
+    fn incompatible(&self, field: &str, expected: &str) -> anyhow::Error {
+        anyhow!(
+            "The `{}::{}` {} of the chain is incompatible with \
+            cargo-contract: expected a field `{field}` of {expected}",
+            self.pallet,
+            self.name,
+            self.item,
+        )
+    }
+
+    /// Returns the field `name` consisting of bytes, e.g. an account or a hash.
+    fn bytes(&self, name: &str, expected: &str) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        collect_bytes(self.field(name, expected)?, &mut bytes)
+            .ok_or_else(|| self.incompatible(name, expected))?;
+        Ok(bytes)
+    }
+
+    fn u128(&self, name: &str) -> Result<u128> {
+        self.field(name, "an integer")?
+            .as_u128()
+            .ok_or_else(|| self.incompatible(name, "an integer"))
+    }
+
+    pub fn u32(&self, name: &str) -> Result<u32> {
+        self.u128(name)?
+            .try_into()
+            .map_err(|_| self.incompatible(name, "an u32"))
+    }
+
+    fn account_id(&self, name: &str) -> Result<AccountId> {
+        let expected = "32 or 20 bytes";
+        AccountId::from_bytes(&self.bytes(name, expected)?)
+            .map_err(|_| self.incompatible(name, expected))
+    }
+
+    fn hash(&self, name: &str) -> Result<CodeHash> {
+        let expected = "32 bytes";
+        let bytes: [u8; 32] = self
+            .bytes(name, expected)?
+            .try_into()
+            .map_err(|_| self.incompatible(name, expected))?;
+        Ok(bytes.into())
+    }
+}
+
+/// Collects the bytes of a value which consists of bytes, possibly wrapped in
+/// composites like `AccountId32([u8; 32])`.
+pub fn collect_bytes(
+    value: &scale_value::Value<TypeId>,
+    bytes: &mut Vec<u8>,
+) -> Option<()> {
+    match value.value {
+        ValueDef::Composite(ref composite) => {
+            composite
+                .values()
+                .try_for_each(|value| collect_bytes(value, bytes))
+        }
+        ValueDef::Primitive(Primitive::U128(byte)) => {
+            bytes.push(u8::try_from(byte).ok()?);
+            Some(())
+        }
+        _ => None,
+    }
+}
+
+/// The `CodeStored` event of `pallet-contracts`.
+pub struct CodeStored {
+    pub code_hash: CodeHash,
+}
+
+impl ContractsEvent for CodeStored {
+    const EVENT: &'static str = "CodeStored";
+
+    fn from_fields(fields: &Fields) -> Result<Self> {
+        Ok(Self {
+            code_hash: fields.hash("code_hash")?,
+        })
+    }
+}
+
+/// The `CodeRemoved` event of `pallet-contracts`.
+pub struct CodeRemoved {
+    pub code_hash: CodeHash,
+}
+
+impl ContractsEvent for CodeRemoved {
+    const EVENT: &'static str = "CodeRemoved";
+
+    fn from_fields(fields: &Fields) -> Result<Self> {
+        Ok(Self {
+            code_hash: fields.hash("code_hash")?,
+        })
+    }
+}
+
+/// The `Instantiated` event of `pallet-contracts`.
+pub struct Instantiated {
+    pub contract: AccountId,
+}
+
+impl ContractsEvent for Instantiated {
+    const EVENT: &'static str = "Instantiated";
+
+    fn from_fields(fields: &Fields) -> Result<Self> {
+        Ok(Self {
+            contract: fields.account_id("contract")?,
+        })
+    }
+}
+
+/// The `ContractInfoOf` storage item of a contract.
+pub struct ContractInfo {
+    pub trie_id: Vec<u8>,
+    pub code_hash: CodeHash,
+    pub storage_items: u32,
+    pub storage_item_deposit: Balance,
+}
+
+impl ContractInfo {
+    /// Fetches the info of the `contract` at the block `block_hash`, if it exists.
+    pub async fn fetch(
+        client: &Client,
+        contract: &AccountId,
+        block_hash: BlockHash,
+    ) -> Result<Option<Self>> {
+        let address = subxt::dynamic::storage(
+            CONTRACTS_PALLET,
+            "ContractInfoOf",
+            vec![Static(contract)],
+        );
+        let Some(info) = client.storage().at(block_hash).fetch(&address).await? else {
+            return Ok(None)
+        };
+        let ValueDef::Composite(values) = info.to_value()?.value else {
+            anyhow::bail!("Failed to decode the info of contract {contract}")
+        };
+        let fields = Fields {
+            pallet: CONTRACTS_PALLET,
+            item: "storage item",
+            name: "ContractInfoOf",
+            values,
+        };
+        Ok(Some(Self {
+            trie_id: fields.bytes("trie_id", "bytes")?,
+            code_hash: fields.hash("code_hash")?,
+            storage_items: fields.u32("storage_items")?,
+            storage_item_deposit: fields.u128("storage_item_deposit")?,
+        }))
+    }
+}
+
+/// Whether the code with the hash is stored on chain at the block `block_hash`.
+pub async fn code_exists(
+    client: &Client,
+    code_hash: CodeHash,
+    block_hash: BlockHash,
+) -> Result<bool> {
+    let address =
+        subxt::dynamic::storage(CONTRACTS_PALLET, "OwnerInfoOf", vec![Static(code_hash)]);
+    let owner_info = client.storage().at(block_hash).fetch(&address).await?;
+    Ok(owner_info.is_some())
+}
+
+/// Returns `true` if the `event` is a `ContractEmitted` event, whose data is decoded
+/// with the transcoder of the contract.
+pub fn is_contract_emitted(event: &EventDetails) -> bool {
+    event.pallet_name() == CONTRACTS_PALLET && event.variant_name() == "ContractEmitted"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::runtime_api::api::{
+        self,
+        runtime_types::pallet_contracts::wasm::Determinism,
+    };
+    use subxt::{
+        ext::frame_metadata::RuntimeMetadataPrefixed,
+        utils::{
+            AccountId32,
+            MultiAddress,
+        },
+    };
+
+    fn metadata() -> Metadata {
+        let metadata = include_bytes!("../runtime_api/contracts_runtime.scale");
+        let metadata =
+            <RuntimeMetadataPrefixed as scale::Decode>::decode(&mut &metadata[..])
+                .unwrap();
+        Metadata::try_from(metadata).unwrap()
+    }
+
+    fn call_data(call: &impl TxPayload, metadata: &Metadata) -> Vec<u8> {
+        let mut out = Vec::new();
+        call.encode_call_data_to(metadata, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn dynamic_calls_are_encoded_like_the_static_calls() {
+        // given
+        let metadata = metadata();
+        let contracts = api::tx().contracts();
+        let gas_limit = Weight::from_parts(3_000_000_000, 65_536);
+        let storage_deposit_limit = Some(scale::Compact(1_000));
+        let code_hash = CodeHash::repeat_byte(2);
+        let contract = AccountId32([3; 32]);
+
+        // when
+        let calls = [
+            (
+                ContractsCall::upload_code(vec![0, 1, 2], storage_deposit_limit),
+                call_data(
+                    &contracts.upload_code(
+                        vec![0, 1, 2],
+                        storage_deposit_limit,
+                        Determinism::Enforced,
+                    ),
+                    &metadata,
+                ),
+            ),
+            (
+                ContractsCall::instantiate_with_code(
+                    7,
+                    gas_limit,
+                    None,
+                    vec![0, 1, 2],
+                    vec![4],
+                    vec![5],
+                ),
+                call_data(
+                    &contracts.instantiate_with_code(
+                        7,
+                        gas_limit.into(),
+                        None,
+                        vec![0, 1, 2],
+                        vec![4],
+                        vec![5],
+                    ),
+                    &metadata,
+                ),
+            ),
+            (
+                ContractsCall::instantiate(
+                    7,
+                    gas_limit,
+                    storage_deposit_limit,
+                    code_hash,
+                    vec![4],
+                    vec![5],
+                ),
+                call_data(
+                    &contracts.instantiate(
+                        7,
+                        gas_limit.into(),
+                        storage_deposit_limit,
+                        code_hash,
+                        vec![4],
+                        vec![5],
+                    ),
+                    &metadata,
+                ),
+            ),
+            (
+                ContractsCall::call(
+                    contract.clone().into(),
+                    7,
+                    gas_limit,
+                    storage_deposit_limit,
+                    vec![4],
+                ),
+                call_data(
+                    &contracts.call(
+                        contract.into(),
+                        7,
+                        gas_limit.into(),
+                        storage_deposit_limit,
+                        vec![4],
+                    ),
+                    &metadata,
+                ),
+            ),
+            (
+                ContractsCall::remove_code(code_hash),
+                call_data(&contracts.remove_code(code_hash), &metadata),
+            ),
+        ];
+
+        // then
+        for (call, expected) in calls {
+            assert_eq!(call_data(&call, &metadata), expected, "{}", call.name);
+        }
+    }
+
+    #[test]
+    fn call_destinations_are_encoded_as_the_address_of_the_profile() {
+        // given
+        let metadata = metadata();
+        let call_with_dest = |dest| {
+            let mut call = ContractsCall::call(
+                AccountId::Id32(AccountId32([3; 32])),
+                7,
+                Weight::from_parts(3_000_000_000, 65_536),
+                None,
+                vec![4],
+            );
+            call.args[0].1 = dest;
+            call
+        };
+        let static_call_with_dest = |dest| {
+            call_data(
+                &api::tx().contracts().call(
+                    dest,
+                    7,
+                    Weight::from_parts(3_000_000_000, 65_536).into(),
+                    None,
+                    vec![4],
+                ),
+                &metadata,
+            )
+        };
+
+        // when
+        let id = address(AccountId::Id32(AccountId32([3; 32])), AddressFormat::Id);
+        let address20 = address(AccountId::Id20([3; 20]), AddressFormat::Address20);
+        let account_id = address(AccountId::Id20([3; 20]), AddressFormat::AccountId);
+
+        // then
+        assert_eq!(
+            call_data(&call_with_dest(id), &metadata),
+            static_call_with_dest(MultiAddress::Id(AccountId32([3; 32])))
+        );
+        assert_eq!(
+            call_data(&call_with_dest(address20), &metadata),
+            static_call_with_dest(MultiAddress::Address20([3; 20]))
+        );
+        assert_eq!(account_id, Value::from_bytes([3; 20]));
+    }
+
+    #[test]
+    fn incompatible_calls_are_reported() {
+        // given
+        let metadata = metadata();
+        let call = ContractsCall {
+            name: "remove_code",
+            args: vec![("hash", Value::from_bytes([2; 32]))],
+        };
+
+        // when
+        let result = call.payload(&metadata);
+
+        // then
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "The `Contracts::remove_code` call of the chain is incompatible with \
+            cargo-contract: it takes the arguments (code_hash), instead of (hash)"
+        );
+    }
+
+    #[test]
+    fn optional_arguments_are_left_out_for_calls_not_taking_them() {
+        // given
+        let metadata = metadata();
+        let mut call = ContractsCall::remove_code(CodeHash::repeat_byte(2));
+        call.args
+            .push(("determinism", Value::unnamed_variant("Enforced", [])));
+
+        // when
+        let encoded = call_data(&call, &metadata);
+
+        // then
+        assert_eq!(
+            encoded,
+            call_data(
+                &ContractsCall::remove_code(CodeHash::repeat_byte(2)),
+                &metadata
+            )
+        );
+    }
+
+    #[test]
+    fn event_fields_are_decoded_by_name() {
+        // given
+        let bytes = |byte: u8, len: usize| {
+            let bytes = vec![Value::u128(byte.into()); len];
+            // e.g. `AccountId32([u8; 32])`
+            Value::unnamed_composite([Value::unnamed_composite(bytes)]).map_context(|_| 0)
+        };
+        let fields = Fields {
+            pallet: CONTRACTS_PALLET,
+            item: "event",
+            name: "Instantiated",
+            values: Composite::named([
+                ("deployer", bytes(1, 32)),
+                ("contract", bytes(2, 32)),
+            ]),
+        };
+        let h160_fields = Fields {
+            pallet: CONTRACTS_PALLET,
+            item: "event",
+            name: "Instantiated",
+            values: Composite::named([
+                ("deployer", bytes(1, 20)),
+                ("contract", bytes(2, 20)),
+            ]),
+        };
+
+        // when
+        let instantiated = Instantiated::from_fields(&fields).unwrap();
+        let h160_instantiated = Instantiated::from_fields(&h160_fields).unwrap();
+        let missing = CodeStored::from_fields(&fields);
+
+        // then
+        assert_eq!(instantiated.contract, AccountId::Id32(AccountId32([2; 32])));
+        assert_eq!(h160_instantiated.contract, AccountId::Id20([2; 20]));
+        assert_eq!(
+            missing.err().unwrap().to_string(),
+            "The `Contracts::Instantiated` event of the chain is incompatible with \
+            cargo-contract: expected a field `code_hash` of 32 bytes"
+        );
+    }
+}
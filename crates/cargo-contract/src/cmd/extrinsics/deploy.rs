@@ -0,0 +1,1198 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    display_dry_run_result_warning,
+    events::{
+        DisplayEvents,
+        ExtrinsicDetails,
+    },
+    instantiate::Code,
+    prompt_confirm_tx,
+    step::{
+        call_description,
+        default_constructor,
+        step_artifacts,
+        step_value,
+        StepCall,
+    },
+    Client,
+    ContractArtifacts,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    Origin,
+    ProxyOpts,
+    Signer,
+    Submission,
+    SubmittedExtrinsic,
+    TokenMetadata,
+    WaitFor,
+};
+use crate::{
+    cmd::{
+        block_hash,
+        extrinsics::contracts_pallet::{
+            self,
+            ContractInfo,
+            ContractsEvent,
+            Instantiated,
+        },
+        CodeHash,
+    },
+    name_value_println,
+    DEFAULT_KEY_COL_WIDTH,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use colored::Colorize as _;
+use contract_build::util::decode_hex;
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+        HashSet,
+    },
+    fmt::{
+        Debug,
+        Display,
+    },
+    ops::Range,
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+use subxt::{
+    Config,
+    OnlineClient,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The file name of the lockfile, next to the deployment manifest by default.
+const DEFAULT_LOCKFILE: &str = "deploy.lock";
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "deploy",
+    about = "Deploy the contracts of a deployment manifest"
+)]
+pub struct DeployCommand {
+    /// Path to the TOML deployment manifest listing the code to upload, the contracts
+    /// to instantiate and the messages to call after.
+    #[clap(value_parser)]
+    deploy_manifest: PathBuf,
+    /// Path to the lockfile recording the code hashes and contracts of the deployment,
+    /// `deploy.lock` next to the deployment manifest by default.
+    #[clap(long, value_parser)]
+    lockfile: Option<PathBuf>,
+    /// The contract artifacts of `extrinsic_opts` are used by the entries which specify
+    /// neither a `file` nor a `manifest_path`.
+    #[clap(flatten)]
+    extrinsic_opts: ExtrinsicOpts,
+    #[clap(flatten)]
+    proxy_opts: ProxyOpts,
+    /// Export the deployment output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+/// A deployment manifest.
+///
+/// Relative paths are resolved against the directory of the manifest. The arguments of
+/// constructors and messages can reference the address of a contract of the manifest
+/// with `${name}`, and the code hash of an upload or contract with `${name.code_hash}`.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeployManifest {
+    #[serde(default)]
+    upload: Vec<Upload>,
+    #[serde(default)]
+    contract: Vec<Contract>,
+    #[serde(default)]
+    call: Vec<Call>,
+}
+
+/// Code to upload, e.g. for contracts instantiated by other contracts.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Upload {
+    name: String,
+    file: Option<PathBuf>,
+    manifest_path: Option<PathBuf>,
+}
+
+/// A contract to instantiate, uploading its code unless it is stored already.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Contract {
+    name: String,
+    file: Option<PathBuf>,
+    manifest_path: Option<PathBuf>,
+    #[serde(default = "default_constructor")]
+    constructor: String,
+    #[serde(default)]
+    args: Vec<String>,
+    value: Option<String>,
+    salt: Option<String>,
+    gas_limit: Option<u64>,
+    proof_size: Option<u64>,
+}
+
+/// A message of a contract of the manifest to call once all contracts are
+/// instantiated.
+#[derive(Debug, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Call {
+    contract: String,
+    message: String,
+    #[serde(default)]
+    args: Vec<String>,
+    value: Option<String>,
+    gas_limit: Option<u64>,
+    proof_size: Option<u64>,
+}
+
+impl DeployManifest {
+    /// Loads and validates the deployment manifest.
+    fn load(path: &Path) -> Result<Self> {
+        let toml = std::fs::read_to_string(path).with_context(|| {
+            format!("Failed to read deployment manifest {}", path.display())
+        })?;
+        let manifest: DeployManifest = toml::from_str(&toml).with_context(|| {
+            format!("Failed to parse deployment manifest {}", path.display())
+        })?;
+        manifest
+            .validate()
+            .with_context(|| format!("Invalid deployment manifest {}", path.display()))?;
+        Ok(manifest)
+    }
+
+    /// Checks that the names of the uploads and contracts are unique, and that the
+    /// calls and arguments reference contracts and uploads of the manifest.
+    fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        let uploads = self.upload.iter().map(|upload| &upload.name);
+        let contracts = self.contract.iter().map(|contract| &contract.name);
+        for name in uploads.chain(contracts) {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                anyhow::bail!(
+                    "Invalid name `{name}`, expected ASCII letters, digits, `_` or `-`"
+                )
+            }
+            if !names.insert(name.as_str()) {
+                anyhow::bail!("The name `{name}` is used more than once")
+            }
+        }
+
+        let contract_args = self.contract.iter().flat_map(|contract| &contract.args);
+        let call_args = self.call.iter().flat_map(|call| &call.args);
+        for arg in contract_args.chain(call_args) {
+            for (_, reference) in references(arg)? {
+                match reference {
+                    Reference::Address(name) if self.contract(name).is_none() => {
+                        anyhow::bail!(
+                            "The argument `{arg}` references the address of `{name}`, \
+                            which is not a contract of the manifest"
+                        )
+                    }
+                    Reference::CodeHash(name) if !names.contains(name) => {
+                        anyhow::bail!(
+                            "The argument `{arg}` references the code hash of `{name}`, \
+                            which is neither an upload nor a contract of the manifest"
+                        )
+                    }
+                    _ => (),
+                }
+            }
+        }
+        for call in &self.call {
+            if self.contract(&call.contract).is_none() {
+                anyhow::bail!(
+                    "The message `{}` is called on `{}`, which is not a contract of the \
+                    manifest",
+                    call.message,
+                    call.contract
+                )
+            }
+        }
+        Ok(())
+    }
+
+    fn contract(&self, name: &str) -> Option<&Contract> {
+        self.contract.iter().find(|contract| contract.name == name)
+    }
+
+    /// Returns the contracts in the order they are instantiated: each after the
+    /// contracts its arguments reference, and otherwise in the order of the manifest.
+    fn contract_order(&self) -> Result<Vec<&Contract>> {
+        let mut dependencies = Vec::new();
+        for contract in &self.contract {
+            let mut names = Vec::new();
+            for arg in &contract.args {
+                names.extend(references(arg)?.into_iter().map(|(_, r)| r.name()));
+            }
+            let indices = self
+                .contract
+                .iter()
+                .enumerate()
+                .filter(|(_, dependency)| names.contains(&dependency.name.as_str()))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            dependencies.push(indices);
+        }
+
+        let mut placed = vec![false; self.contract.len()];
+        let mut order = Vec::new();
+        while order.len() < self.contract.len() {
+            let next = (0..self.contract.len()).find(|&index| {
+                !placed[index] && dependencies[index].iter().all(|&d| placed[d])
+            });
+            match next {
+                Some(index) => {
+                    placed[index] = true;
+                    order.push(&self.contract[index]);
+                }
+                None => {
+                    let cycle = self
+                        .contract
+                        .iter()
+                        .zip(&placed)
+                        .filter(|(_, placed)| !**placed)
+                        .map(|(contract, _)| format!("`{}`", contract.name))
+                        .collect::<Vec<_>>();
+                    anyhow::bail!(
+                        "The arguments of the contracts {} reference each other in a \
+                        cycle",
+                        cycle.join(", ")
+                    )
+                }
+            }
+        }
+        Ok(order)
+    }
+}
+
+/// A reference of an argument to an upload or contract of the manifest.
+#[derive(Debug, PartialEq)]
+enum Reference<'a> {
+    /// `${name}`, the address of a contract.
+    Address(&'a str),
+    /// `${name.code_hash}`, the code hash of an upload or contract.
+    CodeHash(&'a str),
+}
+
+impl<'a> Reference<'a> {
+    fn name(&self) -> &'a str {
+        match self {
+            Reference::Address(name) | Reference::CodeHash(name) => name,
+        }
+    }
+}
+
+/// Returns the references of an argument, along with their range in the argument.
+fn references(arg: &str) -> Result<Vec<(Range<usize>, Reference<'_>)>> {
+    let mut references = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = arg[offset..].find("${").map(|start| offset + start) {
+        let end = arg[start..]
+            .find('}')
+            .map(|end| start + end + 1)
+            .ok_or_else(|| anyhow!("Unterminated reference in the argument `{arg}`"))?;
+        let reference = match arg[start + 2..end - 1].split_once('.') {
+            None => Reference::Address(&arg[start + 2..end - 1]),
+            Some((name, "code_hash")) => Reference::CodeHash(name),
+            Some(_) => {
+                anyhow::bail!(
+                    "Invalid reference `{}` in the argument `{arg}`, expected `${{name}}` \
+                    or `${{name.code_hash}}`",
+                    &arg[start..end]
+                )
+            }
+        };
+        references.push((start..end, reference));
+        offset = end;
+    }
+    Ok(references)
+}
+
+/// An upload or contract of the manifest which is deployed.
+#[derive(Clone, Debug)]
+struct Deployed {
+    code_hash: CodeHash,
+    /// The address of a contract, `None` for an upload.
+    address: Option<AccountId>,
+}
+
+/// Substitutes the references of an argument with the code hashes and addresses of
+/// the deployed uploads and contracts. Returns `None` if the argument references one
+/// which is not deployed yet.
+fn resolve(arg: &str, deployed: &HashMap<String, Deployed>) -> Result<Option<String>> {
+    let mut resolved = String::new();
+    let mut offset = 0;
+    for (range, reference) in references(arg)? {
+        let Some(deployed) = deployed.get(reference.name()) else {
+            return Ok(None)
+        };
+        let value = match reference {
+            Reference::Address(_) => {
+                deployed
+                    .address
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .ok_or_else(|| anyhow!("`{}` has no address", reference.name()))?
+            }
+            Reference::CodeHash(_) => format!("{:?}", deployed.code_hash),
+        };
+        resolved.push_str(&arg[offset..range.start]);
+        resolved.push_str(&value);
+        offset = range.end;
+    }
+    resolved.push_str(&arg[offset..]);
+    Ok(Some(resolved))
+}
+
+/// Resolves all arguments, `None` if any of them references an upload or contract which
+/// is not deployed yet.
+fn resolve_all(
+    args: &[String],
+    deployed: &HashMap<String, Deployed>,
+) -> Result<Option<Vec<String>>> {
+    let mut resolved = Vec::new();
+    for arg in args {
+        match resolve(arg, deployed)? {
+            Some(arg) => resolved.push(arg),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(resolved))
+}
+
+/// The lockfile of a deployment, recording the code hashes and contracts resulting
+/// from the entries of the manifest.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+struct DeployLock {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    upload: BTreeMap<String, LockedUpload>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    contract: BTreeMap<String, LockedContract>,
+    /// The calls in the order of the manifest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    call: Vec<LockedCall>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct LockedUpload {
+    code_hash: String,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct LockedContract {
+    address: String,
+    code_hash: String,
+    constructor: String,
+    /// The arguments with their references resolved.
+    args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+struct LockedCall {
+    /// The address of the contract called.
+    contract: String,
+    message: String,
+    /// The arguments with their references resolved.
+    args: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+impl DeployLock {
+    /// Loads the lockfile, empty if it does not exist yet.
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default())
+        }
+        let toml = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lockfile {}", path.display()))?;
+        toml::from_str(&toml)
+            .with_context(|| format!("Failed to parse lockfile {}", path.display()))
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let toml = format!(
+            "# This file is generated by `cargo contract deploy`.\n\
+            # It is not intended for manual editing.\n\n{}",
+            toml::to_string_pretty(self)?
+        );
+        std::fs::write(path, toml)
+            .with_context(|| format!("Failed to write lockfile {}", path.display()))
+    }
+
+    /// Removes the entries of uploads, contracts and calls which are no longer in the
+    /// manifest.
+    fn retain(&mut self, manifest: &DeployManifest) {
+        self.upload
+            .retain(|name, _| manifest.upload.iter().any(|upload| &upload.name == name));
+        self.contract
+            .retain(|name, _| manifest.contract(name).is_some());
+        self.call.truncate(manifest.call.len());
+    }
+
+    fn set_call(&mut self, index: usize, call: LockedCall) {
+        match self.call.get_mut(index) {
+            Some(locked) => *locked = call,
+            None => self.call.push(call),
+        }
+    }
+}
+
+/// Whether an entry of the manifest is deployed.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+enum EntryStatus {
+    /// The entry is deployed already, as recorded in the lockfile.
+    UpToDate,
+    /// The entry is to be deployed.
+    Pending,
+    /// The entry was deployed by this run.
+    Executed,
+}
+
+impl Display for EntryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryStatus::UpToDate => write!(f, "up to date"),
+            EntryStatus::Pending => write!(f, "pending"),
+            EntryStatus::Executed => write!(f, "executed"),
+        }
+    }
+}
+
+/// The outcome of an entry of the manifest.
+#[derive(serde::Serialize)]
+struct EntryResult {
+    entry: String,
+    status: EntryStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contract: Option<String>,
+    /// The details of the executed extrinsic, if requested with `--output-extrinsic`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extrinsic: Option<ExtrinsicDetails>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    events: Option<DisplayEvents>,
+}
+
+impl EntryResult {
+    fn new(entry: String, status: EntryStatus, deployed: Option<&Deployed>) -> Self {
+        Self {
+            entry,
+            status,
+            code_hash: deployed.map(|deployed| format!("{:?}", deployed.code_hash)),
+            contract: deployed
+                .and_then(|deployed| deployed.address.as_ref())
+                .map(ToString::to_string),
+            extrinsic: None,
+            events: None,
+        }
+    }
+
+    /// Displays the entry without its events.
+    fn display_status(&self, index: usize) {
+        println!(
+            "{:>width$} {}",
+            format!("Entry {}", index + 1).bright_green().bold(),
+            self.entry.bright_white(),
+            width = DEFAULT_KEY_COL_WIDTH
+        );
+        name_value_println!("Status", self.status.to_string());
+        if let Some(code_hash) = &self.code_hash {
+            name_value_println!("Code hash", code_hash);
+        }
+        if let Some(contract) = &self.contract {
+            name_value_println!("Contract", contract);
+        }
+    }
+}
+
+/// The outcome of the deployment.
+#[derive(serde::Serialize)]
+struct DeployResult<'a> {
+    entries: &'a [EntryResult],
+}
+
+impl DeployResult<'_> {
+    fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+impl DeployCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    pub fn run(&self) -> Result<(), ErrorVariant> {
+        let manifest = DeployManifest::load(&self.deploy_manifest)?;
+        let contracts = manifest.contract_order()?;
+        let lockfile = self.lockfile();
+        let lock = DeployLock::load(&lockfile)?;
+        if self.extrinsic_opts.execute && self.extrinsic_opts.wait_for() == WaitFor::None
+        {
+            return Err(anyhow!(
+                "The deployment requires the results of its extrinsics, `--wait none` is \
+                not supported"
+            )
+            .into())
+        }
+        // the entries depend on the results of the preceding ones, so the extrinsics
+        // can neither be signed offline nor await the approvals of a multisig account
+        let signer = Signer::Key(self.extrinsic_opts.signer()?);
+        let origin = self.proxy_opts.origin(Origin::from(signer))?;
+        let url = self.extrinsic_opts.url_to_string();
+
+        async_std::task::block_on(async {
+            let client = OnlineClient::from_url(url.clone()).await?;
+            let token_metadata = TokenMetadata::query(&client).await?;
+            let deployment = Deployment {
+                command: self,
+                manifest: &manifest,
+                contracts,
+                client,
+                origin,
+                url,
+                token_metadata,
+            };
+
+            let plan = deployment.run(&mut lock.clone(), None).await?;
+            let up_to_date = plan
+                .iter()
+                .all(|entry| entry.status == EntryStatus::UpToDate);
+            if !self.extrinsic_opts.execute || up_to_date {
+                if self.output_json {
+                    println!("{}", DeployResult { entries: &plan }.to_json()?);
+                } else {
+                    display_entries(&plan);
+                    if !self.extrinsic_opts.execute {
+                        display_dry_run_result_warning("deploy");
+                    }
+                }
+                return Ok(())
+            }
+            if !self.extrinsic_opts.skip_confirm {
+                prompt_confirm_tx(|| display_entries(&plan))?;
+            }
+
+            let mut lock = lock;
+            let entries = deployment.run(&mut lock, Some(&lockfile)).await?;
+            lock.retain(&manifest);
+            lock.save(&lockfile)?;
+            if self.output_json {
+                println!("{}", DeployResult { entries: &entries }.to_json()?);
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the path to the lockfile.
+    fn lockfile(&self) -> PathBuf {
+        self.lockfile.clone().unwrap_or_else(|| {
+            self.deploy_manifest
+                .parent()
+                .unwrap_or_else(|| Path::new(""))
+                .join(DEFAULT_LOCKFILE)
+        })
+    }
+}
+
+/// A run of the deployment against a chain.
+struct Deployment<'a> {
+    command: &'a DeployCommand,
+    manifest: &'a DeployManifest,
+    /// The contracts in the order they are instantiated.
+    contracts: Vec<&'a Contract>,
+    client: Client,
+    origin: Origin,
+    url: String,
+    token_metadata: TokenMetadata,
+}
+
+impl Deployment<'_> {
+    /// Walks the uploads, the contracts and the calls of the manifest, and determines
+    /// whether they are deployed as recorded in the lock.
+    ///
+    /// If a `lockfile` is given, the pending entries are deployed, and the lockfile is
+    /// updated after each of them. Otherwise the entries depending on pending ones are
+    /// reported as pending too.
+    async fn run(
+        &self,
+        lock: &mut DeployLock,
+        lockfile: Option<&Path>,
+    ) -> Result<Vec<EntryResult>, ErrorVariant> {
+        let mut deployed = HashMap::new();
+        let mut entries = Vec::new();
+
+        for upload in &self.manifest.upload {
+            let artifacts = self.artifacts(&upload.file, &upload.manifest_path)?;
+            let code_hash: CodeHash = artifacts.code_hash()?.into();
+            let entry = format!("upload {}", upload.name);
+            let result = Deployed {
+                code_hash,
+                address: None,
+            };
+            let locked = LockedUpload {
+                code_hash: format!("{code_hash:?}"),
+            };
+            if self.code_exists(code_hash).await? {
+                entries.push(EntryResult::new(
+                    entry,
+                    EntryStatus::UpToDate,
+                    Some(&result),
+                ));
+                lock.upload.insert(upload.name.clone(), locked);
+            } else if let Some(lockfile) = lockfile {
+                let code = artifacts
+                    .code
+                    .ok_or_else(|| anyhow!("Contract code not found for `{entry}`"))?;
+                let call = StepCall::Upload { code: code.code };
+                let submitted = self.submit(&entry, &call, None, None).await?;
+                self.record(&mut entries, entry, &submitted, None, Some(&result))?;
+                lock.upload.insert(upload.name.clone(), locked);
+                lock.save(lockfile)?;
+            } else {
+                entries.push(EntryResult::new(
+                    entry,
+                    EntryStatus::Pending,
+                    Some(&result),
+                ));
+                continue
+            }
+            deployed.insert(upload.name.clone(), result);
+        }
+
+        for contract in &self.contracts {
+            let artifacts = self.artifacts(&contract.file, &contract.manifest_path)?;
+            let code_hash: CodeHash = artifacts.code_hash()?.into();
+            let entry = format!(
+                "instantiate {} {}",
+                contract.name,
+                call_description(&contract.constructor, &contract.args)
+            );
+            let args = resolve_all(&contract.args, &deployed)?;
+            let locked = lock.contract.get(&contract.name).filter(|locked| {
+                locked.code_hash == format!("{code_hash:?}")
+                    && locked.constructor == contract.constructor
+                    && Some(&locked.args) == args.as_ref()
+                    && locked.value == contract.value
+                    && locked.salt == contract.salt
+            });
+            let address = match locked {
+                Some(locked) => {
+                    let address =
+                        AccountId::from_str(&locked.address).map_err(|err| {
+                            anyhow!(
+                                "Invalid address {} in lockfile: {err}",
+                                locked.address
+                            )
+                        })?;
+                    self.contract_exists(&address).await?.then_some(address)
+                }
+                None => None,
+            };
+
+            if let Some(address) = address {
+                let result = Deployed {
+                    code_hash,
+                    address: Some(address),
+                };
+                entries.push(EntryResult::new(
+                    entry,
+                    EntryStatus::UpToDate,
+                    Some(&result),
+                ));
+                deployed.insert(contract.name.clone(), result);
+            } else if let Some(lockfile) = lockfile {
+                let args = args.ok_or_else(|| {
+                    anyhow!("The arguments of `{entry}` reference pending entries")
+                })?;
+                let transcoder = artifacts.contract_transcoder()?;
+                let data = transcoder.encode(&contract.constructor, &args)?;
+                let code = if self.code_exists(code_hash).await? {
+                    Code::Existing(code_hash)
+                } else {
+                    let code = artifacts.code.ok_or_else(|| {
+                        anyhow!("Contract code not found for `{entry}`")
+                    })?;
+                    Code::Upload(code.code)
+                };
+                let salt = contract
+                    .salt
+                    .as_deref()
+                    .map(decode_hex)
+                    .transpose()
+                    .with_context(|| format!("Invalid salt of `{}`", contract.name))?
+                    .unwrap_or_default();
+                let call = StepCall::Instantiate {
+                    code,
+                    value: step_value(contract.value.as_deref(), &self.token_metadata)?,
+                    data,
+                    salt,
+                };
+                let submitted = self
+                    .submit(&entry, &call, contract.gas_limit, contract.proof_size)
+                    .await?;
+                // contracts instantiated by the constructor are reported before
+                let instantiated = match &submitted.events {
+                    Some(events) => Instantiated::find_last(events)?,
+                    None => None,
+                };
+                let address = instantiated
+                    .map(|instantiated| instantiated.contract)
+                    .ok_or_else(|| anyhow!("Failed to find Instantiated event"))?;
+                let result = Deployed {
+                    code_hash,
+                    address: Some(address.clone()),
+                };
+                self.record(
+                    &mut entries,
+                    entry,
+                    &submitted,
+                    Some(&transcoder),
+                    Some(&result),
+                )?;
+                deployed.insert(contract.name.clone(), result);
+                lock.contract.insert(
+                    contract.name.clone(),
+                    LockedContract {
+                        address: address.to_string(),
+                        code_hash: format!("{code_hash:?}"),
+                        constructor: contract.constructor.clone(),
+                        args,
+                        value: contract.value.clone(),
+                        salt: contract.salt.clone(),
+                    },
+                );
+                lock.save(lockfile)?;
+            } else {
+                let result = Deployed {
+                    code_hash,
+                    address: None,
+                };
+                entries.push(EntryResult::new(
+                    entry,
+                    EntryStatus::Pending,
+                    Some(&result),
+                ));
+            }
+        }
+
+        for (index, call) in self.manifest.call.iter().enumerate() {
+            let contract = self
+                .manifest
+                .contract(&call.contract)
+                .expect("the called contracts are validated; qed");
+            let entry = format!(
+                "call {} {}",
+                call.contract,
+                call_description(&call.message, &call.args)
+            );
+            let address = deployed
+                .get(&call.contract)
+                .and_then(|deployed| deployed.address.clone());
+            let args = resolve_all(&call.args, &deployed)?;
+            let locked = address.as_ref().zip(args.as_ref()).map(|(address, args)| {
+                LockedCall {
+                    contract: address.to_string(),
+                    message: call.message.clone(),
+                    args: args.clone(),
+                    value: call.value.clone(),
+                }
+            });
+            if locked.is_some() && lock.call.get(index) == locked.as_ref() {
+                entries.push(EntryResult::new(entry, EntryStatus::UpToDate, None));
+            } else if let Some(lockfile) = lockfile {
+                let (address, locked) = address
+                    .zip(locked)
+                    .ok_or_else(|| anyhow!("`{entry}` references pending entries"))?;
+                let artifacts =
+                    self.artifacts(&contract.file, &contract.manifest_path)?;
+                let transcoder = artifacts.contract_transcoder()?;
+                let call_data = transcoder.encode(&call.message, &locked.args)?;
+                let step = StepCall::Call {
+                    contract: address,
+                    message: call.message.clone(),
+                    value: step_value(call.value.as_deref(), &self.token_metadata)?,
+                    data: call_data,
+                };
+                let submitted = self
+                    .submit(&entry, &step, call.gas_limit, call.proof_size)
+                    .await?;
+                self.record(&mut entries, entry, &submitted, Some(&transcoder), None)?;
+                lock.set_call(index, locked);
+                lock.save(lockfile)?;
+            } else {
+                entries.push(EntryResult::new(entry, EntryStatus::Pending, None));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Records an executed entry, displaying it unless the output is JSON.
+    fn record(
+        &self,
+        entries: &mut Vec<EntryResult>,
+        entry: String,
+        submitted: &SubmittedExtrinsic<DefaultConfig>,
+        transcoder: Option<&ContractMessageTranscoder>,
+        deployed: Option<&Deployed>,
+    ) -> Result<()> {
+        let mut result = EntryResult::new(entry, EntryStatus::Executed, deployed);
+        let events =
+            DisplayEvents::from_events(submitted, transcoder, &self.client.metadata())?;
+        if self.command.extrinsic_opts.output_extrinsic() {
+            result.extrinsic = events.extrinsic().cloned();
+        }
+        result.events = Some(events);
+        if !self.command.output_json {
+            result.display_status(entries.len());
+            if let Some(events) = &result.events {
+                let verbosity = self.command.extrinsic_opts.verbosity()?;
+                println!(
+                    "{}",
+                    events.display_events(verbosity, &self.token_metadata)?
+                );
+            }
+        }
+        entries.push(result);
+        Ok(())
+    }
+
+    fn artifacts(
+        &self,
+        file: &Option<PathBuf>,
+        manifest_path: &Option<PathBuf>,
+    ) -> Result<ContractArtifacts> {
+        let dir = self
+            .command
+            .deploy_manifest
+            .parent()
+            .unwrap_or_else(|| Path::new(""));
+        step_artifacts(
+            &self.command.extrinsic_opts,
+            dir,
+            file.as_ref(),
+            manifest_path.as_ref(),
+        )
+    }
+
+    /// Whether the code with the hash is stored on chain.
+    async fn code_exists(&self, code_hash: CodeHash) -> Result<bool> {
+        let block_hash = block_hash(&self.client, None).await?;
+        contracts_pallet::code_exists(&self.client, code_hash, block_hash).await
+    }
+
+    /// Whether a contract is instantiated at the address.
+    async fn contract_exists(&self, address: &AccountId) -> Result<bool> {
+        let block_hash = block_hash(&self.client, None).await?;
+        let contract_info =
+            ContractInfo::fetch(&self.client, address, block_hash).await?;
+        Ok(contract_info.is_some())
+    }
+
+    /// Submits the call of an entry, with the gas limit of the manifest or estimated
+    /// by a dry run.
+    async fn submit(
+        &self,
+        entry: &str,
+        call: &StepCall,
+        gas_limit: Option<u64>,
+        proof_size: Option<u64>,
+    ) -> Result<SubmittedExtrinsic<DefaultConfig>, ErrorVariant> {
+        let opts = &self.command.extrinsic_opts;
+        let storage_deposit_limit = opts.storage_deposit_limit(&self.token_metadata)?;
+        let gas_limit = match call.gas_limit(gas_limit, proof_size) {
+            Some(gas_limit) => gas_limit,
+            None if opts.skip_dry_run => {
+                return Err(anyhow!(
+                    "Contracts and calls require `gas_limit` and `proof_size` if \
+                    `--skip-dry-run` is specified"
+                )
+                .into())
+            }
+            None => {
+                if !self.command.output_json {
+                    super::print_dry_running_status(entry);
+                }
+                call.dry_run(
+                    &self.url,
+                    self.origin.account_id(),
+                    storage_deposit_limit.map(|limit| limit.0),
+                )
+                .await?
+                .gas_limit(
+                    gas_limit,
+                    proof_size,
+                    self.command.output_json,
+                    &self.client.metadata(),
+                )?
+            }
+        };
+        match call
+            .submit(
+                gas_limit,
+                storage_deposit_limit,
+                &self.client,
+                &self.origin,
+                opts.wait_for(),
+            )
+            .await?
+        {
+            Submission::Dispatched(submitted) => Ok(submitted),
+            Submission::Approved(_) | Submission::Unsigned => {
+                Err(anyhow!(
+                    "The deployment requires the results of its extrinsics, `{entry}` \
+                    was not dispatched"
+                )
+                .into())
+            }
+        }
+    }
+}
+
+/// Displays the status of the entries before any of them is executed.
+fn display_entries(entries: &[EntryResult]) {
+    for (index, entry) in entries.iter().enumerate() {
+        entry.display_status(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(toml: &str) -> DeployManifest {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn manifest_is_parsed() {
+        let manifest = manifest(
+            r#"
+            [[upload]]
+            name = "pair"
+            file = "pair.contract"
+
+            [[contract]]
+            name = "factory"
+            file = "factory.contract"
+            args = ["${pair.code_hash}"]
+
+            [[contract]]
+            name = "router"
+            manifest_path = "router/Cargo.toml"
+            constructor = "new_with_factory"
+            args = ["${factory}"]
+            salt = "0x01"
+            value = "1 UNIT"
+
+            [[call]]
+            contract = "factory"
+            message = "set_router"
+            args = ["${router}"]
+            gas_limit = 1000
+            proof_size = 100
+            "#,
+        );
+
+        assert!(manifest.validate().is_ok());
+        assert_eq!(
+            manifest.upload[0].file,
+            Some(PathBuf::from("pair.contract"))
+        );
+        assert_eq!(manifest.contract[0].constructor, "new");
+        assert_eq!(manifest.contract[1].constructor, "new_with_factory");
+        assert_eq!(manifest.contract[1].value.as_deref(), Some("1 UNIT"));
+        assert_eq!(manifest.call[0].gas_limit, Some(1000));
+    }
+
+    #[test]
+    fn references_are_parsed() {
+        let parsed = references("[${a}, ${b.code_hash}]").unwrap();
+
+        assert_eq!(
+            parsed,
+            vec![
+                (1..5, Reference::Address("a")),
+                (7..21, Reference::CodeHash("b"))
+            ]
+        );
+        assert!(references("${a").is_err());
+        assert!(references("${a.address}").is_err());
+    }
+
+    #[test]
+    fn references_are_resolved() {
+        // given
+        let address =
+            AccountId::from_str("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")
+                .unwrap();
+        let deployed = HashMap::from([(
+            "a".to_string(),
+            Deployed {
+                code_hash: CodeHash::repeat_byte(1),
+                address: Some(address),
+            },
+        )]);
+
+        // when
+        let resolved = resolve("(${a}, ${a.code_hash})", &deployed).unwrap();
+        let pending = resolve("${b}", &deployed).unwrap();
+
+        // then
+        assert_eq!(
+            resolved,
+            Some(format!(
+                "(5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY, 0x{})",
+                "01".repeat(32)
+            ))
+        );
+        assert_eq!(pending, None);
+    }
+
+    #[test]
+    fn contracts_are_ordered_after_their_references() {
+        let manifest = manifest(
+            r#"
+            [[contract]]
+            name = "router"
+            args = ["${factory}", "${token}"]
+
+            [[contract]]
+            name = "token"
+
+            [[contract]]
+            name = "factory"
+            args = ["${token.code_hash}"]
+            "#,
+        );
+
+        let order = manifest.contract_order().unwrap();
+
+        let names = order
+            .iter()
+            .map(|contract| contract.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["token", "factory", "router"]);
+    }
+
+    #[test]
+    fn cyclic_references_are_rejected() {
+        let manifest = manifest(
+            r#"
+            [[contract]]
+            name = "a"
+            args = ["${b}"]
+
+            [[contract]]
+            name = "b"
+            args = ["${a}"]
+
+            [[contract]]
+            name = "c"
+            "#,
+        );
+
+        let err = manifest.contract_order().unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "The arguments of the contracts `a`, `b` reference each other in a cycle"
+        );
+    }
+
+    #[test]
+    fn invalid_references_are_rejected() {
+        let unknown = manifest(
+            r#"
+            [[contract]]
+            name = "a"
+            args = ["${b}"]
+            "#,
+        );
+        let upload_address = manifest(
+            r#"
+            [[upload]]
+            name = "a"
+
+            [[contract]]
+            name = "b"
+            args = ["${a}"]
+            "#,
+        );
+        let duplicate = manifest(
+            r#"
+            [[upload]]
+            name = "a"
+
+            [[contract]]
+            name = "a"
+            "#,
+        );
+
+        assert!(unknown.validate().is_err());
+        assert!(upload_address.validate().is_err());
+        assert_eq!(
+            duplicate.validate().unwrap_err().to_string(),
+            "The name `a` is used more than once"
+        );
+    }
+
+    #[test]
+    fn lock_roundtrips_through_toml() {
+        let mut lock = DeployLock::default();
+        lock.upload.insert(
+            "pair".to_string(),
+            LockedUpload {
+                code_hash: format!("{:?}", CodeHash::repeat_byte(1)),
+            },
+        );
+        lock.call.push(LockedCall {
+            contract: "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY".to_string(),
+            message: "flip".to_string(),
+            args: Vec::new(),
+            value: None,
+        });
+
+        let toml = toml::to_string_pretty(&lock).unwrap();
+
+        assert_eq!(toml::from_str::<DeployLock>(&toml).unwrap(), lock);
+    }
+}
@@ -27,6 +27,8 @@ pub enum ErrorVariant {
     Module(ModuleError),
     #[serde(rename = "generic_error")]
     Generic(GenericError),
+    #[serde(rename = "transaction_error")]
+    Transaction(TransactionError),
 }
 
 impl From<subxt::Error> for ErrorVariant {
@@ -66,6 +68,27 @@ impl From<&str> for ErrorVariant {
     }
 }
 
+impl From<TransactionError> for ErrorVariant {
+    fn from(error: TransactionError) -> Self {
+        Self::Transaction(error)
+    }
+}
+
+impl From<&contract_sandbox::Error> for ErrorVariant {
+    fn from(error: &contract_sandbox::Error) -> Self {
+        match error.pallet_error() {
+            Some(name) => {
+                ErrorVariant::Module(ModuleError {
+                    pallet: String::from("Contracts"),
+                    error: name.to_string(),
+                    docs: vec![error.to_string()],
+                })
+            }
+            None => ErrorVariant::Generic(GenericError::from_message(error.to_string())),
+        }
+    }
+}
+
 #[derive(serde::Serialize)]
 pub struct ModuleError {
     pub pallet: String,
@@ -84,6 +107,51 @@ impl GenericError {
     }
 }
 
+/// The reasons for which a submitted extrinsic did not reach the stage of its lifecycle
+/// it was waited for.
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TransactionError {
+    /// The extrinsic was dropped from the transaction pool because of a limit.
+    Dropped,
+    /// The extrinsic is not valid in the current state of the chain.
+    Invalid,
+    /// The extrinsic was replaced in the pool by the extrinsic `by`, with the same
+    /// sender and nonce but a higher priority.
+    Usurped { by: String },
+    /// The block including the extrinsic was retracted, and the extrinsic was not
+    /// included again.
+    Retracted { block_hash: String },
+    /// The block including the extrinsic was not finalized within 512 blocks.
+    FinalityTimeout { block_hash: String },
+}
+
+impl Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dropped => {
+                write!(f, "The extrinsic was dropped from the transaction pool")
+            }
+            Self::Invalid => write!(f, "The extrinsic is invalid"),
+            Self::Usurped { by } => {
+                write!(f, "The extrinsic was replaced in the pool by {by}")
+            }
+            Self::Retracted { block_hash } => {
+                write!(
+                    f,
+                    "The block {block_hash} including the extrinsic was retracted"
+                )
+            }
+            Self::FinalityTimeout { block_hash } => {
+                write!(
+                    f,
+                    "The block {block_hash} including the extrinsic was not finalized in time"
+                )
+            }
+        }
+    }
+}
+
 impl ErrorVariant {
     pub fn from_dispatch_error(
         error: &DispatchError,
@@ -123,6 +191,7 @@ impl Display for ErrorVariant {
                 ))
             }
             ErrorVariant::Generic(err) => write!(f, "{}", err.error),
+            ErrorVariant::Transaction(err) => write!(f, "TransactionError: {err}"),
         }
     }
 }
@@ -15,12 +15,21 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
+    contracts_pallet::is_contract_emitted,
     BalanceVariant,
     DefaultConfig,
+    SubmittedExtrinsic,
     TokenMetadata,
 };
 use crate::{
-    cmd::runtime_api::api::contracts::events::ContractEmitted,
+    cmd::{
+        runtime_api::api::{
+            balances::events::Withdraw,
+            transaction_payment::events::TransactionFeePaid,
+            utility::events::ItemCompleted,
+        },
+        Balance,
+    },
     DEFAULT_KEY_COL_WIDTH,
 };
 use colored::Colorize as _;
@@ -28,11 +37,13 @@ use contract_build::Verbosity;
 use contract_transcode::{
     ContractMessageTranscoder,
     Hex,
+    Transcoder,
     TranscoderBuilder,
     Value,
 };
 
 use anyhow::Result;
+use scale::Encode;
 use scale_info::form::PortableForm;
 use std::{
     fmt::Write,
@@ -41,7 +52,11 @@ use std::{
 use subxt::{
     self,
     blocks::ExtrinsicEvents,
-    events::StaticEvent,
+    events::{
+        EventDetails,
+        StaticEvent,
+    },
+    ext::scale_value::Composite,
 };
 
 /// Field that represent data of an event from invoking a contract extrinsic.
@@ -77,81 +92,281 @@ pub struct Event {
     pub fields: Vec<Field>,
 }
 
+/// The stage of its lifecycle a submitted extrinsic reached.
+#[derive(Clone, Copy, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtrinsicStatus {
+    /// Accepted into the transaction pool.
+    Submitted,
+    /// Included in a block.
+    InBlock,
+    /// Included in a finalized block.
+    Finalized,
+}
+
+impl std::fmt::Display for ExtrinsicStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Submitted => write!(f, "Submitted"),
+            Self::InBlock => write!(f, "In block"),
+            Self::Finalized => write!(f, "Finalized"),
+        }
+    }
+}
+
+/// Details of a submitted extrinsic.
+#[derive(Clone, serde::Serialize)]
+pub struct ExtrinsicDetails {
+    /// The hash of the extrinsic.
+    pub hash: String,
+    /// The stage of its lifecycle the extrinsic reached.
+    pub status: ExtrinsicStatus,
+    /// The hash of the block including the extrinsic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<String>,
+    /// The index of the extrinsic in the block including it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    /// The fee actually paid for the extrinsic.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee_paid: Option<Balance>,
+}
+
+impl ExtrinsicDetails {
+    /// Returns the details of the submitted extrinsic.
+    fn from_submitted(submitted: &SubmittedExtrinsic<DefaultConfig>) -> Result<Self> {
+        let hash = format!("{:?}", submitted.hash);
+        let Some(result) = &submitted.events else {
+            return Ok(ExtrinsicDetails {
+                hash,
+                status: ExtrinsicStatus::Submitted,
+                block_hash: None,
+                index: None,
+                fee_paid: None,
+            })
+        };
+        Ok(ExtrinsicDetails {
+            hash,
+            status: if submitted.finalized {
+                ExtrinsicStatus::Finalized
+            } else {
+                ExtrinsicStatus::InBlock
+            },
+            block_hash: Some(format!("{:?}", result.block_hash())),
+            index: Some(result.extrinsic_index()),
+            fee_paid: fee_paid(result)?,
+        })
+    }
+}
+
+/// Returns the `actual_fee` of the `TransactionFeePaid` event, decoded by name since the
+/// event also holds the account of the chain paying the fee.
+fn fee_paid(events: &ExtrinsicEvents<DefaultConfig>) -> Result<Option<Balance>> {
+    for event in events.iter() {
+        let event = event?;
+        if <TransactionFeePaid as StaticEvent>::is_event(
+            event.pallet_name(),
+            event.variant_name(),
+        ) {
+            let Composite::Named(fields) = event.field_values()? else {
+                return Ok(None)
+            };
+            return Ok(fields
+                .iter()
+                .find(|(name, _)| name == "actual_fee")
+                .and_then(|(_, fee)| fee.as_u128()))
+        }
+    }
+    Ok(None)
+}
+
+/// The events of a `utility.batch` or `utility.batch_all` extrinsic, split by the call
+/// of the batch which emitted them.
+pub struct BatchEvents {
+    /// The events of the extrinsic itself, e.g. for the payment of its fee.
+    pub extrinsic: DisplayEvents,
+    /// The events of each call of the batch which completed.
+    pub items: Vec<DisplayEvents>,
+}
+
 /// Displays events produced from invoking a contract extrinsic.
+///
+/// Serialized as the array of the events, the details of the extrinsic are only part of
+/// the JSON returned by [`DisplayEvents::to_json`] if requested.
 #[derive(serde::Serialize)]
-pub struct DisplayEvents(Vec<Event>);
+#[serde(transparent)]
+pub struct DisplayEvents {
+    /// The extrinsic which emitted the events, `None` for an execution in the sandbox.
+    #[serde(skip)]
+    extrinsic: Option<ExtrinsicDetails>,
+    events: Vec<Event>,
+}
+
+/// The events of an extrinsic together with the details of the extrinsic.
+#[derive(serde::Serialize)]
+struct DisplayExtrinsic<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extrinsic: Option<&'a ExtrinsicDetails>,
+    events: &'a [Event],
+}
 
 impl DisplayEvents {
-    /// Parses events and returns an object which can be serialised
+    /// Parses the events of the submitted extrinsic and returns an object which can be
+    /// serialised.
     pub fn from_events(
-        result: &ExtrinsicEvents<DefaultConfig>,
+        submitted: &SubmittedExtrinsic<DefaultConfig>,
         transcoder: Option<&ContractMessageTranscoder>,
         subxt_metadata: &subxt::Metadata,
     ) -> Result<DisplayEvents> {
+        let extrinsic = ExtrinsicDetails::from_submitted(submitted)?;
         let mut events: Vec<Event> = vec![];
+        if let Some(result) = &submitted.events {
+            let decoder = EventDecoder::new(subxt_metadata);
+            for event in result.iter() {
+                events.push(decoder.decode(&event?, transcoder)?);
+            }
+        }
+        Ok(DisplayEvents {
+            extrinsic: Some(extrinsic),
+            events,
+        })
+    }
 
-        let runtime_metadata = subxt_metadata.runtime_metadata();
-        let events_transcoder = TranscoderBuilder::new(&runtime_metadata.types)
-            .with_default_custom_type_transcoders()
-            .done();
-
-        for event in result.iter() {
-            let event = event?;
-            tracing::debug!("displaying event {:?}", event);
-
-            let event_metadata =
-                subxt_metadata.event(event.pallet_index(), event.variant_index())?;
-            let event_fields = event_metadata.fields();
-
-            let mut event_entry = Event {
-                pallet: event.pallet_name().to_string(),
-                name: event.variant_name().to_string(),
-                fields: vec![],
-            };
-
-            let event_data = &mut event.field_bytes();
-            let mut unnamed_field_name = 0;
-            for field_metadata in event_fields {
-                if <ContractEmitted as StaticEvent>::is_event(
+    /// Parses the events of a submitted `utility.batch` or `utility.batch_all`
+    /// extrinsic, decoding the contract events of each call of the batch using the
+    /// [`ContractMessageTranscoder`] of the same index in `transcoders`, if any.
+    pub fn from_batch_events(
+        submitted: &SubmittedExtrinsic<DefaultConfig>,
+        transcoders: &[Option<&ContractMessageTranscoder>],
+        subxt_metadata: &subxt::Metadata,
+    ) -> Result<BatchEvents> {
+        let extrinsic = ExtrinsicDetails::from_submitted(submitted)?;
+        let mut items = Vec::new();
+        let mut events = Vec::new();
+        if let Some(result) = &submitted.events {
+            let decoder = EventDecoder::new(subxt_metadata);
+            let mut extrinsic_events = Vec::new();
+            for (index, event) in result.iter().enumerate() {
+                let event = event?;
+                if <ItemCompleted as StaticEvent>::is_event(
                     event.pallet_name(),
                     event.variant_name(),
-                ) && field_metadata.name == Some("data".to_string())
-                {
-                    tracing::debug!("event data: {:?}", hex::encode(&event_data));
-                    let field = contract_event_data_field(
-                        transcoder,
-                        field_metadata,
-                        event_data,
-                    )?;
-                    event_entry.fields.push(field);
-                } else {
-                    let field_name = field_metadata
-                        .name
-                        .as_ref()
-                        .map(|s| s.to_string())
-                        .unwrap_or_else(|| {
-                            let name = unnamed_field_name.to_string();
-                            unnamed_field_name += 1;
-                            name
-                        });
-
-                    let decoded_field = events_transcoder.decode(
-                        &runtime_metadata.types,
-                        field_metadata.ty.id,
-                        event_data,
-                    )?;
-                    let field = Field::new(
-                        field_name,
-                        decoded_field,
-                        field_metadata.type_name.as_ref().map(|s| s.to_string()),
+                ) {
+                    items.push(DisplayEvents {
+                        extrinsic: None,
+                        events: std::mem::take(&mut events),
+                    });
+                    continue
+                }
+                let transcoder = transcoders.get(items.len()).copied().flatten();
+                // the fee is withdrawn before the calls of the batch are dispatched
+                let is_fee = index == 0
+                    && <Withdraw as StaticEvent>::is_event(
+                        event.pallet_name(),
+                        event.variant_name(),
                     );
-                    event_entry.fields.push(field);
+                let event = decoder.decode(&event, transcoder)?;
+                if is_fee {
+                    extrinsic_events.push(event)
+                } else {
+                    events.push(event)
                 }
             }
-            events.push(event_entry);
+            extrinsic_events.append(&mut events);
+            events = extrinsic_events;
         }
+        Ok(BatchEvents {
+            extrinsic: DisplayEvents {
+                extrinsic: Some(extrinsic),
+                events,
+            },
+            items,
+        })
+    }
 
-        Ok(DisplayEvents(events))
+    /// Converts the events emitted by an execution in the sandbox, decoding the data of
+    /// contract events using the [`ContractMessageTranscoder`] if available.
+    pub fn from_sandbox_events(
+        sandbox_events: &[contract_sandbox::Event],
+        transcoder: Option<&ContractMessageTranscoder>,
+    ) -> Result<DisplayEvents> {
+        let account = |name: &str, account: &sp_core::crypto::AccountId32| {
+            Field::new(
+                name.to_string(),
+                Value::Literal(account.to_string()),
+                Some("T::AccountId".to_string()),
+            )
+        };
+        let hash = |name: &str, hash: &sp_core::H256| -> Result<Field> {
+            Ok(Field::new(
+                name.to_string(),
+                Value::Hex(Hex::from_str(&hex::encode(hash))?),
+                Some("T::Hash".to_string()),
+            ))
+        };
+        let mut events = Vec::new();
+        for event in sandbox_events {
+            let (name, fields) = match event {
+                contract_sandbox::Event::Instantiated { deployer, contract } => {
+                    (
+                        "Instantiated",
+                        vec![
+                            account("deployer", deployer),
+                            account("contract", contract),
+                        ],
+                    )
+                }
+                contract_sandbox::Event::Called { caller, contract } => {
+                    (
+                        "Called",
+                        vec![account("caller", caller), account("contract", contract)],
+                    )
+                }
+                contract_sandbox::Event::DelegateCalled {
+                    contract,
+                    code_hash,
+                } => {
+                    (
+                        "DelegateCalled",
+                        vec![
+                            account("contract", contract),
+                            hash("code_hash", code_hash)?,
+                        ],
+                    )
+                }
+                contract_sandbox::Event::ContractEmitted { contract, data, .. } => {
+                    // encoded as `Vec<u8>`, like the data of the event of a node
+                    let data = data.encode();
+                    let data = Field::new(
+                        String::from("data"),
+                        contract_event_data(transcoder, &mut &data[..])?,
+                        Some("Vec<u8>".to_string()),
+                    );
+                    ("ContractEmitted", vec![account("contract", contract), data])
+                }
+                contract_sandbox::Event::Terminated {
+                    contract,
+                    beneficiary,
+                } => {
+                    (
+                        "Terminated",
+                        vec![
+                            account("contract", contract),
+                            account("beneficiary", beneficiary),
+                        ],
+                    )
+                }
+            };
+            events.push(Event {
+                pallet: String::from("Contracts"),
+                name: name.to_string(),
+                fields,
+            });
+        }
+        Ok(DisplayEvents {
+            extrinsic: None,
+            events,
+        })
     }
 
     /// Displays events in a human readable format
@@ -161,12 +376,42 @@ impl DisplayEvents {
         token_metadata: &TokenMetadata,
     ) -> Result<String> {
         let event_field_indent: usize = DEFAULT_KEY_COL_WIDTH - 3;
-        let mut out = format!(
-            "{:>width$}\n",
+        let mut out = String::new();
+        if let Some(extrinsic) = &self.extrinsic {
+            let mut line = |name: &str, value: String| {
+                let _ = writeln!(
+                    out,
+                    "{:>width$} {}",
+                    name.bright_purple().bold(),
+                    value,
+                    width = DEFAULT_KEY_COL_WIDTH
+                );
+            };
+            line("Extrinsic", extrinsic.hash.clone());
+            line("Status", extrinsic.status.to_string());
+            if let Some(block_hash) = &extrinsic.block_hash {
+                line("Block", block_hash.clone());
+            }
+            if let Some(index) = extrinsic.index {
+                line("Index", index.to_string());
+            }
+            if let Some(fee_paid) = extrinsic.fee_paid {
+                line(
+                    "Fee paid",
+                    BalanceVariant::from(fee_paid, Some(token_metadata))?.to_string(),
+                );
+            }
+            if extrinsic.block_hash.is_none() {
+                return Ok(out)
+            }
+        }
+        let _ = writeln!(
+            out,
+            "{:>width$}",
             "Events".bright_purple().bold(),
             width = DEFAULT_KEY_COL_WIDTH
         );
-        for event in &self.0 {
+        for event in &self.events {
             let _ = writeln!(
                 out,
                 "{:>width$} {} ➜ {}",
@@ -201,9 +446,100 @@ impl DisplayEvents {
         Ok(out)
     }
 
-    /// Returns an event result in json format
-    pub fn to_json(&self) -> Result<String> {
-        Ok(serde_json::to_string_pretty(self)?)
+    /// Returns the details of the extrinsic which emitted the events, if any.
+    pub fn extrinsic(&self) -> Option<&ExtrinsicDetails> {
+        self.extrinsic.as_ref()
+    }
+
+    /// Returns an event result in json format, the array of the events or the object
+    /// `{"extrinsic": …, "events": […]}` if `with_extrinsic` is set.
+    pub fn to_json(&self, with_extrinsic: bool) -> Result<String> {
+        if with_extrinsic {
+            let extrinsic = DisplayExtrinsic {
+                extrinsic: self.extrinsic.as_ref(),
+                events: &self.events,
+            };
+            Ok(serde_json::to_string_pretty(&extrinsic)?)
+        } else {
+            Ok(serde_json::to_string_pretty(self)?)
+        }
+    }
+}
+
+/// Decodes the events of an extrinsic using the runtime metadata.
+struct EventDecoder<'a> {
+    subxt_metadata: &'a subxt::Metadata,
+    events_transcoder: Transcoder,
+}
+
+impl<'a> EventDecoder<'a> {
+    fn new(subxt_metadata: &'a subxt::Metadata) -> Self {
+        let events_transcoder =
+            TranscoderBuilder::new(&subxt_metadata.runtime_metadata().types)
+                .with_default_custom_type_transcoders()
+                .done();
+        Self {
+            subxt_metadata,
+            events_transcoder,
+        }
+    }
+
+    /// Decodes the event, the data of a contract event using the
+    /// [`ContractMessageTranscoder`] if available.
+    fn decode(
+        &self,
+        event: &EventDetails,
+        transcoder: Option<&ContractMessageTranscoder>,
+    ) -> Result<Event> {
+        tracing::debug!("displaying event {:?}", event);
+
+        let runtime_metadata = self.subxt_metadata.runtime_metadata();
+        let event_metadata = self
+            .subxt_metadata
+            .event(event.pallet_index(), event.variant_index())?;
+        let event_fields = event_metadata.fields();
+
+        let mut event_entry = Event {
+            pallet: event.pallet_name().to_string(),
+            name: event.variant_name().to_string(),
+            fields: vec![],
+        };
+
+        let event_data = &mut event.field_bytes();
+        let mut unnamed_field_name = 0;
+        for field_metadata in event_fields {
+            if is_contract_emitted(event)
+                && field_metadata.name == Some("data".to_string())
+            {
+                tracing::debug!("event data: {:?}", hex::encode(&event_data));
+                let field =
+                    contract_event_data_field(transcoder, field_metadata, event_data)?;
+                event_entry.fields.push(field);
+            } else {
+                let field_name = field_metadata
+                    .name
+                    .as_ref()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| {
+                        let name = unnamed_field_name.to_string();
+                        unnamed_field_name += 1;
+                        name
+                    });
+
+                let decoded_field = self.events_transcoder.decode(
+                    &runtime_metadata.types,
+                    field_metadata.ty.id,
+                    event_data,
+                )?;
+                let field = Field::new(
+                    field_name,
+                    decoded_field,
+                    field_metadata.type_name.as_ref().map(|s| s.to_string()),
+                );
+                event_entry.fields.push(field);
+            }
+        }
+        Ok(event_entry)
     }
 }
 
@@ -214,6 +550,19 @@ fn contract_event_data_field(
     field_metadata: &scale_info::Field<PortableForm>,
     event_data: &mut &[u8],
 ) -> Result<Field> {
+    Ok(Field::new(
+        String::from("data"),
+        contract_event_data(transcoder, event_data)?,
+        field_metadata.type_name.as_ref().map(|s| s.to_string()),
+    ))
+}
+
+/// Decodes the data of a contract event using the [`ContractMessageTranscoder`] if
+/// available, falls back to the hex encoded data.
+fn contract_event_data(
+    transcoder: Option<&ContractMessageTranscoder>,
+    event_data: &mut &[u8],
+) -> Result<Value> {
     let event_value = if let Some(transcoder) = transcoder {
         match transcoder.decode_contract_event(event_data) {
             Ok(contract_event) => contract_event,
@@ -228,9 +577,54 @@ fn contract_event_data_field(
     } else {
         Value::Hex(Hex::from_str(&hex::encode(event_data))?)
     };
-    Ok(Field::new(
-        String::from("data"),
-        event_value,
-        field_metadata.type_name.as_ref().map(|s| s.to_string()),
-    ))
+    Ok(event_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extrinsic_details_are_only_serialized_if_requested() {
+        // given
+        let events = DisplayEvents {
+            extrinsic: Some(ExtrinsicDetails {
+                hash: "0x01".to_string(),
+                status: ExtrinsicStatus::Finalized,
+                block_hash: Some("0x02".to_string()),
+                index: Some(1),
+                fee_paid: Some(100),
+            }),
+            events: vec![Event {
+                pallet: "Contracts".to_string(),
+                name: "Called".to_string(),
+                fields: Vec::new(),
+            }],
+        };
+
+        // when
+        let events_json: serde_json::Value =
+            serde_json::from_str(&events.to_json(false).unwrap()).unwrap();
+        let extrinsic_json: serde_json::Value =
+            serde_json::from_str(&events.to_json(true).unwrap()).unwrap();
+
+        // then
+        let expected_events = serde_json::json!([
+            { "pallet": "Contracts", "name": "Called", "fields": [] }
+        ]);
+        assert_eq!(events_json, expected_events);
+        assert_eq!(
+            extrinsic_json,
+            serde_json::json!({
+                "extrinsic": {
+                    "hash": "0x01",
+                    "status": "finalized",
+                    "block_hash": "0x02",
+                    "index": 1,
+                    "fee_paid": 100,
+                },
+                "events": expected_events,
+            })
+        );
+    }
 }
@@ -18,26 +18,40 @@ use super::{
     display_contract_exec_result,
     prompt_confirm_tx,
     state_call,
-    submit_extrinsic,
     BalanceVariant,
     Client,
     ContractMessageTranscoder,
     DefaultConfig,
     ExtrinsicOpts,
-    PairSigner,
+    MultisigOpts,
+    OfflineOpts,
+    Origin,
+    ProxyOpts,
+    SandboxOpts,
     StorageDeposit,
+    Submission,
+    SubmittedExtrinsic,
     MAX_KEY_COL_WIDTH,
 };
 use crate::{
     cmd::{
         extrinsics::{
+            contracts_pallet::{
+                CodeStored,
+                ContractsCall,
+                ContractsEvent,
+                Instantiated,
+            },
             display_contract_exec_result_debug,
             display_dry_run_result_warning,
-            events::DisplayEvents,
+            events::{
+                DisplayEvents,
+                ExtrinsicDetails,
+            },
+            sandbox,
             ErrorVariant,
             TokenMetadata,
         },
-        runtime_api::api,
         Balance,
         CodeHash,
     },
@@ -53,13 +67,17 @@ use contract_build::{
     Verbosity,
 };
 
-use pallet_contracts_primitives::ContractInstantiateResult;
+use pallet_contracts_primitives::{
+    ContractInstantiateResult,
+    ContractResult,
+    InstantiateReturnValue,
+};
 
 use scale::Encode;
 use sp_core::Bytes;
 use sp_weights::Weight;
+use std::fmt::Display;
 use subxt::{
-    blocks::ExtrinsicEvents,
     Config,
     OnlineClient,
 };
@@ -74,6 +92,14 @@ pub struct InstantiateCommand {
     args: Vec<String>,
     #[clap(flatten)]
     extrinsic_opts: ExtrinsicOpts,
+    #[clap(flatten)]
+    sandbox_opts: SandboxOpts,
+    #[clap(flatten)]
+    offline_opts: OfflineOpts,
+    #[clap(flatten)]
+    multisig_opts: MultisigOpts,
+    #[clap(flatten)]
+    proxy_opts: ProxyOpts,
     /// Transfers an initial balance to the instantiated contract
     #[clap(name = "value", long, default_value = "0")]
     value: BalanceVariant,
@@ -114,17 +140,22 @@ impl InstantiateCommand {
         let artifacts = self.extrinsic_opts.contract_artifacts()?;
         let transcoder = artifacts.contract_transcoder()?;
         let data = transcoder.encode(&self.constructor, &self.args)?;
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
+        let signer = self.offline_opts.signer(&self.extrinsic_opts)?;
+        let origin = self.proxy_opts.origin(self.multisig_opts.origin(signer)?)?;
         let url = self.extrinsic_opts.url_to_string();
         let verbosity = self.extrinsic_opts.verbosity()?;
         let code = if let Some(code) = artifacts.code {
-            Code::Upload(code.0)
+            Code::Upload(code.code)
         } else {
             let code_hash = artifacts.code_hash()?;
             Code::Existing(code_hash.into())
         };
         let salt = self.salt.clone().map(|s| s.0).unwrap_or_default();
 
+        if self.sandbox_opts.is_enabled() {
+            return self.run_in_sandbox(code, data, salt, &origin, &transcoder)
+        }
+
         async_std::task::block_on(async move {
             let client = OnlineClient::from_url(url.clone()).await?;
 
@@ -153,7 +184,7 @@ impl InstantiateCommand {
                 url,
                 client,
                 verbosity,
-                signer,
+                origin,
                 transcoder,
                 output_json: self.output_json,
             };
@@ -161,6 +192,85 @@ impl InstantiateCommand {
             exec.exec(self.extrinsic_opts.execute).await
         })
     }
+
+    /// Instantiates the contract in the sandbox instead of on a node. Its state is only
+    /// changed if the instantiation is executed with `-x`.
+    fn run_in_sandbox(
+        &self,
+        code: Code,
+        data: Vec<u8>,
+        salt: Vec<u8>,
+        origin: &Origin,
+        transcoder: &ContractMessageTranscoder,
+    ) -> Result<(), ErrorVariant> {
+        let token_metadata = sandbox::token_metadata();
+        let mut sandbox = self.sandbox_opts.load()?;
+        let gas_limit = self.gas_limit.map(|ref_time| {
+            Weight::from_parts(ref_time, self.proof_size.unwrap_or_default())
+        });
+        let storage_deposit_limit = self
+            .extrinsic_opts
+            .storage_deposit_limit
+            .as_ref()
+            .map(|bv| bv.denominate_balance(&token_metadata))
+            .transpose()?;
+        let (code, uploaded) = match code {
+            Code::Upload(code) => (contract_sandbox::Code::Upload(code), true),
+            Code::Existing(code_hash) => {
+                (contract_sandbox::Code::Existing(code_hash.0.into()), false)
+            }
+        };
+        let outcome = sandbox.instantiate(
+            sandbox::account_id(origin.account_id())?,
+            self.value.denominate_balance(&token_metadata)?,
+            gas_limit,
+            storage_deposit_limit,
+            code,
+            data,
+            salt,
+        );
+
+        if !self.extrinsic_opts.execute {
+            display_dry_run_result(&outcome.result, self.output_json, |err| {
+                Ok(ErrorVariant::from(err))
+            })?;
+            if outcome.result.result.is_ok() && !self.output_json {
+                display_dry_run_result_warning("instantiate");
+            }
+            return Ok(())
+        }
+        let contract = match outcome.result.result {
+            Ok(ref ret_val) if !ret_val.result.did_revert() => ret_val.account_id.clone(),
+            Ok(_) => return Err("The contract reverted the instantiation".into()),
+            Err(ref err) => return Err(ErrorVariant::from(err)),
+        };
+        sandbox.advance_block();
+        self.sandbox_opts.save(&sandbox)?;
+
+        let events =
+            DisplayEvents::from_sandbox_events(&outcome.events, Some(transcoder))?;
+        let code_hash = sandbox
+            .code_hash(&contract)
+            .filter(|_| uploaded)
+            .map(|code_hash| format!("{code_hash:?}"));
+        if self.output_json {
+            let display_instantiate_result = InstantiateResult {
+                code_hash,
+                contract: Some(contract.to_string()),
+                extrinsic: None,
+                events,
+            };
+            println!("{}", display_instantiate_result.to_json()?)
+        } else {
+            let verbosity = self.extrinsic_opts.verbosity()?;
+            println!("{}", events.display_events(verbosity, &token_metadata)?);
+            if let Some(code_hash) = code_hash {
+                name_value_println!("Code hash", code_hash);
+            }
+            name_value_println!("Contract", contract.to_string());
+        }
+        Ok(())
+    }
 }
 
 struct InstantiateArgs {
@@ -187,7 +297,7 @@ pub struct Exec {
     verbosity: Verbosity,
     url: String,
     client: Client,
-    signer: PairSigner,
+    origin: Origin,
     transcoder: ContractMessageTranscoder,
     output_json: bool,
 }
@@ -197,37 +307,11 @@ impl Exec {
         tracing::debug!("instantiate data {:?}", self.args.data);
         if !execute {
             let result = self.instantiate_dry_run().await?;
-            match result.result {
-                Ok(ref ret_val) => {
-                    let dry_run_result = InstantiateDryRunResult {
-                        result: String::from("Success!"),
-                        contract: ret_val.account_id.to_string(),
-                        reverted: ret_val.result.did_revert(),
-                        data: ret_val.result.data.clone().into(),
-                        gas_consumed: result.gas_consumed,
-                        gas_required: result.gas_required,
-                        storage_deposit: StorageDeposit::from(&result.storage_deposit),
-                    };
-                    if self.output_json {
-                        println!("{}", dry_run_result.to_json()?);
-                    } else {
-                        dry_run_result.print();
-                        display_contract_exec_result_debug::<_, DEFAULT_KEY_COL_WIDTH>(
-                            &result,
-                        )?;
-                        display_dry_run_result_warning("instantiate");
-                    }
-                }
-                Err(ref err) => {
-                    let metadata = self.client.metadata();
-                    let object = ErrorVariant::from_dispatch_error(err, &metadata)?;
-                    if self.output_json {
-                        return Err(object)
-                    } else {
-                        name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
-                        display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(&result)?;
-                    }
-                }
+            display_dry_run_result(&result, self.output_json, |err| {
+                ErrorVariant::from_dispatch_error(err, &self.client.metadata())
+            })?;
+            if result.result.is_ok() && !self.output_json {
+                display_dry_run_result_warning("instantiate");
             }
         } else {
             let gas_limit = self.pre_submit_dry_run_gas_estimate().await?;
@@ -252,30 +336,47 @@ impl Exec {
             prompt_confirm_tx(|| self.print_default_instantiate_preview(gas_limit))?;
         }
 
-        let call = api::tx().contracts().instantiate_with_code(
+        let call = ContractsCall::instantiate_with_code(
             self.args.value,
-            gas_limit.into(),
+            gas_limit,
             self.args.storage_deposit_limit_compact(),
             code.to_vec(),
             self.args.data.clone(),
             self.args.salt.clone(),
         );
-
-        let result = submit_extrinsic(&self.client, &call, &self.signer).await?;
+        let (submitted, dispatched) = match self
+            .origin
+            .submit(&self.client, &call, self.opts.wait_for())
+            .await?
+        {
+            Submission::Dispatched(submitted) => (submitted, true),
+            // the contract is only instantiated once the approvals of the multisig
+            // account reach the threshold
+            Submission::Approved(submitted) => (submitted, false),
+            Submission::Unsigned => return Ok(()),
+        };
+        let token_metadata = TokenMetadata::query(&self.client).await?;
+        let Some(result) = submitted.events.as_ref().filter(|_| dispatched) else {
+            return self
+                .display_result(&submitted, None, None, &token_metadata)
+                .await
+        };
 
         // The CodeStored event is only raised if the contract has not already been
         // uploaded.
-        let code_hash = result
-            .find_first::<api::contracts::events::CodeStored>()?
-            .map(|code_stored| code_stored.code_hash);
+        let code_hash =
+            CodeStored::find_first(result)?.map(|code_stored| code_stored.code_hash);
 
-        let instantiated = result
-            .find_last::<api::contracts::events::Instantiated>()?
+        let instantiated = Instantiated::find_last(result)?
             .ok_or_else(|| anyhow!("Failed to find Instantiated event"))?;
 
-        let token_metadata = TokenMetadata::query(&self.client).await?;
-        self.display_result(&result, code_hash, instantiated.contract, &token_metadata)
-            .await
+        self.display_result(
+            &submitted,
+            code_hash,
+            Some(instantiated.contract),
+            &token_metadata,
+        )
+        .await
     }
 
     async fn instantiate(
@@ -294,44 +395,63 @@ impl Exec {
             })?;
         }
 
-        let call = api::tx().contracts().instantiate(
+        let call = ContractsCall::instantiate(
             self.args.value,
-            gas_limit.into(),
+            gas_limit,
             self.args.storage_deposit_limit_compact(),
             code_hash,
             self.args.data.clone(),
             self.args.salt.clone(),
         );
-
-        let result = submit_extrinsic(&self.client, &call, &self.signer).await?;
-
-        let instantiated = result
-            .find_first::<api::contracts::events::Instantiated>()?
-            .ok_or_else(|| anyhow!("Failed to find Instantiated event"))?;
-
+        let (submitted, dispatched) = match self
+            .origin
+            .submit(&self.client, &call, self.opts.wait_for())
+            .await?
+        {
+            Submission::Dispatched(submitted) => (submitted, true),
+            // the contract is only instantiated once the approvals of the multisig
+            // account reach the threshold
+            Submission::Approved(submitted) => (submitted, false),
+            Submission::Unsigned => return Ok(()),
+        };
         let token_metadata = TokenMetadata::query(&self.client).await?;
-        self.display_result(&result, None, instantiated.contract, &token_metadata)
+        let contract = submitted
+            .events
+            .as_ref()
+            .filter(|_| dispatched)
+            .map(|result| {
+                Instantiated::find_first(result)?
+                    .ok_or_else(|| anyhow!("Failed to find Instantiated event"))
+            })
+            .transpose()?
+            .map(|instantiated| instantiated.contract);
+
+        self.display_result(&submitted, None, contract, &token_metadata)
             .await
     }
 
     async fn display_result(
         &self,
-        result: &ExtrinsicEvents<DefaultConfig>,
+        submitted: &SubmittedExtrinsic<DefaultConfig>,
         code_hash: Option<CodeHash>,
-        contract_address: subxt::utils::AccountId32,
+        contract_address: Option<<DefaultConfig as Config>::AccountId>,
         token_metadata: &TokenMetadata,
     ) -> Result<(), ErrorVariant> {
         let events = DisplayEvents::from_events(
-            result,
+            submitted,
             Some(&self.transcoder),
             &self.client.metadata(),
         )?;
-        let contract_address = contract_address.to_string();
+        let contract_address = contract_address.map(|address| address.to_string());
 
         if self.output_json {
             let display_instantiate_result = InstantiateResult {
                 code_hash: code_hash.map(|ch| format!("{ch:?}")),
-                contract: Some(contract_address),
+                contract: contract_address,
+                extrinsic: events
+                    .extrinsic()
+                    .filter(|_| self.opts.output_extrinsic())
+                    .cloned(),
                 events,
             };
             println!("{}", display_instantiate_result.to_json()?)
@@ -340,7 +460,9 @@ impl Exec {
             if let Some(code_hash) = code_hash {
                 name_value_println!("Code hash", format!("{code_hash:?}"));
             }
-            name_value_println!("Contract", contract_address);
+            if let Some(contract_address) = contract_address {
+                name_value_println!("Contract", contract_address);
+            }
         };
         Ok(())
     }
@@ -357,7 +479,7 @@ impl Exec {
     {
         let storage_deposit_limit = self.args.storage_deposit_limit;
         let call_request = InstantiateRequest {
-            origin: self.signer.account_id().clone(),
+            origin: self.origin.account_id().clone(),
             value: self.args.value,
             gas_limit: None,
             storage_deposit_limit,
@@ -365,7 +487,7 @@ impl Exec {
             data: self.args.data.clone(),
             salt: self.args.salt.clone(),
         };
-        state_call(&self.url, "ContractsApi_instantiate", &call_request).await
+        instantiate_dry_run(&self.url, &call_request).await
     }
 
     /// Dry run the instantiation before tx submission. Returns the gas required estimate.
@@ -384,37 +506,52 @@ impl Exec {
             super::print_dry_running_status(&self.args.constructor);
         }
         let instantiate_result = self.instantiate_dry_run().await?;
-        match instantiate_result.result {
-            Ok(_) => {
-                if !self.output_json {
-                    super::print_gas_required_success(instantiate_result.gas_required);
-                }
-                // use user specified values where provided, otherwise use the estimates
-                let ref_time = self
-                    .args
-                    .gas_limit
-                    .unwrap_or_else(|| instantiate_result.gas_required.ref_time());
-                let proof_size = self
-                    .args
-                    .proof_size
-                    .unwrap_or_else(|| instantiate_result.gas_required.proof_size());
-                Ok(Weight::from_parts(ref_time, proof_size))
+        super::dry_run_gas_limit(
+            &instantiate_result,
+            self.args.gas_limit,
+            self.args.proof_size,
+            self.output_json,
+            &self.client.metadata(),
+        )
+    }
+}
+
+/// Dry runs the instantiation of `request`.
+pub async fn instantiate_dry_run(
+    url: &str,
+    request: &InstantiateRequest,
+) -> Result<ContractInstantiateResult<<DefaultConfig as Config>::AccountId, Balance>> {
+    state_call(url, "ContractsApi_instantiate", request, None).await
+}
+
+/// Displays the result of an instantiation dry run, the error of a failed instantiation
+/// is converted by `error_variant`.
+pub fn display_dry_run_result<AccountId: Display, E>(
+    result: &ContractResult<Result<InstantiateReturnValue<AccountId>, E>, Balance>,
+    output_json: bool,
+    error_variant: impl FnOnce(&E) -> Result<ErrorVariant>,
+) -> Result<(), ErrorVariant> {
+    match result.result {
+        Ok(ref ret_val) => {
+            let dry_run_result = InstantiateDryRunResult::new(ret_val, result);
+            if output_json {
+                println!("{}", dry_run_result.to_json()?);
+            } else {
+                dry_run_result.print();
+                display_contract_exec_result_debug::<_, DEFAULT_KEY_COL_WIDTH>(result)?;
             }
-            Err(ref err) => {
-                let object =
-                    ErrorVariant::from_dispatch_error(err, &self.client.metadata())?;
-                if self.output_json {
-                    Err(anyhow!("{}", serde_json::to_string_pretty(&object)?))
-                } else {
-                    name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
-                    display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(
-                        &instantiate_result,
-                    )?;
-                    Err(anyhow!("Pre-submission dry-run failed. Use --skip-dry-run to skip this step."))
-                }
+        }
+        Err(ref err) => {
+            let object = error_variant(err)?;
+            if output_json {
+                return Err(object)
+            } else {
+                name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
+                display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(result)?;
             }
         }
     }
+    Ok(())
 }
 
 /// Result of a successful contract instantiation for displaying.
@@ -426,6 +563,9 @@ pub struct InstantiateResult {
     /// Instantiated code hash
     #[serde(skip_serializing_if = "Option::is_none")]
     code_hash: Option<String>,
+    /// The details of the instantiate extrinsic, if requested with `--output-extrinsic`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extrinsic: Option<ExtrinsicDetails>,
     /// The events emitted from the instantiate extrinsic invocation.
     events: DisplayEvents,
 }
@@ -453,6 +593,22 @@ pub struct InstantiateDryRunResult {
 }
 
 impl InstantiateDryRunResult {
+    /// Returns the result of a dry run instantiating the contract of `ret_val`.
+    pub fn new<AccountId: Display, R>(
+        ret_val: &InstantiateReturnValue<AccountId>,
+        result: &ContractResult<R, Balance>,
+    ) -> Self {
+        Self {
+            result: String::from("Success!"),
+            contract: ret_val.account_id.to_string(),
+            reverted: ret_val.result.did_revert(),
+            data: ret_val.result.data.clone().into(),
+            gas_consumed: result.gas_consumed,
+            gas_required: result.gas_required,
+            storage_deposit: StorageDeposit::from(&result.storage_deposit),
+        }
+    }
+
     /// Returns a result in json format
     pub fn to_json(&self) -> Result<String> {
         Ok(serde_json::to_string_pretty(self)?)
@@ -477,19 +633,19 @@ impl InstantiateDryRunResult {
 
 /// A struct that encodes RPC parameters required to instantiate a new smart contract.
 #[derive(Encode)]
-struct InstantiateRequest {
-    origin: <DefaultConfig as Config>::AccountId,
-    value: Balance,
-    gas_limit: Option<Weight>,
-    storage_deposit_limit: Option<Balance>,
-    code: Code,
-    data: Vec<u8>,
-    salt: Vec<u8>,
+pub struct InstantiateRequest {
+    pub origin: <DefaultConfig as Config>::AccountId,
+    pub value: Balance,
+    pub gas_limit: Option<Weight>,
+    pub storage_deposit_limit: Option<Balance>,
+    pub code: Code,
+    pub data: Vec<u8>,
+    pub salt: Vec<u8>,
 }
 
 /// Reference to an existing code hash or a new Wasm module.
 #[derive(Clone, Encode)]
-enum Code {
+pub enum Code {
     /// A Wasm module as raw bytes.
     Upload(Vec<u8>),
     /// The code hash of an on-chain Wasm blob.
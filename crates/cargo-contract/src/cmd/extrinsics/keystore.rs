@@ -0,0 +1,500 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    super::chain::{
+        AccountFormat,
+        AccountId,
+    },
+    signer::{
+        CryptoScheme,
+        Keypair,
+    },
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use base64::{
+    engine::general_purpose::STANDARD as BASE64,
+    Engine as _,
+};
+use crypto_secretbox::{
+    aead::{
+        rand_core::RngCore as _,
+        Aead as _,
+        AeadCore as _,
+        KeyInit as _,
+        OsRng,
+    },
+    Key,
+    Nonce,
+    XSalsa20Poly1305,
+};
+use sp_core::{
+    crypto::Pair as _,
+    ecdsa,
+    ed25519,
+    sr25519,
+};
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+/// The PKCS#8 header preceding the secret key in the encrypted content.
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+/// The PKCS#8 divider between the secret key and the public key.
+const PKCS8_DIVIDER: [u8; 5] = [161, 35, 3, 33, 0];
+
+/// The scrypt parameters of polkadot-js: `N = 2^15`, `r = 8` and `p = 1`.
+const SCRYPT_LOG_N: u8 = 15;
+const SCRYPT_R: u32 = 8;
+const SCRYPT_P: u32 = 1;
+const SCRYPT_SALT_LENGTH: usize = 32;
+/// The length of the salt and the scrypt parameters `N`, `p` and `r` prefixing the
+/// encoded content.
+const SCRYPT_LENGTH: usize = SCRYPT_SALT_LENGTH + 3 * 4;
+const NONCE_LENGTH: usize = 24;
+
+/// An account encrypted in the JSON format of polkadot-js, as exported by its apps and
+/// extension.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct KeystoreJson {
+    /// The SS58 address of the account.
+    pub address: String,
+    /// The base64 encoded scrypt parameters, the nonce and the encrypted PKCS#8 key.
+    encoded: String,
+    encoding: Encoding,
+    #[serde(default)]
+    meta: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+struct Encoding {
+    /// `pkcs8` followed by the signature scheme of the key.
+    content: Vec<String>,
+    /// The key derivation and the encryption of the content.
+    #[serde(rename = "type")]
+    kind: Vec<String>,
+    version: String,
+}
+
+impl KeystoreJson {
+    /// Encrypts the key pair with the password.
+    pub fn encrypt(keypair: &Keypair, name: &str, password: &str) -> Result<Self> {
+        let (secret_key, public_key) = match keypair {
+            Keypair::Sr25519(pair) => {
+                // polkadot-js stores the secret key in its ed25519 compatible form
+                let secret_key = schnorrkel::SecretKey::from_bytes(&pair.to_raw_vec())
+                    .map_err(|err| anyhow!("Invalid sr25519 secret key: {err}"))?;
+                (
+                    secret_key.to_ed25519_bytes().to_vec(),
+                    pair.public().0.to_vec(),
+                )
+            }
+            Keypair::Ed25519(pair) => {
+                let public_key = pair.public().0;
+                (
+                    [pair.seed().as_slice(), &public_key].concat(),
+                    public_key.to_vec(),
+                )
+            }
+            Keypair::Ecdsa(pair) => (pair.seed().to_vec(), pair.public().0.to_vec()),
+        };
+        let content =
+            [&PKCS8_HEADER[..], &secret_key, &PKCS8_DIVIDER, &public_key].concat();
+
+        let mut salt = [0u8; SCRYPT_SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+        let cipher = cipher(password, &salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+        let nonce = XSalsa20Poly1305::generate_nonce(&mut OsRng);
+        let encrypted = cipher
+            .encrypt(&nonce, content.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt the secret key"))?;
+        let encoded = [
+            &salt[..],
+            &(1u32 << SCRYPT_LOG_N).to_le_bytes(),
+            &SCRYPT_P.to_le_bytes(),
+            &SCRYPT_R.to_le_bytes(),
+            &nonce,
+            &encrypted,
+        ]
+        .concat();
+
+        let when_created = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let mut meta = serde_json::Map::new();
+        meta.insert("name".into(), name.into());
+        meta.insert("whenCreated".into(), (when_created as u64).into());
+        Ok(Self {
+            address: keypair.account_id()?.to_string(),
+            encoded: BASE64.encode(encoded),
+            encoding: Encoding {
+                content: vec!["pkcs8".into(), keypair.scheme().to_string()],
+                kind: vec!["scrypt".into(), "xsalsa20-poly1305".into()],
+                version: "3".into(),
+            },
+            meta,
+        })
+    }
+
+    /// Decrypts the key pair with the password.
+    pub fn decrypt(&self, password: &str) -> Result<Keypair> {
+        let scheme = self.scheme()?;
+        if self.encoding.kind != ["scrypt", "xsalsa20-poly1305"] {
+            anyhow::bail!(
+                "Unsupported encryption `{}` of the account {}, expected `scrypt` and \
+                `xsalsa20-poly1305`",
+                self.encoding.kind.join(", "),
+                self.address
+            )
+        }
+        let encoded = BASE64
+            .decode(&self.encoded)
+            .context("Invalid base64 encoding of the account")?;
+        if encoded.len() < SCRYPT_LENGTH + NONCE_LENGTH {
+            anyhow::bail!("The encoded account {} is too short", self.address)
+        }
+        let (params, encrypted) = encoded.split_at(SCRYPT_LENGTH);
+        let (salt, params) = params.split_at(SCRYPT_SALT_LENGTH);
+        let param = |index: usize| {
+            let bytes = params[index * 4..(index + 1) * 4]
+                .try_into()
+                .expect("the parameters are 4 bytes each; qed");
+            u32::from_le_bytes(bytes)
+        };
+        // other parameters are rejected before deriving the key, so that a crafted
+        // account can't make the key derivation exhaust the memory or the CPU
+        let (n, p, r) = (param(0), param(1), param(2));
+        if (n, p, r) != (1 << SCRYPT_LOG_N, SCRYPT_P, SCRYPT_R) {
+            anyhow::bail!(
+                "Unsupported scrypt parameters N = {n}, p = {p}, r = {r} of the account \
+                {}, expected N = {}, p = {SCRYPT_P} and r = {SCRYPT_R}",
+                self.address,
+                1u32 << SCRYPT_LOG_N
+            )
+        }
+        let cipher = cipher(password, salt, SCRYPT_LOG_N, SCRYPT_R, SCRYPT_P)?;
+        let (nonce, encrypted) = encrypted.split_at(NONCE_LENGTH);
+        let nonce: [u8; NONCE_LENGTH] =
+            nonce.try_into().expect("the nonce is 24 bytes; qed");
+        let content = cipher
+            .decrypt(&Nonce::from(nonce), encrypted)
+            .map_err(|_| anyhow!("Invalid password for the account {}", self.address))?;
+
+        let secret_key = content
+            .strip_prefix(&PKCS8_HEADER[..])
+            .ok_or_else(|| anyhow!("Invalid PKCS#8 header of the account"))?;
+        // the secret key is either 64 bytes long, or 32 bytes for seeds
+        let secret_key_length = [64, 32]
+            .into_iter()
+            .find(|&length| {
+                secret_key.get(length..length + PKCS8_DIVIDER.len())
+                    == Some(&PKCS8_DIVIDER[..])
+            })
+            .ok_or_else(|| anyhow!("Invalid PKCS#8 content of the account"))?;
+        let secret_key = &secret_key[..secret_key_length];
+        let invalid = |_| anyhow!("Invalid {scheme} secret key of the account");
+        let keypair = match scheme {
+            CryptoScheme::Sr25519 => {
+                let secret_key = schnorrkel::SecretKey::from_ed25519_bytes(secret_key)
+                    .map_err(|err| anyhow!("Invalid sr25519 secret key: {err}"))?;
+                Keypair::Sr25519(
+                    sr25519::Pair::from_seed_slice(&secret_key.to_bytes())
+                        .map_err(invalid)?,
+                )
+            }
+            CryptoScheme::Ed25519 => {
+                let seed = &secret_key[..32];
+                Keypair::Ed25519(ed25519::Pair::from_seed_slice(seed).map_err(invalid)?)
+            }
+            CryptoScheme::Ecdsa => {
+                Keypair::Ecdsa(ecdsa::Pair::from_seed_slice(secret_key).map_err(invalid)?)
+            }
+        };
+
+        let address =
+            <super::DefaultConfig as subxt::Config>::AccountId::from_str(&self.address)
+                .map_err(|err| anyhow!("Invalid address {}: {err}", self.address))?;
+        let format = match address {
+            AccountId::Id32(_) => AccountFormat::Ss58,
+            AccountId::Id20(_) => AccountFormat::H160,
+        };
+        let account_id = keypair.account_id_as(format)?;
+        if account_id != address {
+            anyhow::bail!(
+                "The decrypted key of {} belongs to another account {account_id}",
+                self.address
+            )
+        }
+        Ok(keypair)
+    }
+
+    /// Returns the signature scheme of the key.
+    pub fn scheme(&self) -> Result<CryptoScheme> {
+        match self.encoding.content.as_slice() {
+            [pkcs8, scheme] if pkcs8 == "pkcs8" => scheme.parse(),
+            content => {
+                Err(anyhow!(
+                    "Unsupported content `{}` of the account {}",
+                    content.join(", "),
+                    self.address
+                ))
+            }
+        }
+    }
+
+    fn set_name(&mut self, name: &str) {
+        self.meta.insert("name".into(), name.into());
+    }
+}
+
+/// Returns the cipher with the key derived from the password by scrypt.
+fn cipher(
+    password: &str,
+    salt: &[u8],
+    log_n: u8,
+    r: u32,
+    p: u32,
+) -> Result<XSalsa20Poly1305> {
+    let params = scrypt::Params::new(log_n, r, p, 32)
+        .map_err(|err| anyhow!("Invalid scrypt parameters: {err}"))?;
+    let mut key = Key::default();
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key)
+        .map_err(|err| anyhow!("Failed to derive the key: {err}"))?;
+    Ok(XSalsa20Poly1305::new(&key))
+}
+
+/// A directory of accounts, each stored in the JSON format of polkadot-js in a file
+/// named after the account.
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Keystore {
+    /// Opens the keystore in `dir`, by default `cargo-contract/keystore` in the data
+    /// directory of the user.
+    pub fn open(dir: Option<&Path>) -> Result<Self> {
+        let dir = match dir {
+            Some(dir) => dir.to_path_buf(),
+            None => {
+                dirs::data_dir()
+                    .ok_or_else(|| {
+                        anyhow!("Failed to find the data directory of the user")
+                    })?
+                    .join("cargo-contract")
+                    .join("keystore")
+            }
+        };
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the accounts of the keystore, sorted by name.
+    pub fn list(&self) -> Result<Vec<(String, KeystoreJson)>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new())
+        }
+        let mut accounts = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue
+            }
+            let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+                continue
+            };
+            accounts.push((name.to_string(), self.load(name)?));
+        }
+        accounts.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(accounts)
+    }
+
+    /// Loads the account `name`.
+    pub fn load(&self, name: &str) -> Result<KeystoreJson> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            anyhow::bail!(
+                "No account `{name}` in the keystore {}, add it with `cargo contract \
+                account add {name}`",
+                self.dir.display()
+            )
+        }
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read account {}", path.display()))?;
+        serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse account {}", path.display()))
+    }
+
+    /// Adds the account `name`, failing if an account of the same name exists.
+    pub fn add(&self, name: &str, mut json: KeystoreJson) -> Result<()> {
+        let path = self.path(name)?;
+        if path.exists() {
+            anyhow::bail!("The account `{name}` exists already")
+        }
+        json.set_name(name);
+        std::fs::create_dir_all(&self.dir)?;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options
+            .open(&path)
+            .with_context(|| format!("Failed to create account {}", path.display()))?;
+        serde_json::to_writer_pretty(file, &json)?;
+        Ok(())
+    }
+
+    /// Removes the account `name`.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let path = self.path(name)?;
+        std::fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove account {}", path.display()))
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            anyhow::bail!(
+                "Invalid account name `{name}`, expected ASCII letters, digits, `_` or `-`"
+            )
+        }
+        Ok(self.dir.join(format!("{name}.json")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An ed25519 account in the export format of polkadot-js, encrypted with the
+    /// password `cargo-contract`.
+    const POLKADOT_JS_ACCOUNT: &str = r#"{
+        "encoded": "Y0ea1poJCyWCd+yPum+ZQZov+ySJgVEGV8lEzNEUjpcAgAAAAQAAAAgAAAB4N3tSV1e0lEJ/iQFPl9eZKPOTjRTrUeLJ58J6ASFIZ4DqAXEk6Nobg7fsd0IZMriEm9QP+AptUEga/1AOS1drdHrhUzhTBFKBjZWf82yTO1ifqqjzb50ca50Q9mqqAPjQUEJII4LeeEQ8onb+rdWvc92f8hI7ocz2t+b0vhScpdymQzSk2mtFXEWjHsR1bDwIAZ5e6DqI3X7FiOFk",
+        "encoding": {
+            "content": ["pkcs8", "ed25519"],
+            "type": ["scrypt", "xsalsa20-poly1305"],
+            "version": "3"
+        },
+        "address": "5FtTmpm96dX6cEJM7GAFNY1WbxAs7QS4acPHNFBi7AbjL6ke",
+        "meta": {
+            "genesisHash": "",
+            "name": "fixture",
+            "whenCreated": 1697500800000
+        }
+    }"#;
+
+    #[test]
+    fn accounts_are_encrypted_and_decrypted() {
+        for scheme in [
+            CryptoScheme::Sr25519,
+            CryptoScheme::Ed25519,
+            CryptoScheme::Ecdsa,
+        ] {
+            // given
+            let keypair = Keypair::from_suri(scheme, "//Alice", None).unwrap();
+
+            // when
+            let json = KeystoreJson::encrypt(&keypair, "alice", "secret").unwrap();
+
+            // then
+            assert_eq!(json.scheme().unwrap(), scheme);
+            assert_eq!(json.meta["name"], "alice");
+            let decrypted = json.decrypt("secret").unwrap();
+            assert_eq!(
+                decrypted.account_id().unwrap(),
+                keypair.account_id().unwrap()
+            );
+            assert!(json.decrypt("wrong").is_err());
+        }
+    }
+
+    #[test]
+    fn polkadot_js_accounts_are_decrypted() {
+        // given
+        let json: KeystoreJson = serde_json::from_str(POLKADOT_JS_ACCOUNT).unwrap();
+
+        // when
+        let keypair = json.decrypt("cargo-contract").unwrap();
+
+        // then
+        assert_eq!(keypair.scheme(), CryptoScheme::Ed25519);
+        assert_eq!(
+            keypair.account_id().unwrap().to_string(),
+            "5FtTmpm96dX6cEJM7GAFNY1WbxAs7QS4acPHNFBi7AbjL6ke"
+        );
+    }
+
+    #[test]
+    fn unsupported_scrypt_parameters_are_rejected() {
+        // given
+        let mut json: KeystoreJson = serde_json::from_str(POLKADOT_JS_ACCOUNT).unwrap();
+        let mut encoded = BASE64.decode(&json.encoded).unwrap();
+        // N = 2^30
+        encoded[SCRYPT_SALT_LENGTH..SCRYPT_SALT_LENGTH + 4]
+            .copy_from_slice(&(1u32 << 30).to_le_bytes());
+        json.encoded = BASE64.encode(encoded);
+
+        // when
+        let Err(err) = json.decrypt("cargo-contract") else {
+            panic!("the scrypt parameters must be rejected")
+        };
+
+        // then
+        assert_eq!(
+            err.to_string(),
+            "Unsupported scrypt parameters N = 1073741824, p = 1, r = 8 of the account \
+            5FtTmpm96dX6cEJM7GAFNY1WbxAs7QS4acPHNFBi7AbjL6ke, expected N = 32768, p = 1 \
+            and r = 8"
+        );
+    }
+
+    #[test]
+    fn keystore_adds_lists_and_removes_accounts() {
+        // given
+        let dir = tempfile::tempdir().unwrap();
+        let keystore = Keystore::open(Some(dir.path())).unwrap();
+        let keypair = Keypair::from_suri(CryptoScheme::Sr25519, "//Bob", None).unwrap();
+        let json = KeystoreJson::encrypt(&keypair, "", "").unwrap();
+
+        // when
+        keystore.add("bob", json.clone()).unwrap();
+
+        // then
+        let accounts = keystore.list().unwrap();
+        assert_eq!(accounts.len(), 1);
+        assert_eq!(accounts[0].0, "bob");
+        assert_eq!(accounts[0].1.address, json.address);
+        assert!(keystore.add("bob", json).is_err());
+        assert!(keystore.load("../bob").is_err());
+        keystore.remove("bob").unwrap();
+        assert!(keystore.list().unwrap().is_empty());
+    }
+}
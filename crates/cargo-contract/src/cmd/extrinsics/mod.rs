@@ -15,11 +15,24 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 mod balance;
+mod batch;
 mod call;
+mod contracts_pallet;
+mod deploy;
 mod error;
 mod events;
 mod instantiate;
+mod keystore;
+mod multisig;
+mod offline;
+mod origin;
+mod proxy;
 mod remove;
+mod sandbox;
+mod sign;
+mod signer;
+mod step;
+mod submit;
 mod upload;
 
 #[cfg(test)]
@@ -49,6 +62,7 @@ use std::{
 use crate::{
     cmd::{
         Balance,
+        BlockHash,
         Client,
     },
     DEFAULT_KEY_COL_WIDTH,
@@ -57,6 +71,7 @@ use crate::{
 use contract_build::{
     name_value_println,
     CrateMetadata,
+    Target,
     Verbosity,
     VerbosityFlags,
 };
@@ -65,16 +80,15 @@ use scale::{
     Decode,
     Encode,
 };
-use sp_core::{
-    crypto::Pair,
-    sr25519,
-    Bytes,
-};
+use sp_core::Bytes;
 use sp_weights::Weight;
 use subxt::{
     blocks,
     config,
-    tx,
+    tx::{
+        self,
+        TxStatus,
+    },
     Config,
     OnlineClient,
 };
@@ -84,26 +98,56 @@ use std::{
     path::Path,
 };
 
+use super::chain::profile;
+pub use super::chain::ChainConfig as DefaultConfig;
 pub use balance::{
     BalanceVariant,
     TokenMetadata,
 };
+pub use batch::BatchCommand;
 pub use call::CallCommand;
-use contract_metadata::ContractMetadata;
+use contract_metadata::{
+    ContractMetadata,
+    SourceTarget,
+};
 pub use contract_transcode::ContractMessageTranscoder;
-pub use error::ErrorVariant;
+pub use contracts_pallet::ContractInfo;
+pub use deploy::DeployCommand;
+pub use error::{
+    ErrorVariant,
+    TransactionError,
+};
 pub use instantiate::InstantiateCommand;
+pub use keystore::{
+    Keystore,
+    KeystoreJson,
+};
+pub use multisig::MultisigOpts;
+pub use offline::OfflineOpts;
+pub use origin::{
+    Origin,
+    Signer,
+    Submission,
+};
+pub use proxy::ProxyOpts;
 pub use remove::RemoveCommand;
-pub use subxt::PolkadotConfig as DefaultConfig;
+pub use sandbox::SandboxOpts;
+pub use sign::SignCommand;
+pub use signer::{
+    prompt_secret,
+    CryptoScheme,
+    Keypair,
+    PairSigner,
+    SignerOpts,
+};
+pub use submit::SubmitCommand;
 pub use upload::UploadCommand;
 
-type PairSigner = tx::PairSigner<DefaultConfig, sr25519::Pair>;
-
 /// Arguments required for creating and sending an extrinsic to a substrate node.
 #[derive(Clone, Debug, clap::Args)]
 pub struct ExtrinsicOpts {
-    /// Path to a contract build artifact file: a raw `.wasm` file, a `.contract` bundle,
-    /// or a `.json` metadata file.
+    /// Path to a contract build artifact file: a raw `.wasm` or `.riscv` file, a
+    /// `.contract` bundle, or a `.json` metadata file.
     #[clap(value_parser, conflicts_with = "manifest_path")]
     file: Option<PathBuf>,
     /// Path to the `Cargo.toml` of the contract.
@@ -117,12 +161,8 @@ pub struct ExtrinsicOpts {
         default_value = "ws://localhost:9944"
     )]
     url: url::Url,
-    /// Secret key URI for the account deploying the contract.
-    #[clap(name = "suri", long, short)]
-    suri: String,
-    /// Password for the secret key.
-    #[clap(name = "password", long, short)]
-    password: Option<String>,
+    #[clap(flatten)]
+    signer: SignerOpts,
     #[clap(flatten)]
     verbosity: VerbosityFlags,
     /// Submit the extrinsic for on-chain execution.
@@ -138,6 +178,31 @@ pub struct ExtrinsicOpts {
     /// Before submitting a transaction, do not ask the user for confirmation.
     #[clap(long)]
     skip_confirm: bool,
+    /// The target the chain executes contracts for, contract code built for another
+    /// target is refused.
+    #[clap(long, default_value = "wasm")]
+    target: Target,
+    /// The stage the submitted extrinsic has to reach before the result is reported.
+    #[clap(long, value_enum, default_value = "in-block")]
+    wait: WaitFor,
+    /// Report the hash, block, index and fee of the extrinsic in the JSON output, which
+    /// is then an object holding the `extrinsic` and its `events` instead of the array
+    /// of the events.
+    #[clap(long, requires = "output_json")]
+    output_extrinsic: bool,
+}
+
+/// The stage of its lifecycle a submitted extrinsic has to reach before it is reported.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum WaitFor {
+    /// Wait until the extrinsic is included in a block.
+    #[default]
+    InBlock,
+    /// Wait until the block including the extrinsic is finalized.
+    Finalized,
+    /// Do not wait, report as soon as the extrinsic is accepted into the transaction
+    /// pool.
+    None,
 }
 
 impl ExtrinsicOpts {
@@ -146,13 +211,14 @@ impl ExtrinsicOpts {
         ContractArtifacts::from_manifest_or_file(
             self.manifest_path.as_ref(),
             self.file.as_ref(),
+            Some(self.target),
         )
     }
 
-    /// Returns the signer for contract extrinsics.
-    pub fn signer(&self) -> Result<sr25519::Pair> {
-        Pair::from_string(&self.suri, self.password.as_ref().map(String::as_ref))
-            .map_err(|_| anyhow::anyhow!("Secret string error"))
+    /// Returns the signer for contract extrinsics: the key of the secret key URI, or
+    /// the account of the keystore.
+    pub fn signer(&self) -> Result<PairSigner> {
+        self.signer.signer()
     }
 
     /// Returns the verbosity
@@ -160,6 +226,16 @@ impl ExtrinsicOpts {
         TryFrom::try_from(&self.verbosity)
     }
 
+    /// Returns whether the details of the extrinsic are part of the JSON output.
+    pub fn output_extrinsic(&self) -> bool {
+        self.output_extrinsic
+    }
+
+    /// Returns the stage a submitted extrinsic has to reach before it is reported.
+    pub fn wait_for(&self) -> WaitFor {
+        self.wait
+    }
+
     /// Convert URL to String without omitting the default port
     pub fn url_to_string(&self) -> String {
         let mut res = self.url.to_string();
@@ -195,21 +271,24 @@ pub struct ContractArtifacts {
     metadata_path: PathBuf,
     /// The deserialized contract metadata if the expected metadata file exists.
     metadata: Option<ContractMetadata>,
-    /// The Wasm code of the contract if available.
-    pub code: Option<WasmCode>,
+    /// The code of the contract if available.
+    pub code: Option<ContractCode>,
 }
 
 impl ContractArtifacts {
     /// Load contract artifacts.
+    ///
+    /// If a `target` is given, contract code built for another target is refused.
     pub fn from_manifest_or_file(
         manifest_path: Option<&PathBuf>,
         file: Option<&PathBuf>,
+        target: Option<Target>,
     ) -> Result<ContractArtifacts> {
         let artifact_path = match (manifest_path, file) {
             (manifest_path, None) => {
                 let crate_metadata = CrateMetadata::from_manifest_path(
                     manifest_path,
-                    contract_build::Target::Wasm,
+                    target.unwrap_or_default(),
                 )?;
 
                 if crate_metadata.contract_bundle_path().exists() {
@@ -228,25 +307,37 @@ impl ContractArtifacts {
                 anyhow::bail!("conflicting options: --manifest-path and --file")
             }
         };
-        Self::from_artifact_path(artifact_path.as_path())
+        Self::from_artifact_path(artifact_path.as_path(), target)
     }
     /// Given a contract artifact path, load the contract code and metadata where
     /// possible.
-    fn from_artifact_path(path: &Path) -> Result<Self> {
+    ///
+    /// Fails if a `target` is given and the contract code is built for another target.
+    fn from_artifact_path(path: &Path, target: Option<Target>) -> Result<Self> {
         tracing::debug!("Loading contracts artifacts from `{}`", path.display());
         let (metadata_path, metadata, code) =
             match path.extension().and_then(|ext| ext.to_str()) {
                 Some("contract") | Some("json") => {
                     let metadata = ContractMetadata::load(path)?;
-                    let code = metadata.clone().source.wasm.map(|wasm| WasmCode(wasm.0));
+                    let code = metadata.clone().source.code.map(|code| {
+                        ContractCode {
+                            target: code.target,
+                            code: code.code,
+                        }
+                    });
                     (PathBuf::from(path), Some(metadata), code)
                 }
-                Some("wasm") => {
+                Some(ext @ ("wasm" | "riscv")) => {
                     let file_name = path.file_stem()
-                        .context("WASM bundle file has unreadable name")?
+                        .context("Contract code file has unreadable name")?
                         .to_str()
                         .context("Error parsing filename string")?;
-                    let code = Some(WasmCode(std::fs::read(path)?));
+                    let code = std::fs::read(path)?;
+                    let target = SourceTarget::from_magic(&code).unwrap_or(match ext {
+                        "wasm" => SourceTarget::Wasm,
+                        _ => SourceTarget::RiscV,
+                    });
+                    let code = Some(ContractCode { target, code });
                     let dir = path.parent().map_or_else(PathBuf::new, PathBuf::from);
                     let metadata_path = dir.join(format!("{file_name}.json"));
                     if !metadata_path.exists() {
@@ -257,14 +348,25 @@ impl ContractArtifacts {
                     }
                 }
                 Some(ext) => anyhow::bail!(
-                    "Invalid artifact extension {ext}, expected `.contract`, `.json` or `.wasm`"
+                    "Invalid artifact extension {ext}, expected `.contract`, `.json`, `.wasm` or `.riscv`"
                 ),
                 None => {
                     anyhow::bail!(
-                        "Artifact path has no extension, expected `.contract`, `.json`, or `.wasm`"
+                        "Artifact path has no extension, expected `.contract`, `.json`, `.wasm` or `.riscv`"
                     )
                 }
             };
+        if let (Some(expected), Some(code)) = (target.map(SourceTarget::from), &code) {
+            let found = code.target;
+            if found != expected {
+                anyhow::bail!(
+                    "The contract code of {} is built for `{found}`, but the chain expects \
+                    `{expected}` code. Rebuild the contract with `--target {expected}`, or \
+                    pass `--target {found}` if the chain executes `{found}` contracts.",
+                    path.display(),
+                )
+            }
+        }
         Ok(Self {
             artifacts_path: path.into(),
             metadata_path,
@@ -298,30 +400,37 @@ impl ContractArtifacts {
         Ok(metadata.source.hash.0)
     }
 
-    /// Construct a [`ContractMessageTranscoder`] from contract metadata.
+    /// Construct a [`ContractMessageTranscoder`] from contract metadata, for the
+    /// accounts of the chain.
     pub fn contract_transcoder(&self) -> Result<ContractMessageTranscoder> {
         let metadata = self.metadata()?;
-        ContractMessageTranscoder::try_from(metadata)
-            .context("Failed to deserialize ink project metadata from contract metadata")
+        let ink_project = serde_json::from_value(serde_json::Value::Object(metadata.abi))
+            .context(
+                "Failed to deserialize ink project metadata from contract metadata",
+            )?;
+        Ok(ContractMessageTranscoder::with_account_id_format(
+            ink_project,
+            profile().account_id.transcoder_format(),
+        ))
     }
 }
 
-/// The Wasm code of a contract.
+/// The code of a contract and the target it is built for.
 #[derive(Debug)]
-pub struct WasmCode(Vec<u8>);
+pub struct ContractCode {
+    /// The target the code is built for.
+    pub target: SourceTarget,
+    /// The raw bytes of the code.
+    pub code: Vec<u8>,
+}
 
-impl WasmCode {
+impl ContractCode {
     /// The hash of the contract code: uniquely identifies the contract code on-chain.
     pub fn code_hash(&self) -> [u8; 32] {
-        contract_build::code_hash(&self.0)
+        contract_build::code_hash(&self.code)
     }
 }
 
-/// Create a new [`PairSigner`] from the given [`sr25519::Pair`].
-pub fn pair_signer(pair: sr25519::Pair) -> PairSigner {
-    PairSigner::new(pair)
-}
-
 const STORAGE_DEPOSIT_KEY: &str = "Storage Deposit";
 pub const MAX_KEY_COL_WIDTH: usize = STORAGE_DEPOSIT_KEY.len() + 1;
 
@@ -375,23 +484,33 @@ pub fn display_dry_run_result_warning(command: &str) {
         );
 }
 
-/// Wait for the transaction to be included successfully into a block.
-///
-/// # Errors
+/// An extrinsic submitted with [`submit_extrinsic`].
+pub struct SubmittedExtrinsic<T: Config> {
+    /// The hash of the extrinsic.
+    pub hash: T::Hash,
+    /// The events of the successfully executed extrinsic, `None` if its inclusion was
+    /// not waited for.
+    pub events: Option<blocks::ExtrinsicEvents<T>>,
+    /// Whether the block including the extrinsic is finalized.
+    pub finalized: bool,
+}
+
+/// Submit the extrinsic and wait for it to reach the stage `wait_for` of its lifecycle.
 ///
-/// If a runtime Module error occurs, this will only display the pallet and error indices.
-/// Dynamic lookups of the actual error will be available once the following issue is
-/// resolved: <https://github.com/paritytech/subxt/issues/443>.
+/// Once included, the extrinsic is checked to have been executed successfully.
 ///
-/// # Finality
+/// # Errors
 ///
-/// Currently this will report success once the transaction is included in a block. In the
-/// future there could be a flag to wait for finality before reporting success.
+/// - The extrinsic is dropped, usurped or invalid, see [`TransactionError`].
+/// - Waiting for finality, the block including the extrinsic is retracted without the
+///   extrinsic being included again, or it is not finalized in time.
+/// - The extrinsic failed with a dispatch error.
 async fn submit_extrinsic<T, Call, Signer>(
     client: &OnlineClient<T>,
     call: &Call,
     signer: &Signer,
-) -> core::result::Result<blocks::ExtrinsicEvents<T>, subxt::Error>
+    wait_for: WaitFor,
+) -> core::result::Result<SubmittedExtrinsic<T>, ErrorVariant>
 where
     T: Config,
     Call: tx::TxPayload,
@@ -399,25 +518,113 @@ where
     <T::ExtrinsicParams as config::ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
         Default,
 {
-    client
+    profile().check_signed_extensions(&client.metadata())?;
+    let extrinsic = client
         .tx()
-        .sign_and_submit_then_watch_default(call, signer)
-        .await?
-        .wait_for_in_block()
-        .await?
-        .wait_for_success()
-        .await
+        .create_signed(call, signer, Default::default())
+        .await?;
+    submit_signed_extrinsic(&extrinsic, wait_for).await
+}
+
+/// Submit the signed extrinsic and wait for it to reach the stage `wait_for` of its
+/// lifecycle, see [`submit_extrinsic`].
+async fn submit_signed_extrinsic<T>(
+    extrinsic: &tx::SubmittableExtrinsic<T, OnlineClient<T>>,
+    wait_for: WaitFor,
+) -> core::result::Result<SubmittedExtrinsic<T>, ErrorVariant>
+where
+    T: Config,
+{
+    let mut progress = extrinsic.submit_and_watch().await?;
+    let hash = progress.extrinsic_hash();
+    if wait_for == WaitFor::None {
+        return core::result::Result::Ok(SubmittedExtrinsic {
+            hash,
+            events: None,
+            finalized: false,
+        })
+    }
+
+    let mut retracted = None;
+    while let Some(status) = progress.next_item().await {
+        let (in_block, finalized) = match status? {
+            TxStatus::InBlock(in_block) if wait_for == WaitFor::InBlock => {
+                (in_block, false)
+            }
+            TxStatus::Finalized(in_block) => (in_block, true),
+            TxStatus::Retracted(block_hash) => {
+                // the extrinsic is back in the pool, it may be included in another block
+                tracing::debug!("Block {block_hash:?} including {hash:?} was retracted");
+                retracted = Some(block_hash);
+                continue
+            }
+            TxStatus::FinalityTimeout(block_hash) => {
+                return Err(TransactionError::FinalityTimeout {
+                    block_hash: format!("{block_hash:?}"),
+                }
+                .into())
+            }
+            TxStatus::Usurped(by) => {
+                return Err(TransactionError::Usurped {
+                    by: format!("{by:?}"),
+                }
+                .into())
+            }
+            TxStatus::Dropped => return Err(TransactionError::Dropped.into()),
+            TxStatus::Invalid => return Err(TransactionError::Invalid.into()),
+            TxStatus::Future
+            | TxStatus::Ready
+            | TxStatus::Broadcast(_)
+            | TxStatus::InBlock(_) => continue,
+        };
+        let events = in_block.wait_for_success().await?;
+        return core::result::Result::Ok(SubmittedExtrinsic {
+            hash,
+            events: Some(events),
+            finalized,
+        })
+    }
+    match retracted {
+        Some(block_hash) => {
+            Err(TransactionError::Retracted {
+                block_hash: format!("{block_hash:?}"),
+            }
+            .into())
+        }
+        None => {
+            Err(anyhow!(
+            "The subscription to the status of the extrinsic {hash:?} ended unexpectedly"
+        )
+            .into())
+        }
+    }
 }
 
-async fn state_call<A: Encode, R: Decode>(url: &str, func: &str, args: A) -> Result<R> {
+/// Calls the runtime API function `func` at the block with the hash `at`, or at the best
+/// block if none is given.
+async fn state_call<A: Encode, R: Decode>(
+    url: &str,
+    func: &str,
+    args: A,
+    at: Option<BlockHash>,
+) -> Result<R> {
     let cli = WsClientBuilder::default().build(&url).await?;
-    let params = rpc_params![func, Bytes(args.encode())];
+    let params = rpc_params![func, Bytes(args.encode()), at];
     let bytes: Bytes = cli.request("state_call", params).await?;
     Ok(R::decode(&mut bytes.as_ref())?)
 }
 
 /// Prompt the user to confirm transaction submission.
 fn prompt_confirm_tx<F: FnOnce()>(show_details: F) -> Result<()> {
+    prompt_confirm("Submit?", "Transaction not submitted", show_details)
+}
+
+/// Shows the details of the transaction and asks the user to confirm the `action`.
+fn prompt_confirm<F: FnOnce()>(
+    action: &str,
+    declined: &str,
+    show_details: F,
+) -> Result<()> {
     println!(
         "{} (skip with --skip-confirm)",
         "Confirm transaction details:".bright_white().bold()
@@ -425,7 +632,7 @@ fn prompt_confirm_tx<F: FnOnce()>(show_details: F) -> Result<()> {
     show_details();
     print!(
         "{} ({}/n): ",
-        "Submit?".bright_white().bold(),
+        action.bright_white().bold(),
         "Y".bright_white().bold()
     );
 
@@ -435,7 +642,7 @@ fn prompt_confirm_tx<F: FnOnce()>(show_details: F) -> Result<()> {
     match buf.trim().to_lowercase().as_str() {
         // default is 'y'
         "y" | "" => Ok(()),
-        "n" => Err(anyhow!("Transaction not submitted")),
+        "n" => Err(anyhow!("{declined}")),
         c => Err(anyhow!("Expected either 'y' or 'n', got '{}'", c)),
     }
 }
@@ -458,6 +665,42 @@ fn print_gas_required_success(gas: Weight) {
     );
 }
 
+/// Returns the gas limit of an extrinsic from the `result` of its pre-submission dry run:
+/// the `gas_limit` and `proof_size` specified by the user, the ones not specified
+/// estimated by the dry run. A failed dry run is displayed unless the output is JSON.
+fn dry_run_gas_limit<R>(
+    result: &ContractResult<Result<R, sp_runtime::DispatchError>, Balance>,
+    gas_limit: Option<u64>,
+    proof_size: Option<u64>,
+    output_json: bool,
+    metadata: &subxt::Metadata,
+) -> Result<Weight> {
+    match result.result {
+        core::result::Result::Ok(_) => {
+            if !output_json {
+                print_gas_required_success(result.gas_required);
+            }
+            // use user specified values where provided, otherwise use the estimates
+            let ref_time = gas_limit.unwrap_or_else(|| result.gas_required.ref_time());
+            let proof_size =
+                proof_size.unwrap_or_else(|| result.gas_required.proof_size());
+            Ok(Weight::from_parts(ref_time, proof_size))
+        }
+        Err(ref err) => {
+            let object = ErrorVariant::from_dispatch_error(err, metadata)?;
+            if output_json {
+                Err(anyhow!("{}", serde_json::to_string_pretty(&object)?))
+            } else {
+                name_value_println!("Result", object, MAX_KEY_COL_WIDTH);
+                display_contract_exec_result::<_, MAX_KEY_COL_WIDTH>(result)?;
+                Err(anyhow!(
+                    "Pre-submission dry-run failed. Use --skip-dry-run to skip this step."
+                ))
+            }
+        }
+    }
+}
+
 /// Parse a hex encoded 32 byte hash. Returns error if not exactly 32 bytes.
 pub fn parse_code_hash(input: &str) -> Result<<DefaultConfig as Config>::Hash> {
     let bytes = contract_build::util::decode_hex(input)?;
@@ -515,4 +758,55 @@ mod tests {
         )
         .is_ok())
     }
+
+    #[test]
+    fn transaction_errors_are_reported_with_their_status() {
+        let error = ErrorVariant::from(TransactionError::Retracted {
+            block_hash: String::from("0x01"),
+        });
+
+        let json = serde_json::to_value(&error).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "transaction_error": { "status": "retracted", "block_hash": "0x01" }
+            })
+        );
+        assert_eq!(
+            error.to_string(),
+            "TransactionError: The block 0x01 including the extrinsic was retracted"
+        );
+    }
+
+    #[test]
+    fn code_built_for_another_target_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flipper.riscv");
+        std::fs::write(&path, b"\x7fELF\x01\x01\x01").unwrap();
+
+        let res = ContractArtifacts::from_artifact_path(&path, Some(Target::Wasm));
+
+        assert!(res
+            .unwrap_err()
+            .to_string()
+            .contains("is built for `riscv`, but the chain expects `wasm` code"));
+    }
+
+    #[test]
+    fn code_built_for_the_expected_target_is_loaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("flipper.wasm");
+        std::fs::write(&path, b"\0asm\x01\0\0\0").unwrap();
+
+        let artifacts =
+            ContractArtifacts::from_artifact_path(&path, Some(Target::Wasm)).unwrap();
+
+        let code = artifacts.code.unwrap();
+        assert_eq!(code.target, SourceTarget::Wasm);
+        assert!(
+            ContractArtifacts::from_artifact_path(&path, Some(Target::RiscV)).is_err()
+        );
+        assert!(ContractArtifacts::from_artifact_path(&path, None).is_ok());
+    }
 }
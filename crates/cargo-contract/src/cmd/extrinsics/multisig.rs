@@ -0,0 +1,408 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    Origin,
+    Signer,
+    SubmittedExtrinsic,
+};
+use crate::cmd::{
+    chain::profile,
+    Balance,
+};
+use anyhow::{
+    Context,
+    Result,
+};
+use scale::{
+    Decode,
+    Encode,
+};
+use sp_core::hashing::blake2_256;
+use sp_runtime::DispatchError;
+use sp_weights::Weight;
+use subxt::{
+    tx::TxPayload,
+    utils::{
+        Encoded,
+        Static,
+    },
+    Config,
+    Metadata,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The name of `pallet-multisig` in the runtime. The pallet is not part of the bundled
+/// runtime metadata, its calls and storage are looked up in the metadata of the chain.
+const MULTISIG_PALLET: &str = "Multisig";
+
+/// The prefix of the preimage of multisig accounts, see
+/// `pallet_multisig::multi_account_id`.
+const MULTI_ACCOUNT_PREFIX: &[u8; 16] = b"modlpy/utilisuba";
+
+/// Arguments for dispatching contract calls from a multisig account of `pallet-multisig`.
+#[derive(Clone, Debug, clap::Args)]
+pub struct MultisigOpts {
+    /// Dispatch the call from the multisig account of `--signatories` requiring
+    /// THRESHOLD approvals. The extrinsic approves the call on behalf of the signer, the
+    /// approval reaching the threshold dispatches it.
+    #[clap(long, value_name = "THRESHOLD", requires = "signatories")]
+    multisig: Option<u16>,
+    /// All signatories of the multisig account, including the signer.
+    #[clap(
+        long,
+        value_name = "ADDRESS",
+        num_args = 1..,
+        value_delimiter = ',',
+        requires = "multisig"
+    )]
+    signatories: Vec<AccountId>,
+}
+
+impl MultisigOpts {
+    /// Returns the origin of the calls signed by `signer`, the multisig account if one
+    /// is specified.
+    pub fn origin(&self, signer: Signer) -> Result<Origin> {
+        let multisig = match self.multisig {
+            Some(threshold) => {
+                for signatory in &self.signatories {
+                    profile().check_account(signatory, "--signatories")?;
+                }
+                let multisig = Multisig::new(threshold, self.signatories.clone())?;
+                if !multisig.signatories.contains(signer.account_id()) {
+                    anyhow::bail!(
+                        "The signer {} is not one of the signatories of the multisig account",
+                        signer.account_id()
+                    )
+                }
+                Some(multisig)
+            }
+            None => None,
+        };
+        Ok(Origin::new(signer, multisig))
+    }
+}
+
+/// A multisig account of `pallet-multisig`.
+#[derive(Clone, Debug)]
+pub struct Multisig {
+    threshold: u16,
+    /// The signatories, sorted as required by the pallet.
+    signatories: Vec<AccountId>,
+    account_id: AccountId,
+}
+
+impl Multisig {
+    pub fn new(threshold: u16, mut signatories: Vec<AccountId>) -> Result<Self> {
+        signatories.sort();
+        signatories.dedup();
+        if threshold < 2 {
+            anyhow::bail!("The threshold of a multisig account has to be at least 2")
+        }
+        if usize::from(threshold) > signatories.len() {
+            anyhow::bail!(
+                "The threshold of {threshold} approvals exceeds the {} signatories",
+                signatories.len()
+            )
+        }
+        let preimage = (MULTI_ACCOUNT_PREFIX, &signatories, threshold).encode();
+        Ok(Self {
+            threshold,
+            account_id: AccountId::from_entropy(
+                blake2_256(&preimage),
+                profile().account_id,
+            ),
+            signatories,
+        })
+    }
+
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+
+    /// Returns the approval of `call` by `signatory`: `as_multi` dispatching the call if
+    /// the approval reaches the threshold, `approve_as_multi` otherwise.
+    pub async fn approval<Call: TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+        signatory: &AccountId,
+    ) -> Result<MultisigCall> {
+        let call = client.tx().call_data(call)?;
+        let call_hash = blake2_256(&call);
+        let pending = self.pending(client, call_hash).await?;
+        let approvals = match pending {
+            Some(ref pending) if pending.approvals.contains(signatory) => {
+                anyhow::bail!(
+                    "The call 0x{} is already approved by {signatory}",
+                    hex::encode(call_hash)
+                )
+            }
+            Some(ref pending) => pending.approvals.len() + 1,
+            None => 1,
+        };
+        let other_signatories = self
+            .signatories
+            .iter()
+            .filter(|account_id| *account_id != signatory)
+            .cloned()
+            .collect();
+        // the first approval records the timepoint identifying the multisig operation
+        let maybe_timepoint = pending.map(|pending| pending.when);
+
+        if approvals >= usize::from(self.threshold) {
+            let max_weight = call_weight(client, &call).await?;
+            Ok(MultisigCall::AsMulti(AsMulti {
+                threshold: self.threshold,
+                other_signatories,
+                maybe_timepoint,
+                call: Encoded(call),
+                max_weight,
+            }))
+        } else {
+            Ok(MultisigCall::ApproveAsMulti(ApproveAsMulti {
+                threshold: self.threshold,
+                other_signatories,
+                maybe_timepoint,
+                call_hash,
+                max_weight: Weight::zero(),
+            }))
+        }
+    }
+
+    /// Returns the pending operation of the multisig account approving the call with
+    /// `call_hash`, if any.
+    async fn pending(
+        &self,
+        client: &Client,
+        call_hash: [u8; 32],
+    ) -> Result<Option<PendingMultisig>> {
+        let address = subxt::dynamic::storage(
+            MULTISIG_PALLET,
+            "Multisigs",
+            vec![
+                Static(Encoded(self.account_id.encode())),
+                Static(Encoded(call_hash.to_vec())),
+            ],
+        );
+        let pending = client.storage().at_latest().await?.fetch(&address).await?;
+        pending
+            .map(|pending| PendingMultisig::decode(&mut pending.encoded()))
+            .transpose()
+            .context("Failed to decode the pending multisig operation")
+    }
+
+    /// Checks that the call dispatched by the final approval in `submitted` succeeded.
+    /// The approving extrinsic succeeds even if the call it dispatches fails.
+    pub fn check_executed(
+        &self,
+        submitted: &SubmittedExtrinsic<DefaultConfig>,
+        metadata: &Metadata,
+    ) -> Result<(), ErrorVariant> {
+        let Some(ref events) = submitted.events else {
+            return Ok(())
+        };
+        for event in events.iter() {
+            let event = event?;
+            if event.pallet_name() == MULTISIG_PALLET
+                && event.variant_name() == "MultisigExecuted"
+            {
+                let executed = MultisigExecuted::decode(&mut event.field_bytes())
+                    .context("Failed to decode the MultisigExecuted event")?;
+                if let Err(err) = executed.result {
+                    return Err(ErrorVariant::from_dispatch_error(&err, metadata)?)
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the weight of dispatching `call`, which limits the weight of the `as_multi`
+/// dispatching it.
+async fn call_weight(client: &Client, call: &[u8]) -> Result<Weight> {
+    let len = u32::try_from(call.len())?;
+    let args = (Encoded(call.to_vec()), len).encode();
+    let info = client
+        .rpc()
+        .state_call(
+            "TransactionPaymentCallApi_query_call_info",
+            Some(&args),
+            None,
+        )
+        .await?;
+    // the weight is the first field of the `RuntimeDispatchInfo`
+    Weight::decode(&mut info.0.as_slice())
+        .context("Failed to decode the dispatch info of the call")
+}
+
+/// A call of `pallet-multisig` approving a call of a multisig account.
+pub enum MultisigCall {
+    AsMulti(AsMulti),
+    ApproveAsMulti(ApproveAsMulti),
+}
+
+impl MultisigCall {
+    /// Returns `true` if the approval dispatches the call.
+    pub fn dispatches(&self) -> bool {
+        matches!(self, MultisigCall::AsMulti(_))
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            MultisigCall::AsMulti(_) => "as_multi",
+            MultisigCall::ApproveAsMulti(_) => "approve_as_multi",
+        }
+    }
+}
+
+impl TxPayload for MultisigCall {
+    fn encode_call_data_to(
+        &self,
+        metadata: &Metadata,
+        out: &mut Vec<u8>,
+    ) -> Result<(), subxt::Error> {
+        let pallet = metadata.pallet(MULTISIG_PALLET)?;
+        let call = pallet.call(self.name())?;
+        pallet.index().encode_to(out);
+        call.index().encode_to(out);
+        match self {
+            MultisigCall::AsMulti(args) => args.encode_to(out),
+            MultisigCall::ApproveAsMulti(args) => args.encode_to(out),
+        }
+        Ok(())
+    }
+}
+
+/// The arguments of `as_multi`.
+#[derive(Encode)]
+pub struct AsMulti {
+    threshold: u16,
+    other_signatories: Vec<AccountId>,
+    maybe_timepoint: Option<Timepoint>,
+    /// The encoded call, which is not prefixed with its length.
+    call: Encoded,
+    max_weight: Weight,
+}
+
+/// The arguments of `approve_as_multi`.
+#[derive(Encode)]
+pub struct ApproveAsMulti {
+    threshold: u16,
+    other_signatories: Vec<AccountId>,
+    maybe_timepoint: Option<Timepoint>,
+    call_hash: [u8; 32],
+    max_weight: Weight,
+}
+
+/// The block and extrinsic index of the first approval of a multisig operation.
+#[derive(Clone, Copy, Debug, Encode, Decode)]
+struct Timepoint {
+    height: u32,
+    index: u32,
+}
+
+/// A pending operation of a multisig account, see `pallet_multisig::Multisig`.
+#[derive(Decode)]
+struct PendingMultisig {
+    when: Timepoint,
+    _deposit: Balance,
+    _depositor: AccountId,
+    approvals: Vec<AccountId>,
+}
+
+/// The `MultisigExecuted` event of `pallet-multisig`.
+#[derive(Decode)]
+struct MultisigExecuted {
+    _approving: AccountId,
+    _timepoint: Timepoint,
+    _multisig: AccountId,
+    _call_hash: [u8; 32],
+    result: Result<(), DispatchError>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn account(address: &str) -> AccountId {
+        AccountId::from_str(address).unwrap()
+    }
+
+    #[test]
+    fn multisig_account_is_derived_from_sorted_signatories() {
+        // given
+        let alice = account("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        let bob = account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+        let charlie = account("5FLSigC9HGRKVhB9FiEo4Y3koPsNmBmLJbpXg2mp1hXcS59Y");
+
+        // when
+        let multisig =
+            Multisig::new(2, vec![alice.clone(), bob.clone(), charlie.clone()]).unwrap();
+        let reordered =
+            Multisig::new(2, vec![charlie, alice.clone(), bob, alice]).unwrap();
+
+        // then
+        assert_eq!(
+            multisig.account_id().to_string(),
+            "5DjYJStmdZ2rcqXbXGX7TW85JsrW6uG4y9MUcLq2BoPMpRA7"
+        );
+        assert_eq!(reordered.account_id(), multisig.account_id());
+    }
+
+    #[test]
+    fn multisig_threshold_is_validated() {
+        let alice = account("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY");
+        let bob = account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+
+        assert!(Multisig::new(1, vec![alice.clone(), bob.clone()]).is_err());
+        assert!(Multisig::new(3, vec![alice.clone(), bob.clone()]).is_err());
+        assert!(Multisig::new(2, vec![alice.clone(), alice, bob]).is_ok());
+    }
+
+    #[test]
+    fn approvals_are_encoded_as_the_pallet_expects() {
+        // given
+        let bob = account("5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty");
+        let approval = ApproveAsMulti {
+            threshold: 2,
+            other_signatories: vec![bob.clone()],
+            maybe_timepoint: Some(Timepoint {
+                height: 7,
+                index: 1,
+            }),
+            call_hash: [1; 32],
+            max_weight: Weight::zero(),
+        };
+
+        // when
+        let encoded = approval.encode();
+
+        // then
+        let mut expected = vec![2, 0, 4];
+        expected.extend_from_slice(bob.as_ref());
+        expected.extend_from_slice(&[1, 7, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend_from_slice(&[1; 32]);
+        // the components of the weight are compact encoded
+        expected.extend_from_slice(&[0, 0]);
+        assert_eq!(encoded, expected);
+    }
+}
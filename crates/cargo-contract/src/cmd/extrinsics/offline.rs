@@ -0,0 +1,622 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    super::chain::{
+        profile,
+        ChainExtrinsicParamsBuilder,
+    },
+    contracts_pallet::collect_bytes,
+    Client,
+    DefaultConfig,
+    ExtrinsicOpts,
+    PairSigner,
+    Signer,
+};
+use anyhow::{
+    anyhow,
+    Context,
+    Result,
+};
+use scale::{
+    Decode,
+    Encode,
+};
+use scale_info::{
+    PortableRegistry,
+    TypeDef,
+    TypeDefPrimitive,
+};
+use serde::{
+    de::DeserializeOwned,
+    Deserialize,
+    Serialize,
+};
+use sp_core::Bytes;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use subxt::{
+    config::{
+        polkadot::Era,
+        ExtrinsicParams,
+        Hasher,
+    },
+    ext::{
+        frame_metadata::RuntimeMetadataPrefixed,
+        scale_value::{
+            self,
+            scale::TypeId,
+            Composite,
+            Value,
+            ValueDef,
+        },
+    },
+    tx::{
+        self,
+        Signer as _,
+    },
+    Config,
+    Metadata,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+type Hash = <DefaultConfig as Config>::Hash;
+
+/// The version of the extrinsic format, signed extrinsics of version 4 have the
+/// highest bit set.
+const SIGNED_EXTRINSIC_VERSION: u8 = 0b1000_0000 + 4;
+
+/// Signer payloads longer than this are hashed before being signed.
+const MAX_UNHASHED_PAYLOAD_LEN: usize = 256;
+
+/// Arguments for preparing extrinsics to be signed offline.
+#[derive(Clone, Debug, clap::Args)]
+pub struct OfflineOpts {
+    /// The account of the extrinsic if its key is not available: dry runs are executed
+    /// on behalf of the account, the extrinsic can only be written with
+    /// `--unsigned-out` to be signed offline.
+    #[clap(long, value_name = "ADDRESS", conflicts_with_all = ["suri", "account"])]
+    origin: Option<AccountId>,
+    /// Write the extrinsic of `--origin` to FILE, to be signed offline with `cargo
+    /// contract sign`, instead of submitting it.
+    #[clap(long, value_name = "FILE", requires_all = ["origin", "execute"])]
+    unsigned_out: Option<PathBuf>,
+}
+
+impl OfflineOpts {
+    /// Returns the signer of the extrinsic: the `--origin` account, or the key of the
+    /// `extrinsic_opts`.
+    pub fn signer(&self, extrinsic_opts: &ExtrinsicOpts) -> Result<Signer> {
+        match self.origin {
+            Some(ref account_id) => {
+                profile().check_account(account_id, "--origin")?;
+                Ok(Signer::Offline {
+                    account_id: account_id.clone(),
+                    unsigned_out: self.unsigned_out.clone(),
+                })
+            }
+            None => Ok(Signer::Key(extrinsic_opts.signer()?)),
+        }
+    }
+}
+
+/// An extrinsic to be signed offline: its call together with the parameters of the
+/// chain and account which are signed with it.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct UnsignedExtrinsic {
+    /// The account which has to sign the extrinsic.
+    pub account: AccountId,
+    /// The SCALE encoded call.
+    pub call: Bytes,
+    /// The nonce of the account.
+    pub nonce: <DefaultConfig as Config>::Index,
+    /// The period the extrinsic is valid for.
+    pub era: Era,
+    /// The block the period of a mortal extrinsic starts at, the genesis block for
+    /// immortal ones.
+    pub mortality_checkpoint: Hash,
+    pub genesis_hash: Hash,
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    /// The SCALE encoded metadata of the chain, to decode the call before signing it.
+    pub metadata: Bytes,
+}
+
+/// A call decoded with the metadata of the chain.
+#[derive(Debug, PartialEq)]
+pub struct DecodedCall {
+    pub pallet: String,
+    pub call: String,
+    /// The arguments of the call by name, bytes are displayed as hex.
+    pub args: Vec<(String, String)>,
+}
+
+impl UnsignedExtrinsic {
+    /// Prepares the immortal extrinsic of `call` to be signed by `account`.
+    pub async fn new<Call: tx::TxPayload>(
+        client: &Client,
+        call: &Call,
+        account: &AccountId,
+    ) -> Result<Self> {
+        profile().check_signed_extensions(&client.metadata())?;
+        let call = client.tx().call_data(call)?;
+        let nonce = client.rpc().system_account_next_index(account).await?;
+        let runtime = client.runtime_version();
+        Ok(Self {
+            account: account.clone(),
+            call: call.into(),
+            nonce,
+            era: Era::Immortal,
+            mortality_checkpoint: client.genesis_hash(),
+            genesis_hash: client.genesis_hash(),
+            spec_version: runtime.spec_version,
+            transaction_version: runtime.transaction_version,
+            metadata: RuntimeMetadataPrefixed::from(
+                client.metadata().runtime_metadata().clone(),
+            )
+            .encode()
+            .into(),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        read_json(path, "unsigned extrinsic")
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        write_json(self, path, "unsigned extrinsic")
+    }
+
+    fn params(&self) -> <DefaultConfig as Config>::ExtrinsicParams {
+        let other_params =
+            ChainExtrinsicParamsBuilder::new().era(self.era, self.mortality_checkpoint);
+        <<DefaultConfig as Config>::ExtrinsicParams as ExtrinsicParams<_, _>>::new(
+            self.spec_version,
+            self.transaction_version,
+            self.nonce,
+            self.genesis_hash,
+            other_params,
+        )
+    }
+
+    /// Returns the metadata of the chain.
+    fn metadata(&self) -> Result<Metadata> {
+        let metadata = RuntimeMetadataPrefixed::decode(&mut &self.metadata[..])
+            .context("Failed to decode the metadata of the chain")?;
+        Metadata::try_from(metadata)
+            .map_err(|err| anyhow!("Invalid metadata of the chain: {err}"))
+    }
+
+    /// Decodes the call with the metadata of the chain.
+    pub fn decode_call(&self) -> Result<DecodedCall> {
+        let metadata = self.metadata()?;
+        let (&pallet_index, call) = self
+            .call
+            .split_first()
+            .ok_or_else(|| anyhow!("The call of the extrinsic is empty"))?;
+        let pallet = metadata
+            .runtime_metadata()
+            .pallets
+            .iter()
+            .find(|pallet| pallet.index == pallet_index)
+            .ok_or_else(|| {
+                anyhow!("The chain has no pallet with index {pallet_index}")
+            })?;
+        let calls = pallet.calls.as_ref().ok_or_else(|| {
+            anyhow!("The pallet `{}` of the chain has no calls", pallet.name)
+        })?;
+        let input = &mut &call[..];
+        let value =
+            scale_value::scale::decode_as_type(input, calls.ty.id, metadata.types())
+                .map_err(|err| {
+                    anyhow!("Failed to decode the `{}` call: {err}", pallet.name)
+                })?;
+        if !input.is_empty() {
+            anyhow::bail!(
+                "The `{}` call of the extrinsic has {} trailing bytes",
+                pallet.name,
+                input.len()
+            )
+        }
+        let ValueDef::Variant(variant) = value.value else {
+            anyhow::bail!("The calls of the pallet `{}` are no enum", pallet.name)
+        };
+        let args = match variant.values {
+            Composite::Named(args) => {
+                args.iter()
+                    .map(|(name, value)| {
+                        (name.clone(), display_value(value, metadata.types()))
+                    })
+                    .collect()
+            }
+            Composite::Unnamed(args) => {
+                args.iter()
+                    .enumerate()
+                    .map(|(index, value)| {
+                        (index.to_string(), display_value(value, metadata.types()))
+                    })
+                    .collect()
+            }
+        };
+        Ok(DecodedCall {
+            pallet: pallet.name.clone(),
+            call: variant.name,
+            args,
+        })
+    }
+
+    /// Returns the payload which is signed: the call, followed by the parameters
+    /// included in the extrinsic and the ones only signed.
+    pub fn signer_payload(&self) -> Vec<u8> {
+        let params = self.params();
+        let mut payload = self.call.to_vec();
+        params.encode_extra_to(&mut payload);
+        params.encode_additional_to(&mut payload);
+        payload
+    }
+
+    /// Signs the extrinsic, the `signer` has to be the account of the extrinsic.
+    pub fn sign(&self, signer: &PairSigner) -> Result<SignedExtrinsic> {
+        if signer.account_id() != &self.account {
+            anyhow::bail!(
+                "The extrinsic has to be signed by {}, not by {}",
+                self.account,
+                signer.account_id()
+            )
+        }
+        profile().check_signed_extensions(&self.metadata()?)?;
+        let payload = self.signer_payload();
+        let signature = if payload.len() > MAX_UNHASHED_PAYLOAD_LEN {
+            signer.sign(<DefaultConfig as Config>::Hasher::hash(&payload).as_ref())
+        } else {
+            signer.sign(&payload)
+        };
+
+        let mut extrinsic = vec![SIGNED_EXTRINSIC_VERSION];
+        signer.address().encode_to(&mut extrinsic);
+        signature.encode_to(&mut extrinsic);
+        self.params().encode_extra_to(&mut extrinsic);
+        extrinsic.extend_from_slice(&self.call);
+        // the extrinsic is prefixed with its length
+        let extrinsic = extrinsic.encode();
+
+        Ok(SignedExtrinsic {
+            account: self.account.clone(),
+            hash: <DefaultConfig as Config>::Hasher::hash(&extrinsic),
+            extrinsic: extrinsic.into(),
+        })
+    }
+}
+
+/// An extrinsic signed offline, ready to be submitted.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignedExtrinsic {
+    /// The account which signed the extrinsic.
+    pub account: AccountId,
+    /// The hash of the extrinsic.
+    pub hash: Hash,
+    /// The SCALE encoded extrinsic.
+    pub extrinsic: Bytes,
+}
+
+impl SignedExtrinsic {
+    /// Loads the signed extrinsic, failing if its hash does not match its bytes.
+    pub fn load(path: &Path) -> Result<Self> {
+        let signed: Self = read_json(path, "signed extrinsic")?;
+        let hash = <DefaultConfig as Config>::Hasher::hash(&signed.extrinsic);
+        if hash != signed.hash {
+            anyhow::bail!(
+                "The hash {:?} of the signed extrinsic {} does not match its bytes, \
+                which hash to {hash:?}",
+                signed.hash,
+                path.display()
+            )
+        }
+        Ok(signed)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        write_json(self, path, "signed extrinsic")
+    }
+}
+
+/// Displays the `value` of a call argument, values of byte types as hex.
+fn display_value(value: &Value<TypeId>, types: &PortableRegistry) -> String {
+    let display_composite = |composite: &Composite<TypeId>| {
+        match composite {
+            Composite::Named(values) => {
+                let values = values
+                    .iter()
+                    .map(|(name, value)| {
+                        format!("{name}: {}", display_value(value, types))
+                    })
+                    .collect::<Vec<_>>();
+                format!(" {{ {} }}", values.join(", "))
+            }
+            Composite::Unnamed(values) if values.is_empty() => String::new(),
+            Composite::Unnamed(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| display_value(value, types))
+                    .collect::<Vec<_>>();
+                format!("({})", values.join(", "))
+            }
+        }
+    };
+    match &value.value {
+        ValueDef::Composite(composite) => {
+            let mut bytes = Vec::new();
+            if is_bytes(value.context, types)
+                && collect_bytes(value, &mut bytes).is_some()
+            {
+                format!("0x{}", hex::encode(bytes))
+            } else {
+                display_composite(composite).trim_start().to_string()
+            }
+        }
+        ValueDef::Variant(variant) => {
+            format!("{}{}", variant.name, display_composite(&variant.values))
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Whether the type is a sequence or an array of bytes, possibly wrapped in composites
+/// of a single field like `AccountId32([u8; 32])`.
+fn is_bytes(id: TypeId, types: &PortableRegistry) -> bool {
+    let is_u8 = |id: u32| {
+        matches!(
+            types.resolve(id).map(|ty| &ty.type_def),
+            Some(TypeDef::Primitive(TypeDefPrimitive::U8))
+        )
+    };
+    match types.resolve(id).map(|ty| &ty.type_def) {
+        Some(TypeDef::Sequence(sequence)) => is_u8(sequence.type_param.id),
+        Some(TypeDef::Array(array)) => is_u8(array.type_param.id),
+        Some(TypeDef::Composite(composite)) => {
+            match composite.fields.as_slice() {
+                [field] => is_bytes(field.ty.id, types),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T> {
+    let json = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the {what} {}", path.display()))?;
+    serde_json::from_str(&json)
+        .with_context(|| format!("Failed to parse the {what} {}", path.display()))
+}
+
+fn write_json<T: Serialize>(value: &T, path: &Path, what: &str) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)?;
+    std::fs::write(path, json)
+        .with_context(|| format!("Failed to write the {what} {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cmd::{
+        extrinsics::{
+            CryptoScheme,
+            Keypair,
+        },
+        runtime_api::api,
+    };
+    use subxt::{
+        rpc::types::RuntimeVersion,
+        tx::TxPayload,
+        OfflineClient,
+    };
+
+    fn offline_client() -> OfflineClient<DefaultConfig> {
+        let metadata = include_bytes!("../runtime_api/contracts_runtime.scale");
+        let metadata =
+            <RuntimeMetadataPrefixed as scale::Decode>::decode(&mut &metadata[..])
+                .unwrap();
+        OfflineClient::new(
+            Hash::repeat_byte(1),
+            RuntimeVersion {
+                spec_version: 100,
+                transaction_version: 1,
+                other: Default::default(),
+            },
+            Metadata::try_from(metadata).unwrap(),
+        )
+    }
+
+    fn unsigned_extrinsic<Call: TxPayload>(
+        client: &OfflineClient<DefaultConfig>,
+        call: &Call,
+        account: &AccountId,
+    ) -> UnsignedExtrinsic {
+        UnsignedExtrinsic {
+            account: account.clone(),
+            call: client.tx().call_data(call).unwrap().into(),
+            nonce: 7,
+            era: Era::Immortal,
+            mortality_checkpoint: client.genesis_hash(),
+            genesis_hash: client.genesis_hash(),
+            spec_version: 100,
+            transaction_version: 1,
+            metadata: RuntimeMetadataPrefixed::from(
+                client.metadata().runtime_metadata().clone(),
+            )
+            .encode()
+            .into(),
+        }
+    }
+
+    #[test]
+    fn signed_offline_extrinsics_match_extrinsics_signed_online() {
+        // given
+        let client = offline_client();
+        // ed25519 signatures are deterministic, unlike sr25519 ones
+        let signer: PairSigner =
+            Keypair::from_suri(CryptoScheme::Ed25519, "//Alice", None)
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let short_call = api::tx().contracts().remove_code([2; 32].into());
+        let long_call = api::tx().contracts().upload_code(
+            vec![0; 1024],
+            None,
+            api::runtime_types::pallet_contracts::wasm::Determinism::Enforced,
+        );
+
+        // when
+        let short = unsigned_extrinsic(&client, &short_call, signer.account_id())
+            .sign(&signer)
+            .unwrap();
+        let long = unsigned_extrinsic(&client, &long_call, signer.account_id())
+            .sign(&signer)
+            .unwrap();
+
+        // then
+        let expected = client
+            .tx()
+            .create_signed_with_nonce(&short_call, &signer, 7, Default::default())
+            .unwrap();
+        assert_eq!(short.extrinsic.0, expected.encoded());
+        assert_eq!(
+            short.hash,
+            <DefaultConfig as Config>::Hasher::hash(expected.encoded())
+        );
+        let expected = client
+            .tx()
+            .create_signed_with_nonce(&long_call, &signer, 7, Default::default())
+            .unwrap();
+        assert_eq!(long.extrinsic.0, expected.encoded());
+    }
+
+    #[test]
+    fn extrinsics_are_only_signed_by_their_account() {
+        // given
+        let client = offline_client();
+        let alice: PairSigner =
+            Keypair::from_suri(CryptoScheme::Sr25519, "//Alice", None)
+                .unwrap()
+                .try_into()
+                .unwrap();
+        let bob: PairSigner = Keypair::from_suri(CryptoScheme::Sr25519, "//Bob", None)
+            .unwrap()
+            .try_into()
+            .unwrap();
+        let call = api::tx().contracts().remove_code([2; 32].into());
+        let unsigned = unsigned_extrinsic(&client, &call, alice.account_id());
+
+        // when
+        let result = unsigned.sign(&bob);
+
+        // then
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("The extrinsic has to be signed by 5GrwvaEF"));
+    }
+
+    #[test]
+    fn calls_are_decoded_with_the_metadata_of_the_chain() {
+        // given
+        let client = offline_client();
+        let account = Keypair::from_suri(CryptoScheme::Sr25519, "//Alice", None)
+            .unwrap()
+            .account_id()
+            .unwrap();
+        let call = subxt::dynamic::tx(
+            "Contracts",
+            "call",
+            vec![
+                Value::unnamed_variant("Id", [Value::from_bytes([3; 32])]),
+                Value::u128(5),
+                Value::named_composite([
+                    ("ref_time", Value::u128(1)),
+                    ("proof_size", Value::u128(2)),
+                ]),
+                Value::unnamed_variant("None", []),
+                Value::from_bytes([0xca, 0xfe]),
+            ],
+        );
+        let unsigned = unsigned_extrinsic(&client, &call, &account);
+
+        // when
+        let decoded = unsigned.decode_call().unwrap();
+
+        // then
+        let arg = |name: &str| {
+            decoded
+                .args
+                .iter()
+                .find(|(arg, _)| arg == name)
+                .map(|(_, value)| value.as_str())
+                .unwrap()
+        };
+        assert_eq!(decoded.pallet, "Contracts");
+        assert_eq!(decoded.call, "call");
+        assert_eq!(arg("dest"), format!("Id(0x{})", "03".repeat(32)));
+        assert_eq!(arg("value"), "5");
+        assert_eq!(arg("gas_limit"), "{ ref_time: 1, proof_size: 2 }");
+        assert_eq!(arg("storage_deposit_limit"), "None");
+        assert_eq!(arg("data"), "0xcafe");
+    }
+
+    #[test]
+    fn signed_extrinsics_not_matching_their_hash_are_rejected() {
+        // given
+        let signed = SignedExtrinsic {
+            account: subxt::utils::AccountId32([1; 32]).into(),
+            hash: Hash::repeat_byte(2),
+            extrinsic: vec![0; 8].into(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signed.json");
+        signed.write(&path).unwrap();
+
+        // when
+        let result = SignedExtrinsic::load(&path);
+
+        // then
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .starts_with("The hash 0x0202"));
+    }
+
+    #[test]
+    fn unsigned_extrinsics_are_written_and_loaded() {
+        // given
+        let client = offline_client();
+        let account = Keypair::from_suri(CryptoScheme::Sr25519, "//Alice", None)
+            .unwrap()
+            .account_id()
+            .unwrap();
+        let call = api::tx().contracts().remove_code([2; 32].into());
+        let unsigned = unsigned_extrinsic(&client, &call, &account);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("unsigned.json");
+
+        // when
+        unsigned.write(&path).unwrap();
+
+        // then
+        assert_eq!(UnsignedExtrinsic::load(&path).unwrap(), unsigned);
+    }
+}
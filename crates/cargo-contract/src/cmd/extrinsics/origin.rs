@@ -0,0 +1,197 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    multisig::Multisig,
+    offline::UnsignedExtrinsic,
+    proxy::Proxy,
+    submit_extrinsic,
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    PairSigner,
+    SubmittedExtrinsic,
+    WaitFor,
+};
+use anyhow::anyhow;
+use contract_build::name_value_println;
+use std::path::PathBuf;
+use subxt::{
+    tx,
+    Config,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The account signing an extrinsic.
+#[allow(clippy::large_enum_variant)]
+pub enum Signer {
+    /// The extrinsic is signed with the key of the account and submitted.
+    Key(PairSigner),
+    /// The key of the account is not available, the extrinsic is written to
+    /// `unsigned_out` to be signed offline.
+    Offline {
+        account_id: AccountId,
+        unsigned_out: Option<PathBuf>,
+    },
+}
+
+impl Signer {
+    pub fn account_id(&self) -> &AccountId {
+        match self {
+            Signer::Key(signer) => signer.account_id(),
+            Signer::Offline { account_id, .. } => account_id,
+        }
+    }
+
+    /// Signs and submits the extrinsic of `call`, or writes it to be signed offline.
+    async fn submit<Call: tx::TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+        wait_for: WaitFor,
+    ) -> Result<Submission, ErrorVariant> {
+        match self {
+            Signer::Key(signer) => {
+                let submitted = submit_extrinsic(client, call, signer, wait_for).await?;
+                Ok(Submission::Dispatched(submitted))
+            }
+            Signer::Offline {
+                account_id,
+                unsigned_out: Some(path),
+            } => {
+                let unsigned = UnsignedExtrinsic::new(client, call, account_id).await?;
+                unsigned.write(path)?;
+                name_value_println!("Account", unsigned.account.to_string());
+                name_value_println!("Nonce", unsigned.nonce.to_string());
+                name_value_println!("Unsigned", path.display().to_string());
+                println!(
+                    "Sign the extrinsic offline with `cargo contract sign`, then submit it \
+                    with `cargo contract submit`."
+                );
+                Ok(Submission::Unsigned)
+            }
+            Signer::Offline {
+                unsigned_out: None, ..
+            } => {
+                Err(anyhow!(
+                    "Without its key, the extrinsic of `--origin` can only be written \
+                    with `--unsigned-out` to be signed offline"
+                )
+                .into())
+            }
+        }
+    }
+}
+
+/// The origin contract extrinsics are dispatched from: the account of the signer, or a
+/// multisig account the signer is a signatory of, optionally on behalf of an account
+/// they are a proxy of.
+pub struct Origin {
+    signer: Signer,
+    multisig: Option<Multisig>,
+    proxy: Option<Proxy>,
+}
+
+impl Origin {
+    pub fn new(signer: Signer, multisig: Option<Multisig>) -> Self {
+        Self {
+            signer,
+            multisig,
+            proxy: None,
+        }
+    }
+
+    /// Dispatches the calls on behalf of the account of `proxy`.
+    pub fn with_proxy(self, proxy: Proxy) -> Self {
+        Self {
+            proxy: Some(proxy),
+            ..self
+        }
+    }
+
+    /// Returns the account the calls are dispatched from, which dry runs are executed
+    /// on behalf of.
+    pub fn account_id(&self) -> &AccountId {
+        match (&self.proxy, &self.multisig) {
+            (Some(proxy), _) => proxy.account_id(),
+            (None, Some(multisig)) => multisig.account_id(),
+            (None, None) => self.signer.account_id(),
+        }
+    }
+
+    /// Submits `call`, wrapped in a `proxy` call if it is dispatched on behalf of
+    /// another account.
+    pub async fn submit<Call: tx::TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+        wait_for: WaitFor,
+    ) -> Result<Submission, ErrorVariant> {
+        let Some(ref proxy) = self.proxy else {
+            return self.submit_as_signatory(client, call, wait_for).await
+        };
+        let proxy_call = proxy.call(client, call)?;
+        let submission = self
+            .submit_as_signatory(client, &proxy_call, wait_for)
+            .await?;
+        if let Submission::Dispatched(ref submitted) = submission {
+            proxy.check_executed(submitted, &client.metadata())?;
+        }
+        Ok(submission)
+    }
+
+    /// Submits `call`, wrapped in the approval of the signatory if it is dispatched from
+    /// a multisig account.
+    async fn submit_as_signatory<Call: tx::TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+        wait_for: WaitFor,
+    ) -> Result<Submission, ErrorVariant> {
+        let Some(ref multisig) = self.multisig else {
+            return self.signer.submit(client, call, wait_for).await
+        };
+        let approval = multisig
+            .approval(client, call, self.signer.account_id())
+            .await?;
+        match self.signer.submit(client, &approval, wait_for).await? {
+            Submission::Dispatched(submitted) if approval.dispatches() => {
+                multisig.check_executed(&submitted, &client.metadata())?;
+                Ok(Submission::Dispatched(submitted))
+            }
+            Submission::Dispatched(submitted) => Ok(Submission::Approved(submitted)),
+            submission => Ok(submission),
+        }
+    }
+}
+
+impl From<Signer> for Origin {
+    fn from(signer: Signer) -> Self {
+        Self::new(signer, None)
+    }
+}
+
+/// The outcome of submitting a call with [`Origin::submit`].
+pub enum Submission {
+    /// The extrinsic is written to be signed offline.
+    Unsigned,
+    /// The signatory approved the call of the multisig account, it is dispatched once
+    /// the threshold of approvals is reached.
+    Approved(SubmittedExtrinsic<DefaultConfig>),
+    /// The call is dispatched.
+    Dispatched(SubmittedExtrinsic<DefaultConfig>),
+}
@@ -0,0 +1,173 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    Client,
+    DefaultConfig,
+    ErrorVariant,
+    Origin,
+    SubmittedExtrinsic,
+};
+use crate::cmd::chain::profile;
+use anyhow::{
+    Context,
+    Result,
+};
+use scale::{
+    Decode,
+    Encode,
+};
+use sp_runtime::DispatchError;
+use subxt::{
+    tx::TxPayload,
+    utils::Encoded,
+    Config,
+    Metadata,
+};
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// The name of `pallet-proxy` in the runtime. The pallet is not part of the bundled
+/// runtime metadata, its calls are looked up in the metadata of the chain.
+const PROXY_PALLET: &str = "Proxy";
+
+/// Arguments for dispatching contract extrinsics through `pallet-proxy`.
+#[derive(Clone, Debug, clap::Args)]
+pub struct ProxyOpts {
+    /// Dispatch the call on behalf of the account at ADDRESS, which the signer is a
+    /// proxy of. Dry runs are executed on behalf of this account.
+    #[clap(long, value_name = "ADDRESS")]
+    proxy_for: Option<AccountId>,
+}
+
+impl ProxyOpts {
+    /// Returns `origin` dispatching its calls on behalf of the `--proxy-for` account, if
+    /// one is specified.
+    pub fn origin(&self, origin: Origin) -> Result<Origin> {
+        match self.proxy_for {
+            Some(ref real) => {
+                profile().check_account(real, "--proxy-for")?;
+                Ok(origin.with_proxy(Proxy { real: real.clone() }))
+            }
+            None => Ok(origin),
+        }
+    }
+}
+
+/// An account calls are dispatched on behalf of by one of its proxies.
+#[derive(Clone, Debug)]
+pub struct Proxy {
+    real: AccountId,
+}
+
+impl Proxy {
+    pub fn account_id(&self) -> &AccountId {
+        &self.real
+    }
+
+    /// Returns the `proxy` call dispatching `call` on behalf of the account.
+    pub fn call<Call: TxPayload>(
+        &self,
+        client: &Client,
+        call: &Call,
+    ) -> Result<ProxyCall> {
+        Ok(ProxyCall {
+            real: self.real.clone().into(),
+            force_proxy_type: None,
+            call: Encoded(client.tx().call_data(call)?),
+        })
+    }
+
+    /// Checks that the call dispatched by the proxy in `submitted` succeeded. The
+    /// `proxy` extrinsic succeeds even if the call it dispatches fails.
+    pub fn check_executed(
+        &self,
+        submitted: &SubmittedExtrinsic<DefaultConfig>,
+        metadata: &Metadata,
+    ) -> Result<(), ErrorVariant> {
+        let Some(ref events) = submitted.events else {
+            return Ok(())
+        };
+        for event in events.iter() {
+            let event = event?;
+            if event.pallet_name() == PROXY_PALLET
+                && event.variant_name() == "ProxyExecuted"
+            {
+                let result =
+                    <Result<(), DispatchError>>::decode(&mut event.field_bytes())
+                        .context("Failed to decode the ProxyExecuted event")?;
+                if let Err(err) = result {
+                    return Err(ErrorVariant::from_dispatch_error(&err, metadata)?)
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The arguments of the `proxy` call of `pallet-proxy`.
+#[derive(Encode)]
+pub struct ProxyCall {
+    real: <DefaultConfig as Config>::Address,
+    /// The type of the proxy is not restricted, as it is specific to the runtime.
+    force_proxy_type: Option<()>,
+    /// The encoded call, which is not prefixed with its length.
+    call: Encoded,
+}
+
+impl TxPayload for ProxyCall {
+    fn encode_call_data_to(
+        &self,
+        metadata: &Metadata,
+        out: &mut Vec<u8>,
+    ) -> Result<(), subxt::Error> {
+        let pallet = metadata.pallet(PROXY_PALLET)?;
+        let call = pallet.call("proxy")?;
+        pallet.index().encode_to(out);
+        call.index().encode_to(out);
+        self.encode_to(out);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn proxy_calls_are_encoded_as_the_pallet_expects() {
+        // given
+        let alice =
+            AccountId::from_str("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")
+                .unwrap();
+        let call = ProxyCall {
+            real: alice.clone().into(),
+            force_proxy_type: None,
+            call: Encoded(vec![8, 1, 2]),
+        };
+
+        // when
+        let encoded = call.encode();
+
+        // then
+        // the real account is a `MultiAddress::Id`, followed by no proxy type
+        let mut expected = vec![0];
+        expected.extend_from_slice(alice.as_ref());
+        expected.extend_from_slice(&[0, 8, 1, 2]);
+        assert_eq!(encoded, expected);
+    }
+}
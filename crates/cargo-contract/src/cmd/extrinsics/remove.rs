@@ -15,25 +15,28 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
-    submit_extrinsic,
     Client,
     ContractMessageTranscoder,
     DefaultConfig,
     ExtrinsicOpts,
-    PairSigner,
+    Origin,
+    ProxyOpts,
+    Signer,
+    Submission,
     TokenMetadata,
 };
 use crate::{
     cmd::{
         extrinsics::{
+            contracts_pallet::{
+                CodeRemoved,
+                ContractsCall,
+                ContractsEvent,
+            },
             events::DisplayEvents,
             parse_code_hash,
             ErrorVariant,
         },
-        runtime_api::api::{
-            self,
-            contracts::events::CodeRemoved,
-        },
         CodeHash,
     },
     name_value_println,
@@ -53,6 +56,8 @@ pub struct RemoveCommand {
     code_hash: Option<<DefaultConfig as Config>::Hash>,
     #[clap(flatten)]
     extrinsic_opts: ExtrinsicOpts,
+    #[clap(flatten)]
+    proxy_opts: ProxyOpts,
     /// Export the call output as JSON.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
@@ -66,7 +71,8 @@ impl RemoveCommand {
     pub fn run(&self) -> Result<(), ErrorVariant> {
         let artifacts = self.extrinsic_opts.contract_artifacts()?;
         let transcoder = artifacts.contract_transcoder()?;
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
+        let signer = Signer::Key(self.extrinsic_opts.signer()?);
+        let origin = self.proxy_opts.origin(Origin::from(signer))?;
 
         let artifacts_path = artifacts.artifact_path().to_path_buf();
 
@@ -90,31 +96,13 @@ impl RemoveCommand {
         async_std::task::block_on(async {
             let url = self.extrinsic_opts.url_to_string();
             let client = OnlineClient::from_url(url.clone()).await?;
-            if let Some(code_removed) = self
-                .remove_code(
-                    &client,
-                    sp_core::H256(final_code_hash),
-                    &signer,
-                    &transcoder,
-                )
-                .await?
-            {
-                let remove_result = code_removed.code_hash;
-
-                if self.output_json {
-                    println!("{}", &remove_result);
-                } else {
-                    name_value_println!("Code hash", format!("{remove_result:?}"));
-                }
-                Result::<(), ErrorVariant>::Ok(())
-            } else {
-                let error_code_hash = hex::encode(final_code_hash);
-                Err(anyhow::anyhow!(
-                    "Error removing the code for the supplied code hash: {}",
-                    error_code_hash
-                )
-                .into())
-            }
+            self.remove_code(
+                &client,
+                sp_core::H256(final_code_hash),
+                &origin,
+                &transcoder,
+            )
+            .await
         })
     }
 
@@ -122,26 +110,48 @@ impl RemoveCommand {
         &self,
         client: &Client,
         code_hash: CodeHash,
-        signer: &PairSigner,
+        origin: &Origin,
         transcoder: &ContractMessageTranscoder,
-    ) -> Result<Option<CodeRemoved>, ErrorVariant> {
-        let call = api::tx()
-            .contracts()
-            .remove_code(sp_core::H256(code_hash.0));
+    ) -> Result<(), ErrorVariant> {
+        let call = ContractsCall::remove_code(code_hash);
 
-        let result = submit_extrinsic(client, &call, signer).await?;
+        let Submission::Dispatched(submitted) = origin
+            .submit(client, &call, self.extrinsic_opts.wait_for())
+            .await?
+        else {
+            return Ok(())
+        };
         let display_events =
-            DisplayEvents::from_events(&result, Some(transcoder), &client.metadata())?;
+            DisplayEvents::from_events(&submitted, Some(transcoder), &client.metadata())?;
 
         let output = if self.output_json {
-            display_events.to_json()?
+            display_events.to_json(self.extrinsic_opts.output_extrinsic())?
         } else {
             let token_metadata = TokenMetadata::query(client).await?;
             display_events
                 .display_events(self.extrinsic_opts.verbosity()?, &token_metadata)?
         };
         println!("{output}");
-        let code_removed = result.find_first::<CodeRemoved>()?;
-        Ok(code_removed)
+
+        let Some(result) = submitted.events else {
+            return Ok(())
+        };
+        if let Some(code_removed) = CodeRemoved::find_first(&result)? {
+            let remove_result = code_removed.code_hash;
+
+            if self.output_json {
+                println!("{}", &remove_result);
+            } else {
+                name_value_println!("Code hash", format!("{remove_result:?}"));
+            }
+            Ok(())
+        } else {
+            let error_code_hash = hex::encode(code_hash);
+            Err(anyhow::anyhow!(
+                "Error removing the code for the supplied code hash: {}",
+                error_code_hash
+            )
+            .into())
+        }
     }
 }
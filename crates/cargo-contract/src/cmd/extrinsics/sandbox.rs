@@ -0,0 +1,77 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    DefaultConfig,
+    TokenMetadata,
+};
+use anyhow::Result;
+use contract_sandbox::Sandbox;
+use sp_core::crypto::AccountId32;
+use std::path::PathBuf;
+use subxt::Config;
+
+/// Arguments for executing contracts in a local sandbox instead of on a node.
+#[derive(Clone, Debug, clap::Args)]
+pub struct SandboxOpts {
+    /// Execute the contract in a local sandbox instead of on the node at `--url`.
+    #[clap(long, conflicts_with = "unsigned_out")]
+    sandbox: bool,
+    /// JSON file keeping the state of the sandbox between invocations, it is only
+    /// updated when executing with `-x`. Without it the sandbox starts empty.
+    #[clap(long, value_name = "FILE", requires = "sandbox")]
+    sandbox_state: Option<PathBuf>,
+}
+
+impl SandboxOpts {
+    /// Returns `true` if the contract is to be executed in the sandbox.
+    pub fn is_enabled(&self) -> bool {
+        self.sandbox
+    }
+
+    /// Loads the sandbox from the state file, if any.
+    pub fn load(&self) -> Result<Sandbox> {
+        match self.sandbox_state {
+            Some(ref path) => Sandbox::load(path),
+            None => Ok(Sandbox::new()),
+        }
+    }
+
+    /// Saves the state of the `sandbox` to the state file, if any.
+    pub fn save(&self, sandbox: &Sandbox) -> Result<()> {
+        match self.sandbox_state {
+            Some(ref path) => sandbox.save(path),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The token of the sandbox, the same as the one of a local development node.
+pub fn token_metadata() -> TokenMetadata {
+    TokenMetadata {
+        token_decimals: 12,
+        symbol: String::from("UNIT"),
+    }
+}
+
+/// Converts an account of the chain config to the account type of the sandbox, which
+/// only supports 32 byte accounts.
+pub fn account_id(account: &<DefaultConfig as Config>::AccountId) -> Result<AccountId32> {
+    let account: [u8; 32] = account.as_ref().try_into().map_err(|_| {
+        anyhow::anyhow!("The sandbox only supports 32 byte accounts, not {account}")
+    })?;
+    Ok(AccountId32::new(account))
+}
@@ -0,0 +1,73 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    offline::UnsignedExtrinsic,
+    prompt_confirm,
+    SignerOpts,
+};
+use crate::name_value_println;
+use anyhow::Result;
+use std::path::PathBuf;
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "sign",
+    about = "Sign an extrinsic written with `--unsigned-out`, without a node"
+)]
+pub struct SignCommand {
+    /// Path to the unsigned extrinsic.
+    #[clap(value_parser)]
+    unsigned: PathBuf,
+    /// Write the signed extrinsic to FILE, to be submitted with `cargo contract submit`.
+    #[clap(long, value_name = "FILE")]
+    signed_out: PathBuf,
+    #[clap(flatten)]
+    signer: SignerOpts,
+    /// Before signing the extrinsic, do not ask the user for confirmation.
+    #[clap(long)]
+    skip_confirm: bool,
+}
+
+impl SignCommand {
+    pub fn run(&self) -> Result<()> {
+        let unsigned = UnsignedExtrinsic::load(&self.unsigned)?;
+        // the call is decoded before signing, so that the user knows what is signed
+        let call = unsigned.decode_call()?;
+        let show_details = || {
+            name_value_println!("Account", unsigned.account.to_string());
+            name_value_println!("Call", format!("{}::{}", call.pallet, call.call));
+            for (name, value) in &call.args {
+                name_value_println!(name, value);
+            }
+            name_value_println!("Genesis Hash", format!("{:?}", unsigned.genesis_hash));
+            name_value_println!("Spec Version", unsigned.spec_version.to_string());
+            name_value_println!("Nonce", unsigned.nonce.to_string());
+        };
+        if self.skip_confirm {
+            show_details();
+        } else {
+            prompt_confirm("Sign?", "Extrinsic not signed", show_details)?;
+        }
+        let signer = self.signer.signer()?;
+        let signed = unsigned.sign(&signer)?;
+        signed.write(&self.signed_out)?;
+
+        name_value_println!("Hash", format!("{:?}", signed.hash));
+        name_value_println!("Signed", self.signed_out.display().to_string());
+        Ok(())
+    }
+}
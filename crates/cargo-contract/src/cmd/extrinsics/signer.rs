@@ -0,0 +1,336 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    super::chain::{
+        profile,
+        AccountFormat,
+        AccountId,
+        Signature,
+    },
+    DefaultConfig,
+    Keystore,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use sp_core::{
+    blake2_256,
+    crypto::Pair as _,
+    ecdsa,
+    ed25519,
+    keccak_256,
+    sr25519,
+};
+use std::{
+    io::IsTerminal as _,
+    path::PathBuf,
+};
+use subxt::{
+    tx,
+    utils::{
+        AccountId32,
+        MultiSignature,
+    },
+    Config,
+};
+
+/// The signature scheme of a key.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum CryptoScheme {
+    /// Schnorr signatures on Ristretto, the default of Substrate chains.
+    #[default]
+    Sr25519,
+    /// Ed25519 signatures.
+    Ed25519,
+    /// ECDSA signatures on secp256k1.
+    Ecdsa,
+}
+
+impl CryptoScheme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CryptoScheme::Sr25519 => "sr25519",
+            CryptoScheme::Ed25519 => "ed25519",
+            CryptoScheme::Ecdsa => "ecdsa",
+        }
+    }
+}
+
+impl std::fmt::Display for CryptoScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for CryptoScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sr25519" => Ok(CryptoScheme::Sr25519),
+            "ed25519" => Ok(CryptoScheme::Ed25519),
+            "ecdsa" => Ok(CryptoScheme::Ecdsa),
+            _ => Err(anyhow!("Unsupported signature scheme `{s}`")),
+        }
+    }
+}
+
+/// A key pair of one of the supported signature schemes.
+#[derive(Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum Keypair {
+    Sr25519(sr25519::Pair),
+    Ed25519(ed25519::Pair),
+    Ecdsa(ecdsa::Pair),
+}
+
+impl Keypair {
+    /// Derives the key pair from a secret key URI, e.g. a mnemonic phrase with an
+    /// optional derivation path, or a dev account such as `//Alice`.
+    pub fn from_suri(
+        scheme: CryptoScheme,
+        suri: &str,
+        password: Option<&str>,
+    ) -> Result<Self> {
+        let error = |_| anyhow!("Secret string error");
+        Ok(match scheme {
+            CryptoScheme::Sr25519 => {
+                Keypair::Sr25519(
+                    sr25519::Pair::from_string(suri, password).map_err(error)?,
+                )
+            }
+            CryptoScheme::Ed25519 => {
+                Keypair::Ed25519(
+                    ed25519::Pair::from_string(suri, password).map_err(error)?,
+                )
+            }
+            CryptoScheme::Ecdsa => {
+                Keypair::Ecdsa(ecdsa::Pair::from_string(suri, password).map_err(error)?)
+            }
+        })
+    }
+
+    pub fn scheme(&self) -> CryptoScheme {
+        match self {
+            Keypair::Sr25519(_) => CryptoScheme::Sr25519,
+            Keypair::Ed25519(_) => CryptoScheme::Ed25519,
+            Keypair::Ecdsa(_) => CryptoScheme::Ecdsa,
+        }
+    }
+
+    /// Returns the account of the key pair on the chain.
+    pub fn account_id(&self) -> Result<AccountId> {
+        self.account_id_as(profile().account_id)
+    }
+
+    /// Returns the account of the key pair on chains with accounts of the `format`.
+    pub fn account_id_as(&self, format: AccountFormat) -> Result<AccountId> {
+        match (format, self) {
+            (AccountFormat::Ss58, Keypair::Sr25519(pair)) => {
+                Ok(AccountId::Id32(AccountId32(pair.public().0)))
+            }
+            (AccountFormat::Ss58, Keypair::Ed25519(pair)) => {
+                Ok(AccountId::Id32(AccountId32(pair.public().0)))
+            }
+            // the account of an ECDSA key is the hash of its compressed public key
+            (AccountFormat::Ss58, Keypair::Ecdsa(pair)) => {
+                Ok(AccountId::Id32(AccountId32(blake2_256(&pair.public().0))))
+            }
+            // the account of an Ethereum key is the end of the hash of its uncompressed
+            // public key
+            (AccountFormat::H160, Keypair::Ecdsa(pair)) => {
+                let public = libsecp256k1::PublicKey::parse_compressed(&pair.public().0)
+                    .map_err(|err| anyhow!("Invalid ECDSA public key: {err:?}"))?;
+                let hash = keccak_256(&public.serialize()[1..]);
+                let mut account = [0; 20];
+                account.copy_from_slice(&hash[12..]);
+                Ok(AccountId::Id20(account))
+            }
+            (AccountFormat::H160, keypair) => {
+                Err(anyhow!(
+                    "The accounts of the chain are `h160` accounts of ECDSA keys, \
+                    not of {} keys",
+                    keypair.scheme()
+                ))
+            }
+        }
+    }
+}
+
+/// Signs extrinsics with a [`Keypair`].
+pub struct PairSigner {
+    keypair: Keypair,
+    account_id: AccountId,
+}
+
+impl PairSigner {
+    /// Returns the account signing the extrinsics.
+    pub fn account_id(&self) -> &AccountId {
+        &self.account_id
+    }
+}
+
+impl TryFrom<Keypair> for PairSigner {
+    type Error = anyhow::Error;
+
+    fn try_from(keypair: Keypair) -> Result<Self> {
+        let account_id = keypair.account_id()?;
+        Ok(Self {
+            keypair,
+            account_id,
+        })
+    }
+}
+
+impl tx::Signer<DefaultConfig> for PairSigner {
+    fn account_id(&self) -> &AccountId {
+        PairSigner::account_id(self)
+    }
+
+    fn address(&self) -> <DefaultConfig as Config>::Address {
+        self.account_id.clone().into()
+    }
+
+    fn sign(&self, signer_payload: &[u8]) -> <DefaultConfig as Config>::Signature {
+        match (&self.account_id, &self.keypair) {
+            (AccountId::Id20(_), Keypair::Ecdsa(pair)) => {
+                Signature::Ethereum(pair.sign_prehashed(&keccak_256(signer_payload)).0)
+            }
+            (_, Keypair::Sr25519(pair)) => {
+                Signature::Multi(MultiSignature::Sr25519(pair.sign(signer_payload).0))
+            }
+            (_, Keypair::Ed25519(pair)) => {
+                Signature::Multi(MultiSignature::Ed25519(pair.sign(signer_payload).0))
+            }
+            (_, Keypair::Ecdsa(pair)) => {
+                Signature::Multi(MultiSignature::Ecdsa(pair.sign(signer_payload).0))
+            }
+        }
+    }
+}
+
+/// Arguments selecting the key signing extrinsics.
+#[derive(Clone, Debug, clap::Args)]
+pub struct SignerOpts {
+    /// Secret key URI for the account signing the extrinsics, prompted for on a
+    /// terminal if neither `--suri` nor `--account` is specified.
+    #[clap(name = "suri", long, short, conflicts_with = "account")]
+    suri: Option<String>,
+    /// Name of the account of the keystore signing the extrinsics.
+    #[clap(long)]
+    account: Option<String>,
+    /// Password for the secret key, or for the account of the keystore. The password of
+    /// an account is prompted for on a terminal if not specified.
+    #[clap(name = "password", long, short)]
+    password: Option<String>,
+    /// The signature scheme of the secret key URI.
+    #[clap(long, value_enum, default_value = "sr25519")]
+    scheme: CryptoScheme,
+    /// Directory of the keystore, `cargo-contract/keystore` in the data directory of the
+    /// user by default.
+    #[clap(long, value_parser, env = "CARGO_CONTRACT_KEYSTORE")]
+    keystore: Option<PathBuf>,
+}
+
+impl SignerOpts {
+    /// Returns the signer: the key of the secret key URI, or the account of the keystore.
+    pub fn signer(&self) -> Result<PairSigner> {
+        let password = self.password.as_deref();
+        let keypair = match (&self.suri, &self.account) {
+            (Some(suri), _) => Keypair::from_suri(self.scheme, suri, password)?,
+            (None, Some(account)) => {
+                let json = Keystore::open(self.keystore.as_deref())?.load(account)?;
+                let password = match password {
+                    Some(password) => password.to_string(),
+                    None => {
+                        prompt_secret(
+                            &format!("Password of account `{account}`"),
+                            "specify it with `--password`",
+                        )?
+                    }
+                };
+                json.decrypt(&password)?
+            }
+            (None, None) => {
+                let suri = prompt_secret(
+                    "Secret key URI",
+                    "specify either `--suri` or `--account`",
+                )?;
+                Keypair::from_suri(self.scheme, &suri, password)?
+            }
+        };
+        keypair.try_into()
+    }
+}
+
+/// Prompts for a secret on the terminal without echoing it.
+///
+/// Fails if there is no terminal to prompt on, the error then suggests `alternative`.
+pub fn prompt_secret(prompt: &str, alternative: &str) -> Result<String> {
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "{prompt} required but there is no terminal to prompt on, {alternative}"
+        )
+    }
+    Ok(rpassword::prompt_password(format!("{prompt}: "))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accounts_of_all_schemes_are_derived_from_suri() {
+        let sr25519 = Keypair::from_suri(CryptoScheme::Sr25519, "//Alice", None).unwrap();
+        let ed25519 = Keypair::from_suri(CryptoScheme::Ed25519, "//Alice", None).unwrap();
+        let ecdsa = Keypair::from_suri(CryptoScheme::Ecdsa, "//Alice", None).unwrap();
+
+        assert_eq!(
+            sr25519.account_id().unwrap().to_string(),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+        assert_eq!(
+            ed25519.account_id().unwrap().to_string(),
+            "5FA9nQDVg267DEd8m1ZypXLBnvN7SFxYwV7ndqSYGiN9TTpu"
+        );
+        // the account of an ECDSA key is the hash of its compressed public key
+        assert_eq!(
+            ecdsa.account_id().unwrap().to_string(),
+            "5C7C2Z5sWbytvHpuLTvzKunnnRwQxft1jiqrLD5rhucQ5S9X"
+        );
+    }
+
+    #[test]
+    fn h160_accounts_are_derived_from_ecdsa_keys() {
+        // given
+        let ecdsa = Keypair::from_suri(CryptoScheme::Ecdsa, "//Alice", None).unwrap();
+        let sr25519 = Keypair::from_suri(CryptoScheme::Sr25519, "//Alice", None).unwrap();
+
+        // when
+        let account = ecdsa.account_id_as(AccountFormat::H160).unwrap();
+
+        // then
+        // the Ethereum address of the public key
+        // 0x020a1091341fe5664bfa1782d5e04779689068c916b04cb365ec3153755684d9a1
+        assert_eq!(
+            account.to_string(),
+            "0xe04cc55ebee1cbce552f250e85c57b70b2e2625b"
+        );
+        assert!(sr25519.account_id_as(AccountFormat::H160).is_err());
+    }
+}
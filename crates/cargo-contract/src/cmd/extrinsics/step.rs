@@ -0,0 +1,399 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    call::{
+        self,
+        call_dry_run,
+        CallDryRunResult,
+        CallRequest,
+    },
+    contracts_pallet::ContractsCall,
+    instantiate::{
+        self,
+        instantiate_dry_run,
+        Code,
+        InstantiateDryRunResult,
+        InstantiateRequest,
+    },
+    upload::{
+        self,
+        upload_dry_run,
+        CodeUploadRequest,
+        UploadDryRunResult,
+    },
+    BalanceVariant,
+    Client,
+    ContractArtifacts,
+    ContractMessageTranscoder,
+    DefaultConfig,
+    ErrorVariant,
+    ExtrinsicOpts,
+    Origin,
+    Submission,
+    TokenMetadata,
+    WaitFor,
+};
+use crate::cmd::{
+    Balance,
+    CodeHash,
+};
+use anyhow::Result;
+use pallet_contracts_primitives::{
+    CodeUploadResult,
+    ContractExecResult,
+    ContractInstantiateResult,
+};
+use sp_weights::Weight;
+use std::{
+    path::{
+        Path,
+        PathBuf,
+    },
+    str::FromStr,
+};
+use subxt::Config;
+
+type AccountId = <DefaultConfig as Config>::AccountId;
+
+/// Loads the artifacts of a step from its `file` or `manifest_path`, relative to `dir`,
+/// or the artifacts of the command if the step specifies neither.
+pub fn step_artifacts(
+    opts: &ExtrinsicOpts,
+    dir: &Path,
+    file: Option<&PathBuf>,
+    manifest_path: Option<&PathBuf>,
+) -> Result<ContractArtifacts> {
+    if file.is_none() && manifest_path.is_none() {
+        return opts.contract_artifacts()
+    }
+    ContractArtifacts::from_manifest_or_file(
+        manifest_path.map(|path| dir.join(path)).as_ref(),
+        file.map(|path| dir.join(path)).as_ref(),
+        Some(opts.target),
+    )
+}
+
+/// Denominates the value transferred by a step, `0` if none is given.
+pub fn step_value(
+    value: Option<&str>,
+    token_metadata: &TokenMetadata,
+) -> Result<Balance> {
+    match value {
+        Some(value) => {
+            BalanceVariant::from_str(value)?.denominate_balance(token_metadata)
+        }
+        None => Ok(0),
+    }
+}
+
+pub fn default_constructor() -> String {
+    String::from("new")
+}
+
+/// Describes the call of a constructor or message with its arguments to the user.
+pub fn call_description(name: &str, args: &[String]) -> String {
+    if args.is_empty() {
+        name.to_string()
+    } else {
+        format!("{name} {}", args.join(" "))
+    }
+}
+
+/// A `contracts` extrinsic of a multi step command, encoded and ready to be dry run and
+/// submitted like the extrinsics of the `upload`, `instantiate` and `call` commands.
+pub enum StepCall {
+    Upload {
+        code: Vec<u8>,
+    },
+    Instantiate {
+        code: Code,
+        value: Balance,
+        data: Vec<u8>,
+        salt: Vec<u8>,
+    },
+    Call {
+        contract: AccountId,
+        message: String,
+        value: Balance,
+        data: Vec<u8>,
+    },
+}
+
+impl StepCall {
+    /// Dry runs the call on behalf of `origin` against the current state of the chain.
+    pub async fn dry_run(
+        &self,
+        url: &str,
+        origin: &AccountId,
+        storage_deposit_limit: Option<Balance>,
+    ) -> Result<StepDryRun> {
+        match self {
+            StepCall::Upload { code } => {
+                let request = CodeUploadRequest::new(
+                    origin.clone(),
+                    code.clone(),
+                    storage_deposit_limit,
+                );
+                Ok(StepDryRun::Upload(upload_dry_run(url, &request).await?))
+            }
+            StepCall::Instantiate {
+                code,
+                value,
+                data,
+                salt,
+            } => {
+                let request = InstantiateRequest {
+                    origin: origin.clone(),
+                    value: *value,
+                    gas_limit: None,
+                    storage_deposit_limit,
+                    code: code.clone(),
+                    data: data.clone(),
+                    salt: salt.clone(),
+                };
+                let result = instantiate_dry_run(url, &request).await?;
+                Ok(StepDryRun::Instantiate(result))
+            }
+            StepCall::Call {
+                contract,
+                message,
+                value,
+                data,
+            } => {
+                let request = CallRequest {
+                    origin: origin.clone(),
+                    dest: contract.clone(),
+                    value: *value,
+                    gas_limit: None,
+                    storage_deposit_limit,
+                    input_data: data.clone(),
+                };
+                let result = call_dry_run(url, &request, None).await?;
+                Ok(StepDryRun::Call {
+                    result,
+                    message: message.clone(),
+                })
+            }
+        }
+    }
+
+    /// Returns the gas limit of the call if it is known without a dry run: uploads do
+    /// not take one, and the calls whose `gas_limit` and `proof_size` are both given.
+    pub fn gas_limit(
+        &self,
+        gas_limit: Option<u64>,
+        proof_size: Option<u64>,
+    ) -> Option<Weight> {
+        match self {
+            StepCall::Upload { .. } => Some(Weight::zero()),
+            _ => {
+                gas_limit.zip(proof_size).map(|(ref_time, proof_size)| {
+                    Weight::from_parts(ref_time, proof_size)
+                })
+            }
+        }
+    }
+
+    /// Returns the call of `pallet-contracts`, e.g. to be wrapped into a
+    /// `utility.batch_all`.
+    pub fn contracts_call(
+        &self,
+        gas_limit: Weight,
+        storage_deposit_limit: Option<scale::Compact<Balance>>,
+    ) -> ContractsCall {
+        match self {
+            StepCall::Upload { code } => {
+                ContractsCall::upload_code(code.clone(), storage_deposit_limit)
+            }
+            StepCall::Instantiate {
+                code: Code::Upload(code),
+                value,
+                data,
+                salt,
+            } => {
+                ContractsCall::instantiate_with_code(
+                    *value,
+                    gas_limit,
+                    storage_deposit_limit,
+                    code.clone(),
+                    data.clone(),
+                    salt.clone(),
+                )
+            }
+            StepCall::Instantiate {
+                code: Code::Existing(code_hash),
+                value,
+                data,
+                salt,
+            } => {
+                ContractsCall::instantiate(
+                    *value,
+                    gas_limit,
+                    storage_deposit_limit,
+                    *code_hash,
+                    data.clone(),
+                    salt.clone(),
+                )
+            }
+            StepCall::Call {
+                contract,
+                value,
+                data,
+                ..
+            } => {
+                ContractsCall::call(
+                    contract.clone(),
+                    *value,
+                    gas_limit,
+                    storage_deposit_limit,
+                    data.clone(),
+                )
+            }
+        }
+    }
+
+    /// Submits the call in an extrinsic of its own from `origin`.
+    pub async fn submit(
+        &self,
+        gas_limit: Weight,
+        storage_deposit_limit: Option<scale::Compact<Balance>>,
+        client: &Client,
+        origin: &Origin,
+        wait_for: WaitFor,
+    ) -> Result<Submission, ErrorVariant> {
+        let call = self.contracts_call(gas_limit, storage_deposit_limit);
+        origin.submit(client, &call, wait_for).await
+    }
+}
+
+/// The result of the dry run of a [`StepCall`].
+pub enum StepDryRun {
+    Upload(CodeUploadResult<CodeHash, Balance>),
+    Instantiate(ContractInstantiateResult<AccountId, Balance>),
+    Call {
+        result: ContractExecResult<Balance>,
+        message: String,
+    },
+}
+
+impl StepDryRun {
+    /// Returns the gas limit of the call: the `gas_limit` and `proof_size` given, the
+    /// ones not given estimated by the dry run.
+    pub fn gas_limit(
+        &self,
+        gas_limit: Option<u64>,
+        proof_size: Option<u64>,
+        output_json: bool,
+        metadata: &subxt::Metadata,
+    ) -> Result<Weight> {
+        match self {
+            StepDryRun::Upload(_) => Ok(Weight::zero()),
+            StepDryRun::Instantiate(result) => {
+                super::dry_run_gas_limit(
+                    result,
+                    gas_limit,
+                    proof_size,
+                    output_json,
+                    metadata,
+                )
+            }
+            StepDryRun::Call { result, .. } => {
+                super::dry_run_gas_limit(
+                    result,
+                    gas_limit,
+                    proof_size,
+                    output_json,
+                    metadata,
+                )
+            }
+        }
+    }
+
+    /// Displays the result like the dry runs of the `upload`, `instantiate` and `call`
+    /// commands.
+    pub fn display(
+        &self,
+        transcoder: Option<&ContractMessageTranscoder>,
+        metadata: &subxt::Metadata,
+    ) -> Result<(), ErrorVariant> {
+        match self {
+            StepDryRun::Upload(result) => {
+                upload::display_dry_run_result(result, false, metadata)
+            }
+            StepDryRun::Instantiate(result) => {
+                instantiate::display_dry_run_result(result, false, |err| {
+                    ErrorVariant::from_dispatch_error(err, metadata)
+                })
+            }
+            StepDryRun::Call { result, message } => {
+                let transcoder =
+                    transcoder.expect("calls are encoded by their transcoder; qed");
+                call::display_dry_run_result(result, transcoder, message, false, |err| {
+                    ErrorVariant::from_dispatch_error(err, metadata)
+                })
+            }
+        }
+    }
+
+    /// Returns the result of a successful dry run like the `upload`, `instantiate` and
+    /// `call` commands output it in JSON format, or the error of a failed one.
+    pub fn output(
+        &self,
+        transcoder: Option<&ContractMessageTranscoder>,
+        metadata: &subxt::Metadata,
+    ) -> Result<Result<StepDryRunOutput, ErrorVariant>> {
+        let error = match self {
+            StepDryRun::Upload(Ok(result)) => {
+                return Ok(Ok(StepDryRunOutput::Upload(UploadDryRunResult::new(
+                    result,
+                ))))
+            }
+            StepDryRun::Instantiate(result) => {
+                match result.result {
+                    Ok(ref ret_val) => {
+                        let output = InstantiateDryRunResult::new(ret_val, result);
+                        return Ok(Ok(StepDryRunOutput::Instantiate(output)))
+                    }
+                    Err(ref err) => err,
+                }
+            }
+            StepDryRun::Call { result, message } => {
+                match result.result {
+                    Ok(ref ret_val) => {
+                        let transcoder = transcoder
+                            .expect("calls are encoded by their transcoder; qed");
+                        let output =
+                            CallDryRunResult::new(ret_val, result, transcoder, message)?;
+                        return Ok(Ok(StepDryRunOutput::Call(output)))
+                    }
+                    Err(ref err) => err,
+                }
+            }
+            StepDryRun::Upload(Err(err)) => err,
+        };
+        Ok(Err(ErrorVariant::from_dispatch_error(error, metadata)?))
+    }
+}
+
+/// The output of a successful dry run of a [`StepCall`].
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+pub enum StepDryRunOutput {
+    Upload(UploadDryRunResult),
+    Instantiate(InstantiateDryRunResult),
+    Call(CallDryRunResult),
+}
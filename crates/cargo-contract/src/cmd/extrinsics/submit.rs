@@ -0,0 +1,126 @@
+// Copyright 2018-2020 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    events::DisplayEvents,
+    offline::SignedExtrinsic,
+    submit_signed_extrinsic,
+    ContractArtifacts,
+    DefaultConfig,
+    ErrorVariant,
+    TokenMetadata,
+    WaitFor,
+};
+use crate::name_value_println;
+use anyhow::Result;
+use contract_build::{
+    Verbosity,
+    VerbosityFlags,
+};
+use std::path::PathBuf;
+use subxt::{
+    config::Hasher as _,
+    tx::SubmittableExtrinsic,
+    Config,
+    OnlineClient,
+};
+
+#[derive(Debug, clap::Args)]
+#[clap(
+    name = "submit",
+    about = "Submit an extrinsic signed offline with `cargo contract sign`"
+)]
+pub struct SubmitCommand {
+    /// Path to the signed extrinsic.
+    #[clap(value_parser)]
+    signed: PathBuf,
+    /// Path to a contract build artifact file decoding the events of the contract: a
+    /// `.contract` bundle or a `.json` metadata file.
+    #[clap(long, value_parser, conflicts_with = "manifest_path")]
+    artifact: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract decoding the events of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Websockets url of a substrate node.
+    #[clap(
+        name = "url",
+        long,
+        value_parser,
+        default_value = "ws://localhost:9944"
+    )]
+    url: url::Url,
+    #[clap(flatten)]
+    verbosity: VerbosityFlags,
+    /// The stage the submitted extrinsic has to reach before the result is reported.
+    #[clap(long, value_enum, default_value = "in-block")]
+    wait: WaitFor,
+    /// Export the submission output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+    /// Report the hash, block, index and fee of the extrinsic in the JSON output, which
+    /// is then an object holding the `extrinsic` and its `events` instead of the array
+    /// of the events.
+    #[clap(long, requires = "output_json")]
+    output_extrinsic: bool,
+}
+
+impl SubmitCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    pub fn run(&self) -> Result<(), ErrorVariant> {
+        let signed = SignedExtrinsic::load(&self.signed)?;
+        let verbosity = Verbosity::try_from(&self.verbosity)?;
+        // the contract events are only decoded if the contract is specified
+        let transcoder = if self.artifact.is_some() || self.manifest_path.is_some() {
+            let artifacts = ContractArtifacts::from_manifest_or_file(
+                self.manifest_path.as_ref(),
+                self.artifact.as_ref(),
+                None,
+            )?;
+            Some(artifacts.contract_transcoder()?)
+        } else {
+            None
+        };
+
+        async_std::task::block_on(async {
+            let client = OnlineClient::from_url(self.url.clone()).await?;
+            let extrinsic =
+                SubmittableExtrinsic::from_bytes(client.clone(), signed.extrinsic.0);
+            if !self.output_json {
+                let hash = <DefaultConfig as Config>::Hasher::hash(extrinsic.encoded());
+                name_value_println!("Account", signed.account.to_string());
+                name_value_println!("Hash", format!("{hash:?}"));
+            }
+            let submitted = submit_signed_extrinsic(&extrinsic, self.wait).await?;
+            let display_events = DisplayEvents::from_events(
+                &submitted,
+                transcoder.as_ref(),
+                &client.metadata(),
+            )?;
+
+            let output = if self.output_json {
+                display_events.to_json(self.output_extrinsic)?
+            } else {
+                let token_metadata = TokenMetadata::query(&client).await?;
+                display_events.display_events(verbosity, &token_metadata)?
+            };
+            println!("{output}");
+            Ok(())
+        })
+    }
+}
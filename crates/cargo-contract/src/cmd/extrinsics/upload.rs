@@ -17,31 +17,38 @@
 use super::{
     display_dry_run_result_warning,
     state_call,
-    submit_extrinsic,
     Client,
     DefaultConfig,
     ExtrinsicOpts,
-    PairSigner,
+    OfflineOpts,
+    Origin,
+    ProxyOpts,
+    Submission,
     TokenMetadata,
 };
 use crate::{
     cmd::{
         extrinsics::{
+            contracts_pallet::{
+                CodeStored,
+                ContractsCall,
+                ContractsEvent,
+            },
             events::DisplayEvents,
+            ContractCode,
             ErrorVariant,
-            WasmCode,
-        },
-        runtime_api::api::{
-            self,
-            runtime_types::pallet_contracts::wasm::Determinism,
         },
+        runtime_api::api::runtime_types::pallet_contracts::wasm::Determinism,
         Balance,
         CodeHash,
     },
     name_value_println,
 };
 use anyhow::Result;
-use pallet_contracts_primitives::CodeUploadResult;
+use pallet_contracts_primitives::{
+    CodeUploadResult,
+    CodeUploadReturnValue,
+};
 use scale::Encode;
 use std::fmt::Debug;
 use subxt::{
@@ -54,6 +61,10 @@ use subxt::{
 pub struct UploadCommand {
     #[clap(flatten)]
     extrinsic_opts: ExtrinsicOpts,
+    #[clap(flatten)]
+    offline_opts: OfflineOpts,
+    #[clap(flatten)]
+    proxy_opts: ProxyOpts,
     /// Export the call output in JSON format.
     #[clap(long, conflicts_with = "verbose")]
     output_json: bool,
@@ -66,7 +77,8 @@ impl UploadCommand {
 
     pub fn run(&self) -> Result<(), ErrorVariant> {
         let artifacts = self.extrinsic_opts.contract_artifacts()?;
-        let signer = super::pair_signer(self.extrinsic_opts.signer()?);
+        let signer = self.offline_opts.signer(&self.extrinsic_opts)?;
+        let origin = self.proxy_opts.origin(Origin::from(signer))?;
 
         let artifacts_path = artifacts.artifact_path().to_path_buf();
         let code = artifacts.code.ok_or_else(|| {
@@ -75,54 +87,19 @@ impl UploadCommand {
                 artifacts_path.display()
             )
         })?;
-        let code_hash = code.code_hash();
 
         async_std::task::block_on(async {
             let url = self.extrinsic_opts.url_to_string();
             let client = OnlineClient::from_url(url.clone()).await?;
 
             if !self.extrinsic_opts.execute {
-                match self.upload_code_rpc(code, &client, &signer).await? {
-                    Ok(result) => {
-                        let upload_result = UploadDryRunResult {
-                            result: String::from("Success!"),
-                            code_hash: format!("{:?}", result.code_hash),
-                            deposit: result.deposit,
-                        };
-                        if self.output_json {
-                            println!("{}", upload_result.to_json()?);
-                        } else {
-                            upload_result.print();
-                            display_dry_run_result_warning("upload");
-                        }
-                    }
-                    Err(err) => {
-                        let metadata = client.metadata();
-                        let err = ErrorVariant::from_dispatch_error(&err, &metadata)?;
-                        if self.output_json {
-                            return Err(err)
-                        } else {
-                            name_value_println!("Result", err);
-                        }
-                    }
-                }
-            } else if let Some(code_stored) =
-                self.upload_code(&client, code, &signer).await?
-            {
-                let upload_result = UploadResult {
-                    code_hash: format!("{:?}", code_stored.code_hash),
-                };
-                if self.output_json {
-                    println!("{}", upload_result.to_json()?);
-                } else {
-                    upload_result.print();
+                let result = self.upload_code_rpc(code, &client, &origin).await?;
+                display_dry_run_result(&result, self.output_json, &client.metadata())?;
+                if result.is_ok() && !self.output_json {
+                    display_dry_run_result_warning("upload");
                 }
             } else {
-                let code_hash = hex::encode(code_hash);
-                return Err(anyhow::anyhow!(
-                    "This contract has already been uploaded with code hash: 0x{code_hash}"
-                )
-                .into())
+                self.upload_code(&client, code, &origin).await?;
             }
             Ok(())
         })
@@ -130,9 +107,9 @@ impl UploadCommand {
 
     async fn upload_code_rpc(
         &self,
-        code: WasmCode,
+        code: ContractCode,
         client: &Client,
-        signer: &PairSigner,
+        origin: &Origin,
     ) -> Result<CodeUploadResult<CodeHash, Balance>> {
         let url = self.extrinsic_opts.url_to_string();
         let token_metadata = TokenMetadata::query(client).await?;
@@ -142,44 +119,64 @@ impl UploadCommand {
             .as_ref()
             .map(|bv| bv.denominate_balance(&token_metadata))
             .transpose()?;
-        let call_request = CodeUploadRequest {
-            origin: signer.account_id().clone(),
-            code: code.0,
+        let call_request = CodeUploadRequest::new(
+            origin.account_id().clone(),
+            code.code,
             storage_deposit_limit,
-            determinism: Determinism::Enforced,
-        };
-        state_call(&url, "ContractsApi_upload_code", call_request).await
+        );
+        upload_dry_run(&url, &call_request).await
     }
 
     async fn upload_code(
         &self,
         client: &Client,
-        code: WasmCode,
-        signer: &PairSigner,
-    ) -> Result<Option<api::contracts::events::CodeStored>, ErrorVariant> {
+        code: ContractCode,
+        origin: &Origin,
+    ) -> Result<(), ErrorVariant> {
+        let code_hash = code.code_hash();
         let token_metadata = TokenMetadata::query(client).await?;
         let storage_deposit_limit =
             self.extrinsic_opts.storage_deposit_limit(&token_metadata)?;
-        let call = crate::cmd::runtime_api::api::tx().contracts().upload_code(
-            code.0,
-            storage_deposit_limit,
-            Determinism::Enforced,
-        );
-
-        let result = submit_extrinsic(client, &call, signer).await?;
+        let call = ContractsCall::upload_code(code.code, storage_deposit_limit);
+        let Submission::Dispatched(submitted) = origin
+            .submit(client, &call, self.extrinsic_opts.wait_for())
+            .await?
+        else {
+            return Ok(())
+        };
         let display_events =
-            DisplayEvents::from_events(&result, None, &client.metadata())?;
+            DisplayEvents::from_events(&submitted, None, &client.metadata())?;
 
         let output = if self.output_json {
-            display_events.to_json()?
+            display_events.to_json(self.extrinsic_opts.output_extrinsic())?
         } else {
             let token_metadata = TokenMetadata::query(client).await?;
             display_events
                 .display_events(self.extrinsic_opts.verbosity()?, &token_metadata)?
         };
         println!("{output}");
-        let code_stored = result.find_first::<api::contracts::events::CodeStored>()?;
-        Ok(code_stored)
+
+        // the code hash is only known once the extrinsic is included
+        let Some(result) = submitted.events else {
+            return Ok(())
+        };
+        if let Some(code_stored) = CodeStored::find_first(&result)? {
+            let upload_result = UploadResult {
+                code_hash: format!("{:?}", code_stored.code_hash),
+            };
+            if self.output_json {
+                println!("{}", upload_result.to_json()?);
+            } else {
+                upload_result.print();
+            }
+            Ok(())
+        } else {
+            let code_hash = hex::encode(code_hash);
+            Err(anyhow::anyhow!(
+                "This contract has already been uploaded with code hash: 0x{code_hash}"
+            )
+            .into())
+        }
     }
 }
 
@@ -192,6 +189,56 @@ pub struct CodeUploadRequest {
     determinism: Determinism,
 }
 
+impl CodeUploadRequest {
+    pub fn new(
+        origin: <DefaultConfig as Config>::AccountId,
+        code: Vec<u8>,
+        storage_deposit_limit: Option<Balance>,
+    ) -> Self {
+        Self {
+            origin,
+            code,
+            storage_deposit_limit,
+            determinism: Determinism::Enforced,
+        }
+    }
+}
+
+/// Dry runs the upload of the code of `request`.
+pub async fn upload_dry_run(
+    url: &str,
+    request: &CodeUploadRequest,
+) -> Result<CodeUploadResult<CodeHash, Balance>> {
+    state_call(url, "ContractsApi_upload_code", request, None).await
+}
+
+/// Displays the result of an upload dry run.
+pub fn display_dry_run_result(
+    result: &CodeUploadResult<CodeHash, Balance>,
+    output_json: bool,
+    metadata: &subxt::Metadata,
+) -> Result<(), ErrorVariant> {
+    match result {
+        Ok(result) => {
+            let upload_result = UploadDryRunResult::new(result);
+            if output_json {
+                println!("{}", upload_result.to_json()?);
+            } else {
+                upload_result.print();
+            }
+        }
+        Err(err) => {
+            let err = ErrorVariant::from_dispatch_error(err, metadata)?;
+            if output_json {
+                return Err(err)
+            } else {
+                name_value_println!("Result", err);
+            }
+        }
+    }
+    Ok(())
+}
+
 #[derive(serde::Serialize)]
 pub struct UploadResult {
     code_hash: String,
@@ -215,6 +262,14 @@ impl UploadResult {
 }
 
 impl UploadDryRunResult {
+    pub fn new(result: &CodeUploadReturnValue<CodeHash, Balance>) -> Self {
+        Self {
+            result: String::from("Success!"),
+            code_hash: format!("{:?}", result.code_hash),
+            deposit: result.deposit,
+        }
+    }
+
     pub fn to_json(&self) -> Result<String> {
         Ok(serde_json::to_string_pretty(self)?)
     }
@@ -15,14 +15,18 @@
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
 use super::{
-    runtime_api::api::{self,},
+    block_hash,
+    BlockRef,
     Client,
     DefaultConfig,
 };
 use crate::{
     cmd::{
-        extrinsics::MAX_KEY_COL_WIDTH,
-        runtime_api::api::runtime_types::pallet_contracts::storage::ContractInfo,
+        chain::profile,
+        extrinsics::{
+            ContractInfo,
+            MAX_KEY_COL_WIDTH,
+        },
         Balance,
         CodeHash,
         ErrorVariant,
@@ -53,6 +57,10 @@ pub struct InfoCommand {
         default_value = "ws://localhost:9944"
     )]
     url: url::Url,
+    /// The hash or number of the block to fetch the contract information at, the best
+    /// block by default.
+    #[clap(long, value_name = "BLOCK")]
+    at: Option<BlockRef>,
     /// Export the instantiate output in JSON format.
     #[clap(name = "output-json", long)]
     output_json: bool,
@@ -60,6 +68,7 @@ pub struct InfoCommand {
 
 impl InfoCommand {
     pub fn run(&self) -> Result<(), ErrorVariant> {
+        profile().check_account(&self.contract, "--contract")?;
         tracing::debug!(
             "Getting contract information for AccountId {:?}",
             self.contract
@@ -73,7 +82,7 @@ impl InfoCommand {
 
             match info_result {
                 Some(info_result) => {
-                    let convert_trie_id = hex::encode(info_result.trie_id.0);
+                    let convert_trie_id = hex::encode(info_result.trie_id);
                     let info_to_json = InfoToJson {
                         trie_id: convert_trie_id,
                         code_hash: info_result.code_hash,
@@ -99,17 +108,8 @@ impl InfoCommand {
     }
 
     async fn fetch_contract_info(&self, client: &Client) -> Result<Option<ContractInfo>> {
-        let info_contract_call =
-            api::storage().contracts().contract_info_of(&self.contract);
-
-        let contract_info_of = client
-            .storage()
-            .at_latest()
-            .await?
-            .fetch(&info_contract_call)
-            .await?;
-
-        Ok(contract_info_of)
+        let block_hash = block_hash(client, self.at.as_ref()).await?;
+        ContractInfo::fetch(client, &self.contract, block_hash).await
     }
 }
 
@@ -14,36 +14,124 @@
 // You should have received a copy of the GNU General Public License
 // along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod account;
 pub mod build;
+pub mod chain;
 pub mod decode;
 pub mod encode;
 pub mod info;
 pub mod runtime_api;
+pub mod storage;
+pub mod verify;
 
 pub(crate) use self::{
+    account::AccountCommand,
     build::{
         BuildCommand,
         CheckCommand,
     },
     decode::DecodeCommand,
     info::InfoCommand,
+    storage::StorageCommand,
+    verify::VerifyCommand,
 };
 mod extrinsics;
 
 pub(crate) use self::extrinsics::{
+    BatchCommand,
     CallCommand,
+    ContractArtifacts,
+    DeployCommand,
     ErrorVariant,
     InstantiateCommand,
     RemoveCommand,
+    SignCommand,
+    SubmitCommand,
     UploadCommand,
 };
 
+use anyhow::{
+    anyhow,
+    Result,
+};
+use std::str::FromStr;
 use subxt::{
     Config,
     OnlineClient,
 };
 
-pub use subxt::PolkadotConfig as DefaultConfig;
+pub use chain::ChainConfig as DefaultConfig;
 type Client = OnlineClient<DefaultConfig>;
 type Balance = u128;
 type CodeHash = <DefaultConfig as Config>::Hash;
+type BlockHash = <DefaultConfig as Config>::Hash;
+
+/// A block given by its hash or number, e.g. to query the state of a past block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockRef {
+    Hash(BlockHash),
+    Number(u64),
+}
+
+impl FromStr for BlockRef {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        if input.starts_with("0x") {
+            let bytes = contract_build::util::decode_hex(input)?;
+            let hash: [u8; 32] = bytes
+                .try_into()
+                .map_err(|_| anyhow!("A block hash should be 32 bytes in length"))?;
+            Ok(Self::Hash(hash.into()))
+        } else {
+            let number = input.replace('_', "").parse().map_err(|_| {
+                anyhow!("Expected a block hash starting with `0x` or a block number")
+            })?;
+            Ok(Self::Number(number))
+        }
+    }
+}
+
+impl BlockRef {
+    /// Returns the hash of the block, block numbers are resolved via RPC.
+    pub async fn hash(&self, client: &Client) -> Result<BlockHash> {
+        match *self {
+            Self::Hash(hash) => Ok(hash),
+            Self::Number(number) => {
+                client
+                    .rpc()
+                    .block_hash(Some(number.into()))
+                    .await?
+                    .ok_or_else(|| anyhow!("Block #{number} was not found"))
+            }
+        }
+    }
+}
+
+/// Returns the hash of the block `at`, or of the best block if none is given.
+async fn block_hash(client: &Client, at: Option<&BlockRef>) -> Result<BlockHash> {
+    match at {
+        Some(at) => at.hash(client).await,
+        None => {
+            client
+                .rpc()
+                .block_hash(None)
+                .await?
+                .ok_or_else(|| anyhow!("Failed to fetch the hash of the best block"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_refs_are_parsed() {
+        let hash = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+        assert!(matches!(BlockRef::from_str(hash), Ok(BlockRef::Hash(_))));
+        assert_eq!(BlockRef::from_str("1_000").unwrap(), BlockRef::Number(1000));
+        assert!(BlockRef::from_str("0x1234").is_err());
+        assert!(BlockRef::from_str("latest").is_err());
+    }
+}
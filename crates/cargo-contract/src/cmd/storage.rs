@@ -0,0 +1,694 @@
+// Copyright 2018-2023 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::{
+    block_hash,
+    BlockRef,
+    Client,
+    DefaultConfig,
+};
+use crate::cmd::{
+    chain::profile,
+    extrinsics::ContractInfo,
+    ContractArtifacts,
+    ErrorVariant,
+};
+use anyhow::{
+    anyhow,
+    Result,
+};
+use colored::Colorize as _;
+use contract_transcode::{
+    ContractMessageTranscoder,
+    Value,
+};
+use ink_metadata::layout::{
+    Layout,
+    LayoutKey,
+};
+use scale::{
+    Encode,
+    Input,
+};
+use scale_info::form::PortableForm;
+use sp_core::{
+    hashing::blake2_128,
+    Bytes,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::PathBuf,
+};
+use subxt::{
+    rpc::rpc_params,
+    Config,
+    OnlineClient,
+};
+
+/// The prefix of the storage keys of child tries of the default type.
+const CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:default:";
+/// The number of storage keys requested at once.
+const KEYS_PAGE_SIZE: u32 = 1000;
+
+#[derive(Debug, clap::Args)]
+#[clap(name = "storage", about = "Display the storage of a contract")]
+pub struct StorageCommand {
+    /// The address of the contract to display the storage of.
+    #[clap(name = "contract", long, env = "CONTRACT")]
+    contract: <DefaultConfig as Config>::AccountId,
+    /// Path to a contract build artifact file: a `.contract` bundle or a `.json`
+    /// metadata file. The storage is decoded using the storage layout of its metadata.
+    #[clap(value_parser, conflicts_with = "manifest_path")]
+    file: Option<PathBuf>,
+    /// Path to the `Cargo.toml` of the contract.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// Websockets url of a substrate node.
+    #[clap(
+        name = "url",
+        long,
+        value_parser,
+        default_value = "ws://localhost:9944"
+    )]
+    url: url::Url,
+    /// The hash or number of the block to fetch the storage at, the best block by
+    /// default.
+    #[clap(long, value_name = "BLOCK")]
+    at: Option<BlockRef>,
+    /// Export the storage in JSON format.
+    #[clap(name = "output-json", long)]
+    output_json: bool,
+}
+
+impl StorageCommand {
+    pub fn run(&self) -> Result<(), ErrorVariant> {
+        profile().check_account(&self.contract, "--contract")?;
+        let artifacts = ContractArtifacts::from_manifest_or_file(
+            self.manifest_path.as_ref(),
+            self.file.as_ref(),
+            None,
+        )?;
+        let transcoder = artifacts.contract_transcoder()?;
+
+        async_std::task::block_on(async {
+            let client =
+                OnlineClient::<DefaultConfig>::from_url(self.url.clone()).await?;
+            let cells = self.fetch_contract_storage(&client).await?;
+            let storage = ContractStorage::decode(cells, &transcoder);
+            if self.output_json {
+                println!("{}", storage.to_json()?);
+            } else {
+                print!("{}", storage.display());
+            }
+            Ok(())
+        })
+    }
+
+    /// Fetches all cells of the child trie of the contract, by their unhashed key if it
+    /// could be recovered.
+    async fn fetch_contract_storage(
+        &self,
+        client: &Client,
+    ) -> Result<BTreeMap<Vec<u8>, Vec<u8>>> {
+        let block_hash = block_hash(client, self.at.as_ref()).await?;
+        let contract_info = ContractInfo::fetch(client, &self.contract, block_hash)
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "No contract information was found for account id {}",
+                    self.contract
+                )
+            })?;
+        let child_storage_key =
+            Bytes([CHILD_STORAGE_KEY_PREFIX, &contract_info.trie_id].concat());
+
+        let mut cells = BTreeMap::new();
+        let mut start_key: Option<Bytes> = None;
+        loop {
+            let keys: Vec<Bytes> = client
+                .rpc()
+                .request(
+                    "childstate_getKeysPaged",
+                    rpc_params![
+                        &child_storage_key,
+                        Option::<Bytes>::None,
+                        KEYS_PAGE_SIZE,
+                        &start_key,
+                        block_hash
+                    ],
+                )
+                .await?;
+            let values: Vec<Option<Bytes>> = client
+                .rpc()
+                .request(
+                    "childstate_getStorageEntries",
+                    rpc_params![&child_storage_key, &keys, block_hash],
+                )
+                .await?;
+            let is_last_page = keys.len() < KEYS_PAGE_SIZE as usize;
+            start_key = keys.last().cloned();
+            for (key, value) in keys.into_iter().zip(values) {
+                if let Some(value) = value {
+                    cells.insert(unhashed_key(key.0), value.0);
+                }
+            }
+            if is_last_page {
+                break
+            }
+        }
+        Ok(cells)
+    }
+}
+
+/// Returns the key a contract stored a cell under, `pallet-contracts` hashes it with
+/// `Blake2_128Concat`. The key is returned as is if it is not hashed this way.
+fn unhashed_key(key: Vec<u8>) -> Vec<u8> {
+    if key.len() < 16 {
+        return key
+    }
+    let (hash, unhashed) = key.split_at(16);
+    if blake2_128(unhashed) == hash {
+        unhashed.to_vec()
+    } else {
+        key
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// The storage of a contract, decoded using the storage layout of its metadata.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct ContractStorage {
+    /// The root cell of the contract storage.
+    root: StorageCell,
+    /// The cells which do not match the storage layout.
+    raw: Vec<RawCell>,
+}
+
+/// A node of the decoded storage layout.
+#[derive(Debug, PartialEq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageCell {
+    /// A value encoded in the cell of its parent.
+    Value(Value),
+    /// A struct with named fields.
+    Struct {
+        name: String,
+        fields: Vec<StorageField>,
+    },
+    /// The variant of an enum which is set.
+    Enum {
+        name: String,
+        variant: String,
+        fields: Vec<StorageField>,
+    },
+    /// A fixed size array.
+    Array(Vec<StorageCell>),
+    /// A cell stored under a key of its own: the root of the contract storage, a `Lazy`
+    /// or a `Mapping`.
+    Root {
+        key: String,
+        /// The value stored under the key, `None` if no value matching the layout is
+        /// stored.
+        value: Option<Box<StorageCell>>,
+        /// The entries of a `Mapping`, stored under the key followed by the encoded
+        /// mapping key.
+        entries: Vec<MappingEntry>,
+    },
+}
+
+/// A named field of a struct or enum variant.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct StorageField {
+    name: String,
+    value: StorageCell,
+}
+
+/// An entry of a `Mapping`.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct MappingEntry {
+    /// The SCALE encoded key of the entry, its type is not part of the storage layout.
+    key: String,
+    value: StorageCell,
+}
+
+/// A cell which does not match the storage layout.
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct RawCell {
+    key: String,
+    value: String,
+}
+
+impl ContractStorage {
+    /// Decodes the `cells` of a contract, by their unhashed key, using the storage layout
+    /// of the metadata of the `transcoder`.
+    pub fn decode(
+        cells: BTreeMap<Vec<u8>, Vec<u8>>,
+        transcoder: &ContractMessageTranscoder,
+    ) -> Self {
+        let mut decoder = StorageDecoder { transcoder, cells };
+        let root = match transcoder.metadata().layout() {
+            Layout::Root(root) => decoder.decode_root(root.root_key(), root.layout()),
+            layout => decoder.decode_root(&LayoutKey::new(0u32), layout),
+        };
+        let raw = decoder
+            .cells
+            .into_iter()
+            .map(|(key, value)| {
+                RawCell {
+                    key: to_hex(&key),
+                    value: to_hex(&value),
+                }
+            })
+            .collect();
+        Self { root, raw }
+    }
+
+    /// Returns the storage in JSON format.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Returns the storage as a tree in a human readable format.
+    pub fn display(&self) -> String {
+        let mut out = String::new();
+        display_cell(&mut out, 0, None, &self.root);
+        if !self.raw.is_empty() {
+            let _ = writeln!(
+                out,
+                "{}",
+                "Cells not matching the storage layout"
+                    .bright_purple()
+                    .bold()
+            );
+            for cell in &self.raw {
+                let _ = writeln!(out, "  {}: {}", cell.key.bright_white(), cell.value);
+            }
+        }
+        out
+    }
+}
+
+/// Writes the `cell` named `name` at the `indent` to `out`.
+fn display_cell(out: &mut String, indent: usize, name: Option<&str>, cell: &StorageCell) {
+    let label = |default: &str| name.unwrap_or(default).bright_white();
+    match cell {
+        StorageCell::Value(value) => {
+            let _ = writeln!(out, "{:indent$}{}: {value}", "", label(""));
+        }
+        StorageCell::Struct {
+            name: struct_name,
+            fields,
+        } => {
+            let _ = writeln!(out, "{:indent$}{}", "", label(struct_name));
+            for field in fields {
+                display_cell(out, indent + 2, Some(&field.name), &field.value);
+            }
+        }
+        StorageCell::Enum {
+            name: enum_name,
+            variant,
+            fields,
+        } => {
+            let _ = writeln!(out, "{:indent$}{}: {variant}", "", label(enum_name));
+            for field in fields {
+                display_cell(out, indent + 2, Some(&field.name), &field.value);
+            }
+        }
+        StorageCell::Array(elements) => {
+            let _ = writeln!(out, "{:indent$}{}", "", label(""));
+            for (index, element) in elements.iter().enumerate() {
+                display_cell(out, indent + 2, Some(&format!("[{index}]")), element);
+            }
+        }
+        StorageCell::Root {
+            key,
+            value,
+            entries,
+        } => {
+            let default = match value.as_deref() {
+                Some(StorageCell::Struct { name, .. }) => name.as_str(),
+                _ => "root",
+            };
+            let name = format!("{} [{key}]", name.unwrap_or(default));
+            if let Some(value) = value {
+                display_cell(out, indent, Some(&name), value);
+            }
+            if !entries.is_empty() {
+                let _ = writeln!(out, "{:indent$}{}", "", name.bright_white());
+                for entry in entries {
+                    display_cell(out, indent + 2, Some(&entry.key), &entry.value);
+                }
+            }
+            if value.is_none() && entries.is_empty() {
+                let _ = writeln!(out, "{:indent$}{}: <empty>", "", name.bright_white());
+            }
+        }
+    }
+}
+
+/// Decodes the cells of a contract, removing all cells which match the layout.
+struct StorageDecoder<'a> {
+    transcoder: &'a ContractMessageTranscoder,
+    cells: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl StorageDecoder<'_> {
+    /// Decodes the cell stored under the `root_key`, as well as the `Mapping` entries
+    /// stored under keys prefixed with it.
+    ///
+    /// The layout of a `Mapping` is the same as the one of a `Lazy`, but only a `Lazy`
+    /// and the storage of the contract itself are stored under the root key. Longer keys
+    /// of these roots are therefore not decoded as entries but kept raw.
+    fn decode_root(
+        &mut self,
+        root_key: &LayoutKey,
+        layout: &Layout<PortableForm>,
+    ) -> StorageCell {
+        let key = root_key.key().encode();
+        let value = self.decode_cell(&key, layout).map(Box::new);
+        if value.is_some() || self.cells.contains_key(&key) {
+            return StorageCell::Root {
+                key: to_hex(&key),
+                value,
+                entries: Vec::new(),
+            }
+        }
+        let entry_keys = self
+            .cells
+            .keys()
+            .filter(|entry_key| {
+                entry_key.len() > key.len() && entry_key.starts_with(&key)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let entries = entry_keys
+            .into_iter()
+            .filter_map(|entry_key| {
+                let value = self.decode_cell(&entry_key, layout)?;
+                Some(MappingEntry {
+                    key: to_hex(&entry_key[key.len()..]),
+                    value,
+                })
+            })
+            .collect();
+        StorageCell::Root {
+            key: to_hex(&key),
+            value,
+            entries,
+        }
+    }
+
+    /// Decodes the cell stored under the `key`, which is only removed if it matches the
+    /// `layout` exactly.
+    fn decode_cell(
+        &mut self,
+        key: &[u8],
+        layout: &Layout<PortableForm>,
+    ) -> Option<StorageCell> {
+        let value = self.cells.remove(key)?;
+        let input = &mut &value[..];
+        match self.decode_packed(layout, input) {
+            Ok(cell) if input.is_empty() => Some(cell),
+            res => {
+                tracing::debug!(
+                    "The cell {} does not match the storage layout: {:?}",
+                    to_hex(key),
+                    res.err()
+                );
+                self.cells.insert(key.to_vec(), value);
+                None
+            }
+        }
+    }
+
+    /// Decodes the `layout` from the `input`, the cell of a parent its value is packed
+    /// into.
+    fn decode_packed(
+        &mut self,
+        layout: &Layout<PortableForm>,
+        input: &mut &[u8],
+    ) -> Result<StorageCell> {
+        match layout {
+            Layout::Leaf(leaf) => {
+                let value = self.transcoder.decode(leaf.ty().id, input)?;
+                Ok(StorageCell::Value(value))
+            }
+            Layout::Root(root) => Ok(self.decode_root(root.root_key(), root.layout())),
+            Layout::Struct(layout) => {
+                Ok(StorageCell::Struct {
+                    name: layout.name().to_string(),
+                    fields: self.decode_fields(layout.fields(), input)?,
+                })
+            }
+            Layout::Enum(layout) => {
+                let discriminant = input.read_byte()?;
+                let variant = layout
+                    .variants()
+                    .iter()
+                    .find(|(d, _)| d.value() == discriminant as usize)
+                    .map(|(_, variant)| variant)
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "No variant of {} with the discriminant {discriminant}",
+                            layout.name()
+                        )
+                    })?;
+                Ok(StorageCell::Enum {
+                    name: layout.name().to_string(),
+                    variant: variant.name().to_string(),
+                    fields: self.decode_fields(variant.fields(), input)?,
+                })
+            }
+            Layout::Array(layout) => {
+                let elements = (0..layout.len())
+                    .map(|_| self.decode_packed(layout.layout(), input))
+                    .collect::<Result<_>>()?;
+                Ok(StorageCell::Array(elements))
+            }
+            Layout::Hash(_) => anyhow::bail!("Hash layouts are not supported"),
+        }
+    }
+
+    fn decode_fields(
+        &mut self,
+        fields: &[ink_metadata::layout::FieldLayout<PortableForm>],
+        input: &mut &[u8],
+    ) -> Result<Vec<StorageField>> {
+        fields
+            .iter()
+            .map(|field| {
+                Ok(StorageField {
+                    name: field.name().to_string(),
+                    value: self.decode_packed(field.layout(), input)?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The metadata of a contract storing a `u32` and a `bool` in its root, a `Lazy<u32>`
+    /// and a `Mapping<AccountId, u128>`.
+    const METADATA: &str = r#"{
+        "version": "4",
+        "types": [
+            {"id": 0, "type": {"def": {"primitive": "u32"}}},
+            {"id": 1, "type": {"def": {"primitive": "bool"}}},
+            {"id": 2, "type": {"def": {"primitive": "u128"}}}
+        ],
+        "storage": {"root": {"root_key": "0x00000000", "layout": {"struct": {
+            "name": "Counter",
+            "fields": [
+                {"name": "count", "layout": {"leaf": {"key": "0x00000000", "ty": 0}}},
+                {"name": "enabled", "layout": {"leaf": {"key": "0x00000000", "ty": 1}}},
+                {"name": "limit", "layout": {"root": {
+                    "root_key": "0x00000001",
+                    "layout": {"leaf": {"key": "0x00000001", "ty": 0}}
+                }}},
+                {"name": "balances", "layout": {"root": {
+                    "root_key": "0x00000002",
+                    "layout": {"leaf": {"key": "0x00000002", "ty": 2}}
+                }}}
+            ]
+        }}}},
+        "spec": {
+            "constructors": [],
+            "docs": [],
+            "environment": {
+                "accountId": {"displayName": ["AccountId"], "type": 0},
+                "balance": {"displayName": ["Balance"], "type": 2},
+                "blockNumber": {"displayName": ["BlockNumber"], "type": 0},
+                "chainExtension": {"displayName": ["ChainExtension"], "type": 0},
+                "hash": {"displayName": ["Hash"], "type": 0},
+                "maxEventTopics": 4,
+                "timestamp": {"displayName": ["Timestamp"], "type": 0}
+            },
+            "events": [],
+            "lang_error": {"displayName": ["LangError"], "type": 0},
+            "messages": []
+        }
+    }"#;
+
+    fn transcoder() -> ContractMessageTranscoder {
+        ContractMessageTranscoder::new(serde_json::from_str(METADATA).unwrap())
+    }
+
+    fn root(
+        key: &str,
+        value: Option<StorageCell>,
+        entries: Vec<MappingEntry>,
+    ) -> StorageCell {
+        StorageCell::Root {
+            key: key.to_string(),
+            value: value.map(Box::new),
+            entries,
+        }
+    }
+
+    #[test]
+    fn storage_is_decoded_using_the_layout() {
+        // given
+        let cells = BTreeMap::from([
+            (0u32.encode(), (7u32, true).encode()),
+            (1u32.encode(), 100u32.encode()),
+            ((2u32, [1u8; 2]).encode(), 5u128.encode()),
+            ((2u32, [2u8; 2]).encode(), 6u128.encode()),
+            (vec![9], vec![1, 2, 3]),
+        ]);
+
+        // when
+        let storage = ContractStorage::decode(cells, &transcoder());
+
+        // then
+        let field = |name: &str, value| {
+            StorageField {
+                name: name.to_string(),
+                value,
+            }
+        };
+        let entry = |key: &str, value| {
+            MappingEntry {
+                key: key.to_string(),
+                value: StorageCell::Value(Value::UInt(value)),
+            }
+        };
+        let expected_root = root(
+            "0x00000000",
+            Some(StorageCell::Struct {
+                name: "Counter".to_string(),
+                fields: vec![
+                    field("count", StorageCell::Value(Value::UInt(7))),
+                    field("enabled", StorageCell::Value(Value::Bool(true))),
+                    field(
+                        "limit",
+                        root(
+                            "0x01000000",
+                            Some(StorageCell::Value(Value::UInt(100))),
+                            vec![],
+                        ),
+                    ),
+                    field(
+                        "balances",
+                        root(
+                            "0x02000000",
+                            None,
+                            vec![entry("0x0101", 5), entry("0x0202", 6)],
+                        ),
+                    ),
+                ],
+            }),
+            vec![],
+        );
+        assert_eq!(storage.root, expected_root);
+        assert_eq!(
+            storage.raw,
+            vec![RawCell {
+                key: "0x09".to_string(),
+                value: "0x010203".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn cells_not_matching_the_layout_are_kept_raw() {
+        // given
+        let cells = BTreeMap::from([
+            // the value is missing the `bool` of the root struct
+            (0u32.encode(), 7u32.encode()),
+            (1u32.encode(), vec![1]),
+        ]);
+
+        // when
+        let storage = ContractStorage::decode(cells, &transcoder());
+
+        // then
+        let StorageCell::Root { value, .. } = storage.root else {
+            panic!("the storage root is a root cell")
+        };
+        assert!(value.is_none());
+        assert_eq!(
+            storage.raw,
+            vec![
+                RawCell {
+                    key: "0x00000000".to_string(),
+                    value: "0x07000000".to_string(),
+                },
+                RawCell {
+                    key: "0x01000000".to_string(),
+                    value: "0x01".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn longer_keys_of_roots_storing_a_value_are_kept_raw() {
+        // given
+        let cells = BTreeMap::from([
+            (0u32.encode(), (7u32, true).encode()),
+            (1u32.encode(), 100u32.encode()),
+            // the `limit` is a `Lazy`, it has no entries
+            ((1u32, 3u32).encode(), 200u32.encode()),
+        ]);
+
+        // when
+        let storage = ContractStorage::decode(cells, &transcoder());
+
+        // then
+        assert_eq!(
+            storage.raw,
+            vec![RawCell {
+                key: "0x0100000003000000".to_string(),
+                value: "0xc8000000".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn blake2_128_concat_keys_are_unhashed() {
+        let key = 1u32.encode();
+        let hashed = [&blake2_128(&key)[..], &key].concat();
+        assert_eq!(unhashed_key(hashed), key);
+        assert_eq!(unhashed_key(vec![0; 20]), vec![0; 20]);
+    }
+}
@@ -0,0 +1,317 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{
+    Context,
+    Result,
+};
+use colored::Colorize;
+use contract_build::{
+    BuildArtifacts,
+    BuildInfo,
+    ExecuteArgs,
+    ImageVariant,
+    ManifestPath,
+    Verbosity,
+    VerbosityFlags,
+};
+use contract_metadata::ContractMetadata;
+use std::{
+    fmt,
+    path::PathBuf,
+};
+
+/// Checks if a contract in the given workspace matches that of a reference contract.
+#[derive(Debug, clap::Args)]
+#[clap(name = "verify")]
+pub struct VerifyCommand {
+    /// Path to the `Cargo.toml` of the contract to verify.
+    #[clap(long, value_parser)]
+    manifest_path: Option<PathBuf>,
+    /// The reference contract bundle (`.contract`) or metadata (`.json`) that the
+    /// workspace will be checked against.
+    #[clap(long)]
+    contract: PathBuf,
+    #[clap(flatten)]
+    verbosity: VerbosityFlags,
+    /// Export the verification output in JSON format.
+    #[clap(long, conflicts_with = "verbose")]
+    output_json: bool,
+}
+
+impl VerifyCommand {
+    pub fn is_json(&self) -> bool {
+        self.output_json
+    }
+
+    pub fn run(&self) -> Result<VerificationResult> {
+        let manifest_path = ManifestPath::try_from(self.manifest_path.as_ref())?;
+        let mut verbosity: Verbosity = TryFrom::try_from(&self.verbosity)?;
+        if self.output_json {
+            verbosity = Verbosity::Quiet;
+        }
+
+        let metadata = ContractMetadata::load(&self.contract)?;
+        let build_info = read_build_info(&metadata).with_context(|| {
+            format!(
+                "The build information of the reference contract {} could not be read",
+                self.contract.display()
+            )
+        })?;
+
+        // The image digest is only recorded for verifiable builds, in that case we
+        // rebuild inside of the very same image.
+        let (verifiable, image) = match build_info.image {
+            Some(ref digest) => (true, ImageVariant::Custom(digest.clone())),
+            None => (false, ImageVariant::Default),
+        };
+
+        let args = ExecuteArgs {
+            manifest_path,
+            verbosity,
+            build_mode: build_info.build_mode,
+            build_artifact: BuildArtifacts::All,
+            optimization_passes: Some(build_info.wasm_opt_settings.optimization_passes),
+            keep_debug_symbols: build_info.wasm_opt_settings.keep_debug_symbols,
+            target: build_info.target,
+            verifiable,
+            image,
+            ..Default::default()
+        };
+        let build_result = contract_build::execute(args)?;
+        let built_contract = build_result
+            .dest_wasm
+            .context("The build did not produce any contract code")?;
+        let built_metadata = build_result
+            .metadata_result
+            .context("The build did not produce any contract metadata")?;
+        let built = ContractMetadata::load(built_metadata.dest_metadata)?;
+
+        let is_verified = built.source.hash == metadata.source.hash;
+        // The build parameters are only of interest if they could explain why the
+        // code hashes differ.
+        let mismatches = if is_verified {
+            Vec::new()
+        } else {
+            build_info_mismatches(&build_info, &read_build_info(&built)?)
+        };
+
+        Ok(VerificationResult {
+            is_verified,
+            reference_contract: self.contract.clone(),
+            reference_code_hash: format!("0x{}", hex::encode(metadata.source.hash.0)),
+            built_contract,
+            built_code_hash: format!("0x{}", hex::encode(built.source.hash.0)),
+            mismatches,
+        })
+    }
+}
+
+/// Returns the build information recorded in the metadata of a contract.
+fn read_build_info(metadata: &ContractMetadata) -> Result<BuildInfo> {
+    let build_info = metadata
+        .source
+        .build_info
+        .clone()
+        .context("The contract does not contain any build information")?;
+    serde_json::from_value(serde_json::Value::Object(build_info))
+        .context("The build information could not be parsed")
+}
+
+/// A recorded build parameter which differs from the one used for the rebuild.
+#[derive(Debug, Eq, PartialEq, serde::Serialize)]
+pub struct Mismatch {
+    /// The name of the parameter.
+    pub parameter: &'static str,
+    /// The value recorded in the reference contract.
+    pub expected: String,
+    /// The value used when rebuilding the contract.
+    pub found: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The reference contract was built with {} `{}`, but `{}` was used for the \
+            rebuild.",
+            self.parameter, self.expected, self.found
+        )
+    }
+}
+
+/// Returns all parameters recorded for the reference contract which differ from the
+/// ones recorded by the rebuild.
+///
+/// The version of the `wasm-opt` library is tied to the `cargo-contract` version, it
+/// is covered by comparing the latter.
+fn build_info_mismatches(recorded: &BuildInfo, rebuilt: &BuildInfo) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    let mut compare = |parameter, expected: String, found: String| {
+        if expected != found {
+            mismatches.push(Mismatch {
+                parameter,
+                expected,
+                found,
+            })
+        }
+    };
+
+    compare(
+        "Rust toolchain",
+        recorded.rust_toolchain.clone(),
+        rebuilt.rust_toolchain.clone(),
+    );
+    compare(
+        "cargo-contract version",
+        recorded.cargo_contract_version.to_string(),
+        rebuilt.cargo_contract_version.to_string(),
+    );
+    compare(
+        "build mode",
+        recorded.build_mode.to_string(),
+        rebuilt.build_mode.to_string(),
+    );
+    compare(
+        "wasm-opt optimization passes",
+        recorded.wasm_opt_settings.optimization_passes.to_string(),
+        rebuilt.wasm_opt_settings.optimization_passes.to_string(),
+    );
+    compare(
+        "wasm-opt debug symbols",
+        recorded.wasm_opt_settings.keep_debug_symbols.to_string(),
+        rebuilt.wasm_opt_settings.keep_debug_symbols.to_string(),
+    );
+    compare(
+        "target",
+        recorded.target.llvm_target().to_string(),
+        rebuilt.target.llvm_target().to_string(),
+    );
+    let image =
+        |image: &Option<String>| image.clone().unwrap_or_else(|| "none".to_string());
+    compare("image", image(&recorded.image), image(&rebuilt.image));
+    mismatches
+}
+
+/// The result of verifying a contract.
+#[derive(serde::Serialize)]
+pub struct VerificationResult {
+    pub is_verified: bool,
+    pub reference_contract: PathBuf,
+    pub reference_code_hash: String,
+    pub built_contract: PathBuf,
+    pub built_code_hash: String,
+    /// Recorded build parameters which differ from the local build environment.
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl VerificationResult {
+    /// Display the result in a human readable format.
+    pub fn display(&self) -> String {
+        if self.is_verified {
+            return format!(
+                "\n{} {} against reference contract {}",
+                "Successfully verified contract".bright_green().bold(),
+                self.built_contract.display().to_string().bold(),
+                self.reference_contract.display().to_string().bold(),
+            )
+        }
+        let mut out = format!(
+            "\n{} the code hash {} of the reference contract does not match the code \
+            hash {} of the built contract.\n",
+            "Failed to verify contract:".bright_red().bold(),
+            self.reference_code_hash.bold(),
+            self.built_code_hash.bold(),
+        );
+        if self.mismatches.is_empty() {
+            out.push_str(
+                "All recorded build parameters match the local environment, the sources \
+                most likely differ from the ones the reference contract was built from.",
+            );
+        } else {
+            out.push_str("The following recorded build parameters differ:\n");
+            for mismatch in &self.mismatches {
+                out.push_str(&format!("  - {mismatch}\n"));
+            }
+        }
+        out
+    }
+
+    /// Display the result in a pretty formatted JSON string.
+    pub fn serialize_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use contract_build::{
+        BuildMode,
+        OptimizationPasses,
+        WasmOptSettings,
+    };
+
+    fn build_info(rust_toolchain: &str, cargo_contract_version: &str) -> BuildInfo {
+        BuildInfo {
+            rust_toolchain: rust_toolchain.to_string(),
+            cargo_contract_version: cargo_contract_version.parse().unwrap(),
+            build_mode: BuildMode::Release,
+            wasm_opt_settings: WasmOptSettings {
+                optimization_passes: OptimizationPasses::Z,
+                keep_debug_symbols: false,
+            },
+            image: None,
+            target: Default::default(),
+        }
+    }
+
+    #[test]
+    fn reports_mismatching_build_parameters() {
+        let recorded = build_info("stable-x86_64-unknown-linux-gnu", "3.0.1");
+        let rebuilt = build_info("nightly-x86_64-unknown-linux-gnu", "3.1.0");
+
+        let mismatches = build_info_mismatches(&recorded, &rebuilt);
+
+        assert_eq!(
+            mismatches,
+            vec![
+                Mismatch {
+                    parameter: "Rust toolchain",
+                    expected: "stable-x86_64-unknown-linux-gnu".to_string(),
+                    found: "nightly-x86_64-unknown-linux-gnu".to_string(),
+                },
+                Mismatch {
+                    parameter: "cargo-contract version",
+                    expected: "3.0.1".to_string(),
+                    found: "3.1.0".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn identical_build_parameters_are_not_reported() {
+        let recorded = BuildInfo {
+            image: Some("paritytech/contracts-verifiable@sha256:1234".to_string()),
+            ..build_info("stable-x86_64-unknown-linux-gnu", "3.0.1")
+        };
+
+        let mismatches = build_info_mismatches(&recorded, &recorded.clone());
+
+        assert!(mismatches.is_empty());
+    }
+}
@@ -19,17 +19,27 @@
 mod cmd;
 
 use self::cmd::{
+    AccountCommand,
+    BatchCommand,
     BuildCommand,
     CallCommand,
     CheckCommand,
     DecodeCommand,
+    DeployCommand,
     ErrorVariant,
     InfoCommand,
     InstantiateCommand,
     RemoveCommand,
+    SignCommand,
+    StorageCommand,
+    SubmitCommand,
     UploadCommand,
+    VerifyCommand,
+};
+use cmd::{
+    chain::ChainProfile,
+    encode::EncodeCommand,
 };
-use cmd::encode::EncodeCommand;
 use contract_build::{
     name_value_println,
     util::DEFAULT_KEY_COL_WIDTH,
@@ -37,8 +47,10 @@ use contract_build::{
 };
 use std::{
     fmt::Debug,
-    path::PathBuf,
-    str::FromStr,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
 use anyhow::{
@@ -84,17 +96,15 @@ pub(crate) enum Opts {
 pub(crate) struct ContractArgs {
     #[clap(subcommand)]
     cmd: Command,
-}
-
-#[derive(Debug, Default, Clone, PartialEq, Eq)]
-pub(crate) struct HexData(pub Vec<u8>);
-
-impl FromStr for HexData {
-    type Err = hex::FromHexError;
-
-    fn from_str(input: &str) -> std::result::Result<Self, Self::Err> {
-        hex::decode(input).map(HexData)
-    }
+    /// Path to a TOML profile describing the accounts, addresses and signed extensions
+    /// of the chain, for chains which are not Polkadot like.
+    #[clap(
+        long,
+        value_parser,
+        env = "CARGO_CONTRACT_CHAIN_PROFILE",
+        global = true
+    )]
+    chain_profile: Option<PathBuf>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -125,6 +135,23 @@ enum Command {
     /// Call a contract
     #[clap(name = "call")]
     Call(CallCommand),
+    /// Submit the upload, instantiate and call steps of a batch file in a single
+    /// extrinsic
+    #[clap(name = "batch")]
+    Batch(BatchCommand),
+    /// Deploy the contracts of a deployment manifest in the order of their
+    /// references, recording them in a lockfile
+    #[clap(name = "deploy")]
+    Deploy(DeployCommand),
+    /// Sign an extrinsic written with `--unsigned-out`, without a node
+    #[clap(name = "sign")]
+    Sign(SignCommand),
+    /// Submit an extrinsic signed offline with `cargo contract sign`
+    #[clap(name = "submit")]
+    Submit(SubmitCommand),
+    /// Manage the accounts of the local keystore signing the extrinsics
+    #[clap(name = "account")]
+    Account(AccountCommand),
     /// Encodes a contracts input calls and their arguments
     #[clap(name = "encode")]
     Encode(EncodeCommand),
@@ -137,6 +164,12 @@ enum Command {
     /// Display information about a contract
     #[clap(name = "info")]
     Info(InfoCommand),
+    /// Display the storage of a contract, decoded using its storage layout
+    #[clap(name = "storage")]
+    Storage(StorageCommand),
+    /// Verify that a given contract binary matches the build result of the workspace
+    #[clap(name = "verify")]
+    Verify(VerifyCommand),
 }
 
 fn main() {
@@ -144,7 +177,9 @@ fn main() {
 
     let Opts::Contract(args) = Opts::parse();
 
-    match exec(args.cmd) {
+    match install_chain_profile(args.chain_profile.as_deref())
+        .and_then(|()| exec(args.cmd))
+    {
         Ok(()) => {}
         Err(err) => {
             eprintln!("{err:?}");
@@ -153,6 +188,13 @@ fn main() {
     }
 }
 
+fn install_chain_profile(path: Option<&Path>) -> Result<()> {
+    match path {
+        Some(path) => ChainProfile::load(path)?.install(),
+        None => Ok(()),
+    }
+}
+
 fn exec(cmd: Command) -> Result<()> {
     match &cmd {
         Command::New { name, target_dir } => {
@@ -160,6 +202,18 @@ fn exec(cmd: Command) -> Result<()> {
             println!("Created contract {name}");
             Ok(())
         }
+        Command::Build(build) if build.is_workspace_build() => {
+            let results = build.exec_workspace().map_err(format_err)?;
+
+            if build.is_json() {
+                println!("{}", serde_json::to_string_pretty(&results)?)
+            } else {
+                for result in results.iter().filter(|r| r.verbosity.is_verbose()) {
+                    println!("{}", result.display())
+                }
+            }
+            Ok(())
+        }
         Command::Build(build) => {
             let result = build.exec().map_err(format_err)?;
 
@@ -195,6 +249,23 @@ fn exec(cmd: Command) -> Result<()> {
             call.run()
                 .map_err(|err| map_extrinsic_err(err, call.is_json()))
         }
+        Command::Batch(batch) => {
+            batch
+                .run()
+                .map_err(|err| map_extrinsic_err(err, batch.is_json()))
+        }
+        Command::Deploy(deploy) => {
+            deploy
+                .run()
+                .map_err(|err| map_extrinsic_err(err, deploy.is_json()))
+        }
+        Command::Sign(sign) => sign.run().map_err(format_err),
+        Command::Submit(submit) => {
+            submit
+                .run()
+                .map_err(|err| map_extrinsic_err(err, submit.is_json()))
+        }
+        Command::Account(account) => account.run().map_err(format_err),
         Command::Encode(encode) => encode.run().map_err(format_err),
         Command::Decode(decode) => decode.run().map_err(format_err),
         Command::Remove(remove) => {
@@ -203,6 +274,20 @@ fn exec(cmd: Command) -> Result<()> {
                 .map_err(|err| map_extrinsic_err(err, remove.is_json()))
         }
         Command::Info(info) => info.run().map_err(format_err),
+        Command::Storage(storage) => storage.run().map_err(format_err),
+        Command::Verify(verify) => {
+            let result = verify.run().map_err(format_err)?;
+
+            if verify.is_json() {
+                println!("{}", result.serialize_json()?)
+            } else {
+                println!("{}", result.display())
+            }
+            if !result.is_verified {
+                anyhow::bail!("The contract could not be verified")
+            }
+            Ok(())
+        }
     }
 }
 
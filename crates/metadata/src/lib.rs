@@ -28,11 +28,11 @@
 //! let language = SourceLanguage::new(Language::Ink, Version::new(2, 1, 0));
 //! let compiler =
 //!     SourceCompiler::new(Compiler::RustC, Version::parse("1.46.0-nightly").unwrap());
-//! let wasm = SourceWasm::new(vec![0u8]);
+//! let code = SourceCode::new(SourceTarget::Wasm, vec![0u8]);
 //! // Optional information about how the contract was build
 //! let build_info: Map<String, Value> = Map::new();
 //! let source = Source::new(
-//!     Some(wasm),
+//!     Some(code),
 //!     CodeHash([0u8; 32]),
 //!     language,
 //!     compiler,
@@ -95,7 +95,7 @@ use url::Url;
 /// Smart contract metadata.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ContractMetadata {
-    /// Information about the contract's Wasm code.
+    /// Information about the contract's code.
     pub source: Source,
     /// Metadata about the contract.
     pub contract: Contract,
@@ -123,8 +123,9 @@ impl ContractMetadata {
         }
     }
 
-    pub fn remove_source_wasm_attribute(&mut self) {
-        self.source.wasm = None;
+    /// Removes the bundled code of the contract from the metadata.
+    pub fn remove_source_code(&mut self) {
+        self.source.code = None;
     }
 
     /// Reads the file and tries to parse it as instance of `ContractMetadata`.
@@ -142,7 +143,7 @@ impl ContractMetadata {
     }
 }
 
-/// Representation of the Wasm code hash.
+/// Representation of the code hash.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
 pub struct CodeHash(
     #[serde(
@@ -159,19 +160,22 @@ impl From<[u8; 32]> for CodeHash {
     }
 }
 
-/// Information about the contract's Wasm code.
+/// Information about the contract's code.
 #[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(try_from = "SourceFields")]
 pub struct Source {
-    /// The hash of the contract's Wasm code.
+    /// The hash of the contract's code.
     pub hash: CodeHash,
     /// The language used to write the contract.
     pub language: SourceLanguage,
     /// The compiler used to compile the contract.
     pub compiler: SourceCompiler,
-    /// The actual Wasm code of the contract, for optionally bundling the code
-    /// with the metadata.
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub wasm: Option<SourceWasm>,
+    /// The actual code of the contract, for optionally bundling the code with the
+    /// metadata.
+    ///
+    /// The code is stored under the name of its target, i.e. `wasm` or `riscv`.
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub code: Option<SourceCode>,
     /// Extra information about the environment in which the contract was built.
     ///
     /// Useful for producing deterministic builds.
@@ -182,7 +186,7 @@ pub struct Source {
 impl Source {
     /// Constructs a new InkProjectSource.
     pub fn new(
-        wasm: Option<SourceWasm>,
+        code: Option<SourceCode>,
         hash: CodeHash,
         language: SourceLanguage,
         compiler: SourceCompiler,
@@ -192,40 +196,159 @@ impl Source {
             hash,
             language,
             compiler,
-            wasm,
+            code,
             build_info,
         }
     }
 }
 
-/// The bytes of the compiled Wasm smart contract.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct SourceWasm(
-    #[serde(
-        serialize_with = "byte_str::serialize_as_byte_str",
-        deserialize_with = "byte_str::deserialize_from_byte_str"
-    )]
-    /// The raw bytes of the Wasm code.
-    pub Vec<u8>,
-);
+/// The deserialized representation of [`Source`].
+///
+/// The code is deserialized explicitly from the field of its target, so that malformed
+/// code is reported instead of being treated as missing.
+#[derive(Deserialize)]
+struct SourceFields {
+    hash: CodeHash,
+    language: SourceLanguage,
+    compiler: SourceCompiler,
+    #[serde(default, deserialize_with = "deserialize_code")]
+    wasm: Option<Vec<u8>>,
+    #[serde(default, deserialize_with = "deserialize_code")]
+    riscv: Option<Vec<u8>>,
+    build_info: Option<Map<String, Value>>,
+}
+
+fn deserialize_code<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    byte_str::deserialize_from_byte_str(deserializer).map(Some)
+}
+
+impl TryFrom<SourceFields> for Source {
+    type Error = String;
+
+    fn try_from(fields: SourceFields) -> Result<Self, Self::Error> {
+        let code =
+            match (fields.wasm, fields.riscv) {
+                (Some(_), Some(_)) => return Err(
+                    "The source contains code for both the `wasm` and the `riscv` target"
+                        .to_string(),
+                ),
+                (Some(code), None) => Some(SourceCode::new(SourceTarget::Wasm, code)),
+                (None, Some(code)) => Some(SourceCode::new(SourceTarget::RiscV, code)),
+                (None, None) => None,
+            };
+        Ok(Source::new(
+            code,
+            fields.hash,
+            fields.language,
+            fields.compiler,
+            fields.build_info,
+        ))
+    }
+}
 
-impl SourceWasm {
-    /// Constructs a new `SourceWasm`.
-    pub fn new(wasm: Vec<u8>) -> Self {
-        SourceWasm(wasm)
+/// The target architecture the code of a contract is built for.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceTarget {
+    /// WebAssembly
+    #[default]
+    Wasm,
+    /// RISC-V
+    RiscV,
+}
+
+impl SourceTarget {
+    /// Determines the target of the raw `code` by its magic number.
+    pub fn from_magic(code: &[u8]) -> Option<Self> {
+        if code.starts_with(b"\0asm") {
+            Some(Self::Wasm)
+        } else if code.starts_with(b"\x7fELF") {
+            Some(Self::RiscV)
+        } else {
+            None
+        }
     }
 }
 
-impl Display for SourceWasm {
+impl Display for SourceTarget {
+    fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
+        match self {
+            Self::Wasm => write!(f, "wasm"),
+            Self::RiscV => write!(f, "riscv"),
+        }
+    }
+}
+
+/// The compiled code of a smart contract and the target it is built for.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(from = "TargetCode", into = "TargetCode")]
+pub struct SourceCode {
+    /// The target the code is built for.
+    pub target: SourceTarget,
+    /// The raw bytes of the code.
+    pub code: Vec<u8>,
+}
+
+impl SourceCode {
+    /// Constructs a new `SourceCode`.
+    pub fn new(target: SourceTarget, code: Vec<u8>) -> Self {
+        SourceCode { target, code }
+    }
+}
+
+impl Display for SourceCode {
     fn fmt(&self, f: &mut Formatter<'_>) -> DisplayResult {
         write!(f, "0x").expect("failed writing to string");
-        for byte in &self.0 {
+        for byte in &self.code {
             write!(f, "{byte:02x}").expect("failed writing to string");
         }
         write!(f, "")
     }
 }
 
+/// The serialized representation of [`SourceCode`], keyed by the name of the target.
+///
+/// This keeps the `wasm` field of metadata written before other targets were supported.
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TargetCode {
+    Wasm(
+        #[serde(
+            serialize_with = "byte_str::serialize_as_byte_str",
+            deserialize_with = "byte_str::deserialize_from_byte_str"
+        )]
+        Vec<u8>,
+    ),
+    RiscV(
+        #[serde(
+            serialize_with = "byte_str::serialize_as_byte_str",
+            deserialize_with = "byte_str::deserialize_from_byte_str"
+        )]
+        Vec<u8>,
+    ),
+}
+
+impl From<TargetCode> for SourceCode {
+    fn from(code: TargetCode) -> Self {
+        match code {
+            TargetCode::Wasm(code) => SourceCode::new(SourceTarget::Wasm, code),
+            TargetCode::RiscV(code) => SourceCode::new(SourceTarget::RiscV, code),
+        }
+    }
+}
+
+impl From<SourceCode> for TargetCode {
+    fn from(code: SourceCode) -> Self {
+        match code.target {
+            SourceTarget::Wasm => TargetCode::Wasm(code.code),
+            SourceTarget::RiscV => TargetCode::RiscV(code.code),
+        }
+    }
+}
+
 /// The language and version in which a smart contract is written.
 #[derive(Clone, Debug)]
 pub struct SourceLanguage {
@@ -683,7 +806,7 @@ mod tests {
             Compiler::RustC,
             Version::parse("1.46.0-nightly").unwrap(),
         );
-        let wasm = SourceWasm::new(vec![0u8, 1u8, 2u8]);
+        let code = SourceCode::new(SourceTarget::Wasm, vec![0u8, 1u8, 2u8]);
         let build_info = json! {
             {
                 "example_compiler_version": 42,
@@ -696,7 +819,7 @@ mod tests {
         .clone();
 
         let source = Source::new(
-            Some(wasm),
+            Some(code),
             CodeHash([0u8; 32]),
             language,
             compiler,
@@ -842,7 +965,7 @@ mod tests {
             Compiler::RustC,
             Version::parse("1.46.0-nightly").unwrap(),
         );
-        let wasm = SourceWasm::new(vec![0u8, 1u8, 2u8]);
+        let code = SourceCode::new(SourceTarget::Wasm, vec![0u8, 1u8, 2u8]);
         let build_info = json! {
             {
                 "example_compiler_version": 42,
@@ -855,7 +978,7 @@ mod tests {
         .clone();
 
         let source = Source::new(
-            Some(wasm),
+            Some(code),
             CodeHash([0u8; 32]),
             language,
             compiler,
@@ -901,4 +1024,108 @@ mod tests {
         let decoded = serde_json::from_value::<ContractMetadata>(json);
         assert!(decoded.is_ok())
     }
+
+    #[test]
+    fn code_is_stored_under_the_name_of_its_target() {
+        let language = SourceLanguage::new(Language::Ink, Version::new(4, 2, 0));
+        let compiler =
+            SourceCompiler::new(Compiler::RustC, Version::parse("1.69.0").unwrap());
+        let code = SourceCode::new(SourceTarget::RiscV, vec![0x7f, b'E', b'L', b'F']);
+        let source =
+            Source::new(Some(code), CodeHash([0u8; 32]), language, compiler, None);
+
+        let json = serde_json::to_value(&source).unwrap();
+
+        assert_eq!(json["riscv"], "0x7f454c46");
+        assert!(json.get("wasm").is_none());
+        let decoded: Source = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            decoded.code,
+            Some(SourceCode::new(
+                SourceTarget::RiscV,
+                vec![0x7f, b'E', b'L', b'F']
+            ))
+        );
+    }
+
+    #[test]
+    fn decoding_wasm_field_works() {
+        let json = json! {
+            {
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "language": "ink! 4.2.0",
+                "compiler": "rustc 1.69.0",
+                "wasm": "0x0061736d"
+            }
+        };
+
+        let source: Source = serde_json::from_value(json).unwrap();
+
+        assert_eq!(
+            source.code,
+            Some(SourceCode::new(SourceTarget::Wasm, b"\0asm".to_vec()))
+        );
+    }
+
+    #[test]
+    fn decoding_without_code_works() {
+        let json = json! {
+            {
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "language": "ink! 4.2.0",
+                "compiler": "rustc 1.69.0"
+            }
+        };
+
+        let source: Source = serde_json::from_value(json).unwrap();
+
+        assert_eq!(source.code, None);
+    }
+
+    #[test]
+    fn decoding_malformed_code_fails() {
+        let json = json! {
+            {
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "language": "ink! 4.2.0",
+                "compiler": "rustc 1.69.0",
+                "wasm": "0x0061736z"
+            }
+        };
+
+        let result = serde_json::from_value::<Source>(json);
+
+        let err = result.expect_err("malformed code must not be decoded as missing");
+        assert!(err.to_string().contains("invalid hex character"), "{err}");
+    }
+
+    #[test]
+    fn decoding_code_of_both_targets_fails() {
+        let json = json! {
+            {
+                "hash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "language": "ink! 4.2.0",
+                "compiler": "rustc 1.69.0",
+                "wasm": "0x0061736d",
+                "riscv": "0x7f454c46"
+            }
+        };
+
+        let result = serde_json::from_value::<Source>(json);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn target_is_determined_by_magic() {
+        assert_eq!(
+            SourceTarget::from_magic(b"\0asm\x01\0\0\0"),
+            Some(SourceTarget::Wasm)
+        );
+        assert_eq!(
+            SourceTarget::from_magic(b"\x7fELF\x01\x01"),
+            Some(SourceTarget::RiscV)
+        );
+        assert_eq!(SourceTarget::from_magic(b"{}"), None);
+    }
 }
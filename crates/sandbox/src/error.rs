@@ -0,0 +1,94 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+/// The reasons for which the execution of a contract in the sandbox failed.
+///
+/// Except for [`Error::UnsupportedHostFunction`], the variants correspond to the errors
+/// of `pallet-contracts` of the same name.
+#[derive(Clone, Debug, Eq, PartialEq, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid combination of flags supplied to a host function")]
+    InvalidCallFlags,
+    #[error("The gas limit was exhausted")]
+    OutOfGas,
+    #[error("The output buffer supplied to a host function was too small")]
+    OutputBufferTooSmall,
+    #[error("The balance of the sender did not suffice to transfer the value")]
+    TransferFailed,
+    #[error("The maximum depth of nested calls was reached")]
+    MaxCallDepthReached,
+    #[error("No contract was found at the specified address")]
+    ContractNotFound,
+    #[error("No code could be found at the supplied code hash")]
+    CodeNotFound,
+    #[error(
+        "A buffer outside of the memory of the contract was passed to a host function"
+    )]
+    OutOfBounds,
+    #[error("The input passed to a host function failed to decode")]
+    DecodingFailed,
+    #[error("The contract trapped during execution")]
+    ContractTrapped,
+    #[error("The value to store exceeds the maximum size of a storage item")]
+    ValueTooLarge,
+    #[error("A contract was terminated while still being on the call stack")]
+    TerminatedWhileReentrant,
+    #[error("The input was forwarded to a callee and is no longer available")]
+    InputForwarded,
+    #[error("An event was emitted with too many topics")]
+    TooManyTopics,
+    #[error("A contract with the same address already exists")]
+    DuplicateContract,
+    #[error("A contract terminated itself in its constructor")]
+    TerminatedInConstructor,
+    #[error("A contract was called which is already on the call stack")]
+    ReentranceDenied,
+    #[error("The storage deposit exceeds the storage deposit limit")]
+    StorageDepositLimitExhausted,
+    #[error("The contract code is no valid Wasm module or misses an export")]
+    CodeRejected,
+    #[error("The host function `{0}` is not supported by the sandbox")]
+    UnsupportedHostFunction(String),
+}
+
+impl Error {
+    /// Returns the name of the corresponding error of `pallet-contracts`, if any.
+    pub fn pallet_error(&self) -> Option<&'static str> {
+        let name = match self {
+            Self::InvalidCallFlags => "InvalidCallFlags",
+            Self::OutOfGas => "OutOfGas",
+            Self::OutputBufferTooSmall => "OutputBufferTooSmall",
+            Self::TransferFailed => "TransferFailed",
+            Self::MaxCallDepthReached => "MaxCallDepthReached",
+            Self::ContractNotFound => "ContractNotFound",
+            Self::CodeNotFound => "CodeNotFound",
+            Self::OutOfBounds => "OutOfBounds",
+            Self::DecodingFailed => "DecodingFailed",
+            Self::ContractTrapped => "ContractTrapped",
+            Self::ValueTooLarge => "ValueTooLarge",
+            Self::TerminatedWhileReentrant => "TerminatedWhileReentrant",
+            Self::InputForwarded => "InputForwarded",
+            Self::TooManyTopics => "TooManyTopics",
+            Self::DuplicateContract => "DuplicateContract",
+            Self::TerminatedInConstructor => "TerminatedInConstructor",
+            Self::ReentranceDenied => "ReentranceDenied",
+            Self::StorageDepositLimitExhausted => "StorageDepositLimitExhausted",
+            Self::CodeRejected => "CodeRejected",
+            Self::UnsupportedHostFunction(_) => return None,
+        };
+        Some(name)
+    }
+}
@@ -0,0 +1,626 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Executes Wasm contracts locally, without a node.
+//!
+//! The [`Sandbox`] is an in-memory chain: it keeps the uploaded codes, the instantiated
+//! contracts with their storage and the balances of all accounts. Contracts are executed
+//! by an embedded interpreter which implements the `seal*` host functions of
+//! `pallet-contracts`, hence they can be instantiated and called in tests or CI:
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use contract_sandbox::{
+//!     Code,
+//!     Sandbox,
+//! };
+//! use sp_core::crypto::AccountId32;
+//!
+//! let alice = AccountId32::new([1; 32]);
+//! let code = std::fs::read("target/ink/flipper.wasm")?;
+//! // the selector of the `new` constructor and its argument
+//! let data = vec![0x9b, 0xae, 0x9d, 0x5e, 0x00];
+//!
+//! let mut sandbox = Sandbox::new();
+//! let instantiated = sandbox
+//!     .instantiate(
+//!         alice.clone(),
+//!         0,
+//!         None,
+//!         None,
+//!         Code::Upload(code),
+//!         data,
+//!         vec![],
+//!     )
+//!     .result
+//!     .result?;
+//! // the selector of the `get` message
+//! let input = vec![0x2f, 0x86, 0x5b, 0xd9];
+//! let called = sandbox.call(alice, instantiated.account_id, 0, None, None, input);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The results have the same shape as the results of a dry run via the RPC of a node,
+//! the returned data can therefore be decoded by the `contract-transcode` crate.
+//!
+//! The sandbox is deterministic: the gas is measured in instructions executed by the
+//! interpreter rather than in the weight of a chain, and the block number and timestamp
+//! only advance by [`Sandbox::advance_block`].
+
+mod error;
+mod runtime;
+mod state;
+
+pub use self::error::Error;
+pub use pallet_contracts_primitives::{
+    Code,
+    ContractResult,
+    ExecReturnValue,
+    InstantiateReturnValue,
+    ReturnFlags,
+    StorageDeposit,
+};
+
+use anyhow::{
+    Context,
+    Result,
+};
+use runtime::{
+    Ext,
+    Frame,
+    FrameKind,
+};
+use scale::Encode;
+use sp_core::{
+    crypto::AccountId32,
+    hashing,
+    H256,
+};
+use sp_weights::Weight;
+use state::State;
+use std::{
+    fs,
+    path::Path,
+};
+
+/// The balance type of the sandbox.
+pub type Balance = u128;
+
+/// The balance of accounts unknown to the sandbox, the same as for the development
+/// accounts of a local node.
+pub const ENDOWMENT: Balance = 1 << 60;
+/// The existential deposit of the sandbox.
+pub const MINIMUM_BALANCE: Balance = 1;
+/// The deposit for each item in the storage of a contract.
+pub const DEPOSIT_PER_ITEM: Balance = 100_000_000_000;
+/// The deposit for each byte in the storage of a contract.
+pub const DEPOSIT_PER_BYTE: Balance = 5_000_000;
+/// The gas limit of an execution if none is given, in executed instructions.
+pub const DEFAULT_GAS_LIMIT: u64 = 10_000_000_000;
+/// The milliseconds the timestamp advances with each block.
+pub const BLOCK_TIME: u64 = 6_000;
+
+/// An event emitted by the execution of a contract.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    /// A contract was instantiated by the `deployer`.
+    Instantiated {
+        deployer: AccountId32,
+        contract: AccountId32,
+    },
+    /// A contract was called by the `caller`.
+    Called {
+        caller: AccountId32,
+        contract: AccountId32,
+    },
+    /// A contract executed the code of another one.
+    DelegateCalled {
+        contract: AccountId32,
+        code_hash: H256,
+    },
+    /// A contract emitted an event.
+    ContractEmitted {
+        contract: AccountId32,
+        topics: Vec<H256>,
+        data: Vec<u8>,
+    },
+    /// A contract terminated itself, transferring its balance to the `beneficiary`.
+    Terminated {
+        contract: AccountId32,
+        beneficiary: AccountId32,
+    },
+}
+
+/// The outcome of instantiating or calling a contract in the sandbox.
+#[derive(Debug)]
+pub struct ExecResult<R> {
+    /// The result, as returned by the dry run of a node.
+    pub result: ContractResult<Result<R, Error>, Balance>,
+    /// The events emitted by the execution, empty if it failed.
+    pub events: Vec<Event>,
+}
+
+/// An in-memory chain executing contracts.
+#[derive(Clone, Debug, Default)]
+pub struct Sandbox {
+    state: State,
+}
+
+impl Sandbox {
+    /// Creates an empty sandbox.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads the state of a sandbox from the JSON file at `path`, an empty sandbox is
+    /// created if the file does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new())
+        }
+        let json = fs::read(path).with_context(|| {
+            format!("Failed to read the sandbox state from {}", path.display())
+        })?;
+        let state = serde_json::from_slice(&json).with_context(|| {
+            format!(
+                "Failed to deserialize the sandbox state of {}",
+                path.display()
+            )
+        })?;
+        Ok(Self { state })
+    }
+
+    /// Saves the state of the sandbox as JSON to the file at `path`.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(&self.state)?;
+        fs::write(path, json).with_context(|| {
+            format!("Failed to write the sandbox state to {}", path.display())
+        })
+    }
+
+    /// Returns the number of the current block.
+    pub fn block_number(&self) -> u32 {
+        self.state.block_number
+    }
+
+    /// Advances to the next block.
+    pub fn advance_block(&mut self) {
+        self.state.block_number += 1;
+        self.state.timestamp += BLOCK_TIME;
+    }
+
+    /// Returns the free balance of the `account`.
+    pub fn balance(&self, account: &AccountId32) -> Balance {
+        self.state.balance(account)
+    }
+
+    /// Sets the free balance of the `account`.
+    pub fn set_balance(&mut self, account: AccountId32, balance: Balance) {
+        self.state.balances.insert(account, balance);
+    }
+
+    /// Uploads the contract `code`, returns its code hash.
+    pub fn upload_code(&mut self, code: Vec<u8>) -> Result<H256, Error> {
+        upload_code(&mut self.state, code)
+    }
+
+    /// Returns the code hash of the `contract`, `None` if there is no contract.
+    pub fn code_hash(&self, contract: &AccountId32) -> Option<H256> {
+        self.state
+            .contracts
+            .get(contract)
+            .map(|contract| contract.code_hash)
+    }
+
+    /// Returns the value stored under the `key` by the `contract`.
+    pub fn storage(&self, contract: &AccountId32, key: &[u8]) -> Option<&[u8]> {
+        let contract = self.state.contracts.get(contract)?;
+        contract
+            .storage
+            .get(&sp_core::Bytes(key.to_vec()))
+            .map(|value| &value.0[..])
+    }
+
+    /// Instantiates a contract, the parameters are the ones of the `instantiate` dry run
+    /// of a node.
+    ///
+    /// The state changes are kept if the instantiation succeeds, dry runs can be
+    /// performed on a clone of the sandbox.
+    #[allow(clippy::too_many_arguments)]
+    pub fn instantiate(
+        &mut self,
+        origin: AccountId32,
+        value: Balance,
+        gas_limit: Option<Weight>,
+        storage_deposit_limit: Option<Balance>,
+        code: Code<H256>,
+        data: Vec<u8>,
+        salt: Vec<u8>,
+    ) -> ExecResult<InstantiateReturnValue<AccountId32>> {
+        let mut account_id = None;
+        let outcome =
+            self.transact(origin.clone(), gas_limit, storage_deposit_limit, |state| {
+                let code_hash = match code {
+                    Code::Upload(code) => upload_code(state, code)?,
+                    Code::Existing(code_hash) => code_hash,
+                };
+                let account = contract_address(&origin, &code_hash, &data, &salt);
+                account_id = Some(account.clone());
+                Ok(Frame {
+                    kind: FrameKind::Deploy,
+                    account,
+                    caller: origin,
+                    value,
+                    input: Some(data),
+                    code_hash,
+                })
+            });
+        let ContractResult {
+            gas_consumed,
+            gas_required,
+            storage_deposit,
+            debug_message,
+            result,
+        } = outcome.result;
+        let result = result.map(|result| {
+            InstantiateReturnValue {
+                result,
+                account_id: account_id.expect("the frame has been prepared; qed"),
+            }
+        });
+        ExecResult {
+            result: ContractResult {
+                gas_consumed,
+                gas_required,
+                storage_deposit,
+                debug_message,
+                result,
+            },
+            events: outcome.events,
+        }
+    }
+
+    /// Calls the contract `dest`, the parameters are the ones of the `call` dry run of a
+    /// node.
+    ///
+    /// The state changes are kept if the call succeeds, dry runs can be performed on a
+    /// clone of the sandbox.
+    pub fn call(
+        &mut self,
+        origin: AccountId32,
+        dest: AccountId32,
+        value: Balance,
+        gas_limit: Option<Weight>,
+        storage_deposit_limit: Option<Balance>,
+        input_data: Vec<u8>,
+    ) -> ExecResult<ExecReturnValue> {
+        self.transact(origin.clone(), gas_limit, storage_deposit_limit, |_| {
+            Ok(Frame {
+                kind: FrameKind::Call,
+                account: dest,
+                caller: origin,
+                value,
+                input: Some(input_data),
+                code_hash: H256::zero(),
+            })
+        })
+    }
+
+    /// Executes the frame returned by `prepare` as a transaction of the `origin`, the
+    /// state is only changed if it succeeds.
+    fn transact(
+        &mut self,
+        origin: AccountId32,
+        gas_limit: Option<Weight>,
+        storage_deposit_limit: Option<Balance>,
+        prepare: impl FnOnce(&mut State) -> Result<Frame, Error>,
+    ) -> ExecResult<ExecReturnValue> {
+        let fuel_limit = gas_limit.map_or(DEFAULT_GAS_LIMIT, |weight| weight.ref_time());
+        let deposit_before = self.state.storage_deposit();
+        let mut ext = Ext::new(self.state.clone(), origin, fuel_limit);
+        let result = prepare(&mut ext.state)
+            .and_then(|frame| runtime::execute(&mut ext, frame).map_err(|err| err.error));
+
+        let deposit_after = ext.state.storage_deposit();
+        let storage_deposit = if deposit_after >= deposit_before {
+            StorageDeposit::Charge(deposit_after - deposit_before)
+        } else {
+            StorageDeposit::Refund(deposit_before - deposit_after)
+        };
+        let result = match result {
+            Ok(_)
+                if storage_deposit_limit
+                    .is_some_and(|limit| storage_deposit.charge_or_zero() > limit) =>
+            {
+                Err(Error::StorageDepositLimitExhausted)
+            }
+            result => result,
+        };
+        let (storage_deposit, events) = if result.is_ok() {
+            self.state = ext.state;
+            (storage_deposit, ext.events)
+        } else {
+            (StorageDeposit::Charge(0), Vec::new())
+        };
+
+        let gas_consumed = Weight::from_parts(fuel_limit - ext.fuel_left, 0);
+        ExecResult {
+            result: ContractResult {
+                gas_consumed,
+                gas_required: gas_consumed,
+                storage_deposit,
+                debug_message: ext.debug_message,
+                result,
+            },
+            events,
+        }
+    }
+}
+
+/// Returns the address of a contract instantiated by the `deployer`, derived the same
+/// way as by `pallet-contracts`.
+pub fn contract_address(
+    deployer: &AccountId32,
+    code_hash: &H256,
+    input_data: &[u8],
+    salt: &[u8],
+) -> AccountId32 {
+    let entropy = (b"contract_addr_v1", deployer, code_hash, input_data, salt)
+        .using_encoded(hashing::blake2_256);
+    AccountId32::new(entropy)
+}
+
+fn upload_code(state: &mut State, code: Vec<u8>) -> Result<H256, Error> {
+    runtime::validate(&code)?;
+    let code_hash = H256(hashing::blake2_256(&code));
+    state.codes.insert(code_hash, code.into());
+    Ok(code_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// A contract counting its calls: the selector `0` increments the counter and emits
+    /// it as an event, `1` returns the counter and `2` increments it but reverts.
+    const COUNTER: &str = r#"
+        (module
+            (import "seal0" "seal_input" (func $input (param i32 i32)))
+            (import "seal0" "seal_return" (func $return (param i32 i32 i32)))
+            (import "seal2" "set_storage" (func $set (param i32 i32 i32 i32) (result i32)))
+            (import "seal1" "get_storage" (func $get (param i32 i32 i32 i32) (result i32)))
+            (import "seal0" "seal_deposit_event" (func $event (param i32 i32 i32 i32)))
+            (import "env" "memory" (memory 1 1))
+            (data (i32.const 0) "cntr")
+
+            (func $store
+                (drop (call $set (i32.const 0) (i32.const 4) (i32.const 16) (i32.const 4)))
+            )
+            (func (export "deploy")
+                (i32.store (i32.const 16) (i32.const 0))
+                (call $store)
+            )
+            (func (export "call")
+                (i32.store (i32.const 32) (i32.const 32))
+                (call $input (i32.const 64) (i32.const 32))
+                (i32.store (i32.const 32) (i32.const 4))
+                (drop (call $get (i32.const 0) (i32.const 4) (i32.const 16) (i32.const 32)))
+                (if (i32.eq (i32.load8_u (i32.const 64)) (i32.const 1))
+                    (then (call $return (i32.const 0) (i32.const 16) (i32.const 4)))
+                )
+                (i32.store (i32.const 16) (i32.add (i32.load (i32.const 16)) (i32.const 1)))
+                (call $store)
+                (call $event (i32.const 0) (i32.const 0) (i32.const 16) (i32.const 4))
+                (if (i32.eq (i32.load8_u (i32.const 64)) (i32.const 2))
+                    (then (call $return (i32.const 1) (i32.const 16) (i32.const 4)))
+                )
+            )
+        )
+    "#;
+
+    fn alice() -> AccountId32 {
+        AccountId32::new([1; 32])
+    }
+
+    fn deploy(sandbox: &mut Sandbox, wat: &str) -> AccountId32 {
+        let code = wat::parse_str(wat).unwrap();
+        sandbox
+            .instantiate(alice(), 0, None, None, Code::Upload(code), vec![], vec![])
+            .result
+            .result
+            .unwrap()
+            .account_id
+    }
+
+    fn call(
+        sandbox: &mut Sandbox,
+        contract: &AccountId32,
+        selector: u8,
+    ) -> ExecResult<ExecReturnValue> {
+        sandbox.call(alice(), contract.clone(), 0, None, None, vec![selector])
+    }
+
+    #[test]
+    fn instantiated_contract_can_be_called() {
+        // given
+        let mut sandbox = Sandbox::new();
+        let contract = deploy(&mut sandbox, COUNTER);
+
+        // when
+        let incremented = call(&mut sandbox, &contract, 0);
+        let counter = call(&mut sandbox, &contract, 1);
+
+        // then
+        assert!(incremented.result.result.is_ok());
+        assert_eq!(
+            incremented.events,
+            vec![
+                Event::ContractEmitted {
+                    contract: contract.clone(),
+                    topics: vec![],
+                    data: 1u32.encode(),
+                },
+                Event::Called {
+                    caller: alice(),
+                    contract: contract.clone(),
+                },
+            ]
+        );
+        let ret = counter.result.result.unwrap();
+        assert!(!ret.did_revert());
+        assert_eq!(ret.data, 1u32.encode());
+        assert_eq!(
+            sandbox.storage(&contract, b"cntr"),
+            Some(&1u32.encode()[..])
+        );
+        assert!(counter.result.gas_consumed.ref_time() > 0);
+    }
+
+    #[test]
+    fn instantiation_charges_storage_deposit() {
+        // given
+        let mut sandbox = Sandbox::new();
+        let code = wat::parse_str(COUNTER).unwrap();
+
+        // when
+        let res = sandbox
+            .instantiate(alice(), 0, None, None, Code::Upload(code), vec![], vec![])
+            .result;
+
+        // then
+        assert!(res.result.is_ok());
+        assert_eq!(
+            res.storage_deposit,
+            StorageDeposit::Charge(DEPOSIT_PER_ITEM + 8 * DEPOSIT_PER_BYTE)
+        );
+    }
+
+    #[test]
+    fn reverted_call_is_rolled_back() {
+        // given
+        let mut sandbox = Sandbox::new();
+        let contract = deploy(&mut sandbox, COUNTER);
+
+        // when
+        let reverted = call(&mut sandbox, &contract, 2);
+
+        // then
+        let ret = reverted.result.result.unwrap();
+        assert!(ret.did_revert());
+        assert_eq!(ret.data, 1u32.encode());
+        assert!(reverted.events.is_empty());
+        assert_eq!(
+            sandbox.storage(&contract, b"cntr"),
+            Some(&0u32.encode()[..])
+        );
+    }
+
+    #[test]
+    fn calling_unknown_contract_fails() {
+        let mut sandbox = Sandbox::new();
+        let res = call(&mut sandbox, &AccountId32::new([2; 32]), 0);
+        assert_eq!(res.result.result.unwrap_err(), Error::ContractNotFound);
+    }
+
+    #[test]
+    fn endless_loop_runs_out_of_gas() {
+        // given
+        let mut sandbox = Sandbox::new();
+        let contract = deploy(
+            &mut sandbox,
+            r#"
+            (module
+                (import "env" "memory" (memory 1 1))
+                (func (export "deploy"))
+                (func (export "call") (loop $l (br $l)))
+            )
+            "#,
+        );
+
+        // when
+        let res = sandbox.call(
+            alice(),
+            contract,
+            0,
+            Some(Weight::from_parts(1_000_000, 0)),
+            None,
+            vec![],
+        );
+
+        // then
+        assert_eq!(res.result.result.unwrap_err(), Error::OutOfGas);
+        assert_eq!(res.result.gas_consumed, Weight::from_parts(1_000_000, 0));
+    }
+
+    #[test]
+    fn unsupported_host_function_fails_the_call() {
+        // given
+        let mut sandbox = Sandbox::new();
+        let contract = deploy(
+            &mut sandbox,
+            r#"
+            (module
+                (import "seal0" "seal_unknown" (func $unknown))
+                (import "env" "memory" (memory 1 1))
+                (func (export "deploy"))
+                (func (export "call") (call $unknown))
+            )
+            "#,
+        );
+
+        // when
+        let res = call(&mut sandbox, &contract, 0);
+
+        // then
+        assert_eq!(
+            res.result.result.unwrap_err(),
+            Error::UnsupportedHostFunction("seal0::seal_unknown".to_string())
+        );
+    }
+
+    #[test]
+    fn code_without_exports_is_rejected() {
+        let mut sandbox = Sandbox::new();
+        let code = wat::parse_str(r#"(module (import "env" "memory" (memory 1 1)))"#);
+        assert_eq!(sandbox.upload_code(code.unwrap()), Err(Error::CodeRejected));
+    }
+
+    #[test]
+    fn state_is_saved_and_loaded() {
+        // given
+        let tmp_dir = tempfile::Builder::new()
+            .prefix("cargo-contract.test.")
+            .tempdir()
+            .unwrap();
+        let path = tmp_dir.path().join("sandbox.json");
+        let mut sandbox = Sandbox::load(&path).unwrap();
+        let contract = deploy(&mut sandbox, COUNTER);
+        call(&mut sandbox, &contract, 0);
+        sandbox.advance_block();
+
+        // when
+        sandbox.save(&path).unwrap();
+        let mut loaded = Sandbox::load(&path).unwrap();
+
+        // then
+        assert_eq!(loaded.block_number(), 1);
+        assert_eq!(loaded.code_hash(&contract), sandbox.code_hash(&contract));
+        let ret = call(&mut loaded, &contract, 1).result.result.unwrap();
+        assert_eq!(ret.data, 1u32.encode());
+    }
+}
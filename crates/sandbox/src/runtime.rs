@@ -0,0 +1,1340 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+//! The execution of contracts and the host functions exposed to them.
+//!
+//! Every call frame runs in an engine of its own, a nested call or instantiation moves
+//! the [`Ext`] into the store of the callee and back once it returns. The semantics of
+//! the host functions follow `pallet-contracts`, see its documentation for the details of
+//! each function.
+
+use crate::{
+    contract_address,
+    state::{
+        ContractInfo,
+        State,
+    },
+    Balance,
+    Error,
+    Event,
+    MINIMUM_BALANCE,
+};
+use pallet_contracts_primitives::{
+    ExecReturnValue,
+    ReturnFlags,
+};
+use scale::{
+    Decode,
+    Encode,
+};
+use sp_core::{
+    crypto::AccountId32,
+    hashing,
+    Bytes,
+    H256,
+};
+use sp_weights::Weight;
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fmt,
+    mem,
+};
+use wasmi::{
+    core::{
+        HostError,
+        Trap,
+        TrapCode,
+    },
+    Caller,
+    Config,
+    Engine,
+    ExternType,
+    IntoFunc,
+    Linker,
+    Memory,
+    Module,
+    Store,
+};
+
+/// The maximum number of nested call frames.
+const MAX_CALL_DEPTH: usize = 32;
+/// The maximum length of a storage key in bytes.
+const MAX_STORAGE_KEY_LEN: u32 = 128;
+/// The maximum length of a storage value in bytes.
+const MAX_STORAGE_VALUE_LEN: u32 = 16 * 1024;
+/// The maximum number of topics of an event.
+const MAX_EVENT_TOPICS: usize = 4;
+/// Passed as the output pointer to skip copying the output, also returned by storage
+/// functions if there was no value stored.
+const SENTINEL: u32 = u32::MAX;
+
+/// The flags of the `call` host functions.
+const FORWARD_INPUT: u32 = 0b0001;
+const CLONE_INPUT: u32 = 0b0010;
+const TAIL_CALL: u32 = 0b0100;
+const ALLOW_REENTRY: u32 = 0b1000;
+
+/// The codes returned by the host functions to the contract.
+#[derive(Clone, Copy)]
+#[repr(u32)]
+enum ReturnCode {
+    Success = 0,
+    CalleeTrapped = 1,
+    CalleeReverted = 2,
+    KeyNotFound = 3,
+    TransferFailed = 5,
+    CodeNotFound = 7,
+    NotCallable = 8,
+}
+
+/// The context shared by all call frames of an execution.
+pub(crate) struct Ext {
+    /// The state of the chain, changed by the execution.
+    pub state: State,
+    /// The account which signed the transaction.
+    pub origin: AccountId32,
+    /// The events emitted so far.
+    pub events: Vec<Event>,
+    /// The debug messages printed so far.
+    pub debug_message: Vec<u8>,
+    /// The fuel left for the execution.
+    pub fuel_left: u64,
+    /// The contract accounts of the call stack, the innermost last.
+    stack: Vec<AccountId32>,
+}
+
+impl Ext {
+    /// Creates the context of a transaction of the `origin`.
+    pub fn new(state: State, origin: AccountId32, fuel_limit: u64) -> Self {
+        Self {
+            state,
+            origin,
+            events: Vec::new(),
+            debug_message: Vec::new(),
+            fuel_left: fuel_limit,
+            stack: Vec::new(),
+        }
+    }
+}
+
+impl Default for Ext {
+    fn default() -> Self {
+        Self::new(State::default(), AccountId32::new([0; 32]), 0)
+    }
+}
+
+/// The kind of a call frame.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum FrameKind {
+    /// The constructor of a new contract.
+    Deploy,
+    /// A message of an existing contract.
+    Call,
+    /// The code of another contract executed in the context of the caller.
+    DelegateCall,
+}
+
+/// A call frame.
+pub(crate) struct Frame {
+    pub kind: FrameKind,
+    /// The contract the frame is executed for.
+    pub account: AccountId32,
+    /// The account calling the contract.
+    pub caller: AccountId32,
+    /// The value transferred by the call.
+    pub value: Balance,
+    /// The input data of the call, `None` once it has been forwarded to a callee.
+    pub input: Option<Vec<u8>>,
+    /// The hash of the executed code, looked up for [`FrameKind::Call`].
+    pub code_hash: H256,
+}
+
+/// The side of a call which an error is attributed to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ErrorOrigin {
+    /// The call could not be set up, e.g. as the callee does not exist.
+    Caller,
+    /// The execution of the callee failed.
+    Callee,
+}
+
+/// The error of a call frame.
+#[derive(Debug)]
+pub(crate) struct ExecError {
+    pub error: Error,
+    pub origin: ErrorOrigin,
+}
+
+impl ExecError {
+    fn caller(error: Error) -> Self {
+        Self {
+            error,
+            origin: ErrorOrigin::Caller,
+        }
+    }
+}
+
+/// Executes the call `frame`, all its changes are rolled back if it fails or reverts.
+pub(crate) fn execute(ext: &mut Ext, frame: Frame) -> Result<ExecReturnValue, ExecError> {
+    if ext.stack.len() >= MAX_CALL_DEPTH {
+        return Err(ExecError::caller(Error::MaxCallDepthReached))
+    }
+    let state = ext.state.clone();
+    let events = ext.events.len();
+    ext.stack.push(frame.account.clone());
+    let result = enter(ext, frame);
+    ext.stack.pop();
+    if !matches!(result, Ok(ref ret) if !ret.did_revert()) {
+        ext.state = state;
+        ext.events.truncate(events);
+    }
+    result
+}
+
+/// Sets up the contract account of the `frame` and runs its code.
+fn enter(ext: &mut Ext, mut frame: Frame) -> Result<ExecReturnValue, ExecError> {
+    match frame.kind {
+        FrameKind::Deploy => {
+            if ext.state.contracts.contains_key(&frame.account) {
+                return Err(ExecError::caller(Error::DuplicateContract))
+            }
+            if !ext.state.codes.contains_key(&frame.code_hash) {
+                return Err(ExecError::caller(Error::CodeNotFound))
+            }
+            ext.state.contracts.insert(
+                frame.account.clone(),
+                ContractInfo {
+                    code_hash: frame.code_hash,
+                    storage: BTreeMap::new(),
+                },
+            );
+            ext.state.balances.entry(frame.account.clone()).or_insert(0);
+            ext.state.nonce += 1;
+        }
+        FrameKind::Call => {
+            let contract = ext
+                .state
+                .contracts
+                .get(&frame.account)
+                .ok_or(ExecError::caller(Error::ContractNotFound))?;
+            frame.code_hash = contract.code_hash;
+        }
+        FrameKind::DelegateCall => {
+            if !ext.state.codes.contains_key(&frame.code_hash) {
+                return Err(ExecError::caller(Error::CodeNotFound))
+            }
+        }
+    }
+    if frame.kind != FrameKind::DelegateCall
+        && !ext
+            .state
+            .transfer(&frame.caller, &frame.account, frame.value)
+    {
+        return Err(ExecError::caller(Error::TransferFailed))
+    }
+
+    let event = match frame.kind {
+        FrameKind::Deploy => {
+            Event::Instantiated {
+                deployer: frame.caller.clone(),
+                contract: frame.account.clone(),
+            }
+        }
+        FrameKind::Call => {
+            Event::Called {
+                caller: frame.caller.clone(),
+                contract: frame.account.clone(),
+            }
+        }
+        FrameKind::DelegateCall => {
+            Event::DelegateCalled {
+                contract: frame.account.clone(),
+                code_hash: frame.code_hash,
+            }
+        }
+    };
+    let ret = run(ext, frame).map_err(|error| {
+        ExecError {
+            error,
+            origin: ErrorOrigin::Callee,
+        }
+    })?;
+    if !ret.did_revert() {
+        ext.events.push(event);
+    }
+    Ok(ret)
+}
+
+/// Checks that the `code` is a Wasm module exporting the `call` and `deploy` functions.
+pub(crate) fn validate(code: &[u8]) -> Result<(), Error> {
+    let module =
+        Module::new(&Engine::default(), code).map_err(|_| Error::CodeRejected)?;
+    for export in ["call", "deploy"] {
+        if !matches!(module.get_export(export), Some(ExternType::Func(_))) {
+            return Err(Error::CodeRejected)
+        }
+    }
+    Ok(())
+}
+
+/// Runs the code of the `frame` in an engine of its own.
+fn run(ext: &mut Ext, frame: Frame) -> Result<ExecReturnValue, Error> {
+    let code = ext
+        .state
+        .codes
+        .get(&frame.code_hash)
+        .ok_or(Error::CodeNotFound)?
+        .0
+        .clone();
+    let entry_point = match frame.kind {
+        FrameKind::Deploy => "deploy",
+        FrameKind::Call | FrameKind::DelegateCall => "call",
+    };
+
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let module = Module::new(&engine, &code[..]).map_err(|_| Error::CodeRejected)?;
+
+    let fuel_limit = ext.fuel_left;
+    let runtime = Runtime {
+        ext: mem::take(ext),
+        frame,
+        memory: None,
+    };
+    let mut store = Store::new(&engine, runtime);
+    let result = instantiate_and_call(&mut store, &module, entry_point, fuel_limit);
+    // the interpreter does not charge a block it lacks the fuel for, running out of gas
+    // consumes the whole limit nevertheless
+    let fuel_consumed = match result {
+        Err(Error::OutOfGas) => fuel_limit,
+        _ => store.fuel_consumed().unwrap_or_default(),
+    };
+    *ext = store.into_data().ext;
+    ext.fuel_left = fuel_limit.saturating_sub(fuel_consumed);
+    result
+}
+
+fn instantiate_and_call(
+    store: &mut Store<Runtime>,
+    module: &Module,
+    entry_point: &str,
+    fuel_limit: u64,
+) -> Result<ExecReturnValue, Error> {
+    store
+        .add_fuel(fuel_limit)
+        .expect("fuel metering is enabled; qed");
+    let memory_type = module
+        .imports()
+        .find_map(|import| {
+            match import.ty() {
+                ExternType::Memory(ty) => Some(*ty),
+                _ => None,
+            }
+        })
+        .ok_or(Error::CodeRejected)?;
+    let memory =
+        Memory::new(&mut *store, memory_type).map_err(|_| Error::CodeRejected)?;
+    store.data_mut().memory = Some(memory);
+
+    let mut linker = Linker::new(store.engine());
+    linker
+        .define("env", "memory", memory)
+        .expect("the memory is defined once; qed");
+    define_host_functions(&mut linker, module);
+    let instance = linker
+        .instantiate(&mut *store, module)
+        .and_then(|instance| instance.start(&mut *store))
+        .map_err(|_| Error::CodeRejected)?;
+    let func = instance
+        .get_typed_func::<(), ()>(&*store, entry_point)
+        .map_err(|_| Error::CodeRejected)?;
+
+    match func.call(&mut *store, ()) {
+        Ok(()) => {
+            Ok(ExecReturnValue {
+                flags: ReturnFlags::empty(),
+                data: Vec::new(),
+            })
+        }
+        Err(trap) if matches!(trap.trap_code(), Some(TrapCode::OutOfFuel)) => {
+            Err(Error::OutOfGas)
+        }
+        Err(trap) => {
+            match trap.downcast::<Halt>() {
+                Some(Halt::Return(ret)) => Ok(ret),
+                Some(Halt::Error(error)) => Err(error),
+                None => Err(Error::ContractTrapped),
+            }
+        }
+    }
+}
+
+/// The data of the store of a call frame.
+struct Runtime {
+    ext: Ext,
+    frame: Frame,
+    memory: Option<Memory>,
+}
+
+impl Runtime {
+    /// Returns the storage of the contract the frame is executed for.
+    fn storage(&mut self) -> Result<&mut BTreeMap<Bytes, Bytes>, Trap> {
+        self.ext
+            .state
+            .contracts
+            .get_mut(&self.frame.account)
+            .map(|contract| &mut contract.storage)
+            .ok_or_else(|| halt(Error::ContractNotFound))
+    }
+}
+
+/// Stops the execution of a call frame from within a host function.
+#[derive(Debug)]
+enum Halt {
+    /// The contract returned the value.
+    Return(ExecReturnValue),
+    /// The contract used a host function wrongly.
+    Error(Error),
+}
+
+impl fmt::Display for Halt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Return(_) => write!(f, "returned"),
+            Self::Error(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl HostError for Halt {}
+
+fn halt(error: Error) -> Trap {
+    Trap::from(Halt::Error(error))
+}
+
+type Ctx<'a> = Caller<'a, Runtime>;
+
+fn memory(ctx: &Ctx) -> Memory {
+    ctx.data()
+        .memory
+        .expect("the memory is defined before the instantiation; qed")
+}
+
+fn read(ctx: &Ctx, ptr: u32, len: u32) -> Result<Vec<u8>, Trap> {
+    let start = ptr as usize;
+    let end = start.saturating_add(len as usize);
+    memory(ctx)
+        .data(ctx)
+        .get(start..end)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| halt(Error::OutOfBounds))
+}
+
+fn read_array<const N: usize>(ctx: &Ctx, ptr: u32) -> Result<[u8; N], Trap> {
+    let bytes = read(ctx, ptr, N as u32)?;
+    Ok(bytes
+        .try_into()
+        .expect("exactly N bytes have been read; qed"))
+}
+
+fn read_account(ctx: &Ctx, ptr: u32) -> Result<AccountId32, Trap> {
+    read_array(ctx, ptr).map(AccountId32::new)
+}
+
+fn read_balance(ctx: &Ctx, ptr: u32) -> Result<Balance, Trap> {
+    read_array(ctx, ptr).map(Balance::from_le_bytes)
+}
+
+fn read_key(ctx: &Ctx, ptr: u32, len: u32) -> Result<Vec<u8>, Trap> {
+    if len > MAX_STORAGE_KEY_LEN {
+        return Err(halt(Error::DecodingFailed))
+    }
+    read(ctx, ptr, len)
+}
+
+fn decode<D: Decode>(ctx: &Ctx, ptr: u32, len: u32) -> Result<D, Trap> {
+    D::decode(&mut &read(ctx, ptr, len)?[..]).map_err(|_| halt(Error::DecodingFailed))
+}
+
+fn write(ctx: &mut Ctx, ptr: u32, bytes: &[u8]) -> Result<(), Trap> {
+    memory(ctx)
+        .write(ctx, ptr as usize, bytes)
+        .map_err(|_| halt(Error::OutOfBounds))
+}
+
+/// Writes the `bytes` to the buffer at `out_ptr`, whose length is read from and
+/// written to `out_len_ptr`.
+fn write_output(
+    ctx: &mut Ctx,
+    out_ptr: u32,
+    out_len_ptr: u32,
+    bytes: &[u8],
+) -> Result<(), Trap> {
+    if out_ptr == SENTINEL {
+        return Ok(())
+    }
+    let len: u32 = decode(ctx, out_len_ptr, 4)?;
+    if (len as usize) < bytes.len() {
+        return Err(halt(Error::OutputBufferTooSmall))
+    }
+    write(ctx, out_ptr, bytes)?;
+    write(ctx, out_len_ptr, &(bytes.len() as u32).encode())
+}
+
+fn remaining_fuel(ctx: &mut Ctx) -> u64 {
+    ctx.consume_fuel(0).expect("fuel metering is enabled; qed")
+}
+
+fn set_storage(
+    ctx: &mut Ctx,
+    key: Vec<u8>,
+    value_ptr: u32,
+    value_len: u32,
+) -> Result<u32, Trap> {
+    if value_len > MAX_STORAGE_VALUE_LEN {
+        return Err(halt(Error::ValueTooLarge))
+    }
+    let value = read(ctx, value_ptr, value_len)?;
+    let previous = ctx.data_mut().storage()?.insert(Bytes(key), Bytes(value));
+    Ok(previous.map_or(SENTINEL, |value| value.0.len() as u32))
+}
+
+fn clear_storage(ctx: &mut Ctx, key: Vec<u8>) -> Result<u32, Trap> {
+    let previous = ctx.data_mut().storage()?.remove(&Bytes(key));
+    Ok(previous.map_or(SENTINEL, |value| value.0.len() as u32))
+}
+
+fn contains_storage(ctx: &mut Ctx, key: Vec<u8>) -> Result<u32, Trap> {
+    let value = ctx.data_mut().storage()?.get(&Bytes(key));
+    Ok(value.map_or(SENTINEL, |value| value.0.len() as u32))
+}
+
+fn get_storage(
+    ctx: &mut Ctx,
+    key: Vec<u8>,
+    out_ptr: u32,
+    out_len_ptr: u32,
+    take: bool,
+) -> Result<u32, Trap> {
+    let storage = ctx.data_mut().storage()?;
+    let key = Bytes(key);
+    let value = if take {
+        storage.remove(&key)
+    } else {
+        storage.get(&key).cloned()
+    };
+    match value {
+        Some(value) => {
+            write_output(ctx, out_ptr, out_len_ptr, &value.0)?;
+            Ok(ReturnCode::Success as u32)
+        }
+        None => Ok(ReturnCode::KeyNotFound as u32),
+    }
+}
+
+fn transfer(ctx: &mut Ctx, to: AccountId32, value: Balance) -> Result<u32, Trap> {
+    let runtime = ctx.data_mut();
+    let from = runtime.frame.account.clone();
+    if runtime.ext.state.transfer(&from, &to, value) {
+        Ok(ReturnCode::Success as u32)
+    } else {
+        Ok(ReturnCode::TransferFailed as u32)
+    }
+}
+
+/// Executes the `frame` nested into the calling one, with at most `gas` of the fuel
+/// left, or all of it if `gas` is zero.
+fn nested(
+    ctx: &mut Ctx,
+    gas: u64,
+    frame: Frame,
+) -> Result<Result<ExecReturnValue, ExecError>, Trap> {
+    let remaining = remaining_fuel(ctx);
+    let fuel_limit = if gas == 0 {
+        remaining
+    } else {
+        gas.min(remaining)
+    };
+    let mut ext = mem::take(&mut ctx.data_mut().ext);
+    ext.fuel_left = fuel_limit;
+    let result = execute(&mut ext, frame);
+    let fuel_consumed = fuel_limit - ext.fuel_left;
+    ctx.data_mut().ext = ext;
+    ctx.consume_fuel(fuel_consumed)
+        .map_err(|_| halt(Error::OutOfGas))?;
+    Ok(result)
+}
+
+/// Converts the error of a nested frame into the code returned to the caller, fails if
+/// the error traps the caller.
+fn into_return_code(error: ExecError) -> Result<u32, Trap> {
+    let code = match error {
+        ExecError {
+            origin: ErrorOrigin::Callee,
+            ..
+        } => ReturnCode::CalleeTrapped,
+        ExecError {
+            error: Error::TransferFailed,
+            ..
+        } => ReturnCode::TransferFailed,
+        ExecError {
+            error: Error::CodeNotFound,
+            ..
+        } => ReturnCode::CodeNotFound,
+        ExecError {
+            error: Error::ContractNotFound,
+            ..
+        } => ReturnCode::NotCallable,
+        ExecError { error, .. } => return Err(halt(error)),
+    };
+    Ok(code as u32)
+}
+
+/// Writes the output of a nested frame and returns the code for the caller.
+fn nested_output(
+    ctx: &mut Ctx,
+    result: Result<ExecReturnValue, ExecError>,
+    output_ptr: u32,
+    output_len_ptr: u32,
+) -> Result<u32, Trap> {
+    match result {
+        Ok(ret) => {
+            write_output(ctx, output_ptr, output_len_ptr, &ret.data)?;
+            if ret.did_revert() {
+                Ok(ReturnCode::CalleeReverted as u32)
+            } else {
+                Ok(ReturnCode::Success as u32)
+            }
+        }
+        Err(error) => into_return_code(error),
+    }
+}
+
+/// Returns the input for a nested call according to the call `flags`.
+fn call_input(
+    ctx: &mut Ctx,
+    flags: u32,
+    input_ptr: u32,
+    input_len: u32,
+) -> Result<Vec<u8>, Trap> {
+    if flags & !(FORWARD_INPUT | CLONE_INPUT | TAIL_CALL | ALLOW_REENTRY) != 0
+        || flags & (FORWARD_INPUT | CLONE_INPUT) == FORWARD_INPUT | CLONE_INPUT
+    {
+        return Err(halt(Error::InvalidCallFlags))
+    }
+    let input = &mut ctx.data_mut().frame.input;
+    if flags & FORWARD_INPUT != 0 {
+        input.take().ok_or_else(|| halt(Error::InputForwarded))
+    } else if flags & CLONE_INPUT != 0 {
+        input.clone().ok_or_else(|| halt(Error::InputForwarded))
+    } else {
+        read(ctx, input_ptr, input_len)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn call(
+    ctx: &mut Ctx,
+    flags: u32,
+    callee: AccountId32,
+    gas: u64,
+    value: Balance,
+    input_ptr: u32,
+    input_len: u32,
+    output_ptr: u32,
+    output_len_ptr: u32,
+) -> Result<u32, Trap> {
+    let input = call_input(ctx, flags, input_ptr, input_len)?;
+    if flags & ALLOW_REENTRY == 0 && ctx.data().ext.stack.contains(&callee) {
+        return Err(halt(Error::ReentranceDenied))
+    }
+    let frame = Frame {
+        kind: FrameKind::Call,
+        account: callee,
+        caller: ctx.data().frame.account.clone(),
+        value,
+        input: Some(input),
+        code_hash: H256::zero(),
+    };
+    let result = nested(ctx, gas, frame)?;
+    match result {
+        Ok(ret) if flags & TAIL_CALL != 0 => Err(Trap::from(Halt::Return(ret))),
+        result => nested_output(ctx, result, output_ptr, output_len_ptr),
+    }
+}
+
+fn delegate_call(
+    ctx: &mut Ctx,
+    flags: u32,
+    code_hash: H256,
+    input_ptr: u32,
+    input_len: u32,
+    output_ptr: u32,
+    output_len_ptr: u32,
+) -> Result<u32, Trap> {
+    if flags & ALLOW_REENTRY != 0 {
+        return Err(halt(Error::InvalidCallFlags))
+    }
+    let input = call_input(ctx, flags, input_ptr, input_len)?;
+    let caller = &ctx.data().frame;
+    let frame = Frame {
+        kind: FrameKind::DelegateCall,
+        account: caller.account.clone(),
+        caller: caller.caller.clone(),
+        value: caller.value,
+        input: Some(input),
+        code_hash,
+    };
+    let result = nested(ctx, 0, frame)?;
+    match result {
+        Ok(ret) if flags & TAIL_CALL != 0 => Err(Trap::from(Halt::Return(ret))),
+        result => nested_output(ctx, result, output_ptr, output_len_ptr),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn instantiate(
+    ctx: &mut Ctx,
+    code_hash: H256,
+    gas: u64,
+    value: Balance,
+    input: Vec<u8>,
+    salt: Vec<u8>,
+    address_ptr: u32,
+    address_len_ptr: u32,
+    output_ptr: u32,
+    output_len_ptr: u32,
+) -> Result<u32, Trap> {
+    let deployer = ctx.data().frame.account.clone();
+    let account = contract_address(&deployer, &code_hash, &input, &salt);
+    let frame = Frame {
+        kind: FrameKind::Deploy,
+        account: account.clone(),
+        caller: deployer,
+        value,
+        input: Some(input),
+        code_hash,
+    };
+    let result = nested(ctx, gas, frame)?;
+    if matches!(result, Ok(ref ret) if !ret.did_revert()) {
+        write_output(ctx, address_ptr, address_len_ptr, &account.encode())?;
+    }
+    nested_output(ctx, result, output_ptr, output_len_ptr)
+}
+
+fn terminate(ctx: &mut Ctx, beneficiary: AccountId32) -> Result<(), Trap> {
+    let runtime = ctx.data_mut();
+    let contract = runtime.frame.account.clone();
+    if runtime.frame.kind == FrameKind::Deploy {
+        return Err(halt(Error::TerminatedInConstructor))
+    }
+    if runtime.ext.stack.iter().filter(|a| **a == contract).count() > 1 {
+        return Err(halt(Error::TerminatedWhileReentrant))
+    }
+    let state = &mut runtime.ext.state;
+    state.transfer(&contract, &beneficiary, state.balance(&contract));
+    state.contracts.remove(&contract);
+    runtime.ext.events.push(Event::Terminated {
+        contract,
+        beneficiary,
+    });
+    Err(Trap::from(Halt::Return(ExecReturnValue {
+        flags: ReturnFlags::empty(),
+        data: Vec::new(),
+    })))
+}
+
+fn deposit_event(
+    ctx: &mut Ctx,
+    topics_ptr: u32,
+    topics_len: u32,
+    data_ptr: u32,
+    data_len: u32,
+) -> Result<(), Trap> {
+    let topics: Vec<H256> = if topics_len == 0 {
+        Vec::new()
+    } else {
+        decode(ctx, topics_ptr, topics_len)?
+    };
+    if topics.len() > MAX_EVENT_TOPICS {
+        return Err(halt(Error::TooManyTopics))
+    }
+    let data = read(ctx, data_ptr, data_len)?;
+    let runtime = ctx.data_mut();
+    runtime.ext.events.push(Event::ContractEmitted {
+        contract: runtime.frame.account.clone(),
+        topics,
+        data,
+    });
+    Ok(())
+}
+
+fn hash(
+    ctx: &mut Ctx,
+    input_ptr: u32,
+    input_len: u32,
+    output_ptr: u32,
+    hash: impl Fn(&[u8]) -> Vec<u8>,
+) -> Result<(), Trap> {
+    let input = read(ctx, input_ptr, input_len)?;
+    write(ctx, output_ptr, &hash(&input))
+}
+
+/// Defines the host functions in the linker and records their names.
+struct HostFunctions<'a> {
+    linker: &'a mut Linker<Runtime>,
+    defined: BTreeSet<String>,
+}
+
+impl HostFunctions<'_> {
+    /// Defines the host function `name` of the `module`, as well as its alias prefixed
+    /// with `seal_`.
+    fn define<Params, Results>(
+        &mut self,
+        module: &'static str,
+        name: &'static str,
+        func: impl IntoFunc<Runtime, Params, Results> + Clone,
+    ) -> &mut Self {
+        let mut names = vec![name.to_string()];
+        if !name.starts_with("seal_") {
+            names.push(format!("seal_{name}"));
+        }
+        for name in names {
+            self.linker
+                .func_wrap(module, &name, func.clone())
+                .expect("every host function is defined once; qed");
+            self.defined.insert(format!("{module}::{name}"));
+        }
+        self
+    }
+}
+
+/// Defines all host functions imported by the `module`, those not implemented by the
+/// sandbox trap once they are called.
+fn define_host_functions(linker: &mut Linker<Runtime>, module: &Module) {
+    let mut host = HostFunctions {
+        linker,
+        defined: BTreeSet::new(),
+    };
+    host.define(
+        "seal0",
+        "input",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let input = ctx
+                .data()
+                .frame
+                .input
+                .clone()
+                .ok_or_else(|| halt(Error::InputForwarded))?;
+            write_output(&mut ctx, out_ptr, out_len_ptr, &input)
+        },
+    )
+    .define(
+        "seal0",
+        "seal_return",
+        |ctx: Ctx, flags: u32, data_ptr: u32, data_len: u32| -> Result<(), Trap> {
+            let flags = ReturnFlags::from_bits(flags)
+                .ok_or_else(|| halt(Error::InvalidCallFlags))?;
+            let data = read(&ctx, data_ptr, data_len)?;
+            Err(Trap::from(Halt::Return(ExecReturnValue { flags, data })))
+        },
+    )
+    .define(
+        "seal0",
+        "caller",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let caller = ctx.data().frame.caller.encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &caller)
+        },
+    )
+    .define(
+        "seal0",
+        "address",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let address = ctx.data().frame.account.encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &address)
+        },
+    )
+    .define(
+        "seal0",
+        "balance",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let runtime = ctx.data();
+            let balance = runtime.ext.state.balance(&runtime.frame.account).encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &balance)
+        },
+    )
+    .define(
+        "seal0",
+        "value_transferred",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let value = ctx.data().frame.value.encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &value)
+        },
+    )
+    .define(
+        "seal0",
+        "minimum_balance",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            write_output(&mut ctx, out_ptr, out_len_ptr, &MINIMUM_BALANCE.encode())
+        },
+    )
+    .define(
+        "seal0",
+        "block_number",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let block_number = ctx.data().ext.state.block_number.encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &block_number)
+        },
+    )
+    .define(
+        "seal0",
+        "now",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let now = ctx.data().ext.state.timestamp.encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &now)
+        },
+    )
+    .define(
+        "seal0",
+        "gas_left",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let gas_left = remaining_fuel(&mut ctx).encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &gas_left)
+        },
+    )
+    .define(
+        "seal1",
+        "gas_left",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let gas_left = Weight::from_parts(remaining_fuel(&mut ctx), 0).encode();
+            write_output(&mut ctx, out_ptr, out_len_ptr, &gas_left)
+        },
+    )
+    .define(
+        "seal0",
+        "weight_to_fee",
+        |mut ctx: Ctx, gas: u64, out_ptr: u32, out_len_ptr: u32| {
+            write_output(&mut ctx, out_ptr, out_len_ptr, &Balance::from(gas).encode())
+        },
+    )
+    .define(
+        "seal0",
+        "deposit_event",
+        |mut ctx: Ctx, topics_ptr: u32, topics_len: u32, data_ptr: u32, data_len: u32| {
+            deposit_event(&mut ctx, topics_ptr, topics_len, data_ptr, data_len)
+        },
+    )
+    .define(
+        "seal0",
+        "debug_message",
+        |mut ctx: Ctx, str_ptr: u32, str_len: u32| -> Result<u32, Trap> {
+            let message = read(&ctx, str_ptr, str_len)?;
+            ctx.data_mut().ext.debug_message.extend(message);
+            Ok(ReturnCode::Success as u32)
+        },
+    )
+    .define(
+        "seal0",
+        "hash_sha2_256",
+        |mut ctx: Ctx, input_ptr: u32, input_len: u32, output_ptr: u32| {
+            hash(&mut ctx, input_ptr, input_len, output_ptr, |input| {
+                hashing::sha2_256(input).to_vec()
+            })
+        },
+    )
+    .define(
+        "seal0",
+        "hash_keccak_256",
+        |mut ctx: Ctx, input_ptr: u32, input_len: u32, output_ptr: u32| {
+            hash(&mut ctx, input_ptr, input_len, output_ptr, |input| {
+                hashing::keccak_256(input).to_vec()
+            })
+        },
+    )
+    .define(
+        "seal0",
+        "hash_blake2_256",
+        |mut ctx: Ctx, input_ptr: u32, input_len: u32, output_ptr: u32| {
+            hash(&mut ctx, input_ptr, input_len, output_ptr, |input| {
+                hashing::blake2_256(input).to_vec()
+            })
+        },
+    )
+    .define(
+        "seal0",
+        "hash_blake2_128",
+        |mut ctx: Ctx, input_ptr: u32, input_len: u32, output_ptr: u32| {
+            hash(&mut ctx, input_ptr, input_len, output_ptr, |input| {
+                hashing::blake2_128(input).to_vec()
+            })
+        },
+    )
+    .define(
+        "seal0",
+        "set_storage",
+        |mut ctx: Ctx,
+         key_ptr: u32,
+         value_ptr: u32,
+         value_len: u32|
+         -> Result<(), Trap> {
+            let key = read(&ctx, key_ptr, 32)?;
+            set_storage(&mut ctx, key, value_ptr, value_len).map(drop)
+        },
+    )
+    .define(
+        "seal1",
+        "set_storage",
+        |mut ctx: Ctx, key_ptr: u32, value_ptr: u32, value_len: u32| {
+            let key = read(&ctx, key_ptr, 32)?;
+            set_storage(&mut ctx, key, value_ptr, value_len)
+        },
+    )
+    .define(
+        "seal2",
+        "set_storage",
+        |mut ctx: Ctx, key_ptr: u32, key_len: u32, value_ptr: u32, value_len: u32| {
+            let key = read_key(&ctx, key_ptr, key_len)?;
+            set_storage(&mut ctx, key, value_ptr, value_len)
+        },
+    )
+    .define(
+        "seal0",
+        "clear_storage",
+        |mut ctx: Ctx, key_ptr: u32| -> Result<(), Trap> {
+            let key = read(&ctx, key_ptr, 32)?;
+            clear_storage(&mut ctx, key).map(drop)
+        },
+    )
+    .define(
+        "seal1",
+        "clear_storage",
+        |mut ctx: Ctx, key_ptr: u32, key_len: u32| {
+            let key = read_key(&ctx, key_ptr, key_len)?;
+            clear_storage(&mut ctx, key)
+        },
+    )
+    .define(
+        "seal0",
+        "get_storage",
+        |mut ctx: Ctx, key_ptr: u32, out_ptr: u32, out_len_ptr: u32| {
+            let key = read(&ctx, key_ptr, 32)?;
+            get_storage(&mut ctx, key, out_ptr, out_len_ptr, false)
+        },
+    )
+    .define(
+        "seal1",
+        "get_storage",
+        |mut ctx: Ctx, key_ptr: u32, key_len: u32, out_ptr: u32, out_len_ptr: u32| {
+            let key = read_key(&ctx, key_ptr, key_len)?;
+            get_storage(&mut ctx, key, out_ptr, out_len_ptr, false)
+        },
+    )
+    .define(
+        "seal0",
+        "take_storage",
+        |mut ctx: Ctx, key_ptr: u32, key_len: u32, out_ptr: u32, out_len_ptr: u32| {
+            let key = read_key(&ctx, key_ptr, key_len)?;
+            get_storage(&mut ctx, key, out_ptr, out_len_ptr, true)
+        },
+    )
+    .define("seal0", "contains_storage", |mut ctx: Ctx, key_ptr: u32| {
+        let key = read(&ctx, key_ptr, 32)?;
+        contains_storage(&mut ctx, key)
+    })
+    .define(
+        "seal1",
+        "contains_storage",
+        |mut ctx: Ctx, key_ptr: u32, key_len: u32| {
+            let key = read_key(&ctx, key_ptr, key_len)?;
+            contains_storage(&mut ctx, key)
+        },
+    )
+    .define(
+        "seal0",
+        "transfer",
+        |mut ctx: Ctx, account_ptr: u32, _: u32, value_ptr: u32, _: u32| {
+            let to = read_account(&ctx, account_ptr)?;
+            let value = read_balance(&ctx, value_ptr)?;
+            transfer(&mut ctx, to, value)
+        },
+    )
+    .define(
+        "seal0",
+        "call",
+        |mut ctx: Ctx,
+         callee_ptr: u32,
+         _: u32,
+         gas: u64,
+         value_ptr: u32,
+         _: u32,
+         input_ptr: u32,
+         input_len: u32,
+         output_ptr: u32,
+         output_len_ptr: u32| {
+            let callee = read_account(&ctx, callee_ptr)?;
+            let value = read_balance(&ctx, value_ptr)?;
+            call(
+                &mut ctx,
+                ALLOW_REENTRY,
+                callee,
+                gas,
+                value,
+                input_ptr,
+                input_len,
+                output_ptr,
+                output_len_ptr,
+            )
+        },
+    )
+    .define(
+        "seal1",
+        "call",
+        |mut ctx: Ctx,
+         flags: u32,
+         callee_ptr: u32,
+         gas: u64,
+         value_ptr: u32,
+         input_ptr: u32,
+         input_len: u32,
+         output_ptr: u32,
+         output_len_ptr: u32| {
+            let callee = read_account(&ctx, callee_ptr)?;
+            let value = read_balance(&ctx, value_ptr)?;
+            call(
+                &mut ctx,
+                flags,
+                callee,
+                gas,
+                value,
+                input_ptr,
+                input_len,
+                output_ptr,
+                output_len_ptr,
+            )
+        },
+    )
+    .define(
+        "seal0",
+        "delegate_call",
+        |mut ctx: Ctx,
+         flags: u32,
+         code_hash_ptr: u32,
+         input_ptr: u32,
+         input_len: u32,
+         output_ptr: u32,
+         output_len_ptr: u32| {
+            let code_hash = H256(read_array(&ctx, code_hash_ptr)?);
+            delegate_call(
+                &mut ctx,
+                flags,
+                code_hash,
+                input_ptr,
+                input_len,
+                output_ptr,
+                output_len_ptr,
+            )
+        },
+    )
+    .define(
+        "seal0",
+        "instantiate",
+        |mut ctx: Ctx,
+         code_hash_ptr: u32,
+         _: u32,
+         gas: u64,
+         value_ptr: u32,
+         _: u32,
+         input_ptr: u32,
+         input_len: u32,
+         address_ptr: u32,
+         address_len_ptr: u32,
+         output_ptr: u32,
+         output_len_ptr: u32,
+         salt_ptr: u32,
+         salt_len: u32| {
+            let code_hash = H256(read_array(&ctx, code_hash_ptr)?);
+            let value = read_balance(&ctx, value_ptr)?;
+            let input = read(&ctx, input_ptr, input_len)?;
+            let salt = read(&ctx, salt_ptr, salt_len)?;
+            instantiate(
+                &mut ctx,
+                code_hash,
+                gas,
+                value,
+                input,
+                salt,
+                address_ptr,
+                address_len_ptr,
+                output_ptr,
+                output_len_ptr,
+            )
+        },
+    )
+    .define(
+        "seal1",
+        "instantiate",
+        |mut ctx: Ctx,
+         code_hash_ptr: u32,
+         gas: u64,
+         value_ptr: u32,
+         input_ptr: u32,
+         input_len: u32,
+         address_ptr: u32,
+         address_len_ptr: u32,
+         output_ptr: u32,
+         output_len_ptr: u32,
+         salt_ptr: u32,
+         salt_len: u32| {
+            let code_hash = H256(read_array(&ctx, code_hash_ptr)?);
+            let value = read_balance(&ctx, value_ptr)?;
+            let input = read(&ctx, input_ptr, input_len)?;
+            let salt = read(&ctx, salt_ptr, salt_len)?;
+            instantiate(
+                &mut ctx,
+                code_hash,
+                gas,
+                value,
+                input,
+                salt,
+                address_ptr,
+                address_len_ptr,
+                output_ptr,
+                output_len_ptr,
+            )
+        },
+    )
+    .define(
+        "seal0",
+        "terminate",
+        |mut ctx: Ctx, beneficiary_ptr: u32, _: u32| {
+            let beneficiary = read_account(&ctx, beneficiary_ptr)?;
+            terminate(&mut ctx, beneficiary)
+        },
+    )
+    .define(
+        "seal1",
+        "terminate",
+        |mut ctx: Ctx, beneficiary_ptr: u32| {
+            let beneficiary = read_account(&ctx, beneficiary_ptr)?;
+            terminate(&mut ctx, beneficiary)
+        },
+    )
+    .define(
+        "seal0",
+        "is_contract",
+        |ctx: Ctx, account_ptr: u32| -> Result<u32, Trap> {
+            let account = read_account(&ctx, account_ptr)?;
+            Ok(ctx.data().ext.state.contracts.contains_key(&account) as u32)
+        },
+    )
+    .define("seal0", "caller_is_origin", |ctx: Ctx| -> u32 {
+        let runtime = ctx.data();
+        (runtime.frame.caller == runtime.ext.origin) as u32
+    })
+    .define(
+        "seal0",
+        "code_hash",
+        |mut ctx: Ctx, account_ptr: u32, out_ptr: u32, out_len_ptr: u32| {
+            let account = read_account(&ctx, account_ptr)?;
+            let contracts = &ctx.data().ext.state.contracts;
+            match contracts.get(&account).map(|contract| contract.code_hash) {
+                Some(code_hash) => {
+                    write_output(&mut ctx, out_ptr, out_len_ptr, code_hash.as_bytes())?;
+                    Ok(ReturnCode::Success as u32)
+                }
+                None => Ok(ReturnCode::KeyNotFound as u32),
+            }
+        },
+    )
+    .define(
+        "seal0",
+        "own_code_hash",
+        |mut ctx: Ctx, out_ptr: u32, out_len_ptr: u32| {
+            let runtime = ctx.data();
+            let code_hash = runtime
+                .ext
+                .state
+                .contracts
+                .get(&runtime.frame.account)
+                .map(|contract| contract.code_hash)
+                .ok_or_else(|| halt(Error::ContractNotFound))?;
+            write_output(&mut ctx, out_ptr, out_len_ptr, code_hash.as_bytes())
+        },
+    )
+    .define(
+        "seal0",
+        "set_code_hash",
+        |mut ctx: Ctx, code_hash_ptr: u32| -> Result<u32, Trap> {
+            let code_hash = H256(read_array(&ctx, code_hash_ptr)?);
+            if !ctx.data().ext.state.codes.contains_key(&code_hash) {
+                return Ok(ReturnCode::CodeNotFound as u32)
+            }
+            let runtime = ctx.data_mut();
+            let contract = runtime
+                .ext
+                .state
+                .contracts
+                .get_mut(&runtime.frame.account)
+                .ok_or_else(|| halt(Error::ContractNotFound))?;
+            contract.code_hash = code_hash;
+            Ok(ReturnCode::Success as u32)
+        },
+    )
+    .define("seal0", "reentrance_count", |ctx: Ctx| -> u32 {
+        let runtime = ctx.data();
+        let account = &runtime.frame.account;
+        let count = runtime.ext.stack.iter().filter(|a| *a == account).count();
+        count.saturating_sub(1) as u32
+    })
+    .define(
+        "seal0",
+        "account_reentrance_count",
+        |ctx: Ctx, account_ptr: u32| -> Result<u32, Trap> {
+            let account = read_account(&ctx, account_ptr)?;
+            let stack = &ctx.data().ext.stack;
+            Ok(stack.iter().filter(|a| **a == account).count() as u32)
+        },
+    )
+    .define("seal0", "instantiation_nonce", |ctx: Ctx| -> u64 {
+        ctx.data().ext.state.nonce
+    });
+
+    let defined = mem::take(&mut host.defined);
+    for import in module.imports() {
+        let ExternType::Func(func_type) = import.ty() else {
+            continue
+        };
+        let host_function = format!("{}::{}", import.module(), import.name());
+        if defined.contains(&host_function) {
+            continue
+        }
+        host.linker
+            .func_new(
+                import.module(),
+                import.name(),
+                func_type.clone(),
+                move |_, _, _| {
+                    Err(halt(Error::UnsupportedHostFunction(host_function.clone())))
+                },
+            )
+            .expect("unsupported host functions are not defined yet; qed");
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2018-2022 Parity Technologies (UK) Ltd.
+// This file is part of cargo-contract.
+//
+// cargo-contract is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// cargo-contract is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with cargo-contract.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::{
+    Balance,
+    DEPOSIT_PER_BYTE,
+    DEPOSIT_PER_ITEM,
+    ENDOWMENT,
+};
+use sp_core::{
+    crypto::AccountId32,
+    Bytes,
+    H256,
+};
+use std::collections::BTreeMap;
+
+/// The state of the sandbox chain, serialized to keep it between invocations.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct State {
+    /// The number of the current block.
+    pub block_number: u32,
+    /// The timestamp of the current block in milliseconds.
+    pub timestamp: u64,
+    /// The number of contracts instantiated so far.
+    pub nonce: u64,
+    /// The balances of all accounts which differ from the endowment.
+    pub balances: BTreeMap<AccountId32, Balance>,
+    /// The uploaded contract codes by their hash.
+    pub codes: BTreeMap<H256, Bytes>,
+    /// The instantiated contracts by their address.
+    pub contracts: BTreeMap<AccountId32, ContractInfo>,
+}
+
+/// An instantiated contract.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ContractInfo {
+    /// The hash of the code executed by the contract.
+    pub code_hash: H256,
+    /// The storage of the contract.
+    pub storage: BTreeMap<Bytes, Bytes>,
+}
+
+impl State {
+    /// Returns the free balance of the `account`.
+    ///
+    /// Accounts unknown to the sandbox are endowed, like the development accounts of a
+    /// local node.
+    pub fn balance(&self, account: &AccountId32) -> Balance {
+        self.balances.get(account).copied().unwrap_or(ENDOWMENT)
+    }
+
+    /// Transfers the `value` between the accounts, returns `false` if the balance of
+    /// `from` does not suffice.
+    pub fn transfer(
+        &mut self,
+        from: &AccountId32,
+        to: &AccountId32,
+        value: Balance,
+    ) -> bool {
+        if value == 0 || from == to {
+            return true
+        }
+        let Some(from_balance) = self.balance(from).checked_sub(value) else {
+            return false
+        };
+        let to_balance = self.balance(to).saturating_add(value);
+        self.balances.insert(from.clone(), from_balance);
+        self.balances.insert(to.clone(), to_balance);
+        true
+    }
+
+    /// Returns the deposit the storage of all contracts is charged with.
+    pub fn storage_deposit(&self) -> Balance {
+        self.contracts
+            .values()
+            .flat_map(|contract| &contract.storage)
+            .map(|(key, value)| {
+                DEPOSIT_PER_ITEM
+                    + DEPOSIT_PER_BYTE * (key.0.len() + value.0.len()) as Balance
+            })
+            .sum()
+    }
+}
@@ -194,6 +194,54 @@ impl CustomTypeDecoder for AccountId {
     }
 }
 
+/// The format of the `AccountId` of the chain a contract is deployed on.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AccountIdFormat {
+    /// 32 byte accounts input/output as SS58 encoded literals, see [`AccountId`].
+    #[default]
+    Ss58,
+    /// 20 byte Ethereum style accounts input/output as hex encoded addresses, see
+    /// [`AccountId20`].
+    H160,
+}
+
+/// Custom encoding/decoding for the 20 byte `AccountId` type of chains with Ethereum
+/// style accounts.
+///
+/// Enables an `AccountId` to be input/output as a hex encoded address e.g.
+/// 0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac
+#[derive(Clone)]
+pub struct AccountId20;
+
+impl CustomTypeEncoder for AccountId20 {
+    fn encode_value(&self, value: &Value) -> Result<Vec<u8>> {
+        let bytes = match value {
+            Value::Hex(hex) => hex.bytes().to_vec(),
+            Value::Literal(string) | Value::String(string) => {
+                hex::decode(string.trim_start_matches("0x")).map_err(|e| {
+                    anyhow::anyhow!("Error parsing AccountId from `{}`: {}", string, e)
+                })?
+            }
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Expected a hex encoded address for an AccountId"
+                ))
+            }
+        };
+        let account_id = <[u8; 20]>::try_from(bytes.as_slice()).map_err(|_| {
+            anyhow::anyhow!("Expected an AccountId of 20 bytes, got {}", bytes.len())
+        })?;
+        Ok(account_id.encode())
+    }
+}
+
+impl CustomTypeDecoder for AccountId20 {
+    fn decode_value(&self, input: &mut &[u8]) -> Result<Value> {
+        let account_id = <[u8; 20]>::decode(input)?;
+        Ok(Value::Hex(Hex::from_str(&hex::encode(account_id))?))
+    }
+}
+
 /// Custom decoding for the `Hash` or `[u8; 32]` type so that it is displayed as a hex
 /// encoded string.
 pub struct Hash;
@@ -108,6 +108,7 @@ mod util;
 
 pub use self::{
     account_id::AccountId32,
+    env_types::AccountIdFormat,
     scon::{
         Hex,
         Map,
@@ -155,8 +156,24 @@ pub struct ContractMessageTranscoder {
 
 impl ContractMessageTranscoder {
     pub fn new(metadata: InkProject) -> Self {
-        let transcoder = TranscoderBuilder::new(metadata.registry())
-            .register_custom_type_transcoder::<<ink_env::DefaultEnvironment as ink_env::Environment>::AccountId, _>(env_types::AccountId)
+        Self::with_account_id_format(metadata, AccountIdFormat::Ss58)
+    }
+
+    /// Constructs a transcoder for a contract on a chain whose `AccountId` has the given
+    /// `format`. The 20 byte accounts of [`AccountIdFormat::H160`] are transcoded for
+    /// the `AccountId` type of the environment of the contract.
+    pub fn with_account_id_format(metadata: InkProject, format: AccountIdFormat) -> Self {
+        let builder = TranscoderBuilder::new(metadata.registry());
+        let builder = match format {
+            AccountIdFormat::Ss58 => {
+                builder.register_custom_type_transcoder::<<ink_env::DefaultEnvironment as ink_env::Environment>::AccountId, _>(env_types::AccountId)
+            }
+            AccountIdFormat::H160 => {
+                let account_id = metadata.spec().environment().account_id().ty().id;
+                builder.register_custom_type_transcoder_for_id(account_id, env_types::AccountId20)
+            }
+        };
+        let transcoder = builder
             .register_custom_type_decoder::<<ink_env::DefaultEnvironment as ink_env::Environment>::Hash, _>(env_types::Hash)
             .done();
         Self {
@@ -232,6 +249,11 @@ impl ContractMessageTranscoder {
         Ok(encoded)
     }
 
+    /// Returns the ink! metadata the transcoder is created from.
+    pub fn metadata(&self) -> &InkProject {
+        &self.metadata
+    }
+
     pub fn decode(&self, type_id: u32, input: &mut &[u8]) -> Result<Value> {
         self.transcoder
             .decode(self.metadata.registry(), type_id, input)
@@ -444,6 +466,7 @@ impl CompositeTypeFields {
 }
 
 #[cfg(test)]
+#[allow(unexpected_cfgs)]
 mod tests {
     use super::*;
     use scale::Encode;
@@ -583,6 +606,33 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn encode_account_id_h160_format() -> Result<()> {
+        let metadata = generate_metadata();
+        let transcoder = ContractMessageTranscoder::with_account_id_format(
+            metadata,
+            AccountIdFormat::H160,
+        );
+
+        let encoded = transcoder.encode(
+            "set_account_id",
+            ["0xf24ff3a9cf04c71dbc94d0b566f7a27b94566cac"],
+        )?;
+
+        // encoded args follow the 4 byte selector
+        let encoded_args = &encoded[4..];
+
+        let expected = hex::decode("f24ff3a9cf04c71dbc94d0b566f7a27b94566cac")?;
+        assert_eq!(expected, encoded_args);
+        assert!(transcoder
+            .encode(
+                "set_account_id",
+                ["5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"]
+            )
+            .is_err());
+        Ok(())
+    }
+
     #[test]
     fn encode_account_ids_vec_args() -> Result<()> {
         let metadata = generate_metadata();
@@ -169,6 +169,27 @@ impl TranscoderBuilder {
         this
     }
 
+    /// Registers the `transcoder` for the type with `type_id`, e.g. for a type of the
+    /// environment of a contract which is not known by its path.
+    pub fn register_custom_type_transcoder_for_id<U>(
+        self,
+        type_id: u32,
+        transcoder: U,
+    ) -> Self
+    where
+        U: CustomTypeEncoder + CustomTypeDecoder + Clone + 'static,
+    {
+        let mut this = self;
+        let existing_encoder =
+            this.encoders.insert(type_id, Box::new(transcoder.clone()));
+        let existing_decoder = this.decoders.insert(type_id, Box::new(transcoder));
+        if existing_encoder.is_some() || existing_decoder.is_some() {
+            panic!("Attempted to register transcoder with existing type id {type_id:?}");
+        }
+        tracing::debug!("Registered custom transcoder for type `{:?}`", type_id);
+        this
+    }
+
     pub fn done(self) -> Transcoder {
         let env_types_transcoder = EnvTypesTranscoder::new(self.encoders, self.decoders);
         Transcoder::new(env_types_transcoder)
@@ -275,31 +296,28 @@ mod tests {
 
     #[test]
     fn transcode_integers() -> Result<()> {
-        transcode_roundtrip::<i8>("-128", Value::Int(i8::min_value().into()))?;
-        transcode_roundtrip::<i8>("127", Value::Int(i8::max_value().into()))?;
+        transcode_roundtrip::<i8>("-128", Value::Int(i8::MIN.into()))?;
+        transcode_roundtrip::<i8>("127", Value::Int(i8::MAX.into()))?;
 
-        transcode_roundtrip::<i16>("-32768", Value::Int(i16::min_value().into()))?;
-        transcode_roundtrip::<i16>("32767", Value::Int(i16::max_value().into()))?;
+        transcode_roundtrip::<i16>("-32768", Value::Int(i16::MIN.into()))?;
+        transcode_roundtrip::<i16>("32767", Value::Int(i16::MAX.into()))?;
 
-        transcode_roundtrip::<i32>("-2147483648", Value::Int(i32::min_value().into()))?;
-        transcode_roundtrip::<i32>("2147483647", Value::Int(i32::max_value().into()))?;
+        transcode_roundtrip::<i32>("-2147483648", Value::Int(i32::MIN.into()))?;
+        transcode_roundtrip::<i32>("2147483647", Value::Int(i32::MAX.into()))?;
 
-        transcode_roundtrip::<i64>(
-            "-9223372036854775808",
-            Value::Int(i64::min_value().into()),
-        )?;
+        transcode_roundtrip::<i64>("-9223372036854775808", Value::Int(i64::MIN.into()))?;
         transcode_roundtrip::<i64>(
             "\"9_223_372_036_854_775_807\"",
-            Value::Int(i64::max_value().into()),
+            Value::Int(i64::MAX.into()),
         )?;
 
         transcode_roundtrip::<i128>(
             "-170141183460469231731687303715884105728",
-            Value::Int(i128::min_value()),
+            Value::Int(i128::MIN),
         )?;
         transcode_roundtrip::<i128>(
             "\"170141183460469231731687303715884105727\"",
-            Value::Int(i128::max_value()),
+            Value::Int(i128::MAX),
         )
     }
 